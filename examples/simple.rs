@@ -15,8 +15,9 @@ fn main() {
             data: vec![(377299.0, 1483035.0)],
             crs: Box::new(UTMSystem {
                 utm_zone: 48,
+                hemisphere: Hemisphere::North,
             }),
-            ellipsoid: ellipsoid,
+            ellipsoid,
         }
     ));
 
@@ -24,7 +25,7 @@ fn main() {
         CoordinateBuf {
             data: Vec::new(),
             crs: Box::new(MercatorSystem),
-            ellipsoid: ellipsoid,
+            ellipsoid,
         }
     ));
 
@@ -45,7 +46,7 @@ fn main() {
     let lon_lat_coordinates = CoordinateSource::LonLatBuf(Box::new(
         LonLatBuf {
             data: vec![(-174.726563, -66.086990), (16.171875, 49.386186), (-99.492188, 42.557395)],
-            ellipsoid: ellipsoid,
+            ellipsoid,
         }
     ));
 
@@ -1,4 +1,5 @@
 use prelude::*;
+use projjson::{self, ProjJsonError};
 
 /// A buffer of coordinates. Units are arbitrary.
 #[repr(C)]
@@ -11,6 +12,49 @@ pub struct CoordinateBuf {
     pub ellipsoid: Ellipsoid,
 }
 
+impl CoordinateBuf {
+    /// Creates a buffer for the coordinates in the CRS of a PROJJSON definition
+    ///
+    /// The transformation to WGS84 of a `BoundCRS` is not kept, use
+    /// `projjson::parse_projjson` to read it.
+    pub fn from_projjson(data: Vec<(f64, f64)>, projjson: &str)
+                         -> Result<Self, ProjJsonError>
+    {
+        let definition = projjson::parse_projjson(projjson)?;
+        Ok(CoordinateBuf { data, crs: definition.crs, ellipsoid: definition.ellipsoid })
+    }
+
+    /// Writes the CRS and the ellipsoid of the buffer as PROJJSON
+    pub fn crs_to_projjson(&self)
+                           -> Result<String, ProjJsonError>
+    {
+        projjson::to_projjson(&*self.crs, &self.ellipsoid, None)
+    }
+}
+
+/// Projects the coordinates from the source to the target CRS with a single
+/// `CoordinateSource::project` call, for the readers of geometry formats
+pub(crate) fn project_batch(data: Vec<(f64, f64)>,
+                            source_crs: &dyn Crs, source_ellipsoid: &Ellipsoid,
+                            target_crs: &dyn Crs, target_ellipsoid: &Ellipsoid,
+                            strategy: &mut MultithreadingStrategy)
+                            -> Vec<(f64, f64)>
+{
+    let source = CoordinateSource::CoordinateBuf(Box::new(CoordinateBuf {
+        data,
+        crs: Crs::clone(source_crs),
+        ellipsoid: *source_ellipsoid,
+    }));
+    let mut target = CoordinateSource::CoordinateBuf(Box::new(CoordinateBuf {
+        data: Vec::new(),
+        crs: Crs::clone(target_crs),
+        ellipsoid: *target_ellipsoid,
+    }));
+
+    source.project(&mut target, strategy);
+    target.into_data()
+}
+
 /// Source of the given coordinates
 /// This is needed so we can reproject LatLon directly to the target CRS
 /// without any intermediate steps.
@@ -0,0 +1,153 @@
+//! Unit, axis order and prime meridian adjustments of another CRS
+//!
+//! Corresponds to the `+units` / `+to_meter`, `+axis` and `+pm` parameters of
+//! PROJ strings: the wrapped CRS works in meters, east / north axes and
+//! longitudes relative to Greenwich, the adjusted coordinates may not.
+
+use prelude::*;
+
+/// Direction of a coordinate axis
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum AxisDirection {
+    East,
+    West,
+    North,
+    South,
+}
+
+impl AxisDirection {
+    /// Returns the direction for the PROJ axis letter ('e', 'w', 'n' or 's')
+    pub fn from_char(c: char) -> Option<Self> {
+        use self::AxisDirection::*;
+        match c {
+            'e' => Some(East),
+            'w' => Some(West),
+            'n' => Some(North),
+            's' => Some(South),
+            _ => None,
+        }
+    }
+
+    /// Returns the PROJ axis letter of the direction
+    pub fn to_char(self) -> char {
+        use self::AxisDirection::*;
+        match self {
+            East => 'e',
+            West => 'w',
+            North => 'n',
+            South => 's',
+        }
+    }
+}
+
+/// Another CRS with scaled units, reordered / flipped axes and a prime meridian
+///
+/// The coordinates of this system are `(first axis, second axis)` in units of
+/// `to_meter` meters. The longitudes of the wrapped CRS are relative to the
+/// prime meridian, which is given in degrees east of Greenwich.
+#[derive(Debug)]
+pub struct AdjustedSystem {
+    /// The wrapped CRS
+    pub crs: Box<dyn Crs>,
+    /// Length of one coordinate unit, in meters (`1.0` for meters)
+    pub to_meter: f64,
+    /// Longitude of the prime meridian east of Greenwich, in degrees
+    pub prime_meridian: f64,
+    /// Directions of the first and second coordinate, one of them must be
+    /// east / west and the other one north / south
+    pub axis: (AxisDirection, AxisDirection),
+}
+
+impl Clone for AdjustedSystem {
+    fn clone(&self) -> Self {
+        AdjustedSystem {
+            crs: Crs::clone(&*self.crs),
+            to_meter: self.to_meter,
+            prime_meridian: self.prime_meridian,
+            axis: self.axis,
+        }
+    }
+}
+
+pub mod utils {
+
+    use super::AxisDirection;
+    use super::AxisDirection::*;
+
+    /// Converts a coordinate in the given axis order to (east, north)
+    #[inline]
+    pub fn axis_to_east_north(first: f64, second: f64, axis: (AxisDirection, AxisDirection))
+                              -> (f64, f64)
+    {
+        let (mut east, mut north) = (0.0, 0.0);
+        for &(value, direction) in [(first, axis.0), (second, axis.1)].iter() {
+            match direction {
+                East => east = value,
+                West => east = -value,
+                North => north = value,
+                South => north = -value,
+            }
+        }
+        (east, north)
+    }
+
+    /// Converts an (east, north) coordinate to the given axis order
+    #[inline]
+    pub fn east_north_to_axis(east: f64, north: f64, axis: (AxisDirection, AxisDirection))
+                              -> (f64, f64)
+    {
+        let value = |direction| match direction {
+            East => east,
+            West => -east,
+            North => north,
+            South => -north,
+        };
+        (value(axis.0), value(axis.1))
+    }
+}
+
+impl ToLonLat for AdjustedSystem {
+    fn to_lon_lat(&self, mut data: Vec<(f64, f64)>, ellipsoid: &Ellipsoid, strategy: &mut MultithreadingStrategy)
+                  -> LonLatBuf
+    {
+        for &mut (ref mut x, ref mut y) in data.iter_mut() {
+            let (east, north) = utils::axis_to_east_north(*x, *y, self.axis);
+            *x = east * self.to_meter; *y = north * self.to_meter;
+        }
+
+        let mut buf = self.crs.to_lon_lat(data, ellipsoid, strategy);
+
+        if self.prime_meridian != 0.0 {
+            for &mut (ref mut lon, _) in buf.data.iter_mut() {
+                *lon += self.prime_meridian;
+            }
+        }
+
+        buf
+    }
+}
+
+impl FromLonLat for AdjustedSystem {
+    fn from_lon_lat(&self, mut data: Vec<(f64, f64)>, ellipsoid: &Ellipsoid, strategy: &mut MultithreadingStrategy)
+                    -> CoordinateBuf
+    {
+        if self.prime_meridian != 0.0 {
+            for &mut (ref mut lon, _) in data.iter_mut() {
+                *lon -= self.prime_meridian;
+            }
+        }
+
+        let mut data = self.crs.from_lon_lat(data, ellipsoid, strategy).data;
+
+        for &mut (ref mut x, ref mut y) in data.iter_mut() {
+            let (first, second) = utils::east_north_to_axis(*x / self.to_meter, *y / self.to_meter, self.axis);
+            *x = first; *y = second;
+        }
+
+        CoordinateBuf {
+            data,
+            crs: Box::new(Clone::clone(self)),
+            ellipsoid: *ellipsoid,
+        }
+    }
+}
@@ -0,0 +1,163 @@
+//! Description of a coordinate system by its projection and parameters
+//!
+//! `Description::of` is the only place that inspects the concrete type behind a
+//! `dyn Crs`. The PROJ string, WKT, PROJJSON and EPSG writers match on the
+//! returned `Description`, so a new coordinate system is a compile error in each
+//! of them until it is written or explicitly rejected there.
+//!
+//! `Description::validate` holds the checks for parameter values that a
+//! projection is undefined for, which the PROJ string, WKT and PROJJSON
+//! readers all apply.
+
+use prelude::*;
+use coordinate_systems::igh::utils::GOODE_HOMOLOSINE_LOBES;
+
+/// The projection of a coordinate system, with its parameters
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum Description {
+    LonLat,
+    Utm(UTMSystem),
+    Ups(UPSSystem),
+    Mercator,
+    WebMercator,
+    TransverseMercator(TransverseMercatorSystem),
+    LambertConic(LambertConformalConicSystem),
+    Geostationary(GeostationarySystem),
+    Krovak(KrovakSystem),
+    /// Interrupted Goode Homolosine with the standard lobes (`GOODE_HOMOLOSINE_LOBES`)
+    GoodeHomolosine,
+    Healpix(HealpixSystem),
+    RHealpix(RHealpixSystem),
+}
+
+/// A parameter value that a projection is undefined for
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum Degenerate {
+    /// The scale factor isn't positive
+    ScaleFactor(f64),
+    /// The satellite height isn't positive
+    SatelliteHeight(f64),
+    /// The standard parallels (lat_1, lat_2) are symmetric about the equator,
+    /// which flattens the cone of a conic projection
+    StandardParallels(f64, f64),
+}
+
+impl Description {
+    /// Describes the CRS, `None` for coordinate systems without fixed parameters:
+    /// `AdjustedSystem` (describe its inner CRS instead), `AutoUTMSystem` and
+    /// Goode Homolosine with custom lobes
+    pub(crate) fn of(crs: &dyn Crs) -> Option<Description> {
+        let any = crs.as_any();
+
+        let description = if any.is::<LonLatSystem>() {
+            Description::LonLat
+        } else if let Some(&system) = any.downcast_ref::<UTMSystem>() {
+            Description::Utm(system)
+        } else if let Some(&system) = any.downcast_ref::<UPSSystem>() {
+            Description::Ups(system)
+        } else if any.is::<MercatorSystem>() {
+            Description::Mercator
+        } else if any.is::<WebMercatorSystem>() {
+            Description::WebMercator
+        } else if let Some(&system) = any.downcast_ref::<TransverseMercatorSystem>() {
+            Description::TransverseMercator(system)
+        } else if let Some(&system) = any.downcast_ref::<LambertConformalConicSystem>() {
+            Description::LambertConic(system)
+        } else if let Some(&system) = any.downcast_ref::<GeostationarySystem>() {
+            Description::Geostationary(system)
+        } else if let Some(&system) = any.downcast_ref::<KrovakSystem>() {
+            Description::Krovak(system)
+        } else if any.downcast_ref::<GoodeHomolosineSystem>().is_some_and(|s| s.lobes == &GOODE_HOMOLOSINE_LOBES[..]) {
+            Description::GoodeHomolosine
+        } else if let Some(&system) = any.downcast_ref::<HealpixSystem>() {
+            Description::Healpix(system)
+        } else if let Some(&system) = any.downcast_ref::<RHealpixSystem>() {
+            Description::RHealpix(system)
+        } else {
+            return None;
+        };
+
+        Some(description)
+    }
+
+    /// Checks that the projection is defined for the parameters
+    pub(crate) fn validate(self) -> Result<(), Degenerate> {
+        use self::Description::*;
+        match self {
+            TransverseMercator(system) if system.scale_factor <= 0.0 => Err(Degenerate::ScaleFactor(system.scale_factor)),
+            LambertConic(system) if system.scale_factor <= 0.0 => Err(Degenerate::ScaleFactor(system.scale_factor)),
+            LambertConic(system) if (system.lat_1 + system.lat_2).abs() < 1e-10 =>
+                Err(Degenerate::StandardParallels(system.lat_1, system.lat_2)),
+            Geostationary(system) if system.satellite_height <= 0.0 => Err(Degenerate::SatelliteHeight(system.satellite_height)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Creates the described coordinate system
+    pub(crate) fn to_crs(self) -> Box<dyn Crs> {
+        use self::Description::*;
+        match self {
+            LonLat => Box::new(LonLatSystem),
+            Utm(system) => Box::new(system),
+            Ups(system) => Box::new(system),
+            Mercator => Box::new(MercatorSystem),
+            WebMercator => Box::new(WebMercatorSystem),
+            TransverseMercator(system) => Box::new(system),
+            LambertConic(system) => Box::new(system),
+            Geostationary(system) => Box::new(system),
+            Krovak(system) => Box::new(system),
+            GoodeHomolosine => Box::new(GoodeHomolosineSystem { lobes: &GOODE_HOMOLOSINE_LOBES }),
+            Healpix(system) => Box::new(system),
+            RHealpix(system) => Box::new(system),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proj_string::{parse_proj_string, to_proj_string, ProjStringError};
+    use projjson::{parse_projjson, to_projjson, ProjJsonError};
+    use wkt::{parse_wkt, to_wkt, WktError, WktVersion};
+
+    const FLAT_CONE: LambertConformalConicSystem = LambertConformalConicSystem {
+        lat_0: 0.0, lon_0: 10.0, lat_1: 30.0, lat_2: -30.0,
+        scale_factor: 1.0, false_easting: 0.0, false_northing: 0.0,
+    };
+
+    const ZERO_SCALE: TransverseMercatorSystem = TransverseMercatorSystem {
+        lat_0: 0.0, lon_0: 10.0, scale_factor: 0.0, false_easting: 0.0, false_northing: 0.0,
+    };
+
+    const ZERO_HEIGHT: GeostationarySystem = GeostationarySystem {
+        satellite_height: 0.0, sub_satellite_lon: 0.0, sweep_axis: SweepAxis::Y,
+    };
+
+    #[test]
+    fn validate() {
+        assert_eq!(Description::LambertConic(FLAT_CONE).validate(), Err(Degenerate::StandardParallels(30.0, -30.0)));
+        assert_eq!(Description::TransverseMercator(ZERO_SCALE).validate(), Err(Degenerate::ScaleFactor(0.0)));
+        assert_eq!(Description::Geostationary(ZERO_HEIGHT).validate(), Err(Degenerate::SatelliteHeight(0.0)));
+        assert_eq!(Description::LambertConic(LAMBERT_93).validate(), Ok(()));
+        assert_eq!(Description::TransverseMercator(BRITISH_NATIONAL_GRID).validate(), Ok(()));
+    }
+
+    #[test]
+    fn readers_reject_what_the_writers_write() {
+        let ellipsoid = &WGS_1984_ELLIPSOID;
+
+        let proj = |crs: &dyn Crs| parse_proj_string(&to_proj_string(crs, ellipsoid, None).unwrap()).map(|_| ()).unwrap_err();
+        assert_eq!(proj(&FLAT_CONE), ProjStringError::InvalidValue("lat_2".to_string(), "-30".to_string()));
+        assert_eq!(proj(&ZERO_SCALE), ProjStringError::InvalidValue("k".to_string(), "0".to_string()));
+        assert_eq!(proj(&ZERO_HEIGHT), ProjStringError::InvalidValue("h".to_string(), "0".to_string()));
+
+        let wkt = |crs: &dyn Crs| parse_wkt(&to_wkt(crs, ellipsoid, None, WktVersion::Wkt1).unwrap()).map(|_| ()).unwrap_err();
+        assert_eq!(wkt(&FLAT_CONE), WktError::InvalidValue("standard_parallel_2".to_string(), "-30".to_string()));
+        assert_eq!(wkt(&ZERO_SCALE), WktError::InvalidValue("scale_factor".to_string(), "0".to_string()));
+
+        let projjson = |crs: &dyn Crs| parse_projjson(&to_projjson(crs, ellipsoid, None).unwrap()).map(|_| ()).unwrap_err();
+        assert_eq!(projjson(&FLAT_CONE), ProjJsonError::InvalidValue("Latitude of 2nd standard parallel".to_string(), "-30".to_string()));
+        assert_eq!(projjson(&ZERO_SCALE), ProjJsonError::InvalidValue("Scale factor at natural origin".to_string(), "0".to_string()));
+        assert_eq!(projjson(&ZERO_HEIGHT), ProjJsonError::InvalidValue("Satellite Height".to_string(), "0".to_string()));
+    }
+}
@@ -0,0 +1,298 @@
+//! Geostationary satellite view projection
+//!
+//! Implementation ported from the `geos` projection of PROJ.4,
+//! which is based on:
+//!
+//! LRIT/HRIT Global Specification, CGMS 03, Issue 2.6 (Section 4.4.3.2)
+//!
+//! The projected coordinates are the scanning angles of the satellite
+//! sensor, multiplied by the height of the satellite above the ellipsoid.
+//! Points that can't be seen from the satellite (or pixels that look past
+//! the earth disk) are projected to `(NaN, NaN)`.
+
+use prelude::*;
+
+/// Axis along which the satellite sensor sweeps
+///
+/// GOES satellites sweep along the X axis, while Meteosat and Himawari
+/// sweep along the Y axis.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SweepAxis {
+    /// Sensor sweeps along the X axis (GOES)
+    X,
+    /// Sensor sweeps along the Y axis (Meteosat, Himawari)
+    Y,
+}
+
+/// Geostationary satellite view
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GeostationarySystem {
+    /// Height of the satellite above the ellipsoid surface, in meters
+    /// (for example `35_786_023.0` for GOES-R or `35_785_831.0` for Meteosat)
+    pub satellite_height: f64,
+    /// Longitude of the sub-satellite point, in degrees
+    pub sub_satellite_lon: f64,
+    /// Sweep axis of the satellite sensor
+    pub sweep_axis: SweepAxis,
+}
+
+pub mod utils {
+
+    use prelude::*;
+
+    /// Returns whether the given point (lon / lat, in degrees)
+    /// can be seen by the satellite
+    #[inline]
+    pub fn is_visible(lon: f64, lat: f64, ellipsoid: &Ellipsoid, system: &GeostationarySystem)
+                      -> bool
+    {
+        lonlat_to_geos_xy(lon, lat, ellipsoid, system).is_some()
+    }
+
+    /// Returns whether the given scanning position (x, y, in meters)
+    /// looks at the earth disk
+    #[inline]
+    pub fn is_on_disk(x: f64, y: f64, ellipsoid: &Ellipsoid, system: &GeostationarySystem)
+                      -> bool
+    {
+        geos_xy_to_lonlat(x, y, ellipsoid, system).is_some()
+    }
+
+    /// Function that converts a single lon-lat pair from (lon / lat) to the
+    /// geostationary view (x, y)
+    ///
+    /// Returns: `None` if the point is not visible from the satellite
+    #[inline]
+    pub fn lonlat_to_geos_xy(lon: f64, lat: f64, ellipsoid: &Ellipsoid, system: &GeostationarySystem)
+                             -> Option<(f64, f64)>
+    {
+        let radius_g_1 = system.satellite_height / ellipsoid.a;
+        let radius_g = 1.0 + radius_g_1;
+        let radius_p = ellipsoid.b / ellipsoid.a;
+        let radius_p2 = radius_p * radius_p;
+
+        let lambda = (lon - system.sub_satellite_lon).to_radians();
+
+        // Calculation of the geocentric latitude
+        let phi = (radius_p2 * lat.to_radians().tan()).atan();
+
+        // Vector from the center of the earth to the point on the surface
+        let r = radius_p / (radius_p * phi.cos()).hypot(phi.sin());
+        let vx = r * lambda.cos() * phi.cos();
+        let vy = r * lambda.sin() * phi.cos();
+        let vz = r * phi.sin();
+
+        // Check visibility
+        if (radius_g - vx) * vx - vy * vy - vz * vz / radius_p2 < 0.0 {
+            return None;
+        }
+
+        // Calculation based on the view angles from the satellite
+        let tmp = radius_g - vx;
+        let (x, y) = match system.sweep_axis {
+            SweepAxis::X => (
+                radius_g_1 * (vy / vz.hypot(tmp)).atan(),
+                radius_g_1 * (vz / tmp).atan()
+            ),
+            SweepAxis::Y => (
+                radius_g_1 * (vy / tmp).atan(),
+                radius_g_1 * (vz / vy.hypot(tmp)).atan()
+            ),
+        };
+
+        Some((x * ellipsoid.a, y * ellipsoid.a))
+    }
+
+    /// Function that converts a single X-Y value from the geostationary view
+    /// to lon / lat
+    ///
+    /// Returns: `None` if the scanning position does not look at the earth disk
+    #[inline]
+    pub fn geos_xy_to_lonlat(x: f64, y: f64, ellipsoid: &Ellipsoid, system: &GeostationarySystem)
+                             -> Option<(f64, f64)>
+    {
+        let radius_g_1 = system.satellite_height / ellipsoid.a;
+        let radius_g = 1.0 + radius_g_1;
+        let radius_p = ellipsoid.b / ellipsoid.a;
+        let radius_p2 = radius_p * radius_p;
+        let c = radius_g * radius_g - 1.0;
+
+        let x = x / ellipsoid.a;
+        let y = y / ellipsoid.a;
+
+        // Direction of the view vector from the satellite
+        let mut vx = -1.0;
+        let (mut vy, mut vz) = match system.sweep_axis {
+            SweepAxis::X => {
+                let vz = (y / radius_g_1).tan();
+                ((x / radius_g_1).tan() * 1.0_f64.hypot(vz), vz)
+            },
+            SweepAxis::Y => {
+                let vy = (x / radius_g_1).tan();
+                (vy, (y / radius_g_1).tan() * 1.0_f64.hypot(vy))
+            },
+        };
+
+        // Intersect the view vector with the ellipsoid
+        let a = vz / radius_p;
+        let a = vy * vy + a * a + vx * vx;
+        let b = 2.0 * radius_g * vx;
+        let det = (b * b) - 4.0 * a * c;
+
+        if det < 0.0 {
+            return None;
+        }
+
+        let k = (-b - det.sqrt()) / (2.0 * a);
+        vx = radius_g + k * vx;
+        vy *= k;
+        vz *= k;
+
+        let lambda = vy.atan2(vx);
+        let phi = (vz * lambda.cos() / vx).atan();
+        let phi = (phi.tan() / radius_p2).atan();
+
+        // The visible disc may cross the antimeridian
+        let lon = lambda.to_degrees() + system.sub_satellite_lon;
+        let lon = if lon > 180.0 { lon - 360.0 } else if lon < -180.0 { lon + 360.0 } else { lon };
+
+        Some((lon, phi.to_degrees()))
+    }
+}
+
+impl ToLonLat for GeostationarySystem {
+    fn to_lon_lat(&self, mut data: Vec<(f64, f64)>, ellipsoid: &Ellipsoid, strategy: &mut MultithreadingStrategy)
+                  -> LonLatBuf
+    {
+        let system = *self;
+
+        match *strategy {
+            SingleCore => {
+                for &mut (ref mut x, ref mut y) in data.iter_mut() {
+                    let (lon, lat) = utils::geos_xy_to_lonlat(*x, *y, ellipsoid, &system)
+                        .unwrap_or((f64::NAN, f64::NAN));
+                    *x = lon; *y = lat;
+                }
+            },
+            #[cfg(all(not(target_arch = "wasm32"), feature = "scoped_threadpool"))]
+            MultiCore(ref mut thread_pool) => {
+                let system = &system;
+                thread_pool.scoped(|scoped| {
+                    for &mut (ref mut x, ref mut y) in data.iter_mut() {
+                        scoped.execute(move || {
+                            let (lon, lat) = utils::geos_xy_to_lonlat(*x, *y, ellipsoid, system)
+                                .unwrap_or((f64::NAN, f64::NAN));
+                            *x = lon; *y = lat;
+                        });
+                    }
+                });
+            },
+        }
+
+        LonLatBuf {
+            data,
+            ellipsoid: *ellipsoid,
+        }
+    }
+}
+
+impl FromLonLat for GeostationarySystem {
+    fn from_lon_lat(&self, mut data: Vec<(f64, f64)>, ellipsoid: &Ellipsoid, strategy: &mut MultithreadingStrategy)
+                    -> CoordinateBuf
+    {
+        let system = *self;
+
+        match *strategy {
+            SingleCore => {
+                for &mut (ref mut lon, ref mut lat) in data.iter_mut() {
+                    let (x, y) = utils::lonlat_to_geos_xy(*lon, *lat, ellipsoid, &system)
+                        .unwrap_or((f64::NAN, f64::NAN));
+                    *lon = x; *lat = y;
+                }
+            },
+            #[cfg(all(not(target_arch = "wasm32"), feature = "scoped_threadpool"))]
+            MultiCore(ref mut thread_pool) => {
+                let system = &system;
+                thread_pool.scoped(|scoped| {
+                    for &mut (ref mut lon, ref mut lat) in data.iter_mut() {
+                        scoped.execute(move || {
+                            let (x, y) = utils::lonlat_to_geos_xy(*lon, *lat, ellipsoid, system)
+                                .unwrap_or((f64::NAN, f64::NAN));
+                            *lon = x; *lat = y;
+                        });
+                    }
+                });
+            },
+        }
+
+        CoordinateBuf {
+            data,
+            crs: Box::new(system),
+            ellipsoid: *ellipsoid,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::utils::*;
+
+    const METEOSAT: GeostationarySystem = GeostationarySystem {
+        satellite_height: 35_785_831.0, sub_satellite_lon: 0.0, sweep_axis: SweepAxis::Y,
+    };
+
+    const GOES: GeostationarySystem = GeostationarySystem {
+        satellite_height: 35_785_831.0, sub_satellite_lon: 0.0, sweep_axis: SweepAxis::X,
+    };
+
+    /// GRS 80 with the exact semi-minor axis, `GRS_1980_ELLIPSOID` rounds it to 0.1 mm
+    fn grs80() -> Ellipsoid {
+        Ellipsoid::from_axes(6_378_137.0, 6_378_137.0 * (1.0 - 1.0 / 298.257_222_101))
+    }
+
+    // builtins.gie of PROJ, +proj=geos +ellps=GRS80 +h=35785831 (+sweep=x)
+    #[test]
+    fn reference_points() {
+        for &(system, x, y) in &[(METEOSAT, 222_527.070_365_800, 110_551.303_413_329),
+                                 (GOES, 222_526.008_514_683, 110_553.440_792_600)] {
+            for &(sx, sy) in &[(1.0, 1.0), (1.0, -1.0), (-1.0, 1.0), (-1.0, -1.0)] {
+                let (px, py) = lonlat_to_geos_xy(2.0 * sx, sy, &grs80(), &system).unwrap();
+                assert!((px - sx * x).abs() < 1e-6 && (py - sy * y).abs() < 1e-6, "{:?}: ({}, {})", system.sweep_axis, px, py);
+
+                let (lon, lat) = geos_xy_to_lonlat(px, py, &grs80(), &system).unwrap();
+                assert!((lon - 2.0 * sx).abs() < 1e-12 && (lat - sy).abs() < 1e-12);
+            }
+        }
+
+        let (lon, lat) = geos_xy_to_lonlat(200.0, 100.0, &grs80(), &METEOSAT).unwrap();
+        assert!((lon - 0.001_796_631).abs() < 1e-9 && (lat - 0.000_904_369).abs() < 1e-9, "({}, {})", lon, lat);
+    }
+
+    #[test]
+    fn visibility() {
+        assert!(is_visible(80.0, 0.0, &grs80(), &METEOSAT));
+        assert!(!is_visible(90.0, 0.0, &grs80(), &METEOSAT));
+        assert!(!is_visible(-157.9, 21.3, &grs80(), &METEOSAT));
+        assert!(!is_visible(0.0, 85.0, &grs80(), &METEOSAT));
+        assert!(is_on_disk(0.0, 0.0, &grs80(), &METEOSAT));
+        assert!(!is_on_disk(5_500_000.0, 0.0, &grs80(), &METEOSAT));
+
+        let buf = METEOSAT.from_lon_lat(vec![(0.0, 0.0), (180.0, 0.0)], &grs80(), &mut MultithreadingStrategy::SingleCore);
+        assert_eq!(buf.data[0], (0.0, 0.0));
+        assert!(buf.data[1].0.is_nan() && buf.data[1].1.is_nan());
+    }
+
+    #[test]
+    fn sub_satellite_longitude() {
+        let system = GeostationarySystem { sub_satellite_lon: -137.0, ..GOES };
+        let (x, y) = lonlat_to_geos_xy(-135.0, 1.0, &grs80(), &system).unwrap();
+        assert!((x - 222_526.008_514_683).abs() < 1e-6 && (y - 110_553.440_792_600).abs() < 1e-6);
+
+        // the disk of a satellite near the antimeridian crosses it
+        let system = GeostationarySystem { sub_satellite_lon: 179.0, ..GOES };
+        let (x, y) = lonlat_to_geos_xy(-179.0, 1.0, &grs80(), &system).unwrap();
+        let (lon, lat) = geos_xy_to_lonlat(x, y, &grs80(), &system).unwrap();
+        assert!((lon + 179.0).abs() < 1e-12 && (lat - 1.0).abs() < 1e-12, "({}, {})", lon, lat);
+    }
+}
@@ -0,0 +1,670 @@
+//! HEALPix and rHEALPix projections
+//!
+//! Implementation taken from:
+//!
+//! Calabretta, M. R. and Roukema, B. F., 2007, Mapping on the HEALPix grid,
+//! Monthly Notices of the Royal Astronomical Society, v. 381, p. 865-872
+//!
+//! Gibb, R. G., Raichev, A. and Speth, M., 2013, The rHEALPix Discrete Global Grid System
+//!
+//! Both projections are equal-area. On an ellipsoid, the authalic latitude and
+//! the authalic radius are used, so that areas are preserved as well.
+//!
+//! The `utils` module contains helpers to compute the cell IDs of the
+//! HEALPix (nested scheme) and rHEALPix grids from projected coordinates.
+
+use prelude::*;
+
+/// HEALPix projection
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct HealpixSystem {
+    /// Central meridian, in degrees (cell IDs assume `0.0`)
+    pub lon_0: f64,
+}
+
+/// rHEALPix projection
+///
+/// The polar triangles of the HEALPix projection are rearranged into one
+/// square per pole, placed above / below one of the four equatorial squares.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RHealpixSystem {
+    /// Central meridian, in degrees
+    pub lon_0: f64,
+    /// Position of the north polar square (0 to 3, from west to east)
+    pub north_square: u8,
+    /// Position of the south polar square (0 to 3, from west to east)
+    pub south_square: u8,
+}
+
+pub mod utils {
+
+    use prelude::*;
+    use std::f64::consts::{PI, FRAC_PI_2, FRAC_PI_4};
+
+    /// Face names of the rHEALPix grid: the north square, the four
+    /// equatorial squares from west to east and the south square
+    pub const RHEALPIX_FACES: [char; 6] = ['N', 'O', 'P', 'Q', 'R', 'S'];
+
+    /// Maximum order (nside = 2 ^ order) of HEALPix cell IDs
+    pub const HEALPIX_MAX_ORDER: u8 = 29;
+
+    /// Number of subdivisions per side of a rHEALPix cell
+    const RHEALPIX_NSIDE: u64 = 3;
+
+    #[inline]
+    fn wrap_lambda(lambda: f64) -> f64 {
+        let wrapped = (lambda + PI) % (2.0 * PI);
+        if wrapped < 0.0 { wrapped + PI } else { wrapped - PI }
+    }
+
+    // Index (0 to 3) of the polar triangle / column x falls into
+    #[inline]
+    fn column(x: f64) -> u8 {
+        ((x + PI) / FRAC_PI_2).floor().clamp(0.0, 3.0) as u8
+    }
+
+    // Center of the given column, where the apex of its polar triangle lies
+    #[inline]
+    fn column_center(column: u8) -> f64 {
+        -3.0 * FRAC_PI_4 + FRAC_PI_2 * column as f64
+    }
+
+    /// HEALPix projection on the unit sphere (lambda, phi in radians)
+    #[inline]
+    pub fn healpix_sphere_forward(lambda: f64, phi: f64)
+                                  -> (f64, f64)
+    {
+        let lambda = wrap_lambda(lambda);
+
+        if phi.abs() <= (2.0_f64 / 3.0).asin() {
+            // equatorial region: cylindrical equal-area
+            (lambda, 3.0 * PI / 8.0 * phi.sin())
+        } else {
+            // polar region: collignon
+            let sigma = (3.0 * (1.0 - phi.sin().abs())).sqrt();
+            let lambda_c = column_center(column(lambda));
+            (lambda_c + (lambda - lambda_c) * sigma, FRAC_PI_4.copysign(phi) * (2.0 - sigma))
+        }
+    }
+
+    /// Inverse HEALPix projection on the unit sphere
+    ///
+    /// Returns (NaN, NaN) if (x, y) lies outside of the projected sphere
+    #[inline]
+    pub fn healpix_sphere_inverse(x: f64, y: f64)
+                                  -> (f64, f64)
+    {
+        const EPS: f64 = 1e-12;
+
+        if !(-PI - EPS..=PI + EPS).contains(&x) || y.abs() > FRAC_PI_2 + EPS {
+            return (f64::NAN, f64::NAN);
+        }
+
+        if y.abs() <= FRAC_PI_4 {
+            return (x, (8.0 * y / (3.0 * PI)).asin());
+        }
+
+        let x_c = column_center(column(x));
+        let tau = (2.0 - 4.0 * y.abs() / PI).max(0.0);
+
+        // outside of the polar triangle
+        if (x - x_c).abs() > tau * FRAC_PI_4 + EPS {
+            return (f64::NAN, f64::NAN);
+        }
+
+        let lambda = if tau < EPS { x_c } else { x_c + (x - x_c) / tau };
+        let phi = (1.0 - tau * tau / 3.0).asin().copysign(y);
+
+        (lambda, phi)
+    }
+
+    #[inline]
+    fn rotate_ccw(x: f64, y: f64, quarter_turns: u8) -> (f64, f64) {
+        match quarter_turns % 4 {
+            0 => (x, y),
+            1 => (-y, x),
+            2 => (-x, -y),
+            _ => (y, -x),
+        }
+    }
+
+    // Position of the polar square (0 to 3) and the sign of the pole (1.0 = north)
+    // for a point in the polar region of the rHEALPix projection
+    #[inline]
+    fn polar_square(y: f64, north_square: u8, south_square: u8) -> (u8, f64) {
+        if y > 0.0 { (north_square, 1.0) } else { (south_square, -1.0) }
+    }
+
+    /// Rearranges the polar triangles of the HEALPix projection (unit sphere)
+    /// into the polar squares of the rHEALPix projection
+    pub fn combine_caps(x: f64, y: f64, north_square: u8, south_square: u8)
+                        -> (f64, f64)
+    {
+        if y.abs() <= FRAC_PI_4 {
+            return (x, y);
+        }
+
+        let (square, pole) = polar_square(y, north_square, south_square);
+        let triangle = column(x);
+        let turns = (triangle + 4 - square) % 4;
+
+        // North: triangles are rotated counter-clockwise around the pole,
+        // south: clockwise
+        let (dx, dy) = (x - column_center(triangle), y - FRAC_PI_2 * pole);
+        let (dx, dy) = if pole > 0.0 { rotate_ccw(dx, dy, turns) } else { rotate_ccw(dx, dy, 4 - turns) };
+
+        (column_center(square) + dx, FRAC_PI_2 * pole + dy)
+    }
+
+    /// Inverse of `combine_caps`
+    ///
+    /// Returns (NaN, NaN) if (x, y) lies outside of the rHEALPix projection
+    pub fn split_caps(x: f64, y: f64, north_square: u8, south_square: u8)
+                      -> (f64, f64)
+    {
+        const EPS: f64 = 1e-12;
+
+        if !(-PI - EPS..=PI + EPS).contains(&x) || y.abs() > 3.0 * FRAC_PI_4 + EPS {
+            return (f64::NAN, f64::NAN);
+        }
+
+        if y.abs() <= FRAC_PI_4 {
+            return (x, y);
+        }
+
+        let (square, pole) = polar_square(y, north_square, south_square);
+
+        if (x - column_center(square)).abs() > FRAC_PI_4 + EPS {
+            return (f64::NAN, f64::NAN);
+        }
+
+        let (dx, dy) = (x - column_center(square), y - FRAC_PI_2 * pole);
+
+        // Which side of the square (counted from the equator, in the
+        // direction of the triangle rotation) the point is on
+        let toward_equator = -dy * pole;
+        let turns = if toward_equator >= dx.abs() {
+            0
+        } else if dx >= toward_equator.abs() {
+            1
+        } else if -toward_equator >= dx.abs() {
+            2
+        } else {
+            3
+        };
+
+        let (dx, dy) = if pole > 0.0 { rotate_ccw(dx, dy, 4 - turns) } else { rotate_ccw(dx, dy, turns) };
+        let triangle = (square + turns) % 4;
+
+        (column_center(triangle) + dx, FRAC_PI_2 * pole + dy)
+    }
+
+    /// Function that converts a single lon-lat pair from (lon / lat) to HEALPix (x, y)
+    /// Returns: (x, y)
+    #[inline]
+    pub fn lonlat_to_healpix_xy(lon: f64, lat: f64, ellipsoid: &Ellipsoid, lon_0: f64)
+                                -> (f64, f64)
+    {
+        if lat.abs() > 90.0 {
+            return (f64::NAN, f64::NAN);
+        }
+
+        let radius = ellipsoid.authalic_radius();
+        let beta = ellipsoid.authalic_latitude(lat.to_radians());
+        let (x, y) = healpix_sphere_forward((lon - lon_0).to_radians(), beta);
+        (x * radius, y * radius)
+    }
+
+    /// Function that converts a single X-Y value from HEALPix to lon / lat
+    /// Returns: (lon, lat)
+    #[inline]
+    pub fn healpix_xy_to_lonlat(x: f64, y: f64, ellipsoid: &Ellipsoid, lon_0: f64)
+                                -> (f64, f64)
+    {
+        let radius = ellipsoid.authalic_radius();
+        let (lambda, beta) = healpix_sphere_inverse(x / radius, y / radius);
+        (lambda.to_degrees() + lon_0, ellipsoid.geodetic_latitude(beta).to_degrees())
+    }
+
+    /// Function that converts a single lon-lat pair from (lon / lat) to rHEALPix (x, y)
+    /// Returns: (x, y)
+    #[inline]
+    pub fn lonlat_to_rhealpix_xy(lon: f64, lat: f64, ellipsoid: &Ellipsoid, system: &RHealpixSystem)
+                                 -> (f64, f64)
+    {
+        if lat.abs() > 90.0 {
+            return (f64::NAN, f64::NAN);
+        }
+
+        let radius = ellipsoid.authalic_radius();
+        let beta = ellipsoid.authalic_latitude(lat.to_radians());
+        let (x, y) = healpix_sphere_forward((lon - system.lon_0).to_radians(), beta);
+        let (x, y) = combine_caps(x, y, system.north_square, system.south_square);
+        (x * radius, y * radius)
+    }
+
+    /// Function that converts a single X-Y value from rHEALPix to lon / lat
+    /// Returns: (lon, lat)
+    #[inline]
+    pub fn rhealpix_xy_to_lonlat(x: f64, y: f64, ellipsoid: &Ellipsoid, system: &RHealpixSystem)
+                                 -> (f64, f64)
+    {
+        let radius = ellipsoid.authalic_radius();
+        let (x, y) = split_caps(x / radius, y / radius, system.north_square, system.south_square);
+        let (lambda, beta) = healpix_sphere_inverse(x, y);
+        (lambda.to_degrees() + system.lon_0, ellipsoid.geodetic_latitude(beta).to_degrees())
+    }
+
+    // HEALPix base pixels in units of PI / 4: (face, center x, center y)
+    const HEALPIX_FACE_CENTERS: [(u64, f64, f64); 12] = [
+        (0, 1.0, 1.0), (1, 3.0, 1.0), (2, -3.0, 1.0), (3, -1.0, 1.0),
+        (4, 0.0, 0.0), (5, 2.0, 0.0), (6, 4.0, 0.0), (7, -2.0, 0.0),
+        (8, 1.0, -1.0), (9, 3.0, -1.0), (10, -3.0, -1.0), (11, -1.0, -1.0),
+    ];
+
+    // Interleaves the bits of x (even bits) and y (odd bits)
+    #[inline]
+    fn interleave(x: u64, y: u64) -> u64 {
+        let mut result = 0;
+        for bit in 0..32 {
+            result |= ((x >> bit) & 1) << (2 * bit);
+            result |= ((y >> bit) & 1) << (2 * bit + 1);
+        }
+        result
+    }
+
+    #[inline]
+    fn deinterleave(value: u64) -> (u64, u64) {
+        let (mut x, mut y) = (0, 0);
+        for bit in 0..32 {
+            x |= ((value >> (2 * bit)) & 1) << bit;
+            y |= ((value >> (2 * bit + 1)) & 1) << bit;
+        }
+        (x, y)
+    }
+
+    /// Computes the HEALPix cell ID (nested scheme, nside = 2 ^ order)
+    /// from projected HEALPix coordinates (with `lon_0 = 0.0`)
+    ///
+    /// Returns: `None` if the coordinates are outside of the projected sphere
+    /// or the order is larger than `HEALPIX_MAX_ORDER`
+    pub fn healpix_cell_id(x: f64, y: f64, ellipsoid: &Ellipsoid, order: u8)
+                           -> Option<u64>
+    {
+        if order > HEALPIX_MAX_ORDER {
+            return None;
+        }
+
+        let radius = ellipsoid.authalic_radius();
+        let (x, y) = (x / radius, y / radius);
+
+        if !healpix_sphere_inverse(x, y).0.is_finite() {
+            return None;
+        }
+
+        let u = x / FRAC_PI_4;
+        let v = y / FRAC_PI_4;
+        let nside = 1_u64 << order;
+
+        HEALPIX_FACE_CENTERS.iter().filter_map(|&(face, center_u, center_v)| {
+            // distance to the face center, wrapped around the antimeridian
+            let mut du = u - center_u;
+            if du < -4.0 { du += 8.0; }
+            if du >= 4.0 { du -= 8.0; }
+            let dv = v - center_v;
+
+            if du.abs() + dv.abs() > 1.0 + 1e-12 {
+                return None;
+            }
+
+            // The faces are squares rotated by 45°: x-index grows towards
+            // the north east, y-index towards the north west
+            let ix = ((du + dv + 1.0) / 2.0 * nside as f64).floor().clamp(0.0, (nside - 1) as f64) as u64;
+            let iy = ((dv - du + 1.0) / 2.0 * nside as f64).floor().clamp(0.0, (nside - 1) as f64) as u64;
+
+            Some(face * nside * nside + interleave(ix, iy))
+        }).next()
+    }
+
+    /// Returns the center of a HEALPix cell (nested scheme) in projected HEALPix coordinates
+    pub fn healpix_cell_center(cell_id: u64, ellipsoid: &Ellipsoid, order: u8)
+                               -> Option<(f64, f64)>
+    {
+        if order > HEALPIX_MAX_ORDER {
+            return None;
+        }
+
+        let nside = 1_u64 << order;
+        let face = cell_id / (nside * nside);
+
+        if face > 11 {
+            return None;
+        }
+
+        let (ix, iy) = deinterleave(cell_id % (nside * nside));
+        let s = (ix as f64 + 0.5) / nside as f64;
+        let t = (iy as f64 + 0.5) / nside as f64;
+
+        let (_, center_u, center_v) = HEALPIX_FACE_CENTERS[face as usize];
+        let mut u = center_u + s - t;
+        if u >= 4.0 { u -= 8.0; }
+        let v = center_v + s + t - 1.0;
+
+        let radius = ellipsoid.authalic_radius();
+        Some((u * FRAC_PI_4 * radius, v * FRAC_PI_4 * radius))
+    }
+
+    /// Computes the rHEALPix cell ID (such as `"P31"`) at the given resolution
+    /// from projected rHEALPix coordinates
+    ///
+    /// The ID consists of the face name, followed by one digit (0 to 8) per
+    /// resolution, numbering the 3x3 sub-cells row by row from the north west corner.
+    ///
+    /// Returns: `None` if the coordinates are outside of the rHEALPix projection
+    pub fn rhealpix_cell_id(x: f64, y: f64, ellipsoid: &Ellipsoid, system: &RHealpixSystem, resolution: u8)
+                            -> Option<String>
+    {
+        let radius = ellipsoid.authalic_radius();
+        let (x, y) = (x / radius, y / radius);
+
+        if !split_caps(x, y, system.north_square, system.south_square).0.is_finite() {
+            return None;
+        }
+
+        let col = column(x);
+        let (face, top) = if y > FRAC_PI_4 {
+            if col != system.north_square { return None; }
+            (0, 3.0 * FRAC_PI_4)
+        } else if y < -FRAC_PI_4 {
+            if col != system.south_square { return None; }
+            (5, -FRAC_PI_4)
+        } else {
+            (1 + col as usize, FRAC_PI_4)
+        };
+
+        let left = -PI + FRAC_PI_2 * col as f64;
+
+        // Position within the face, from the north west corner
+        let mut s = ((x - left) / FRAC_PI_2).clamp(0.0, 1.0);
+        let mut t = ((top - y) / FRAC_PI_2).clamp(0.0, 1.0);
+
+        let mut id = String::with_capacity(1 + resolution as usize);
+        id.push(RHEALPIX_FACES[face]);
+
+        for _ in 0..resolution {
+            let n = RHEALPIX_NSIDE as f64;
+            let col = (s * n).floor().min(n - 1.0);
+            let row = (t * n).floor().min(n - 1.0);
+            id.push(::std::char::from_digit((row * n + col) as u32, 10).unwrap_or('0'));
+            s = s * n - col;
+            t = t * n - row;
+        }
+
+        Some(id)
+    }
+
+    /// Returns the center of a rHEALPix cell (such as `"P31"`) in projected rHEALPix coordinates
+    pub fn rhealpix_cell_center(cell_id: &str, ellipsoid: &Ellipsoid, system: &RHealpixSystem)
+                                -> Option<(f64, f64)>
+    {
+        let mut chars = cell_id.chars();
+        let face_name = chars.next()?;
+        let face = RHEALPIX_FACES.iter().position(|&f| f == face_name)?;
+
+        let (col, top) = match face {
+            0 => (system.north_square, 3.0 * FRAC_PI_4),
+            5 => (system.south_square, -FRAC_PI_4),
+            _ => (face as u8 - 1, FRAC_PI_4),
+        };
+
+        let (mut x, mut y) = (-PI + FRAC_PI_2 * col as f64, top);
+        let mut size = FRAC_PI_2;
+
+        for c in chars {
+            let digit = c.to_digit(10).filter(|&d| d < 9)? as u64;
+            size /= RHEALPIX_NSIDE as f64;
+            x += (digit % RHEALPIX_NSIDE) as f64 * size;
+            y -= (digit / RHEALPIX_NSIDE) as f64 * size;
+        }
+
+        let radius = ellipsoid.authalic_radius();
+        Some(((x + size / 2.0) * radius, (y - size / 2.0) * radius))
+    }
+}
+
+impl ToLonLat for HealpixSystem {
+    fn to_lon_lat(&self, mut data: Vec<(f64, f64)>, ellipsoid: &Ellipsoid, strategy: &mut MultithreadingStrategy)
+                  -> LonLatBuf
+    {
+        let lon_0 = self.lon_0;
+
+        match *strategy {
+            SingleCore => {
+                for &mut (ref mut x, ref mut y) in data.iter_mut() {
+                    let (lon, lat) = utils::healpix_xy_to_lonlat(*x, *y, ellipsoid, lon_0);
+                    *x = lon; *y = lat;
+                }
+            },
+            #[cfg(all(not(target_arch = "wasm32"), feature = "scoped_threadpool"))]
+            MultiCore(ref mut thread_pool) => {
+                thread_pool.scoped(|scoped| {
+                    for &mut (ref mut x, ref mut y) in data.iter_mut() {
+                        scoped.execute(move || {
+                            let (lon, lat) = utils::healpix_xy_to_lonlat(*x, *y, ellipsoid, lon_0);
+                            *x = lon; *y = lat;
+                        });
+                    }
+                });
+            },
+        }
+
+        LonLatBuf {
+            data,
+            ellipsoid: *ellipsoid,
+        }
+    }
+}
+
+impl FromLonLat for HealpixSystem {
+    fn from_lon_lat(&self, mut data: Vec<(f64, f64)>, ellipsoid: &Ellipsoid, strategy: &mut MultithreadingStrategy)
+                    -> CoordinateBuf
+    {
+        let lon_0 = self.lon_0;
+
+        match *strategy {
+            SingleCore => {
+                for &mut (ref mut lon, ref mut lat) in data.iter_mut() {
+                    let (x, y) = utils::lonlat_to_healpix_xy(*lon, *lat, ellipsoid, lon_0);
+                    *lon = x; *lat = y;
+                }
+            },
+            #[cfg(all(not(target_arch = "wasm32"), feature = "scoped_threadpool"))]
+            MultiCore(ref mut thread_pool) => {
+                thread_pool.scoped(|scoped| {
+                    for &mut (ref mut lon, ref mut lat) in data.iter_mut() {
+                        scoped.execute(move || {
+                            let (x, y) = utils::lonlat_to_healpix_xy(*lon, *lat, ellipsoid, lon_0);
+                            *lon = x; *lat = y;
+                        });
+                    }
+                });
+            },
+        }
+
+        CoordinateBuf {
+            data,
+            crs: Box::new(*self),
+            ellipsoid: *ellipsoid,
+        }
+    }
+}
+
+impl ToLonLat for RHealpixSystem {
+    fn to_lon_lat(&self, mut data: Vec<(f64, f64)>, ellipsoid: &Ellipsoid, strategy: &mut MultithreadingStrategy)
+                  -> LonLatBuf
+    {
+        let system = *self;
+
+        match *strategy {
+            SingleCore => {
+                for &mut (ref mut x, ref mut y) in data.iter_mut() {
+                    let (lon, lat) = utils::rhealpix_xy_to_lonlat(*x, *y, ellipsoid, &system);
+                    *x = lon; *y = lat;
+                }
+            },
+            #[cfg(all(not(target_arch = "wasm32"), feature = "scoped_threadpool"))]
+            MultiCore(ref mut thread_pool) => {
+                let system = &system;
+                thread_pool.scoped(|scoped| {
+                    for &mut (ref mut x, ref mut y) in data.iter_mut() {
+                        scoped.execute(move || {
+                            let (lon, lat) = utils::rhealpix_xy_to_lonlat(*x, *y, ellipsoid, system);
+                            *x = lon; *y = lat;
+                        });
+                    }
+                });
+            },
+        }
+
+        LonLatBuf {
+            data,
+            ellipsoid: *ellipsoid,
+        }
+    }
+}
+
+impl FromLonLat for RHealpixSystem {
+    fn from_lon_lat(&self, mut data: Vec<(f64, f64)>, ellipsoid: &Ellipsoid, strategy: &mut MultithreadingStrategy)
+                    -> CoordinateBuf
+    {
+        let system = *self;
+
+        match *strategy {
+            SingleCore => {
+                for &mut (ref mut lon, ref mut lat) in data.iter_mut() {
+                    let (x, y) = utils::lonlat_to_rhealpix_xy(*lon, *lat, ellipsoid, &system);
+                    *lon = x; *lat = y;
+                }
+            },
+            #[cfg(all(not(target_arch = "wasm32"), feature = "scoped_threadpool"))]
+            MultiCore(ref mut thread_pool) => {
+                let system = &system;
+                thread_pool.scoped(|scoped| {
+                    for &mut (ref mut lon, ref mut lat) in data.iter_mut() {
+                        scoped.execute(move || {
+                            let (x, y) = utils::lonlat_to_rhealpix_xy(*lon, *lat, ellipsoid, system);
+                            *lon = x; *lat = y;
+                        });
+                    }
+                });
+            },
+        }
+
+        CoordinateBuf {
+            data,
+            crs: Box::new(system),
+            ellipsoid: *ellipsoid,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::utils::*;
+    use std::f64::consts::{PI, FRAC_PI_2, FRAC_PI_4};
+
+    const SPHERE: Ellipsoid = Ellipsoid {
+        a: 6_400_000.0, b: 6_400_000.0, flattening: 0.0, usage_region: "", nice_name: "",
+        offset: 0.0, rotation: 0.0, scaling: 0.0,
+    };
+
+    // builtins.gie of PROJ, +proj=healpix +ellps=GRS80 and +R=6400000
+    #[test]
+    fn reference_points() {
+        let grs80 = Ellipsoid::from_axes(6_378_137.0, 6_378_137.0 * (1.0 - 1.0 / 298.257_222_101));
+        for &(ellipsoid, x, y) in &[(grs80, 222_390.103_949_239, 130_406.588_664_482),
+                                    (SPHERE, 223_402.144_255_274, 131_588.044_441_999)] {
+            for &(sx, sy) in &[(1.0, 1.0), (1.0, -1.0), (-1.0, 1.0), (-1.0, -1.0)] {
+                let (px, py) = lonlat_to_healpix_xy(2.0 * sx, sy, &ellipsoid, 0.0);
+                assert!((px - sx * x).abs() < 1e-6 && (py - sy * y).abs() < 1e-6, "({}, {})", px, py);
+
+                let (lon, lat) = healpix_xy_to_lonlat(px, py, &ellipsoid, 0.0);
+                assert!((lon - 2.0 * sx).abs() < 1e-10 && (lat - sy).abs() < 1e-10, "({}, {})", lon, lat);
+            }
+        }
+    }
+
+    // values of ang2pix_nest of the HEALPix library
+    #[test]
+    fn nested_cell_ids() {
+        let points = [(0.1, 0.1), (45.0, 41.8), (10.0, 20.0), (-100.0, -30.0), (170.0, 60.0),
+                      (-60.0, -75.0), (2.0, 1.0), (135.0, 89.0), (-135.0, -50.0)];
+        let expected: [(u8, [u64; 9]); 3] = [
+            (0, [4, 0, 4, 7, 1, 11, 4, 1, 10]),
+            (3, [304, 15, 310, 450, 95, 706, 282, 127, 655]),
+            (10, [4_980_740, 262_143, 5_085_024, 7_383_638, 1_560_509, 11_581_145, 4_632_120, 2_096_947, 10_734_832]),
+        ];
+
+        for &(order, ref ids) in &expected {
+            for (&(lon, lat), &id) in points.iter().zip(ids) {
+                let (x, y) = lonlat_to_healpix_xy(lon, lat, &SPHERE, 0.0);
+                assert_eq!(healpix_cell_id(x, y, &SPHERE, order), Some(id), "({}, {}) at order {}", lon, lat, order);
+
+                let (cx, cy) = healpix_cell_center(id, &SPHERE, order).unwrap();
+                assert_eq!(healpix_cell_id(cx, cy, &SPHERE, order), Some(id));
+            }
+        }
+
+        assert_eq!(healpix_cell_id(0.0, 0.0, &SPHERE, HEALPIX_MAX_ORDER + 1), None);
+        assert_eq!(healpix_cell_center(12, &SPHERE, 0), None);
+        assert_eq!(healpix_cell_id(0.0, 0.99 * FRAC_PI_2 * SPHERE.a, &SPHERE, 0), None);
+    }
+
+    #[test]
+    fn base_cells() {
+        // the centers of the polar base cells lie on the authalic latitude asin(2 / 3)
+        let radius = WGS_1984_ELLIPSOID.authalic_radius();
+        let (x0, y0) = healpix_cell_center(0, &WGS_1984_ELLIPSOID, 0).unwrap();
+        let (x4, y4) = healpix_cell_center(4, &WGS_1984_ELLIPSOID, 0).unwrap();
+        assert!((x0 - radius * FRAC_PI_4).abs() < 1e-6 && (y0 - x0).abs() < 1e-6);
+        assert!(x4.abs() < 1e-9 && y4.abs() < 1e-9);
+
+        let (lon, lat) = healpix_xy_to_lonlat(x0, y0, &WGS_1984_ELLIPSOID, 0.0);
+        let authalic = WGS_1984_ELLIPSOID.authalic_latitude(lat.to_radians());
+        assert!((lon - 45.0).abs() < 1e-9 && (authalic.sin() - 2.0 / 3.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn rhealpix() {
+        let system = RHealpixSystem { lon_0: 0.0, north_square: 0, south_square: 0 };
+        let radius = SPHERE.authalic_radius();
+        let points = [(0.0, 0.0), (-170.0, 80.0), (100.0, 70.0), (30.0, -60.0), (0.0, 90.0), (0.0, -90.0), (179.0, 45.0)];
+
+        for &(lon, lat) in &points {
+            let (x, y) = lonlat_to_rhealpix_xy(lon, lat, &SPHERE, &system);
+            // the polar squares are above / below the westernmost equatorial square
+            if lat.abs() > 42.0 {
+                assert!(x >= -PI * radius - 1e-6 && x <= -FRAC_PI_2 * radius + 1e-6, "({}, {})", lon, lat);
+            }
+            let (plon, plat) = rhealpix_xy_to_lonlat(x, y, &SPHERE, &system);
+            assert!((plat - lat).abs() < 1e-9, "({}, {}) != ({}, {})", plon, plat, lon, lat);
+            if lat.abs() < 90.0 {
+                assert!((plon - lon).abs() < 1e-9, "({}, {}) != ({}, {})", plon, plat, lon, lat);
+            }
+        }
+
+        let (x, y) = lonlat_to_rhealpix_xy(0.0, 0.0, &SPHERE, &system);
+        assert_eq!(rhealpix_cell_id(x, y, &SPHERE, &system, 0).as_deref(), Some("Q"));
+        assert_eq!(rhealpix_cell_id(x, y, &SPHERE, &system, 1).as_deref(), Some("Q3"));
+        let (x, y) = lonlat_to_rhealpix_xy(0.0, 90.0, &SPHERE, &system);
+        assert_eq!(rhealpix_cell_id(x, y, &SPHERE, &system, 2).as_deref(), Some("N44"));
+        let (x, y) = lonlat_to_rhealpix_xy(0.0, -90.0, &SPHERE, &system);
+        assert_eq!(rhealpix_cell_id(x, y, &SPHERE, &system, 1).as_deref(), Some("S4"));
+
+        let (x, y) = rhealpix_cell_center("P31", &SPHERE, &system).unwrap();
+        assert_eq!(rhealpix_cell_id(x, y, &SPHERE, &system, 2).as_deref(), Some("P31"));
+        assert_eq!(rhealpix_cell_center("X1", &SPHERE, &system), None);
+        assert_eq!(rhealpix_cell_center("P9", &SPHERE, &system), None);
+    }
+}
@@ -0,0 +1,370 @@
+//! Interrupted Goode Homolosine projection
+//!
+//! Implementation ported from the `igh` projection of PROJ.4, based on:
+//!
+//! Goode, J. P., 1925, The Homolosine Projection: a new device for portraying
+//! the Earth's surface entire: Annals of the Association of American Geographers, v. 15, p. 119-125
+//!
+//! The Goode Homolosine is a combination of the sinusoidal projection (between
+//! 40°44'11.8"N and 40°44'11.8"S) and the Mollweide projection (towards the poles).
+//! This is a spherical projection, the radius of the sphere is the semi-major axis
+//! of the ellipsoid.
+//!
+//! The `utils` module contains a generic mechanism for interrupted projections:
+//! a list of `Lobe`s and the `interrupted_forward` / `interrupted_inverse` functions,
+//! which can be used with any (uninterrupted) projection. Points that fall into
+//! the gaps between the lobes are projected to `(NaN, NaN)`.
+
+use prelude::*;
+
+/// Interrupted Goode Homolosine
+#[derive(Debug, Copy, Clone)]
+pub struct GoodeHomolosineSystem {
+    /// Lobes of the projection, use `GOODE_HOMOLOSINE_LOBES` for the
+    /// standard (land-optimized) interruptions
+    pub lobes: &'static [utils::Lobe],
+}
+
+pub mod utils {
+
+    use std::f64::consts::{PI, SQRT_2};
+
+    /// Latitude where the sinusoidal and the Mollweide projection meet (40°44'11.8"), in degrees
+    pub const HOMOLOSINE_LATITUDE: f64 = 40.0 + 44.0 / 60.0 + 11.8 / 3600.0;
+
+    /// A single lobe of an interrupted projection
+    ///
+    /// Each lobe covers one hemisphere between `min_lon` and `max_lon`
+    /// and is projected around its own `central_lon`. All values in degrees.
+    #[derive(Debug, Copy, Clone, PartialEq)]
+    pub struct Lobe {
+        pub min_lon: f64,
+        pub max_lon: f64,
+        pub central_lon: f64,
+        /// Whether the lobe is on the northern (true) or southern (false) hemisphere
+        pub north: bool,
+    }
+
+    /// Standard interruptions of the Goode Homolosine (two northern, four southern lobes)
+    pub const GOODE_HOMOLOSINE_LOBES: [Lobe; 6] = [
+        Lobe { min_lon: -180.0, max_lon: -40.0, central_lon: -100.0, north: true },
+        Lobe { min_lon: -40.0, max_lon: 180.0, central_lon: 30.0, north: true },
+        Lobe { min_lon: -180.0, max_lon: -100.0, central_lon: -160.0, north: false },
+        Lobe { min_lon: -100.0, max_lon: -20.0, central_lon: -60.0, north: false },
+        Lobe { min_lon: -20.0, max_lon: 80.0, central_lon: 20.0, north: false },
+        Lobe { min_lon: 80.0, max_lon: 180.0, central_lon: 140.0, north: false },
+    ];
+
+    /// Tolerance (in degrees) when checking if an inverse-projected point lies within its lobe
+    const LOBE_TOLERANCE: f64 = 1e-10;
+
+    /// Finds the lobe the given point (lon / lat, in degrees) falls into
+    #[inline]
+    pub fn find_lobe(lon: f64, lat: f64, lobes: &[Lobe])
+                     -> Option<&Lobe>
+    {
+        let north = lat >= 0.0;
+        lobes.iter().find(|lobe| lobe.north == north && lon >= lobe.min_lon && lon <= lobe.max_lon)
+    }
+
+    /// Projects a point (lon / lat, in degrees) with an interrupted projection.
+    ///
+    /// `project` receives the longitude relative to the central meridian of the lobe
+    /// and the latitude (both in radians) and returns (x, y) for a unit sphere.
+    /// The result is scaled by `radius`.
+    ///
+    /// Returns (NaN, NaN) if the point isn't covered by any lobe.
+    pub fn interrupted_forward<F>(lon: f64, lat: f64, radius: f64, lobes: &[Lobe], project: F)
+                                  -> (f64, f64)
+        where F: Fn(f64, f64) -> (f64, f64)
+    {
+        let lobe = match find_lobe(lon, lat, lobes) {
+            Some(lobe) => lobe,
+            None => return (f64::NAN, f64::NAN),
+        };
+
+        let (x, y) = project((lon - lobe.central_lon).to_radians(), lat.to_radians());
+        ((x + lobe.central_lon.to_radians()) * radius, y * radius)
+    }
+
+    /// Inverse of `interrupted_forward`
+    ///
+    /// `unproject` receives (x, y) on a unit sphere, relative to the central meridian
+    /// of the lobe and returns (lon, lat) in radians, with the longitude
+    /// relative to the central meridian of the lobe.
+    ///
+    /// Returns (NaN, NaN) if (x, y) lies in a gap between the lobes.
+    pub fn interrupted_inverse<F>(x: f64, y: f64, radius: f64, lobes: &[Lobe], unproject: F)
+                                  -> (f64, f64)
+        where F: Fn(f64, f64) -> (f64, f64)
+    {
+        let x = x / radius;
+        let y = y / radius;
+
+        // Lobes are never wider than their longitude range at the equator
+        let north = y >= 0.0;
+        let lobe = match lobes.iter().find(|lobe| {
+            lobe.north == north && x >= lobe.min_lon.to_radians() && x <= lobe.max_lon.to_radians()
+        }) {
+            Some(lobe) => lobe,
+            None => return (f64::NAN, f64::NAN),
+        };
+
+        let (lambda, phi) = unproject(x - lobe.central_lon.to_radians(), y);
+        let lon = lambda.to_degrees() + lobe.central_lon;
+        let lat = phi.to_degrees();
+
+        if !lon.is_finite() || !lat.is_finite() ||
+           lon < lobe.min_lon - LOBE_TOLERANCE || lon > lobe.max_lon + LOBE_TOLERANCE {
+            return (f64::NAN, f64::NAN);
+        }
+
+        (lon, lat)
+    }
+
+    /// Sinusoidal projection on the unit sphere (lambda, phi in radians)
+    #[inline]
+    pub fn sinusoidal_forward(lambda: f64, phi: f64)
+                              -> (f64, f64)
+    {
+        (lambda * phi.cos(), phi)
+    }
+
+    /// Inverse sinusoidal projection on the unit sphere
+    #[inline]
+    pub fn sinusoidal_inverse(x: f64, y: f64)
+                              -> (f64, f64)
+    {
+        if y.abs() > PI / 2.0 {
+            return (f64::NAN, f64::NAN);
+        }
+
+        (x / y.cos(), y)
+    }
+
+    /// Mollweide projection on the unit sphere (lambda, phi in radians)
+    #[inline]
+    pub fn mollweide_forward(lambda: f64, phi: f64)
+                             -> (f64, f64)
+    {
+        const TOL: f64 = 1e-12;
+
+        // Solve 2 * theta + sin(2 * theta) = PI * sin(phi) with Newton's method
+        let k = PI * phi.sin();
+        let mut theta = phi;
+        let mut iteration_cnt = 30;
+
+        loop {
+            let dtheta = (2.0 * theta + (2.0 * theta).sin() - k) / (2.0 + 2.0 * (2.0 * theta).cos());
+            theta -= dtheta;
+
+            if dtheta.abs() < TOL || iteration_cnt == 0 || !theta.is_finite() { break; } else { iteration_cnt -= 1; }
+        }
+
+        // At the poles Newton's method doesn't converge
+        if !theta.is_finite() || (PI / 2.0 - phi.abs()) < TOL {
+            theta = (PI / 2.0).copysign(phi);
+        }
+
+        (2.0 * SQRT_2 / PI * lambda * theta.cos(), SQRT_2 * theta.sin())
+    }
+
+    /// Inverse Mollweide projection on the unit sphere
+    #[inline]
+    pub fn mollweide_inverse(x: f64, y: f64)
+                             -> (f64, f64)
+    {
+        let theta = (y / SQRT_2).asin();
+        let phi = ((2.0 * theta + (2.0 * theta).sin()) / PI).asin();
+        let lambda = PI * x / (2.0 * SQRT_2 * theta.cos());
+
+        (lambda, phi)
+    }
+
+    /// Vertical offset between the Mollweide part and the sinusoidal part,
+    /// so that both meet at `HOMOLOSINE_LATITUDE`
+    #[inline]
+    fn mollweide_offset()
+                        -> f64
+    {
+        let phi = HOMOLOSINE_LATITUDE.to_radians();
+        mollweide_forward(0.0, phi).1 - sinusoidal_forward(0.0, phi).1
+    }
+
+    /// Homolosine projection on the unit sphere (lambda, phi in radians)
+    #[inline]
+    pub fn homolosine_forward(lambda: f64, phi: f64)
+                              -> (f64, f64)
+    {
+        if phi.abs() <= HOMOLOSINE_LATITUDE.to_radians() {
+            sinusoidal_forward(lambda, phi)
+        } else {
+            let (x, y) = mollweide_forward(lambda, phi);
+            (x, y - mollweide_offset().copysign(phi))
+        }
+    }
+
+    /// Inverse homolosine projection on the unit sphere
+    #[inline]
+    pub fn homolosine_inverse(x: f64, y: f64)
+                              -> (f64, f64)
+    {
+        if y.abs() <= HOMOLOSINE_LATITUDE.to_radians() {
+            sinusoidal_inverse(x, y)
+        } else {
+            mollweide_inverse(x, y + mollweide_offset().copysign(y))
+        }
+    }
+
+    /// Function that converts a single lon-lat pair from (lon / lat) to Goode Homolosine (x, y)
+    /// Returns: (x, y)
+    #[inline]
+    pub fn lonlat_to_goode_xy(lon: f64, lat: f64, radius: f64, lobes: &[Lobe])
+                              -> (f64, f64)
+    {
+        if lat.abs() > 90.0 {
+            return (f64::NAN, f64::NAN);
+        }
+
+        interrupted_forward(lon, lat, radius, lobes, homolosine_forward)
+    }
+
+    /// Function that converts a single X-Y value from Goode Homolosine to lon / lat
+    /// Returns: (lon, lat)
+    #[inline]
+    pub fn goode_xy_to_lonlat(x: f64, y: f64, radius: f64, lobes: &[Lobe])
+                              -> (f64, f64)
+    {
+        interrupted_inverse(x, y, radius, lobes, homolosine_inverse)
+    }
+}
+
+impl ToLonLat for GoodeHomolosineSystem {
+    fn to_lon_lat(&self, mut data: Vec<(f64, f64)>, ellipsoid: &Ellipsoid, strategy: &mut MultithreadingStrategy)
+                  -> LonLatBuf
+    {
+        let lobes = self.lobes;
+        let radius = ellipsoid.a;
+
+        match *strategy {
+            SingleCore => {
+                for &mut (ref mut x, ref mut y) in data.iter_mut() {
+                    let (lon, lat) = utils::goode_xy_to_lonlat(*x, *y, radius, lobes);
+                    *x = lon; *y = lat;
+                }
+            },
+            #[cfg(all(not(target_arch = "wasm32"), feature = "scoped_threadpool"))]
+            MultiCore(ref mut thread_pool) => {
+                thread_pool.scoped(|scoped| {
+                    for &mut (ref mut x, ref mut y) in data.iter_mut() {
+                        scoped.execute(move || {
+                            let (lon, lat) = utils::goode_xy_to_lonlat(*x, *y, radius, lobes);
+                            *x = lon; *y = lat;
+                        });
+                    }
+                });
+            },
+        }
+
+        LonLatBuf {
+            data,
+            ellipsoid: *ellipsoid,
+        }
+    }
+}
+
+impl FromLonLat for GoodeHomolosineSystem {
+    fn from_lon_lat(&self, mut data: Vec<(f64, f64)>, ellipsoid: &Ellipsoid, strategy: &mut MultithreadingStrategy)
+                    -> CoordinateBuf
+    {
+        let lobes = self.lobes;
+        let radius = ellipsoid.a;
+
+        match *strategy {
+            SingleCore => {
+                for &mut (ref mut lon, ref mut lat) in data.iter_mut() {
+                    let (x, y) = utils::lonlat_to_goode_xy(*lon, *lat, radius, lobes);
+                    *lon = x; *lat = y;
+                }
+            },
+            #[cfg(all(not(target_arch = "wasm32"), feature = "scoped_threadpool"))]
+            MultiCore(ref mut thread_pool) => {
+                thread_pool.scoped(|scoped| {
+                    for &mut (ref mut lon, ref mut lat) in data.iter_mut() {
+                        scoped.execute(move || {
+                            let (x, y) = utils::lonlat_to_goode_xy(*lon, *lat, radius, lobes);
+                            *lon = x; *lat = y;
+                        });
+                    }
+                });
+            },
+        }
+
+        CoordinateBuf {
+            data,
+            crs: Box::new(*self),
+            ellipsoid: *ellipsoid,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::utils::*;
+    use std::f64::consts::{PI, SQRT_2};
+
+    const RADIUS: f64 = 6_378_137.0;
+
+    #[test]
+    fn homolosine_parts() {
+        // both parts meet without a step, the Mollweide part is shifted by
+        // Y_COR = 0.05280 of the radius in PROJ
+        let (_, y) = homolosine_forward(0.0, HOMOLOSINE_LATITUDE.to_radians() + 1e-12);
+        assert!((y - HOMOLOSINE_LATITUDE.to_radians()).abs() < 1e-9);
+        let (_, y) = homolosine_forward(0.0, 90_f64.to_radians());
+        assert!((y - (SQRT_2 - 0.052_80)).abs() < 1e-5);
+
+        let (x, y) = mollweide_forward(1.0, 0.0);
+        assert!((x - 2.0 * SQRT_2 / PI).abs() < 1e-15 && y == 0.0);
+    }
+
+    #[test]
+    fn reference_points() {
+        let lobes = &GOODE_HOMOLOSINE_LOBES;
+        for &(lon, lat, x, y) in &[(2.0, 1.0, 223_113.707_520, 111_319.490_793),
+                                   (-100.0, 60.0, -11_131_949.079_327, 6_539_970.861_690),
+                                   (10.0, 60.0, 2_042_456.862_767, 6_539_970.861_690),
+                                   (100.0, -70.0, 13_551_965.682_476, -7_437_681.476_253),
+                                   (-150.0, -30.0, -16_847_063.457_291, -3_339_584.723_798)] {
+            let (px, py) = lonlat_to_goode_xy(lon, lat, RADIUS, lobes);
+            assert!((px - x).abs() < 1e-5 && (py - y).abs() < 1e-5, "({}, {}): ({}, {})", lon, lat, px, py);
+
+            let (plon, plat) = goode_xy_to_lonlat(px, py, RADIUS, lobes);
+            assert!((plon - lon).abs() < 1e-9 && (plat - lat).abs() < 1e-9, "({}, {}): ({}, {})", lon, lat, plon, plat);
+        }
+    }
+
+    #[test]
+    fn system_uses_the_semi_major_axis() {
+        let system = GoodeHomolosineSystem { lobes: &GOODE_HOMOLOSINE_LOBES };
+        let buf = system.from_lon_lat(vec![(2.0, 1.0), (-60.0, -60.0)], &WGS_1984_ELLIPSOID, &mut MultithreadingStrategy::SingleCore);
+        assert!((buf.data[0].0 - 223_113.707_520).abs() < 1e-5 && (buf.data[0].1 - 111_319.490_793).abs() < 1e-5);
+        assert!((buf.data[1].0 + 60_f64.to_radians() * RADIUS).abs() < 1e-6);
+    }
+
+    #[test]
+    fn interruptions() {
+        let lobes = &GOODE_HOMOLOSINE_LOBES;
+        assert_eq!(find_lobe(-40.0, 10.0, lobes).map(|lobe| lobe.central_lon), Some(-100.0));
+        assert_eq!(find_lobe(-39.0, 10.0, lobes).map(|lobe| lobe.central_lon), Some(30.0));
+        assert_eq!(find_lobe(-20.0, -10.0, lobes).map(|lobe| lobe.central_lon), Some(-60.0));
+        assert!(lonlat_to_goode_xy(0.0, 91.0, RADIUS, lobes).0.is_nan());
+
+        // between the southern lobes around -100° and outside of the map
+        let (lon, lat) = goode_xy_to_lonlat(-100_f64.to_radians() * RADIUS, -7_000_000.0, RADIUS, lobes);
+        assert!(lon.is_nan() && lat.is_nan());
+        assert!(goode_xy_to_lonlat(0.0, 9_000_000.0, RADIUS, lobes).0.is_nan());
+        assert!(goode_xy_to_lonlat(3.2 * RADIUS, 0.0, RADIUS, lobes).0.is_nan());
+    }
+}
@@ -0,0 +1,343 @@
+//! Krovak oblique conformal conic projection
+//!
+//! Implementation taken from:
+//!
+//! EPSG Guidance Note 7 part 2, Coordinate Conversions and Transformations
+//! including Formulas, sections 1.3.1.5 (Krovak, EPSG method 9819),
+//! 1.3.1.6 (Krovak North Orientated, 1041) and 1.3.1.7 (Krovak Modified, 1042 / 1043)
+//!
+//! The projection parameters are fixed to the ones used by S-JTSK
+//! (EPSG:2065, EPSG:5514, EPSG:5221, EPSG:5224, EPSG:5228), which is defined
+//! on the `BESSEL_1841_ELLIPSOID`. Longitudes are relative to Greenwich, not Ferro.
+
+use prelude::*;
+
+/// Krovak variant
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum KrovakVariant {
+    /// Standard Krovak projection (EPSG method 9819)
+    Standard,
+    /// Modified Krovak projection with polynomial correction,
+    /// used by S-JTSK/05 (EPSG method 1042)
+    Modified,
+}
+
+/// Order and orientation of the projected coordinates
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum KrovakAxisOrder {
+    /// Classic (Westing, Southing) coordinates, both positive
+    /// in the Czech and Slovak Republic (EPSG:2065)
+    WestingSouthing,
+    /// "East North" variant (Easting, Northing), where Easting = -Westing and
+    /// Northing = -Southing (EPSG:5514)
+    EastingNorthing,
+}
+
+/// Krovak System (S-JTSK), to be used with the `BESSEL_1841_ELLIPSOID`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct KrovakSystem {
+    pub variant: KrovakVariant,
+    pub axis_order: KrovakAxisOrder,
+}
+
+pub mod utils {
+
+    use prelude::*;
+    use std::f64::consts::FRAC_PI_4;
+
+    /// Latitude of the projection centre (49°30'N), in degrees
+    pub const LATITUDE_OF_CENTRE: f64 = 49.5;
+    /// Longitude of origin (24°50'E of Greenwich, 42°30'E of Ferro), in degrees
+    pub const LONGITUDE_OF_ORIGIN: f64 = 24.0 + 50.0 / 60.0;
+    /// Co-latitude of the cone axis (30°17'17.30311"), in degrees
+    pub const CONE_AXIS_COLATITUDE: f64 = 30.0 + 17.0 / 60.0 + 17.303_11 / 3600.0;
+    /// Latitude of the pseudo standard parallel (78°30'N), in degrees
+    pub const PSEUDO_STANDARD_PARALLEL: f64 = 78.5;
+    /// Scale factor on the pseudo standard parallel
+    pub const SCALE_FACTOR: f64 = 0.9999;
+
+    /// False easting / northing of the modified Krovak projection
+    pub const MODIFIED_FALSE_EASTING: f64 = 5_000_000.0;
+    pub const MODIFIED_FALSE_NORTHING: f64 = 5_000_000.0;
+    /// Evaluation point of the modified Krovak correction polynomial
+    pub const MODIFIED_ORDINATE_1: f64 = 1_089_000.0;
+    pub const MODIFIED_ORDINATE_2: f64 = 654_000.0;
+    /// Coefficients C1 - C10 of the modified Krovak correction polynomial
+    pub const MODIFIED_COEFFICIENTS: [f64; 10] = [
+        2.946_529_277e-02,
+        2.515_965_696e-02,
+        1.193_845_912e-07,
+        -4.668_270_147e-07,
+        9.233_980_362e-12,
+        1.523_735_715e-12,
+        1.696_780_024e-18,
+        4.408_314_235e-18,
+        -8.331_083_518e-24,
+        -3.689_471_323e-24,
+    ];
+
+    /// Constants that only depend on the ellipsoid
+    #[derive(Debug, Copy, Clone)]
+    pub struct KrovakConstants {
+        e: f64,
+        b: f64,
+        t0: f64,
+        n: f64,
+        r0: f64,
+        alpha_c: f64,
+        phi_p: f64,
+    }
+
+    impl KrovakConstants {
+        pub fn new(ellipsoid: &Ellipsoid) -> Self {
+            let temp = ellipsoid.b / ellipsoid.a;
+            let e2 = 1.0 - (temp * temp);
+            let e = e2.sqrt();
+
+            let phi_c = LATITUDE_OF_CENTRE.to_radians();
+            let phi_p = PSEUDO_STANDARD_PARALLEL.to_radians();
+            let alpha_c = CONE_AXIS_COLATITUDE.to_radians();
+
+            let a = ellipsoid.a * (1.0 - e2).sqrt() / (1.0 - e2 * phi_c.sin().powi(2));
+            let b = (1.0 + (e2 * phi_c.cos().powi(4) / (1.0 - e2))).sqrt();
+            let gamma0 = (phi_c.sin() / b).asin();
+            let t0 = (FRAC_PI_4 + gamma0 / 2.0).tan() *
+                ((1.0 + e * phi_c.sin()) / (1.0 - e * phi_c.sin())).powf(e * b / 2.0) /
+                (FRAC_PI_4 + phi_c / 2.0).tan().powf(b);
+            let n = phi_p.sin();
+            let r0 = SCALE_FACTOR * a / phi_p.tan();
+
+            KrovakConstants { e, b, t0, n, r0, alpha_c, phi_p }
+        }
+    }
+
+    /// Evaluates the correction polynomial of the modified Krovak projection
+    /// for the reduced coordinates (xr, yr)
+    ///
+    /// Returns: (dX, dY)
+    #[inline]
+    pub fn modified_correction(xr: f64, yr: f64)
+                               -> (f64, f64)
+    {
+        let c = &MODIFIED_COEFFICIENTS;
+        let xr2 = xr * xr;
+        let yr2 = yr * yr;
+
+        let dx = c[0] + c[2] * xr - c[3] * yr - 2.0 * c[5] * xr * yr + c[4] * (xr2 - yr2) +
+            c[6] * xr * (xr2 - 3.0 * yr2) - c[7] * yr * (3.0 * xr2 - yr2) +
+            4.0 * c[8] * xr * yr * (xr2 - yr2) + c[9] * (xr2 * xr2 + yr2 * yr2 - 6.0 * xr2 * yr2);
+
+        let dy = c[1] + c[2] * yr + c[3] * xr + 2.0 * c[4] * xr * yr + c[5] * (xr2 - yr2) +
+            c[7] * xr * (xr2 - 3.0 * yr2) + c[6] * yr * (3.0 * xr2 - yr2) -
+            4.0 * c[9] * xr * yr * (xr2 - yr2) + c[8] * (xr2 * xr2 + yr2 * yr2 - 6.0 * xr2 * yr2);
+
+        (dx, dy)
+    }
+
+    /// Function that converts a single lon-lat pair from (lon / lat) to Krovak (x, y)
+    /// Returns: (x, y), in the axis order of the `system`
+    #[inline]
+    pub fn lonlat_to_krovak_xy(lon: f64, lat: f64, constants: &KrovakConstants, system: &KrovakSystem)
+                               -> (f64, f64)
+    {
+        let KrovakConstants { e, b, t0, n, r0, alpha_c, phi_p } = *constants;
+
+        let phi = lat.to_radians();
+        let lambda = lon.to_radians();
+        let lambda0 = LONGITUDE_OF_ORIGIN.to_radians();
+
+        let u = 2.0 * ((t0 * (phi / 2.0 + FRAC_PI_4).tan().powf(b) /
+            ((1.0 + e * phi.sin()) / (1.0 - e * phi.sin())).powf(e * b / 2.0)).atan() - FRAC_PI_4);
+        let v = b * (lambda0 - lambda);
+        let t = (alpha_c.cos() * u.sin() + alpha_c.sin() * u.cos() * v.cos()).asin();
+        let d = (u.cos() * v.sin() / t.cos()).asin();
+        let theta = n * d;
+        let r = r0 * (FRAC_PI_4 + phi_p / 2.0).tan().powf(n) / (t / 2.0 + FRAC_PI_4).tan().powf(n);
+
+        let xp = r * theta.cos();
+        let yp = r * theta.sin();
+
+        let (southing, westing) = match system.variant {
+            KrovakVariant::Standard => (xp, yp),
+            KrovakVariant::Modified => {
+                let (dx, dy) = modified_correction(xp - MODIFIED_ORDINATE_1, yp - MODIFIED_ORDINATE_2);
+                (xp - dx + MODIFIED_FALSE_NORTHING, yp - dy + MODIFIED_FALSE_EASTING)
+            },
+        };
+
+        match system.axis_order {
+            KrovakAxisOrder::WestingSouthing => (westing, southing),
+            KrovakAxisOrder::EastingNorthing => (-westing, -southing),
+        }
+    }
+
+    /// Function that converts a single X-Y value from Krovak to lon / lat
+    /// Returns: (lon, lat)
+    #[inline]
+    pub fn krovak_xy_to_lonlat(x: f64, y: f64, constants: &KrovakConstants, system: &KrovakSystem)
+                               -> (f64, f64)
+    {
+        let KrovakConstants { e, b, t0, n, r0, alpha_c, phi_p } = *constants;
+
+        let (westing, southing) = match system.axis_order {
+            KrovakAxisOrder::WestingSouthing => (x, y),
+            KrovakAxisOrder::EastingNorthing => (-x, -y),
+        };
+
+        let (xp, yp) = match system.variant {
+            KrovakVariant::Standard => (southing, westing),
+            KrovakVariant::Modified => {
+                let xc = southing - MODIFIED_FALSE_NORTHING;
+                let yc = westing - MODIFIED_FALSE_EASTING;
+
+                // The correction is evaluated at the (unknown) uncorrected
+                // position, so refine it a few times
+                let (mut xp, mut yp) = (xc, yc);
+                for _ in 0..4 {
+                    let (dx, dy) = modified_correction(xp - MODIFIED_ORDINATE_1, yp - MODIFIED_ORDINATE_2);
+                    xp = xc + dx;
+                    yp = yc + dy;
+                }
+                (xp, yp)
+            },
+        };
+
+        let r = xp.hypot(yp);
+        let theta = yp.atan2(xp);
+        let d = theta / phi_p.sin();
+        let t = 2.0 * (((r0 / r).powf(1.0 / n) * (FRAC_PI_4 + phi_p / 2.0).tan()).atan() - FRAC_PI_4);
+        let u = (alpha_c.cos() * t.sin() - alpha_c.sin() * t.cos() * d.cos()).asin();
+        let v = (t.cos() * d.sin() / u.cos()).asin();
+
+        let mut phi = u;
+        let mut iteration_cnt = 15;
+
+        loop {
+            let next = 2.0 * ((t0.powf(-1.0 / b) * (u / 2.0 + FRAC_PI_4).tan().powf(1.0 / b) *
+                ((1.0 + e * phi.sin()) / (1.0 - e * phi.sin())).powf(e / 2.0)).atan() - FRAC_PI_4);
+            let dphi = next - phi;
+            phi = next;
+
+            if dphi.abs() < 1e-12 || iteration_cnt == 0 { break; } else { iteration_cnt -= 1; }
+        }
+
+        let lambda = LONGITUDE_OF_ORIGIN.to_radians() - v / b;
+
+        (lambda.to_degrees(), phi.to_degrees())
+    }
+}
+
+impl ToLonLat for KrovakSystem {
+    fn to_lon_lat(&self, mut data: Vec<(f64, f64)>, ellipsoid: &Ellipsoid, strategy: &mut MultithreadingStrategy)
+                  -> LonLatBuf
+    {
+        let system = *self;
+        let constants = utils::KrovakConstants::new(ellipsoid);
+
+        match *strategy {
+            SingleCore => {
+                for &mut (ref mut x, ref mut y) in data.iter_mut() {
+                    let (lon, lat) = utils::krovak_xy_to_lonlat(*x, *y, &constants, &system);
+                    *x = lon; *y = lat;
+                }
+            },
+            #[cfg(all(not(target_arch = "wasm32"), feature = "scoped_threadpool"))]
+            MultiCore(ref mut thread_pool) => {
+                let (system, constants) = (&system, &constants);
+                thread_pool.scoped(|scoped| {
+                    for &mut (ref mut x, ref mut y) in data.iter_mut() {
+                        scoped.execute(move || {
+                            let (lon, lat) = utils::krovak_xy_to_lonlat(*x, *y, constants, system);
+                            *x = lon; *y = lat;
+                        });
+                    }
+                });
+            },
+        }
+
+        LonLatBuf {
+            data,
+            ellipsoid: *ellipsoid,
+        }
+    }
+}
+
+impl FromLonLat for KrovakSystem {
+    fn from_lon_lat(&self, mut data: Vec<(f64, f64)>, ellipsoid: &Ellipsoid, strategy: &mut MultithreadingStrategy)
+                    -> CoordinateBuf
+    {
+        let system = *self;
+        let constants = utils::KrovakConstants::new(ellipsoid);
+
+        match *strategy {
+            SingleCore => {
+                for &mut (ref mut lon, ref mut lat) in data.iter_mut() {
+                    let (x, y) = utils::lonlat_to_krovak_xy(*lon, *lat, &constants, &system);
+                    *lon = x; *lat = y;
+                }
+            },
+            #[cfg(all(not(target_arch = "wasm32"), feature = "scoped_threadpool"))]
+            MultiCore(ref mut thread_pool) => {
+                let (system, constants) = (&system, &constants);
+                thread_pool.scoped(|scoped| {
+                    for &mut (ref mut lon, ref mut lat) in data.iter_mut() {
+                        scoped.execute(move || {
+                            let (x, y) = utils::lonlat_to_krovak_xy(*lon, *lat, constants, system);
+                            *lon = x; *lat = y;
+                        });
+                    }
+                });
+            },
+        }
+
+        CoordinateBuf {
+            data,
+            crs: Box::new(system),
+            ellipsoid: *ellipsoid,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::utils::*;
+
+    // EPSG Guidance Note 7-2, examples of sections 1.3.1.5 - 1.3.1.7:
+    // 50°12'32.442"N, 16°50'59.179"E on the Bessel ellipsoid
+    const LON: f64 = 16.0 + 50.0 / 60.0 + 59.179 / 3600.0;
+    const LAT: f64 = 50.0 + 12.0 / 60.0 + 32.442 / 3600.0;
+
+    const STANDARD: KrovakSystem = KrovakSystem { variant: KrovakVariant::Standard, axis_order: KrovakAxisOrder::WestingSouthing };
+    const EAST_NORTH: KrovakSystem = KrovakSystem { variant: KrovakVariant::Standard, axis_order: KrovakAxisOrder::EastingNorthing };
+    const MODIFIED: KrovakSystem = KrovakSystem { variant: KrovakVariant::Modified, axis_order: KrovakAxisOrder::WestingSouthing };
+
+    #[test]
+    fn guidance_note_examples() {
+        let constants = KrovakConstants::new(&BESSEL_1841_ELLIPSOID);
+
+        for &(system, x, y) in &[(STANDARD, 568_991.00, 1_050_538.63),
+                                 (EAST_NORTH, -568_991.00, -1_050_538.63),
+                                 (MODIFIED, 5_568_990.91, 6_050_538.71)] {
+            let (px, py) = lonlat_to_krovak_xy(LON, LAT, &constants, &system);
+            assert!((px - x).abs() < 0.01 && (py - y).abs() < 0.01, "{:?}: ({}, {})", system, px, py);
+
+            let (lon, lat) = krovak_xy_to_lonlat(px, py, &constants, &system);
+            assert!((lon - LON).abs() < 1e-10 && (lat - LAT).abs() < 1e-10, "{:?}: ({}, {})", system, lon, lat);
+        }
+    }
+
+    #[test]
+    fn round_trip_over_the_republics() {
+        let points: Vec<(f64, f64)> = (0..=10).flat_map(|i| (0..=5).map(move |j| (12.0 + i as f64, 47.5 + 0.8 * j as f64))).collect();
+        let mut strategy = MultithreadingStrategy::SingleCore;
+
+        for &system in &[STANDARD, EAST_NORTH, MODIFIED] {
+            let projected = system.from_lon_lat(points.clone(), &BESSEL_1841_ELLIPSOID, &mut strategy);
+            let back = system.to_lon_lat(projected.data, &BESSEL_1841_ELLIPSOID, &mut strategy);
+            for (a, b) in back.data.iter().zip(&points) {
+                assert!((a.0 - b.0).abs() < 1e-9 && (a.1 - b.1).abs() < 1e-9, "{:?}: {:?} != {:?}", system, a, b);
+            }
+        }
+    }
+}
@@ -0,0 +1,368 @@
+//! Lambert conformal conic projection
+//!
+//! Implementation taken from:
+//!
+//! Snyder, J. P., 1987, Map Projections - A Working Manual,
+//! U.S. Geological Survey Professional Paper 1395, p. 104-110
+//!
+//! Also contains the French national grids based on this projection:
+//! RGF93 / Lambert-93 (EPSG:2154) and the nine conic conformal zones
+//! RGF93 / CC42 - CC50 (EPSG:3942 - EPSG:3950), which are defined on the
+//! `GRS_1980_ELLIPSOID`.
+
+use prelude::*;
+
+/// Lambert conformal conic system
+///
+/// For the one-standard-parallel variant, set `lat_1` and `lat_2` to the same value.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LambertConformalConicSystem {
+    /// Latitude of origin, in degrees
+    pub lat_0: f64,
+    /// Longitude of origin (central meridian), in degrees
+    pub lon_0: f64,
+    /// First standard parallel, in degrees
+    pub lat_1: f64,
+    /// Second standard parallel, in degrees
+    pub lat_2: f64,
+    /// Scale factor at the standard parallel(s), usually 1.0
+    pub scale_factor: f64,
+    /// False easting, in meters
+    pub false_easting: f64,
+    /// False northing, in meters
+    pub false_northing: f64,
+}
+
+/// RGF93 / Lambert-93 (EPSG:2154)
+pub const LAMBERT_93: LambertConformalConicSystem = LambertConformalConicSystem {
+    lat_0: 46.5,
+    lon_0: 3.0,
+    lat_1: 49.0,
+    lat_2: 44.0,
+    scale_factor: 1.0,
+    false_easting: 700_000.0,
+    false_northing: 6_600_000.0,
+};
+
+/// RGF93 / CC42 (EPSG:3942)
+pub const CC42: LambertConformalConicSystem = conique_conforme(42);
+/// RGF93 / CC43 (EPSG:3943)
+pub const CC43: LambertConformalConicSystem = conique_conforme(43);
+/// RGF93 / CC44 (EPSG:3944)
+pub const CC44: LambertConformalConicSystem = conique_conforme(44);
+/// RGF93 / CC45 (EPSG:3945)
+pub const CC45: LambertConformalConicSystem = conique_conforme(45);
+/// RGF93 / CC46 (EPSG:3946)
+pub const CC46: LambertConformalConicSystem = conique_conforme(46);
+/// RGF93 / CC47 (EPSG:3947)
+pub const CC47: LambertConformalConicSystem = conique_conforme(47);
+/// RGF93 / CC48 (EPSG:3948)
+pub const CC48: LambertConformalConicSystem = conique_conforme(48);
+/// RGF93 / CC49 (EPSG:3949)
+pub const CC49: LambertConformalConicSystem = conique_conforme(49);
+/// RGF93 / CC50 (EPSG:3950)
+pub const CC50: LambertConformalConicSystem = conique_conforme(50);
+
+/// The nine conic conformal zones CC42 - CC50, indexed by `zone - 42`
+pub const CONIQUE_CONFORME_ZONES: [LambertConformalConicSystem; 9] = [
+    CC42, CC43, CC44, CC45, CC46, CC47, CC48, CC49, CC50,
+];
+
+// Each zone is centered on its own parallel, with the standard parallels
+// 0.75° south and north of it and a false northing that grows by 1000 km per zone
+const fn conique_conforme(zone: u8) -> LambertConformalConicSystem {
+    let lat_0 = zone as f64;
+    LambertConformalConicSystem {
+        lat_0,
+        lon_0: 3.0,
+        lat_1: lat_0 - 0.75,
+        lat_2: lat_0 + 0.75,
+        scale_factor: 1.0,
+        false_easting: 1_700_000.0,
+        false_northing: 1_200_000.0 + (zone - 42) as f64 * 1_000_000.0,
+    }
+}
+
+pub mod utils {
+
+    use prelude::*;
+    use coordinate_systems::merc::utils::pj_phi2;
+    use std::f64::consts::FRAC_PI_4;
+
+    /// Bounding box of mainland France and Corsica (min_lon, min_lat, max_lon, max_lat),
+    /// the area of use of Lambert-93 and the CC zones
+    pub const FRANCE_BOUNDS: (f64, f64, f64, f64) = (-4.87, 41.31, 9.63, 51.14);
+
+    /// Calculates the conic conformal zone (42 to 50) a point falls in.
+    /// Each zone is centered on its parallel and is valid 1° north and south of it,
+    /// so the closest zone is chosen.
+    ///
+    /// Inputs: Longitude, in degrees
+    ///         Latitude, in degrees
+    ///
+    /// Returns: `None` if the point is outside of mainland France and Corsica
+    pub fn get_cc_zone(lon: f64, lat: f64)
+                       -> Option<u8>
+    {
+        let (min_lon, min_lat, max_lon, max_lat) = FRANCE_BOUNDS;
+
+        if lon < min_lon || lon > max_lon || lat < min_lat || lat > max_lat {
+            return None;
+        }
+
+        Some(lat.round().clamp(42.0, 50.0) as u8)
+    }
+
+    /// Returns the `LambertConformalConicSystem` of the given conic conformal zone (42 to 50)
+    pub fn cc_zone_system(zone: u8)
+                          -> Option<LambertConformalConicSystem>
+    {
+        match zone {
+            42..=50 => Some(::coordinate_systems::lcc::CONIQUE_CONFORME_ZONES[(zone - 42) as usize]),
+            _ => None,
+        }
+    }
+
+    /// Constants that only depend on the projection parameters and the ellipsoid
+    #[derive(Debug, Copy, Clone)]
+    pub struct LccConstants {
+        e: f64,
+        n: f64,
+        /// `a * k0 * F`
+        a_f: f64,
+        rho0: f64,
+    }
+
+    #[inline]
+    fn m(phi: f64, e: f64) -> f64 {
+        phi.cos() / (1.0 - (e * phi.sin()).powi(2)).sqrt()
+    }
+
+    #[inline]
+    fn t(phi: f64, e: f64) -> f64 {
+        let con = e * phi.sin();
+        (FRAC_PI_4 - phi / 2.0).tan() / ((1.0 - con) / (1.0 + con)).powf(e / 2.0)
+    }
+
+    impl LccConstants {
+        pub fn new(system: &LambertConformalConicSystem, ellipsoid: &Ellipsoid) -> Self {
+            let temp = ellipsoid.b / ellipsoid.a;
+            let e = (1.0 - (temp * temp)).sqrt();
+
+            let phi0 = system.lat_0.to_radians();
+            let phi1 = system.lat_1.to_radians();
+            let phi2 = system.lat_2.to_radians();
+
+            let (m1, t1) = (m(phi1, e), t(phi1, e));
+
+            let n = if (phi1 - phi2).abs() < 1e-10 {
+                phi1.sin()
+            } else {
+                (m1.ln() - m(phi2, e).ln()) / (t1.ln() - t(phi2, e).ln())
+            };
+
+            let a_f = ellipsoid.a * system.scale_factor * m1 / (n * t1.powf(n));
+            let rho0 = a_f * t(phi0, e).powf(n);
+
+            LccConstants { e, n, a_f, rho0 }
+        }
+    }
+
+    /// Function that converts a single lon-lat pair from (lon / lat) to LCC (x, y)
+    /// Returns: (x, y)
+    #[inline]
+    pub fn lonlat_to_lcc_xy(lon: f64, lat: f64, constants: &LccConstants, system: &LambertConformalConicSystem)
+                            -> (f64, f64)
+    {
+        let LccConstants { e, n, a_f, rho0 } = *constants;
+
+        let phi = lat.to_radians();
+        let rho = if (phi.abs() - ::std::f64::consts::FRAC_PI_2).abs() < 1e-10 {
+            // The pole opposite to the cone apex is projected to infinity
+            if phi * n <= 0.0 { return (f64::NAN, f64::NAN); }
+            0.0
+        } else {
+            a_f * t(phi, e).powf(n)
+        };
+
+        let theta = n * (lon - system.lon_0).to_radians();
+
+        (system.false_easting + rho * theta.sin(),
+         system.false_northing + rho0 - rho * theta.cos())
+    }
+
+    /// Function that converts a single X-Y value from LCC to lon / lat
+    /// Returns: (lon, lat)
+    #[inline]
+    pub fn lcc_xy_to_lonlat(x: f64, y: f64, constants: &LccConstants, system: &LambertConformalConicSystem)
+                            -> (f64, f64)
+    {
+        let LccConstants { e, n, a_f, rho0 } = *constants;
+
+        let dx = x - system.false_easting;
+        let dy = rho0 - (y - system.false_northing);
+
+        let rho = n.signum() * dx.hypot(dy);
+        let theta = (n.signum() * dx).atan2(n.signum() * dy);
+
+        let phi = if rho == 0.0 {
+            ::std::f64::consts::FRAC_PI_2.copysign(n)
+        } else {
+            pj_phi2((rho / a_f).powf(1.0 / n), e)
+        };
+
+        ((theta / n).to_degrees() + system.lon_0, phi.to_degrees())
+    }
+}
+
+impl ToLonLat for LambertConformalConicSystem {
+    fn to_lon_lat(&self, mut data: Vec<(f64, f64)>, ellipsoid: &Ellipsoid, strategy: &mut MultithreadingStrategy)
+                  -> LonLatBuf
+    {
+        let system = *self;
+        let constants = utils::LccConstants::new(&system, ellipsoid);
+
+        match *strategy {
+            SingleCore => {
+                for &mut (ref mut x, ref mut y) in data.iter_mut() {
+                    let (lon, lat) = utils::lcc_xy_to_lonlat(*x, *y, &constants, &system);
+                    *x = lon; *y = lat;
+                }
+            },
+            #[cfg(all(not(target_arch = "wasm32"), feature = "scoped_threadpool"))]
+            MultiCore(ref mut thread_pool) => {
+                let (system, constants) = (&system, &constants);
+                thread_pool.scoped(|scoped| {
+                    for &mut (ref mut x, ref mut y) in data.iter_mut() {
+                        scoped.execute(move || {
+                            let (lon, lat) = utils::lcc_xy_to_lonlat(*x, *y, constants, system);
+                            *x = lon; *y = lat;
+                        });
+                    }
+                });
+            },
+        }
+
+        LonLatBuf {
+            data,
+            ellipsoid: *ellipsoid,
+        }
+    }
+}
+
+impl FromLonLat for LambertConformalConicSystem {
+    fn from_lon_lat(&self, mut data: Vec<(f64, f64)>, ellipsoid: &Ellipsoid, strategy: &mut MultithreadingStrategy)
+                    -> CoordinateBuf
+    {
+        let system = *self;
+        let constants = utils::LccConstants::new(&system, ellipsoid);
+
+        match *strategy {
+            SingleCore => {
+                for &mut (ref mut lon, ref mut lat) in data.iter_mut() {
+                    let (x, y) = utils::lonlat_to_lcc_xy(*lon, *lat, &constants, &system);
+                    *lon = x; *lat = y;
+                }
+            },
+            #[cfg(all(not(target_arch = "wasm32"), feature = "scoped_threadpool"))]
+            MultiCore(ref mut thread_pool) => {
+                let (system, constants) = (&system, &constants);
+                thread_pool.scoped(|scoped| {
+                    for &mut (ref mut lon, ref mut lat) in data.iter_mut() {
+                        scoped.execute(move || {
+                            let (x, y) = utils::lonlat_to_lcc_xy(*lon, *lat, constants, system);
+                            *lon = x; *lat = y;
+                        });
+                    }
+                });
+            },
+        }
+
+        CoordinateBuf {
+            data,
+            crs: Box::new(system),
+            ellipsoid: *ellipsoid,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::utils::*;
+
+    const US_FOOT: f64 = 1200.0 / 3937.0;
+
+    fn dms(degrees: f64, minutes: f64, seconds: f64) -> f64 {
+        degrees.signum() * (degrees.abs() + minutes / 60.0 + seconds / 3600.0)
+    }
+
+    fn assert_round_trip(system: &LambertConformalConicSystem, ellipsoid: &Ellipsoid,
+                         lon: f64, lat: f64, x: f64, y: f64, tolerance: f64)
+    {
+        let constants = LccConstants::new(system, ellipsoid);
+        let (px, py) = lonlat_to_lcc_xy(lon, lat, &constants, system);
+        assert!((px - x).abs() < tolerance && (py - y).abs() < tolerance, "({}, {}) != ({}, {})", px, py, x, y);
+
+        let (plon, plat) = lcc_xy_to_lonlat(px, py, &constants, system);
+        assert!((plon - lon).abs() < 1e-10 && (plat - lat).abs() < 1e-10, "({}, {}) != ({}, {})", plon, plat, lon, lat);
+    }
+
+    // EPSG Guidance Note 7-2, example of section 1.3.1.1: JAD69 / Jamaica National Grid
+    #[test]
+    fn guidance_note_one_standard_parallel() {
+        let system = LambertConformalConicSystem {
+            lat_0: 18.0, lon_0: -77.0, lat_1: 18.0, lat_2: 18.0,
+            scale_factor: 1.0, false_easting: 250_000.0, false_northing: 150_000.0,
+        };
+        assert_round_trip(&system, &CLARKE_1866_ELLIPSOID, dms(-76.0, 56.0, 37.26), dms(17.0, 55.0, 55.80),
+                          255_966.58, 142_493.51, 0.01);
+    }
+
+    // EPSG Guidance Note 7-2, example of section 1.3.1.2: NAD27 / Texas South Central
+    #[test]
+    fn guidance_note_two_standard_parallels() {
+        let system = LambertConformalConicSystem {
+            lat_0: dms(27.0, 50.0, 0.0), lon_0: -99.0, lat_1: dms(28.0, 23.0, 0.0), lat_2: dms(30.0, 17.0, 0.0),
+            scale_factor: 1.0, false_easting: 2_000_000.0 * US_FOOT, false_northing: 0.0,
+        };
+        assert_round_trip(&system, &CLARKE_1866_ELLIPSOID, -96.0, 28.5,
+                          2_963_503.91 * US_FOOT, 254_759.80 * US_FOOT, 0.01 * US_FOOT);
+    }
+
+    #[test]
+    fn french_grids() {
+        assert_round_trip(&LAMBERT_93, &GRS_1980_ELLIPSOID, 3.0, 46.5, 700_000.0, 6_600_000.0, 1e-6);
+        for (i, system) in CONIQUE_CONFORME_ZONES.iter().enumerate() {
+            let zone = 42 + i as u8;
+            assert_eq!(cc_zone_system(zone), Some(*system));
+            assert_round_trip(system, &GRS_1980_ELLIPSOID, 3.0, zone as f64,
+                              1_700_000.0, 1_200_000.0 + i as f64 * 1_000_000.0, 1e-6);
+        }
+        assert_eq!(cc_zone_system(41), None);
+        assert_eq!(cc_zone_system(51), None);
+
+        // Lambert-93 is secant on its standard parallels
+        let constants = LccConstants::new(&LAMBERT_93, &GRS_1980_ELLIPSOID);
+        let scale = |lat: f64| {
+            let (_, y0) = lonlat_to_lcc_xy(3.0, lat - 1e-5, &constants, &LAMBERT_93);
+            let (_, y1) = lonlat_to_lcc_xy(3.0, lat + 1e-5, &constants, &LAMBERT_93);
+            let phi = lat.to_radians();
+            let e2 = GRS_1980_ELLIPSOID.eccentricity_squared();
+            let meridian = GRS_1980_ELLIPSOID.a * (1.0 - e2) / (1.0 - e2 * phi.sin().powi(2)).powf(1.5);
+            (y1 - y0) / (2e-5_f64.to_radians() * meridian)
+        };
+        assert!((scale(44.0) - 1.0).abs() < 1e-8 && (scale(49.0) - 1.0).abs() < 1e-8);
+        assert!(scale(46.5) < 0.9991);
+    }
+
+    #[test]
+    fn cc_zones() {
+        assert_eq!(get_cc_zone(2.35, 48.86), Some(49));
+        assert_eq!(get_cc_zone(5.37, 43.30), Some(43));
+        assert_eq!(get_cc_zone(9.0, 41.4), Some(42));
+        assert_eq!(get_cc_zone(2.37, 51.03), Some(50));
+        assert_eq!(get_cc_zone(-5.0, 48.0), None);
+        assert_eq!(get_cc_zone(2.0, 52.0), None);
+    }
+}
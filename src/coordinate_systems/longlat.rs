@@ -0,0 +1,33 @@
+//! Geographic coordinates (longitude / latitude, in degrees)
+
+use prelude::*;
+
+/// Longitude / latitude in degrees, the identity CRS
+///
+/// Useful wherever a `Crs` is required for coordinates that are not projected,
+/// e.g. for `+proj=longlat` definitions.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LonLatSystem;
+
+impl ToLonLat for LonLatSystem {
+    fn to_lon_lat(&self, data: Vec<(f64, f64)>, ellipsoid: &Ellipsoid, _strategy: &mut MultithreadingStrategy)
+                  -> LonLatBuf
+    {
+        LonLatBuf {
+            data,
+            ellipsoid: *ellipsoid,
+        }
+    }
+}
+
+impl FromLonLat for LonLatSystem {
+    fn from_lon_lat(&self, data: Vec<(f64, f64)>, ellipsoid: &Ellipsoid, _strategy: &mut MultithreadingStrategy)
+                    -> CoordinateBuf
+    {
+        CoordinateBuf {
+            data,
+            crs: Box::new(LonLatSystem),
+            ellipsoid: *ellipsoid,
+        }
+    }
+}
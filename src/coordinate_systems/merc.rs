@@ -11,10 +11,21 @@ use prelude::*;
 #[derive(Debug, Copy, Clone)]
 pub struct MercatorSystem;
 
-mod utils {
+/// Web Mercator (EPSG:3857), the spherical Mercator used by web maps
+///
+/// The ellipsoidal lon / lat coordinates are projected as if they were on
+/// a sphere with the semi-major axis of the ellipsoid as radius.
+/// Latitudes are clamped to `WEB_MERCATOR_MAX_LATITUDE`.
+#[derive(Debug, Copy, Clone)]
+pub struct WebMercatorSystem;
+
+pub mod utils {
 
     use std::f64::consts::PI;
 
+    /// Latitude at which the Web Mercator map becomes square (`atan(sinh(PI))`), in degrees
+    pub const WEB_MERCATOR_MAX_LATITUDE: f64 = 85.051_128_779_806_59;
+
     #[inline(always)]
     pub fn pj_phi2(ts: f64, e: f64)
                -> f64
@@ -37,15 +48,14 @@ mod utils {
             if dphi.abs() < TOL || (iteration_cnt - 1) < 0 { break; } else { iteration_cnt -= 1; }
         }
 
-        return phi;
+        phi
     }
 
     /// `temp = ellipsoid.b / ellipsoid.a`
     #[inline(always)]
     pub fn lat_to_mercator_y(mut lat: f64, ellipsoid_a: f64, temp: f64) -> f64 {
 
-        if lat > 89.5  { lat = 89.5; }
-        if lat < -89.5 { lat = -89.5; }
+        lat = lat.clamp(-89.5, 89.5);
 
         let es = 1.0 - (temp * temp);
         let eccent = es.sqrt();
@@ -55,9 +65,7 @@ mod utils {
         let com = 0.5 * eccent;
         let con = (1.0 - con) / (1.0 + con).powf(com);
         let ts = (0.5 * (PI * 0.5 - phi)).tan() / con;
-        let y = 0.0 - ellipsoid_a * ts.ln();
-
-        return y;
+        0.0 - ellipsoid_a * ts.ln()
     }
 
     #[inline(always)]
@@ -75,6 +83,27 @@ mod utils {
     {
         pj_phi2((0.0 - (y / ellipsoid_a)).exp(), e).to_degrees()
     }
+
+    /// Function that converts a single lon-lat pair from (lon / lat) to Web Mercator (x, y)
+    /// Returns: (x, y)
+    #[inline(always)]
+    pub fn lonlat_to_web_mercator_xy(lon: f64, lat: f64, radius: f64)
+                                     -> (f64, f64)
+    {
+        let lat = lat.clamp(-WEB_MERCATOR_MAX_LATITUDE, WEB_MERCATOR_MAX_LATITUDE);
+        (radius * lon.to_radians(),
+         radius * (PI / 4.0 + lat.to_radians() / 2.0).tan().ln())
+    }
+
+    /// Function that converts a single X-Y value from Web Mercator to lon / lat
+    /// Returns: (lon, lat)
+    #[inline(always)]
+    pub fn web_mercator_xy_to_lonlat(x: f64, y: f64, radius: f64)
+                                     -> (f64, f64)
+    {
+        ((x / radius).to_degrees(),
+         (y / radius).sinh().atan().to_degrees())
+    }
 }
 
 impl ToLonLat for MercatorSystem {
@@ -105,7 +134,7 @@ impl ToLonLat for MercatorSystem {
         }
 
         LonLatBuf {
-            data: data,
+            data,
             ellipsoid: *ellipsoid,
         }
     }
@@ -142,10 +171,77 @@ impl FromLonLat for MercatorSystem {
         }
 
         CoordinateBuf {
-            data: data,
+            data,
             crs: Box::new(MercatorSystem),
             ellipsoid: *ellipsoid,
         }
     }
 }
 
+
+impl ToLonLat for WebMercatorSystem {
+    fn to_lon_lat(&self, mut data: Vec<(f64, f64)>, ellipsoid: &Ellipsoid, strategy: &mut MultithreadingStrategy)
+                  -> LonLatBuf
+    {
+        let radius = ellipsoid.a;
+
+        match *strategy {
+            SingleCore => {
+                for &mut (ref mut x, ref mut y) in data.iter_mut() {
+                    let (lon, lat) = utils::web_mercator_xy_to_lonlat(*x, *y, radius);
+                    *x = lon; *y = lat;
+                }
+            },
+            #[cfg(all(not(target_arch = "wasm32"), feature = "scoped_threadpool"))]
+            MultiCore(ref mut thread_pool) => {
+                thread_pool.scoped(|scoped| {
+                    for &mut (ref mut x, ref mut y) in data.iter_mut() {
+                        scoped.execute(move || {
+                            let (lon, lat) = utils::web_mercator_xy_to_lonlat(*x, *y, radius);
+                            *x = lon; *y = lat;
+                        });
+                    }
+                });
+            },
+        }
+
+        LonLatBuf {
+            data,
+            ellipsoid: *ellipsoid,
+        }
+    }
+}
+
+impl FromLonLat for WebMercatorSystem {
+    fn from_lon_lat(&self, mut data: Vec<(f64, f64)>, ellipsoid: &Ellipsoid, strategy: &mut MultithreadingStrategy)
+                    -> CoordinateBuf
+    {
+        let radius = ellipsoid.a;
+
+        match *strategy {
+            SingleCore => {
+                for &mut (ref mut lon, ref mut lat) in data.iter_mut() {
+                    let (x, y) = utils::lonlat_to_web_mercator_xy(*lon, *lat, radius);
+                    *lon = x; *lat = y;
+                }
+            },
+            #[cfg(all(not(target_arch = "wasm32"), feature = "scoped_threadpool"))]
+            MultiCore(ref mut thread_pool) => {
+                thread_pool.scoped(|scoped| {
+                    for &mut (ref mut lon, ref mut lat) in data.iter_mut() {
+                        scoped.execute(move || {
+                            let (x, y) = utils::lonlat_to_web_mercator_xy(*lon, *lat, radius);
+                            *lon = x; *lat = y;
+                        });
+                    }
+                });
+            },
+        }
+
+        CoordinateBuf {
+            data,
+            crs: Box::new(WebMercatorSystem),
+            ellipsoid: *ellipsoid,
+        }
+    }
+}
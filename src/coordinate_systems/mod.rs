@@ -1,2 +1,12 @@
+pub(crate) mod longlat;
+pub(crate) mod adjusted;
 pub(crate) mod merc;
 pub(crate) mod utm;
+pub(crate) mod tmerc;
+pub(crate) mod ups;
+pub(crate) mod geos;
+pub(crate) mod krovak;
+pub(crate) mod igh;
+pub(crate) mod lcc;
+pub(crate) mod healpix;
+pub(crate) mod description;
@@ -0,0 +1,234 @@
+//! Transverse Mercator projection with arbitrary origin, scale factor and false origin
+//!
+//! Implementation taken from:
+//!
+//! Ordnance Survey, 2020, A Guide to Coordinate Systems in Great Britain,
+//! Annex C (Transverse Mercator map projection formulae)
+//!
+//! The series expansions are accurate to about a millimeter within a few degrees
+//! of the central meridian, which covers the national grids defined here:
+//! OSGB36 / British National Grid (EPSG:27700), defined on the `AIRY_1830_ELLIPSOID`,
+//! and TM65 / Irish Grid (EPSG:29902), defined on the `MODIFIED_AIRY_ELLIPSOID`.
+
+use prelude::*;
+
+/// Transverse Mercator system
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TransverseMercatorSystem {
+    /// Latitude of the true origin, in degrees
+    pub lat_0: f64,
+    /// Longitude of the true origin (central meridian), in degrees
+    pub lon_0: f64,
+    /// Scale factor on the central meridian
+    pub scale_factor: f64,
+    /// False easting, in meters
+    pub false_easting: f64,
+    /// False northing, in meters
+    pub false_northing: f64,
+}
+
+/// OSGB36 / British National Grid (EPSG:27700)
+pub const BRITISH_NATIONAL_GRID: TransverseMercatorSystem = TransverseMercatorSystem {
+    lat_0: 49.0,
+    lon_0: -2.0,
+    scale_factor: 0.999_601_271_7,
+    false_easting: 400_000.0,
+    false_northing: -100_000.0,
+};
+
+/// TM65 / Irish Grid (EPSG:29902)
+pub const IRISH_GRID: TransverseMercatorSystem = TransverseMercatorSystem {
+    lat_0: 53.5,
+    lon_0: -8.0,
+    scale_factor: 1.000_035,
+    false_easting: 200_000.0,
+    false_northing: 250_000.0,
+};
+
+pub mod utils {
+
+    use prelude::*;
+
+    /// Developed arc of the meridian from the latitude of the true origin
+    /// to the given latitude (both in radians), multiplied by the scale factor
+    #[inline]
+    fn meridional_arc(phi: f64, phi0: f64, b_f0: f64, n: f64)
+                      -> f64
+    {
+        let (n2, n3) = (n * n, n * n * n);
+        let (dphi, sphi) = (phi - phi0, phi + phi0);
+
+        b_f0 * ((1.0 + n + 1.25 * n2 + 1.25 * n3) * dphi -
+                (3.0 * n + 3.0 * n2 + 2.625 * n3) * dphi.sin() * sphi.cos() +
+                (1.875 * n2 + 1.875 * n3) * (2.0 * dphi).sin() * (2.0 * sphi).cos() -
+                (35.0 / 24.0 * n3) * (3.0 * dphi).sin() * (3.0 * sphi).cos())
+    }
+
+    /// Function that converts a single lon-lat pair from (lon / lat) to transverse Mercator (x, y)
+    /// Returns: (x, y)
+    #[inline]
+    #[allow(non_snake_case)]
+    pub fn lonlat_to_tmerc_xy(lon: f64, lat: f64, ellipsoid: &Ellipsoid, system: &TransverseMercatorSystem)
+                              -> (f64, f64)
+    {
+        let phi = lat.to_radians();
+        let phi0 = system.lat_0.to_radians();
+        let a_f0 = ellipsoid.a * system.scale_factor;
+        let b_f0 = ellipsoid.b * system.scale_factor;
+        let e2 = ellipsoid.eccentricity_squared();
+        let n = (ellipsoid.a - ellipsoid.b) / (ellipsoid.a + ellipsoid.b);
+
+        let (sin_phi, cos_phi) = phi.sin_cos();
+        let tan2 = phi.tan().powi(2);
+
+        // Radii of curvature in the prime vertical (nu) and in the meridian (rho)
+        let temp = 1.0 - e2 * sin_phi * sin_phi;
+        let nu = a_f0 / temp.sqrt();
+        let rho = a_f0 * (1.0 - e2) / temp.powf(1.5);
+        let eta2 = nu / rho - 1.0;
+
+        let M = meridional_arc(phi, phi0, b_f0, n);
+
+        let I = M + system.false_northing;
+        let II = nu / 2.0 * sin_phi * cos_phi;
+        let III = nu / 24.0 * sin_phi * cos_phi.powi(3) * (5.0 - tan2 + 9.0 * eta2);
+        let IIIA = nu / 720.0 * sin_phi * cos_phi.powi(5) * (61.0 - 58.0 * tan2 + tan2 * tan2);
+        let IV = nu * cos_phi;
+        let V = nu / 6.0 * cos_phi.powi(3) * (nu / rho - tan2);
+        let VI = nu / 120.0 * cos_phi.powi(5) *
+            (5.0 - 18.0 * tan2 + tan2 * tan2 + 14.0 * eta2 - 58.0 * tan2 * eta2);
+
+        let P = (lon - system.lon_0).to_radians();
+
+        let x = system.false_easting + IV * P + V * P.powi(3) + VI * P.powi(5);
+        let y = I + II * P.powi(2) + III * P.powi(4) + IIIA * P.powi(6);
+
+        (x, y)
+    }
+
+    /// Function that converts a single X-Y value from transverse Mercator to lon / lat
+    /// Returns: (lon, lat)
+    #[inline]
+    #[allow(non_snake_case)]
+    pub fn tmerc_xy_to_lonlat(x: f64, y: f64, ellipsoid: &Ellipsoid, system: &TransverseMercatorSystem)
+                              -> (f64, f64)
+    {
+        let phi0 = system.lat_0.to_radians();
+        let a_f0 = ellipsoid.a * system.scale_factor;
+        let b_f0 = ellipsoid.b * system.scale_factor;
+        let e2 = ellipsoid.eccentricity_squared();
+        let n = (ellipsoid.a - ellipsoid.b) / (ellipsoid.a + ellipsoid.b);
+
+        let y = y - system.false_northing;
+
+        if !x.is_finite() || !y.is_finite() {
+            return (f64::NAN, f64::NAN);
+        }
+
+        // Iterate the latitude until the meridional arc matches the northing (to 0.01 mm)
+        let mut phi = y / a_f0 + phi0;
+        let mut M = meridional_arc(phi, phi0, b_f0, n);
+        for _ in 0..20 {
+            if (y - M).abs() < 0.000_01 {
+                break;
+            }
+            phi += (y - M) / a_f0;
+            M = meridional_arc(phi, phi0, b_f0, n);
+        }
+
+        let sin_phi = phi.sin();
+        let sec_phi = 1.0 / phi.cos();
+        let tan = phi.tan();
+        let tan2 = tan * tan;
+        let tan4 = tan2 * tan2;
+
+        let temp = 1.0 - e2 * sin_phi * sin_phi;
+        let nu = a_f0 / temp.sqrt();
+        let rho = a_f0 * (1.0 - e2) / temp.powf(1.5);
+        let eta2 = nu / rho - 1.0;
+
+        let VII = tan / (2.0 * rho * nu);
+        let VIII = tan / (24.0 * rho * nu.powi(3)) * (5.0 + 3.0 * tan2 + eta2 - 9.0 * tan2 * eta2);
+        let IX = tan / (720.0 * rho * nu.powi(5)) * (61.0 + 90.0 * tan2 + 45.0 * tan4);
+        let X = sec_phi / nu;
+        let XI = sec_phi / (6.0 * nu.powi(3)) * (nu / rho + 2.0 * tan2);
+        let XII = sec_phi / (120.0 * nu.powi(5)) * (5.0 + 28.0 * tan2 + 24.0 * tan4);
+        let XIIA = sec_phi / (5040.0 * nu.powi(7)) * (61.0 + 662.0 * tan2 + 1320.0 * tan4 + 720.0 * tan4 * tan2);
+
+        let E = x - system.false_easting;
+
+        let lat = phi - VII * E.powi(2) + VIII * E.powi(4) - IX * E.powi(6);
+        let lon = system.lon_0.to_radians() + X * E - XI * E.powi(3) + XII * E.powi(5) - XIIA * E.powi(7);
+
+        (lon.to_degrees(), lat.to_degrees())
+    }
+}
+
+impl ToLonLat for TransverseMercatorSystem {
+    fn to_lon_lat(&self, mut data: Vec<(f64, f64)>, ellipsoid: &Ellipsoid, strategy: &mut MultithreadingStrategy)
+                  -> LonLatBuf
+    {
+        let system = *self;
+
+        match *strategy {
+            SingleCore => {
+                for &mut (ref mut x, ref mut y) in data.iter_mut() {
+                    let (lon, lat) = utils::tmerc_xy_to_lonlat(*x, *y, ellipsoid, &system);
+                    *x = lon; *y = lat;
+                }
+            },
+            #[cfg(all(not(target_arch = "wasm32"), feature = "scoped_threadpool"))]
+            MultiCore(ref mut thread_pool) => {
+                let system = &system;
+                thread_pool.scoped(|scoped| {
+                    for &mut (ref mut x, ref mut y) in data.iter_mut() {
+                        scoped.execute(move || {
+                            let (lon, lat) = utils::tmerc_xy_to_lonlat(*x, *y, ellipsoid, system);
+                            *x = lon; *y = lat;
+                        });
+                    }
+                });
+            },
+        }
+
+        LonLatBuf {
+            data,
+            ellipsoid: *ellipsoid,
+        }
+    }
+}
+
+impl FromLonLat for TransverseMercatorSystem {
+    fn from_lon_lat(&self, mut data: Vec<(f64, f64)>, ellipsoid: &Ellipsoid, strategy: &mut MultithreadingStrategy)
+                    -> CoordinateBuf
+    {
+        let system = *self;
+
+        match *strategy {
+            SingleCore => {
+                for &mut (ref mut lon, ref mut lat) in data.iter_mut() {
+                    let (x, y) = utils::lonlat_to_tmerc_xy(*lon, *lat, ellipsoid, &system);
+                    *lon = x; *lat = y;
+                }
+            },
+            #[cfg(all(not(target_arch = "wasm32"), feature = "scoped_threadpool"))]
+            MultiCore(ref mut thread_pool) => {
+                let system = &system;
+                thread_pool.scoped(|scoped| {
+                    for &mut (ref mut lon, ref mut lat) in data.iter_mut() {
+                        scoped.execute(move || {
+                            let (x, y) = utils::lonlat_to_tmerc_xy(*lon, *lat, ellipsoid, system);
+                            *lon = x; *lat = y;
+                        });
+                    }
+                });
+            },
+        }
+
+        CoordinateBuf {
+            data,
+            crs: Box::new(system),
+            ellipsoid: *ellipsoid,
+        }
+    }
+}
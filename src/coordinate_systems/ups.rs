@@ -0,0 +1,211 @@
+//! Universal Polar Stereographic (UPS)
+//!
+//! Implementation taken from:
+//!
+//! Snyder, J. P., 1987, Map Projections - A Working Manual,
+//! U.S. Geological Survey Professional Paper 1395, p. 154-163
+//!
+//! UPS is the polar stereographic projection used north of 84°N and south of
+//! 80°S, where UTM isn't defined. It uses a scale factor of 0.994 at the pole
+//! and a false easting / northing of 2000 km.
+
+use prelude::*;
+
+/// UPS System
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct UPSSystem {
+    pub hemisphere: Hemisphere,
+}
+
+pub mod utils {
+
+    use prelude::*;
+    use coordinate_systems::merc::utils::pj_phi2;
+    use std::f64::consts::FRAC_PI_4;
+
+    pub const UPS_SCALE_FACTOR: f64 = 0.994;
+    pub const FALSE_EASTING: f64 = 2000000.0;
+    pub const FALSE_NORTHING: f64 = 2000000.0;
+
+    /// `2 * a * k0 / sqrt((1 + e)^(1 + e) * (1 - e)^(1 - e))`
+    #[inline]
+    fn rho_factor(ellipsoid: &Ellipsoid, e: f64)
+                  -> f64
+    {
+        2.0 * ellipsoid.a * UPS_SCALE_FACTOR / ((1.0 + e).powf(1.0 + e) * (1.0 - e).powf(1.0 - e)).sqrt()
+    }
+
+    /// Function that converts a single lon-lat pair from (lon / lat) to UPS (x, y)
+    /// Points on the opposite hemisphere are projected to (NaN, NaN)
+    /// Returns: (x, y)
+    #[inline]
+    pub fn lonlat_to_ups_xy(lon: f64, lat: f64, ellipsoid: &Ellipsoid, hemisphere: Hemisphere)
+                            -> (f64, f64)
+    {
+        let temp = ellipsoid.b / ellipsoid.a;
+        let e = (1.0 - (temp * temp)).sqrt();
+
+        // Work on the north pole, mirror the southern hemisphere
+        let phi = match hemisphere {
+            Hemisphere::North => lat.to_radians(),
+            Hemisphere::South => -lat.to_radians(),
+        };
+
+        if phi < 0.0 || phi.abs() > ::std::f64::consts::FRAC_PI_2 {
+            return (f64::NAN, f64::NAN);
+        }
+
+        let con = e * phi.sin();
+        let t = (FRAC_PI_4 - phi / 2.0).tan() / ((1.0 - con) / (1.0 + con)).powf(e / 2.0);
+        let rho = rho_factor(ellipsoid, e) * t;
+
+        let lambda = lon.to_radians();
+        let x = FALSE_EASTING + rho * lambda.sin();
+        let y = match hemisphere {
+            Hemisphere::North => FALSE_NORTHING - rho * lambda.cos(),
+            Hemisphere::South => FALSE_NORTHING + rho * lambda.cos(),
+        };
+
+        (x, y)
+    }
+
+    /// Function that converts a single X-Y value from UPS to lon / lat
+    /// Returns: (lon, lat)
+    #[inline]
+    pub fn ups_xy_to_lonlat(x: f64, y: f64, ellipsoid: &Ellipsoid, hemisphere: Hemisphere)
+                            -> (f64, f64)
+    {
+        let temp = ellipsoid.b / ellipsoid.a;
+        let e = (1.0 - (temp * temp)).sqrt();
+
+        let dx = x - FALSE_EASTING;
+        let dy = y - FALSE_NORTHING;
+
+        let t = dx.hypot(dy) / rho_factor(ellipsoid, e);
+        let phi = pj_phi2(t, e);
+
+        match hemisphere {
+            Hemisphere::North => (dx.atan2(-dy).to_degrees(), phi.to_degrees()),
+            Hemisphere::South => (dx.atan2(dy).to_degrees(), -phi.to_degrees()),
+        }
+    }
+}
+
+impl ToLonLat for UPSSystem {
+    fn to_lon_lat(&self, mut data: Vec<(f64, f64)>, ellipsoid: &Ellipsoid, strategy: &mut MultithreadingStrategy)
+                  -> LonLatBuf
+    {
+        let hemisphere = self.hemisphere;
+
+        match *strategy {
+            SingleCore => {
+                for &mut (ref mut x, ref mut y) in data.iter_mut() {
+                    let (lon, lat) = utils::ups_xy_to_lonlat(*x, *y, ellipsoid, hemisphere);
+                    *x = lon; *y = lat;
+                }
+            },
+            #[cfg(all(not(target_arch = "wasm32"), feature = "scoped_threadpool"))]
+            MultiCore(ref mut thread_pool) => {
+                thread_pool.scoped(|scoped| {
+                    for &mut (ref mut x, ref mut y) in data.iter_mut() {
+                        scoped.execute(move || {
+                            let (lon, lat) = utils::ups_xy_to_lonlat(*x, *y, ellipsoid, hemisphere);
+                            *x = lon; *y = lat;
+                        });
+                    }
+                });
+            },
+        }
+
+        LonLatBuf {
+            data,
+            ellipsoid: *ellipsoid,
+        }
+    }
+}
+
+impl FromLonLat for UPSSystem {
+    fn from_lon_lat(&self, mut data: Vec<(f64, f64)>, ellipsoid: &Ellipsoid, strategy: &mut MultithreadingStrategy)
+                    -> CoordinateBuf
+    {
+        let hemisphere = self.hemisphere;
+
+        match *strategy {
+            SingleCore => {
+                for &mut (ref mut lon, ref mut lat) in data.iter_mut() {
+                    let (x, y) = utils::lonlat_to_ups_xy(*lon, *lat, ellipsoid, hemisphere);
+                    *lon = x; *lat = y;
+                }
+            },
+            #[cfg(all(not(target_arch = "wasm32"), feature = "scoped_threadpool"))]
+            MultiCore(ref mut thread_pool) => {
+                thread_pool.scoped(|scoped| {
+                    for &mut (ref mut lon, ref mut lat) in data.iter_mut() {
+                        scoped.execute(move || {
+                            let (x, y) = utils::lonlat_to_ups_xy(*lon, *lat, ellipsoid, hemisphere);
+                            *lon = x; *lat = y;
+                        });
+                    }
+                });
+            },
+        }
+
+        CoordinateBuf {
+            data,
+            crs: Box::new(*self),
+            ellipsoid: *ellipsoid,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::utils::*;
+
+    #[test]
+    fn reference_points() {
+        for &(lon, lat, x, y) in &[(45.0, 86.0, 2_314_145.219_1, 1_685_854.780_9),
+                                   (-135.0, -85.0, 1_607_232.311_9, 1_607_232.311_9),
+                                   (0.0, 84.0, 2_000_000.0, 1_333_272.296_3),
+                                   (100.0, -80.0, 3_096_042.908_4, 1_806_738.063_2),
+                                   (-30.0, 88.0, 1_888_965.365_5, 1_807_682.371_7),
+                                   (0.0, 90.0, 2_000_000.0, 2_000_000.0)] {
+            let hemisphere = if lat < 0.0 { Hemisphere::South } else { Hemisphere::North };
+            let (px, py) = lonlat_to_ups_xy(lon, lat, &WGS_1984_ELLIPSOID, hemisphere);
+            assert!((px - x).abs() < 1e-4 && (py - y).abs() < 1e-4, "({}, {}): ({}, {})", lon, lat, px, py);
+
+            let (plon, plat) = ups_xy_to_lonlat(px, py, &WGS_1984_ELLIPSOID, hemisphere);
+            assert!((plat - lat).abs() < 1e-10, "({}, {}): ({}, {})", lon, lat, plon, plat);
+            if lat.abs() < 90.0 {
+                assert!((plon - lon).abs() < 1e-10, "({}, {}): ({}, {})", lon, lat, plon, plat);
+            }
+        }
+    }
+
+    #[test]
+    fn scale() {
+        // the scale is 0.994 at the pole and 1 near 81°07'
+        let scale = |lat: f64| {
+            let (_, y0) = lonlat_to_ups_xy(0.0, lat - 1e-6, &WGS_1984_ELLIPSOID, Hemisphere::North);
+            let (_, y1) = lonlat_to_ups_xy(0.0, lat + 1e-6, &WGS_1984_ELLIPSOID, Hemisphere::North);
+            let phi = lat.to_radians();
+            let e2 = WGS_1984_ELLIPSOID.eccentricity_squared();
+            let meridian = WGS_1984_ELLIPSOID.a * (1.0 - e2) / (1.0 - e2 * phi.sin().powi(2)).powf(1.5);
+            (y1 - y0) / (2e-6_f64.to_radians() * meridian)
+        };
+        assert!((scale(90.0 - 1e-5) - UPS_SCALE_FACTOR).abs() < 1e-6);
+        assert!((scale(81.0 + 7.0 / 60.0) - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn opposite_hemisphere() {
+        assert!(lonlat_to_ups_xy(0.0, -1.0, &WGS_1984_ELLIPSOID, Hemisphere::North).0.is_nan());
+        assert!(lonlat_to_ups_xy(0.0, 1.0, &WGS_1984_ELLIPSOID, Hemisphere::South).0.is_nan());
+
+        let system = UPSSystem { hemisphere: Hemisphere::South };
+        let buf = system.from_lon_lat(vec![(-135.0, -85.0), (0.0, 85.0)], &WGS_1984_ELLIPSOID, &mut MultithreadingStrategy::SingleCore);
+        assert!((buf.data[0].0 - 1_607_232.311_9).abs() < 1e-4);
+        assert!(buf.data[1].0.is_nan() && buf.data[1].1.is_nan());
+    }
+}
@@ -1,11 +1,219 @@
 //! UTM projection
 
 use prelude::*;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// Hemisphere of a UTM / UPS coordinate
+///
+/// Southern hemisphere northings have a false northing of 10 000 km
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Hemisphere {
+    North,
+    South,
+}
 
 /// UTM System
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct UTMSystem {
     pub utm_zone: u8,
+    pub hemisphere: Hemisphere,
+}
+
+/// UTM System where every coordinate has its own zone
+///
+/// When projecting into this system, the zone of each point is chosen
+/// with `get_utm_zone` (and the hemisphere by the sign of the latitude)
+/// and recorded in `zones`, at the same index as the coordinate.
+/// When projecting out of this system, `zones` must contain one entry per
+/// coordinate, coordinates without a zone are projected to (NaN, NaN).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AutoUTMSystem {
+    pub zones: Vec<UTMSystem>,
+}
+
+/// Grid zone designator of the UTM area, such as "32U":
+/// the zone number (1 to 60) plus the latitude band (C to X)
+///
+/// Only zones that actually exist can be constructed, i.e. 32X, 34X and 36X
+/// (absorbed by the Svalbard exception) are rejected.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct UtmZone {
+    number: u8,
+    band: char,
+}
+
+/// Error that can occur when creating or parsing a `UtmZone`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UtmZoneError {
+    /// The zone number isn't between 1 and 60
+    InvalidNumber(u8),
+    /// The latitude band isn't one of C to X (without I and O)
+    InvalidBand(char),
+    /// The zone doesn't exist because of the Svalbard exception (32X, 34X, 36X)
+    NonexistentZone(u8, char),
+    /// The designator is malformed
+    InvalidFormat(String),
+}
+
+impl fmt::Display for UtmZoneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::UtmZoneError::*;
+        match *self {
+            InvalidNumber(number) => write!(f, "invalid UTM zone number: {} (must be 1 to 60)", number),
+            InvalidBand(band) => write!(f, "invalid latitude band: '{}'", band),
+            NonexistentZone(number, band) => write!(f, "UTM zone {}{} does not exist", number, band),
+            InvalidFormat(ref s) => write!(f, "invalid grid zone designator: \"{}\"", s),
+        }
+    }
+}
+
+impl Error for UtmZoneError { }
+
+impl UtmZone {
+    /// Creates a zone from a zone number (1 to 60) and a latitude band (C to X)
+    pub fn new(number: u8, band: char) -> Result<Self, UtmZoneError> {
+        let band = band.to_ascii_uppercase();
+
+        if !(1..=60).contains(&number) {
+            return Err(UtmZoneError::InvalidNumber(number));
+        }
+
+        if !utils::LATITUDE_BANDS.contains(band) {
+            return Err(UtmZoneError::InvalidBand(band));
+        }
+
+        if band == 'X' && (number == 32 || number == 34 || number == 36) {
+            return Err(UtmZoneError::NonexistentZone(number, band));
+        }
+
+        Ok(UtmZone { number, band })
+    }
+
+    /// Returns the zone a point (lon / lat, in degrees) falls in,
+    /// including the Norway / Svalbard exceptions
+    ///
+    /// Returns: `None` if the latitude is outside of 80°S to 84°N
+    pub fn from_lon_lat(lon: f64, lat: f64) -> Option<Self> {
+        if !lon.is_finite() {
+            return None;
+        }
+
+        let band = utils::get_latitude_band(lat)?;
+        Some(UtmZone { number: utils::get_utm_zone(lon, lat), band })
+    }
+
+    /// Zone number, 1 to 60
+    pub fn number(&self) -> u8 {
+        self.number
+    }
+
+    /// Latitude band letter, C to X
+    pub fn band(&self) -> char {
+        self.band
+    }
+
+    /// Hemisphere of the zone (bands C to M are southern)
+    pub fn hemisphere(&self) -> Hemisphere {
+        if self.band < 'N' { Hemisphere::South } else { Hemisphere::North }
+    }
+
+    /// Returns the `UTMSystem` for coordinates in this zone
+    pub fn system(&self) -> UTMSystem {
+        UTMSystem {
+            utm_zone: self.number,
+            hemisphere: self.hemisphere(),
+        }
+    }
+
+    /// Bounding box of the zone in degrees (min_lon, min_lat, max_lon, max_lat)
+    pub fn bounding_box(&self) -> (f64, f64, f64, f64) {
+        let band_index = utils::LATITUDE_BANDS.find(self.band).unwrap_or(0);
+        let min_lat = -80.0 + 8.0 * band_index as f64;
+        let max_lat = if self.band == 'X' { 84.0 } else { min_lat + 8.0 };
+
+        let mut min_lon = -180.0 + 6.0 * f64::from(self.number - 1);
+        let mut max_lon = min_lon + 6.0;
+
+        // Norway / Svalbard exceptions
+        match (self.number, self.band) {
+            (31, 'V') => { max_lon = 3.0; },
+            (32, 'V') => { min_lon = 3.0; },
+            (31, 'X') => { max_lon = 9.0; },
+            (33, 'X') => { min_lon = 9.0; max_lon = 21.0; },
+            (35, 'X') => { min_lon = 21.0; max_lon = 33.0; },
+            (37, 'X') => { min_lon = 33.0; },
+            _ => { },
+        }
+
+        (min_lon, min_lat, max_lon, max_lat)
+    }
+
+    /// Returns all zones that share a border or a corner with this zone,
+    /// wrapping around the antimeridian
+    pub fn neighbours(&self) -> Vec<UtmZone> {
+        const OFFSET: f64 = 1e-6;
+        const STEP: f64 = 0.5;
+
+        let (min_lon, min_lat, max_lon, max_lat) = self.bounding_box();
+        let mut samples = Vec::new();
+
+        // Sample just outside of each edge, including the corners
+        let mut lon = min_lon - OFFSET;
+        while lon <= max_lon + OFFSET {
+            samples.push((lon, min_lat - OFFSET));
+            samples.push((lon, max_lat + OFFSET));
+            lon += STEP;
+        }
+        samples.push((max_lon + OFFSET, min_lat - OFFSET));
+        samples.push((max_lon + OFFSET, max_lat + OFFSET));
+
+        let mut lat = min_lat;
+        while lat <= max_lat {
+            samples.push((min_lon - OFFSET, lat));
+            samples.push((max_lon + OFFSET, lat));
+            lat += STEP;
+        }
+        samples.push((min_lon - OFFSET, max_lat));
+        samples.push((max_lon + OFFSET, max_lat));
+
+        let mut neighbours = Vec::new();
+        for (lon, lat) in samples {
+            if let Some(zone) = UtmZone::from_lon_lat(lon, lat) {
+                if zone != *self && !neighbours.contains(&zone) {
+                    neighbours.push(zone);
+                }
+            }
+        }
+
+        neighbours
+    }
+}
+
+impl fmt::Display for UtmZone {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.number, self.band)
+    }
+}
+
+impl FromStr for UtmZone {
+    type Err = UtmZoneError;
+
+    /// Parses a designator such as "32U" or "4q"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let trimmed = s.trim();
+        let invalid_format = || UtmZoneError::InvalidFormat(s.to_string());
+
+        let band = trimmed.chars().last().ok_or_else(invalid_format)?;
+        let number = &trimmed[..trimmed.len() - band.len_utf8()];
+
+        if number.is_empty() || number.len() > 2 || !number.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(invalid_format());
+        }
+
+        UtmZone::new(number.parse().map_err(|_| invalid_format())?, band)
+    }
 }
 
 pub mod utils {
@@ -16,6 +224,9 @@ pub mod utils {
     pub const FALSE_EASTING: f64 = 500000.0;
     pub const FALSE_NORTHING: f64 = 10000000.0;
 
+    /// Latitude band letters, from 80°S to 84°N, 8° each (X is 12°)
+    pub const LATITUDE_BANDS: &str = "CDEFGHJKLMNPQRSTUVWX";
+
     /// Calculates the UTM zone this longitude falls in
     /// Handles exceptions for Norway / Svalbard
     /// For a visual representation: https://upload.wikimedia.org/wikipedia/commons/a/a5/UTM-Zone.svg
@@ -23,29 +234,62 @@ pub mod utils {
     /// Inputs: Longitude, in degrees
     ///         Latitude, in degrees
     ///
-    /// Returns: UTM Zone (0 to 60)
+    /// Returns: UTM Zone (1 to 60)
     ///
+    /// The latitude is not validated, use `UtmZone::from_lon_lat` to
+    /// also check that the point lies between 80°S and 84°N.
     #[allow(non_snake_case)]
     pub fn get_utm_zone(lon: f64, lat: f64)
     -> u8
     {
+        // Normalize to [-180, 180)
+        let lon = (lon + 180.0).rem_euclid(360.0) - 180.0;
         let mut zone = ((lon + 180.0) / 6.0).floor() + 1.0;
 
-        if lat > 56.0 && lat < 64.0 {
+        if (56.0..64.0).contains(&lat) {
             // Zone V, Norway
-            if lon > 3.0 && lon < 6.0 { zone += 1.0; }
+            if (3.0..6.0).contains(&lon)      { zone += 1.0; }
 
-        } else if lat > 72.0 && lat < 84.0 {
+        } else if (72.0..=84.0).contains(&lat) {
             // Zone X, Svalbard
-            if lon > 6.0 && lon < 9.0         { zone -= 1.0; }
-            if lon > 9.0 && lon < 12.0        { zone += 1.0; }
-            if lon > 18.0 && lon < 21.0       { zone -= 1.0; }
-            if lon > 21.0 && lon < 24.0       { zone += 1.0; }
-            if lon > 30.0 && lon < 33.0       { zone -= 1.0; }
-            if lon > 33.0 && lon < 36.0       { zone += 1.0; }
+            if (6.0..9.0).contains(&lon)      { zone -= 1.0; }
+            if (9.0..12.0).contains(&lon)     { zone += 1.0; }
+            if (18.0..21.0).contains(&lon)    { zone -= 1.0; }
+            if (21.0..24.0).contains(&lon)    { zone += 1.0; }
+            if (30.0..33.0).contains(&lon)    { zone -= 1.0; }
+            if (33.0..36.0).contains(&lon)    { zone += 1.0; }
         }
 
-        return zone as u8;
+        zone as u8
+    }
+
+    /// Returns the `UTMSystem` (zone and hemisphere) a point falls in,
+    /// see `get_utm_zone`
+    ///
+    /// Inputs: Longitude, in degrees
+    ///         Latitude, in degrees
+    #[inline]
+    pub fn get_utm_system(lon: f64, lat: f64)
+                          -> UTMSystem
+    {
+        UTMSystem {
+            utm_zone: get_utm_zone(lon, lat),
+            hemisphere: if lat < 0.0 { Hemisphere::South } else { Hemisphere::North },
+        }
+    }
+
+    /// Calculates the latitude band letter (C to X) this latitude falls in
+    ///
+    /// Returns: `None` if the latitude is outside of 80°S to 84°N
+    pub fn get_latitude_band(lat: f64)
+                             -> Option<char>
+    {
+        if !(-80.0..=84.0).contains(&lat) {
+            return None;
+        }
+
+        let index = (((lat + 80.0) / 8.0).floor() as usize).min(LATITUDE_BANDS.len() - 1);
+        Some(LATITUDE_BANDS.as_bytes()[index] as char)
     }
 
 
@@ -156,11 +400,13 @@ pub mod utils {
     /// Returns: (lon, lat)
     #[inline]
     #[allow(non_snake_case)]
-    pub fn utm_xy_to_lonlat(mut x: f64, mut y: f64, ellipsoid: &Ellipsoid, utm_zone: u8)
+    pub fn utm_xy_to_lonlat(mut x: f64, mut y: f64, ellipsoid: &Ellipsoid, utm_zone: u8, hemisphere: Hemisphere)
                             -> (f64, f64)
     {
         // If in southern hemisphere, adjust y accordingly to be negative
-        y -= FALSE_NORTHING;
+        if hemisphere == Hemisphere::South {
+            y -= FALSE_NORTHING;
+        }
         y /= UTM_SCALE_FACTOR;
 
         x -= FALSE_EASTING;
@@ -170,7 +416,7 @@ pub mod utils {
         let lambda0 = utm_central_meridian(utm_zone);
 
         // Get the value of phif, the footpoint latitude.
-        let phif = footpoint_latitude(y, &ellipsoid);
+        let phif = footpoint_latitude(y, ellipsoid);
 
         // Precalculate ep2
         let ep2 = (ellipsoid.a.powi(2) - ellipsoid.b.powi(2)) / ellipsoid.b.powi(2);
@@ -247,7 +493,7 @@ pub mod utils {
     /// Returns: (x, y)
     #[inline]
     #[allow(non_snake_case)]
-    pub fn lonlat_to_utm_xy(mut lon: f64, mut lat: f64, ellipsoid: &Ellipsoid, utm_zone: u8)
+    pub fn lonlat_to_utm_xy(mut lon: f64, mut lat: f64, ellipsoid: &Ellipsoid, utm_zone: u8, hemisphere: Hemisphere)
                             -> (f64, f64)
     {
         lon = lon.to_radians();
@@ -293,7 +539,7 @@ pub mod utils {
             (N / 5040.0 * lat.cos().powi(7) * l7coef * l.powi(7));
 
         // Calculate northing
-        let mut y = arc_length_of_meridian(lat, &ellipsoid) +
+        let mut y = arc_length_of_meridian(lat, ellipsoid) +
             (t / 2.0 * N * lat.cos().powi(2) * l.powi(2)) +
             (t / 24.0 * N * lat.cos().powi(4) * l4coef * l.powi(4)) +
             (t / 720.0 * N * lat.cos().powi(6) * l6coef * l.powi(6)) +
@@ -301,10 +547,10 @@ pub mod utils {
 
         // Adjust easting and northing for UTM system
         x = (x * UTM_SCALE_FACTOR) + FALSE_EASTING;
-        y = y * UTM_SCALE_FACTOR;
+        y *= UTM_SCALE_FACTOR;
 
-        if y > 0.0 {
-            y = y + FALSE_NORTHING;
+        if hemisphere == Hemisphere::South {
+            y += FALSE_NORTHING;
         }
 
         (x, y)
@@ -316,11 +562,12 @@ impl ToLonLat for UTMSystem {
                   -> LonLatBuf
     {
         let zone = self.utm_zone;
+        let hemisphere = self.hemisphere;
 
         match *strategy {
             SingleCore => {
                 for &mut (ref mut x, ref mut y) in data.iter_mut() {
-                    let (lon, lat) = utils::utm_xy_to_lonlat(*x, *y, ellipsoid, zone);
+                    let (lon, lat) = utils::utm_xy_to_lonlat(*x, *y, ellipsoid, zone, hemisphere);
                     *x = lon; *y = lat;
                 }
             },
@@ -329,7 +576,7 @@ impl ToLonLat for UTMSystem {
                 thread_pool.scoped(|scoped| {
                     for &mut (ref mut x, ref mut y) in data.iter_mut() {
                         scoped.execute(move || {
-                            let (lon, lat) = utils::utm_xy_to_lonlat(*x, *y, ellipsoid, zone);
+                            let (lon, lat) = utils::utm_xy_to_lonlat(*x, *y, ellipsoid, zone, hemisphere);
                             *x = lon; *y = lat;
                         });
                     }
@@ -338,7 +585,7 @@ impl ToLonLat for UTMSystem {
         }
 
         LonLatBuf {
-            data: data,
+            data,
             ellipsoid: *ellipsoid,
         }
     }
@@ -349,11 +596,12 @@ impl FromLonLat for UTMSystem {
                     -> CoordinateBuf
     {
         let zone = self.utm_zone;
+        let hemisphere = self.hemisphere;
 
         match *strategy {
             SingleCore => {
                 for &mut (ref mut lon, ref mut lat) in data.iter_mut() {
-                    let (x, y) = utils::lonlat_to_utm_xy(*lon, *lat, ellipsoid, zone);
+                    let (x, y) = utils::lonlat_to_utm_xy(*lon, *lat, ellipsoid, zone, hemisphere);
                     *lon = x; *lat = y;
                 }
             },
@@ -362,22 +610,225 @@ impl FromLonLat for UTMSystem {
                 thread_pool.scoped(|scoped| {
                     for &mut (ref mut lon, ref mut lat) in data.iter_mut() {
                         scoped.execute(move || {
-                            let (x, y) = utils::lonlat_to_utm_xy(*lon, *lat, ellipsoid, zone);
+                            let (x, y) = utils::lonlat_to_utm_xy(*lon, *lat, ellipsoid, zone, hemisphere);
                             *lon = x; *lat = y;
                         });
                     }
                 });
             },
-            #[cfg(all(not(target_arch = "wasm32"), feature = "scoped_threadpool"))]
-            _ => unimplemented!("Multithreading methods other than SingleCore and MultiCore are not yet implemented!"),
         }
 
         CoordinateBuf {
-            data: data,
+            data,
             crs: Box::new(UTMSystem {
                 utm_zone: self.utm_zone,
+                hemisphere: self.hemisphere,
             }),
             ellipsoid: *ellipsoid,
         }
     }
 }
+
+impl ToLonLat for AutoUTMSystem {
+    fn to_lon_lat(&self, mut data: Vec<(f64, f64)>, ellipsoid: &Ellipsoid, strategy: &mut MultithreadingStrategy)
+                  -> LonLatBuf
+    {
+        let missing = data.len().saturating_sub(self.zones.len());
+        let data_len = data.len();
+        let zones = &self.zones;
+
+        match *strategy {
+            SingleCore => {
+                for (&mut (ref mut x, ref mut y), zone) in data.iter_mut().zip(zones.iter()) {
+                    let (lon, lat) = utils::utm_xy_to_lonlat(*x, *y, ellipsoid, zone.utm_zone, zone.hemisphere);
+                    *x = lon; *y = lat;
+                }
+            },
+            #[cfg(all(not(target_arch = "wasm32"), feature = "scoped_threadpool"))]
+            MultiCore(ref mut thread_pool) => {
+                thread_pool.scoped(|scoped| {
+                    for (&mut (ref mut x, ref mut y), zone) in data.iter_mut().zip(zones.iter()) {
+                        scoped.execute(move || {
+                            let (lon, lat) = utils::utm_xy_to_lonlat(*x, *y, ellipsoid, zone.utm_zone, zone.hemisphere);
+                            *x = lon; *y = lat;
+                        });
+                    }
+                });
+            },
+        }
+
+        for coordinate in data.iter_mut().skip(data_len - missing) {
+            *coordinate = (f64::NAN, f64::NAN);
+        }
+
+        LonLatBuf {
+            data,
+            ellipsoid: *ellipsoid,
+        }
+    }
+}
+
+impl FromLonLat for AutoUTMSystem {
+    fn from_lon_lat(&self, mut data: Vec<(f64, f64)>, ellipsoid: &Ellipsoid, strategy: &mut MultithreadingStrategy)
+                    -> CoordinateBuf
+    {
+        let mut zones = vec![UTMSystem { utm_zone: 0, hemisphere: Hemisphere::North }; data.len()];
+
+        match *strategy {
+            SingleCore => {
+                for (&mut (ref mut lon, ref mut lat), zone) in data.iter_mut().zip(zones.iter_mut()) {
+                    *zone = utils::get_utm_system(*lon, *lat);
+                    let (x, y) = utils::lonlat_to_utm_xy(*lon, *lat, ellipsoid, zone.utm_zone, zone.hemisphere);
+                    *lon = x; *lat = y;
+                }
+            },
+            #[cfg(all(not(target_arch = "wasm32"), feature = "scoped_threadpool"))]
+            MultiCore(ref mut thread_pool) => {
+                thread_pool.scoped(|scoped| {
+                    for (&mut (ref mut lon, ref mut lat), zone) in data.iter_mut().zip(zones.iter_mut()) {
+                        scoped.execute(move || {
+                            *zone = utils::get_utm_system(*lon, *lat);
+                            let (x, y) = utils::lonlat_to_utm_xy(*lon, *lat, ellipsoid, zone.utm_zone, zone.hemisphere);
+                            *lon = x; *lat = y;
+                        });
+                    }
+                });
+            },
+        }
+
+        CoordinateBuf {
+            data,
+            crs: Box::new(AutoUTMSystem { zones }),
+            ellipsoid: *ellipsoid,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zone(s: &str) -> UtmZone {
+        s.parse().unwrap()
+    }
+
+    fn sorted(zones: Vec<UtmZone>) -> Vec<String> {
+        let mut zones: Vec<String> = zones.iter().map(UtmZone::to_string).collect();
+        zones.sort();
+        zones
+    }
+
+    #[test]
+    fn parse_and_format() {
+        assert_eq!(zone("32U"), UtmZone::new(32, 'U').unwrap());
+        assert_eq!(zone(" 4q ").to_string(), "4Q");
+        assert_eq!(zone("04Q"), zone("4Q"));
+
+        assert_eq!("61U".parse::<UtmZone>(), Err(UtmZoneError::InvalidNumber(61)));
+        assert_eq!("0U".parse::<UtmZone>(), Err(UtmZoneError::InvalidNumber(0)));
+        assert_eq!("32I".parse::<UtmZone>(), Err(UtmZoneError::InvalidBand('I')));
+        assert_eq!("32Y".parse::<UtmZone>(), Err(UtmZoneError::InvalidBand('Y')));
+        assert_eq!("34X".parse::<UtmZone>(), Err(UtmZoneError::NonexistentZone(34, 'X')));
+        assert_eq!("U".parse::<UtmZone>(), Err(UtmZoneError::InvalidFormat("U".to_string())));
+        assert_eq!("123U".parse::<UtmZone>(), Err(UtmZoneError::InvalidFormat("123U".to_string())));
+        assert_eq!("".parse::<UtmZone>(), Err(UtmZoneError::InvalidFormat("".to_string())));
+    }
+
+    #[test]
+    fn from_lon_lat() {
+        let designator = |lon, lat| UtmZone::from_lon_lat(lon, lat).map(|zone| zone.to_string());
+        assert_eq!(designator(13.41, 52.52).as_deref(), Some("33U"));
+        assert_eq!(designator(151.2093, -33.8688).as_deref(), Some("56H"));
+        assert_eq!(designator(5.0, 60.0).as_deref(), Some("32V"));
+        assert_eq!(designator(10.0, 78.0).as_deref(), Some("33X"));
+        assert_eq!(designator(0.0, 84.0).as_deref(), Some("31X"));
+        assert_eq!(designator(0.0, -80.0).as_deref(), Some("31C"));
+        assert_eq!(designator(180.0, 0.0).as_deref(), Some("1N"));
+        assert_eq!(designator(-0.000_001, -0.000_001).as_deref(), Some("30M"));
+        assert_eq!(designator(0.0, 84.1), None);
+        assert_eq!(designator(0.0, -80.1), None);
+        assert_eq!(designator(f64::NAN, 0.0), None);
+
+        assert_eq!(zone("56H").system(), UTMSystem { utm_zone: 56, hemisphere: Hemisphere::South });
+        assert_eq!(zone("32M").hemisphere(), Hemisphere::South);
+        assert_eq!(zone("32N").hemisphere(), Hemisphere::North);
+    }
+
+    #[test]
+    fn bounding_boxes() {
+        assert_eq!(zone("32U").bounding_box(), (6.0, 48.0, 12.0, 56.0));
+        assert_eq!(zone("31V").bounding_box(), (0.0, 56.0, 3.0, 64.0));
+        assert_eq!(zone("32V").bounding_box(), (3.0, 56.0, 12.0, 64.0));
+        assert_eq!(zone("31X").bounding_box(), (0.0, 72.0, 9.0, 84.0));
+        assert_eq!(zone("33X").bounding_box(), (9.0, 72.0, 21.0, 84.0));
+        assert_eq!(zone("37X").bounding_box(), (33.0, 72.0, 42.0, 84.0));
+        assert_eq!(zone("1C").bounding_box(), (-180.0, -80.0, -174.0, -72.0));
+
+        // every zone contains the center of its bounding box
+        for number in 1..=60 {
+            for band in utils::LATITUDE_BANDS.chars() {
+                if let Ok(zone) = UtmZone::new(number, band) {
+                    let (min_lon, min_lat, max_lon, max_lat) = zone.bounding_box();
+                    let center = UtmZone::from_lon_lat((min_lon + max_lon) / 2.0, (min_lat + max_lat) / 2.0);
+                    assert_eq!(center, Some(zone));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn neighbours() {
+        assert_eq!(sorted(zone("32U").neighbours()), ["31T", "31U", "32T", "32V", "33T", "33U", "33V"]);
+        assert_eq!(sorted(zone("31V").neighbours()), ["30U", "30V", "30W", "31U", "31W", "32V"]);
+        assert_eq!(sorted(zone("33X").neighbours()), ["31X", "32W", "33W", "34W", "35X"]);
+        assert_eq!(sorted(zone("1C").neighbours()), ["1D", "2C", "2D", "60C", "60D"]);
+    }
+
+    // computed with the Krüger series of the transverse Mercator projection
+    const REFERENCE_POINTS: [(f64, f64, u8, Hemisphere, f64, f64); 5] = [
+        (13.41, 52.52, 33, Hemisphere::North, 392_118.486_6, 5_820_064.675_1),
+        (151.2093, -33.8688, 56, Hemisphere::South, 334_368.633_6, 6_250_948.345_4),
+        (5.0, 60.0, 32, Hemisphere::North, 276_979.926_4, 6_658_157.202_4),
+        (-77.035_258, 38.889_469, 18, Hemisphere::North, 323_481.629_6, 4_306_479.720_0),
+        (170.0, -5.0, 59, Hemisphere::South, 389_140.073_0, 9_447_251.379_1),
+    ];
+
+    #[test]
+    fn reference_points() {
+        for &(lon, lat, zone, hemisphere, x, y) in &REFERENCE_POINTS {
+            let (px, py) = utils::lonlat_to_utm_xy(lon, lat, &WGS_1984_ELLIPSOID, zone, hemisphere);
+            assert!((px - x).abs() < 1e-3 && (py - y).abs() < 1e-3, "({}, {}): ({}, {})", lon, lat, px, py);
+
+            let (plon, plat) = utils::utm_xy_to_lonlat(px, py, &WGS_1984_ELLIPSOID, zone, hemisphere);
+            assert!((plon - lon).abs() < 1e-9 && (plat - lat).abs() < 1e-9, "({}, {}): ({}, {})", lon, lat, plon, plat);
+        }
+    }
+
+    #[test]
+    fn auto_utm_chooses_the_zone_per_point() {
+        let mut strategy = MultithreadingStrategy::SingleCore;
+        let points: Vec<(f64, f64)> = REFERENCE_POINTS.iter().map(|p| (p.0, p.1)).collect();
+
+        let projected = AutoUTMSystem::default().from_lon_lat(points.clone(), &WGS_1984_ELLIPSOID, &mut strategy);
+        for (&(x, y), &(_, _, _, _, ex, ey)) in projected.data.iter().zip(&REFERENCE_POINTS) {
+            assert!((x - ex).abs() < 1e-3 && (y - ey).abs() < 1e-3, "({}, {})", x, y);
+        }
+
+        let system = projected.crs.as_any().downcast_ref::<AutoUTMSystem>().unwrap();
+        let zones: Vec<(u8, Hemisphere)> = system.zones.iter().map(|zone| (zone.utm_zone, zone.hemisphere)).collect();
+        let expected: Vec<(u8, Hemisphere)> = REFERENCE_POINTS.iter().map(|p| (p.2, p.3)).collect();
+        assert_eq!(zones, expected);
+
+        let back = system.to_lon_lat(projected.data.clone(), &WGS_1984_ELLIPSOID, &mut strategy);
+        for (a, b) in back.data.iter().zip(&points) {
+            assert!((a.0 - b.0).abs() < 1e-9 && (a.1 - b.1).abs() < 1e-9, "{:?} != {:?}", a, b);
+        }
+
+        // coordinates without a zone can't be unprojected
+        let mut data = projected.data;
+        data.push((500_000.0, 0.0));
+        let back = system.to_lon_lat(data, &WGS_1984_ELLIPSOID, &mut strategy);
+        assert_eq!(back.data.len(), 6);
+        assert!(back.data[5].0.is_nan() && back.data[5].1.is_nan());
+    }
+}
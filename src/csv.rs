@@ -0,0 +1,385 @@
+//! Streaming CSV / TSV reprojection
+//!
+//! `reproject_csv` reads delimited text record by record, takes the x and y
+//! coordinates from the columns chosen in `CsvOptions` (by header name or by
+//! index), projects them in chunks through `CoordinateSource::project` and writes
+//! every record back, with the coordinates either replaced in place or appended
+//! as new columns. Quoted fields (RFC 4180) and a UTF-8 byte order mark are
+//! handled, all other fields are written back unchanged.
+//!
+//! Coordinates can be decimal numbers or, for geographic coordinates, degrees,
+//! minutes and seconds such as `48°08'14.5"N` or `11 34 31.2 E` (see `dms`).
+//! A record whose coordinates can't be read or projected doesn't stop the file:
+//! it is reported as a `RowError` and written without new coordinates (unchanged
+//! when replacing, with empty appended columns otherwise).
+//!
+//! The CRS of proj5 are two-dimensional, so an optional z column is only checked
+//! to be a number and written as it is.
+//!
+//! ```rust
+//! extern crate proj5;
+//!
+//! use proj5::prelude::*;
+//! use proj5::csv::*;
+//!
+//! fn main() {
+//!     let input = "name,lat,lon\nStuttgart,48.0,9.0\n\"Broken, Inc.\",north,9\nMunich,48°08'14.5\"N,11°34'31.2\"E\n";
+//!     let mut output = Vec::new();
+//!
+//!     let mut options = CsvOptions::new(CsvColumn::Name("lon".to_string()), CsvColumn::Name("lat".to_string()));
+//!     options.output = CsvOutput::Append("easting".to_string(), "northing".to_string());
+//!     options.precision = Some(2);
+//!
+//!     let utm = UTMSystem { utm_zone: 32, hemisphere: Hemisphere::North };
+//!     let mut strategy = MultithreadingStrategy::SingleCore;
+//!     let report = reproject_csv(input.as_bytes(), &mut output, &options,
+//!                                &LonLatSystem, &WGS_1984_ELLIPSOID, &utm, &WGS_1984_ELLIPSOID,
+//!                                &mut strategy).unwrap();
+//!
+//!     assert_eq!(report.rows, 3);
+//!     assert_eq!(report.errors, vec![RowError { line: 3, kind: RowErrorKind::InvalidValue(1, "north".to_string()) }]);
+//!
+//!     let output = String::from_utf8(output).unwrap();
+//!     let lines: Vec<&str> = output.lines().collect();
+//!     assert_eq!(lines[0], "name,lat,lon,easting,northing");
+//!     assert_eq!(lines[1], "Stuttgart,48.0,9.0,500000.00,5316300.22");
+//!     assert_eq!(lines[2], "\"Broken, Inc.\",north,9,,");
+//! }
+//! ```
+
+use prelude::*;
+use coordinate_buf::project_batch;
+use dms;
+use std::error::Error;
+use std::fmt;
+use std::io::{BufRead, Write};
+
+/// Column of a CSV file
+#[derive(Debug, Clone, PartialEq)]
+pub enum CsvColumn {
+    /// Column with the name in the header row
+    Name(String),
+    /// Zero-based index of the column
+    Index(usize),
+}
+
+/// Where the projected coordinates are written
+#[derive(Debug, Clone, PartialEq)]
+pub enum CsvOutput {
+    /// Overwrite the x and y columns
+    Replace,
+    /// Append an x and a y column, with the given header names
+    Append(String, String),
+}
+
+/// Layout of the CSV file and of the output
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvOptions {
+    /// Field delimiter, `b','` for CSV or `b'\t'` for TSV
+    pub delimiter: u8,
+    /// Whether the first record is a header row, required for `CsvColumn::Name`
+    pub has_header: bool,
+    pub x: CsvColumn,
+    pub y: CsvColumn,
+    pub z: Option<CsvColumn>,
+    pub output: CsvOutput,
+    /// Number of records that are projected together
+    pub chunk_size: usize,
+    /// Decimal places of the projected coordinates, `None` for the shortest exact representation
+    pub precision: Option<usize>,
+}
+
+impl CsvOptions {
+    /// Comma-separated values with a header row, replacing the x and y columns in chunks of 10000 records
+    pub fn new(x: CsvColumn, y: CsvColumn) -> Self {
+        CsvOptions {
+            delimiter: b',',
+            has_header: true,
+            x,
+            y,
+            z: None,
+            output: CsvOutput::Replace,
+            chunk_size: 10_000,
+            precision: None,
+        }
+    }
+}
+
+/// Outcome of a reprojection
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvReport {
+    /// Number of records, without the header row
+    pub rows: usize,
+    /// Records that weren't projected
+    pub errors: Vec<RowError>,
+}
+
+/// A record that wasn't projected
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowError {
+    /// One-based line number of the start of the record
+    pub line: usize,
+    pub kind: RowErrorKind,
+}
+
+/// Reason why a record wasn't projected
+#[derive(Debug, Clone, PartialEq)]
+pub enum RowErrorKind {
+    /// The record has no field with the index
+    MissingField(usize),
+    /// The field with the index (first field) isn't a coordinate
+    InvalidValue(usize, String),
+    /// The projection of the coordinates isn't finite, e.g. outside the area of the CRS
+    NotProjectable,
+}
+
+/// Error that stops the reprojection
+#[derive(Debug, Clone, PartialEq)]
+pub enum CsvError {
+    /// Reading or writing failed, with the message of the I/O error
+    Io(String),
+    /// The header row has no column with the name
+    UnknownColumn(String),
+    /// A column is chosen by name, but there is no header row
+    MissingHeader,
+    /// The quoted field that starts on the line isn't closed
+    UnterminatedQuote(usize),
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::CsvError::*;
+        match *self {
+            Io(ref message) => write!(f, "I/O error: {}", message),
+            UnknownColumn(ref name) => write!(f, "no column named \"{}\"", name),
+            MissingHeader => write!(f, "columns can only be chosen by name if there is a header row"),
+            UnterminatedQuote(line) => write!(f, "unterminated quoted field on line {}", line),
+        }
+    }
+}
+
+impl Error for CsvError { }
+
+impl From<::std::io::Error> for CsvError {
+    fn from(error: ::std::io::Error) -> Self {
+        CsvError::Io(error.to_string())
+    }
+}
+
+/// Reprojects the coordinate columns of CSV or TSV text, see the module documentation
+#[allow(clippy::too_many_arguments)]
+pub fn reproject_csv<R: BufRead, W: Write>(input: R, mut output: W, options: &CsvOptions,
+                                           source_crs: &dyn Crs, source_ellipsoid: &Ellipsoid,
+                                           target_crs: &dyn Crs, target_ellipsoid: &Ellipsoid,
+                                           strategy: &mut MultithreadingStrategy)
+                                           -> Result<CsvReport, CsvError>
+{
+    let mut reader = RecordReader { input, delimiter: options.delimiter, line: 0 };
+    let mut report = CsvReport { rows: 0, errors: Vec::new() };
+
+    let header = if options.has_header { reader.next_record()?.map(|(_, fields)| fields) } else { None };
+    let column_index = |column: &CsvColumn| match *column {
+        CsvColumn::Index(index) => Ok(index),
+        CsvColumn::Name(ref name) => header.as_ref().ok_or(CsvError::MissingHeader)?
+            .iter().position(|h| h.trim() == name).ok_or_else(|| CsvError::UnknownColumn(name.clone())),
+    };
+    let x = column_index(&options.x)?;
+    let y = column_index(&options.y)?;
+    let z = options.z.as_ref().map(column_index).transpose()?;
+
+    if let Some(mut header) = header.clone() {
+        if let CsvOutput::Append(ref x_name, ref y_name) = options.output {
+            header.push(x_name.clone());
+            header.push(y_name.clone());
+        }
+        write_record(&mut output, &header, options.delimiter)?;
+    }
+
+    let mut chunk = Vec::new();
+    loop {
+        let record = reader.next_record()?;
+        let end = record.is_none();
+        if let Some(record) = record {
+            chunk.push(record);
+        }
+
+        if chunk.len() >= options.chunk_size.max(1) || (end && !chunk.is_empty()) {
+            report.rows += chunk.len();
+            project_chunk(&mut chunk, (x, y, z), options, &mut report.errors,
+                          source_crs, source_ellipsoid, target_crs, target_ellipsoid, strategy);
+            for (_, fields) in chunk.drain(..) {
+                write_record(&mut output, &fields, options.delimiter)?;
+            }
+        }
+
+        if end {
+            break;
+        }
+    }
+
+    output.flush()?;
+    Ok(report)
+}
+
+/// Projects the coordinates of the records and writes them into the fields
+#[allow(clippy::too_many_arguments)]
+fn project_chunk(chunk: &mut [(usize, Vec<String>)], (x, y, z): (usize, usize, Option<usize>),
+                 options: &CsvOptions, errors: &mut Vec<RowError>,
+                 source_crs: &dyn Crs, source_ellipsoid: &Ellipsoid,
+                 target_crs: &dyn Crs, target_ellipsoid: &Ellipsoid,
+                 strategy: &mut MultithreadingStrategy)
+{
+    let field = |fields: &[String], index: usize| fields.get(index).ok_or(RowErrorKind::MissingField(index)).cloned();
+    let read = |fields: &[String]| -> Result<(f64, f64), RowErrorKind> {
+        let (x_value, y_value) = (field(fields, x)?, field(fields, y)?);
+        let x_coordinate = parse_coordinate(&x_value, true).ok_or_else(|| RowErrorKind::InvalidValue(x, x_value.clone()))?;
+        let y_coordinate = parse_coordinate(&y_value, false).ok_or_else(|| RowErrorKind::InvalidValue(y, y_value.clone()))?;
+        if let Some(z) = z {
+            let z_value = field(fields, z)?;
+            z_value.trim().parse::<f64>().map_err(|_| RowErrorKind::InvalidValue(z, z_value.clone()))?;
+        }
+        Ok((x_coordinate, y_coordinate))
+    };
+
+    // errors of earlier chunks are already in order
+    let first_error = errors.len();
+
+    // only the valid records are projected
+    let mut valid = Vec::new();
+    let mut data = Vec::new();
+    for (i, &(line, ref fields)) in chunk.iter().enumerate() {
+        match read(fields) {
+            Ok(coordinate) => { valid.push(i); data.push(coordinate); },
+            Err(kind) => errors.push(RowError { line, kind }),
+        }
+    }
+
+    let projected = project_batch(data, source_crs, source_ellipsoid, target_crs, target_ellipsoid, strategy);
+    let format = |value: f64| match options.precision {
+        Some(precision) => format!("{:.*}", precision, value),
+        None => value.to_string(),
+    };
+
+    let mut projected = valid.into_iter().zip(projected).peekable();
+    for (i, &mut (line, ref mut fields)) in chunk.iter_mut().enumerate() {
+        let coordinate = match projected.peek() {
+            Some(&(index, coordinate)) if index == i => { projected.next(); Some(coordinate) },
+            _ => None,
+        };
+
+        let coordinate = coordinate.filter(|&(px, py)| {
+            let finite = px.is_finite() && py.is_finite();
+            if !finite {
+                errors.push(RowError { line, kind: RowErrorKind::NotProjectable });
+            }
+            finite
+        });
+
+        match (&options.output, coordinate) {
+            (&CsvOutput::Replace, Some((px, py))) => {
+                fields[x] = format(px);
+                fields[y] = format(py);
+            },
+            (&CsvOutput::Replace, None) => { },
+            (&CsvOutput::Append(..), Some((px, py))) => {
+                fields.push(format(px));
+                fields.push(format(py));
+            },
+            (&CsvOutput::Append(..), None) => {
+                fields.push(String::new());
+                fields.push(String::new());
+            },
+        }
+    }
+
+    // the errors of the projection come after the ones of reading
+    errors[first_error..].sort_by_key(|e| e.line);
+}
+
+/// Reads a decimal number, or degrees, minutes and seconds with the hemisphere
+/// letters of the axis (E / W for x, N / S for y)
+fn parse_coordinate(value: &str, is_x: bool) -> Option<f64> {
+    let value = value.trim();
+    if let Ok(number) = value.parse::<f64>() {
+        return Some(number).filter(|n| n.is_finite());
+    }
+    if is_x { dms::parse_longitude(value).ok() } else { dms::parse_latitude(value).ok() }
+}
+
+/// Reads records, a record can span several lines if a quoted field contains line breaks
+struct RecordReader<R: BufRead> {
+    input: R,
+    delimiter: u8,
+    /// Number of lines read so far
+    line: usize,
+}
+
+impl<R: BufRead> RecordReader<R> {
+    /// Returns the next record and its line number, skipping empty lines
+    fn next_record(&mut self) -> Result<Option<(usize, Vec<String>)>, CsvError> {
+        let delimiter = self.delimiter as char;
+        let mut text = String::new();
+
+        loop {
+            text.clear();
+            if self.input.read_line(&mut text)? == 0 {
+                return Ok(None);
+            }
+            self.line += 1;
+            if self.line == 1 && text.starts_with('\u{feff}') {
+                text.remove(0);
+            }
+            if !text.trim_end_matches(['\r', '\n']).is_empty() {
+                break;
+            }
+        }
+
+        let start = self.line;
+        let mut fields = Vec::new();
+        let mut field = String::new();
+        let mut quoted = false;
+        let mut chars = text.chars().peekable();
+
+        loop {
+            let c = match chars.next() {
+                Some(c) => c,
+                None if quoted => {
+                    // the quoted field continues on the next line
+                    text.clear();
+                    if self.input.read_line(&mut text)? == 0 {
+                        return Err(CsvError::UnterminatedQuote(start));
+                    }
+                    self.line += 1;
+                    chars = text.chars().peekable();
+                    continue;
+                },
+                None => break,
+            };
+
+            match c {
+                '"' if quoted && chars.peek() == Some(&'"') => { chars.next(); field.push('"'); },
+                '"' if quoted => quoted = false,
+                '"' if field.is_empty() => quoted = true,
+                c if quoted => field.push(c),
+                c if c == delimiter => fields.push(::std::mem::take(&mut field)),
+                '\r' | '\n' => { },
+                c => field.push(c),
+            }
+        }
+        fields.push(field);
+        Ok(Some((start, fields)))
+    }
+}
+
+fn write_record<W: Write>(output: &mut W, fields: &[String], delimiter: u8) -> Result<(), CsvError> {
+    let delimiter = delimiter as char;
+    let line = fields.iter().map(|field| {
+        if field.contains([delimiter, '"', '\r', '\n']) {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field.clone()
+        }
+    }).collect::<Vec<String>>().join(&delimiter.to_string());
+    writeln!(output, "{}", line)?;
+    Ok(())
+}
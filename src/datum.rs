@@ -0,0 +1,183 @@
+//! Geodetic datums and Helmert transformations to WGS84
+//!
+//! The datums are the ones known to PROJ.4 (`+datum=...`), with their
+//! `+towgs84` parameters. The CRS readers and writers carry them along losslessly.
+//!
+//! The transformations are applied on geocentric coordinates: `helmert` steps of
+//! a `pipeline`, and `shapefile::reproject_shapefile`, which shifts the points
+//! through WGS84 (source to WGS84, then the inverse of the target transformation)
+//! when the source and target datums differ. `Helmert` rotations always use the
+//! position vector convention (EPSG method 1033), as `+towgs84` does in PROJ;
+//! `helmert` steps choose theirs with `+convention`, where `coordinate_frame`
+//! rotates in the opposite direction.
+
+use ellipsoid::*;
+
+/// Seven-parameter Helmert transformation to WGS84, as in PROJ's `+towgs84`
+///
+/// The rotations use the position vector convention (EPSG method 1033).
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Helmert {
+    /// Translation along the X axis, in meters
+    pub dx: f64,
+    /// Translation along the Y axis, in meters
+    pub dy: f64,
+    /// Translation along the Z axis, in meters
+    pub dz: f64,
+    /// Rotation around the X axis, in arc seconds
+    pub rx: f64,
+    /// Rotation around the Y axis, in arc seconds
+    pub ry: f64,
+    /// Rotation around the Z axis, in arc seconds
+    pub rz: f64,
+    /// Scale difference, in parts per million
+    pub ds: f64,
+}
+
+impl Helmert {
+    /// Transformation that only translates the geocentric coordinates
+    pub const fn translation(dx: f64, dy: f64, dz: f64) -> Self {
+        Helmert { dx, dy, dz, rx: 0.0, ry: 0.0, rz: 0.0, ds: 0.0 }
+    }
+
+    /// Creates the transformation from the 3 or 7 values of a `+towgs84` parameter
+    pub fn from_towgs84(values: &[f64]) -> Option<Self> {
+        match *values {
+            [dx, dy, dz] => Some(Helmert::translation(dx, dy, dz)),
+            [dx, dy, dz, rx, ry, rz, ds] => Some(Helmert { dx, dy, dz, rx, ry, rz, ds }),
+            _ => None,
+        }
+    }
+
+    /// Returns the values of the `+towgs84` parameter, 3 values if the
+    /// transformation is a pure translation, 7 values otherwise
+    pub fn to_towgs84(&self) -> Vec<f64> {
+        if self.is_translation() {
+            vec![self.dx, self.dy, self.dz]
+        } else {
+            vec![self.dx, self.dy, self.dz, self.rx, self.ry, self.rz, self.ds]
+        }
+    }
+
+    /// Whether the rotations and the scale difference are zero
+    pub fn is_translation(&self) -> bool {
+        self.rx == 0.0 && self.ry == 0.0 && self.rz == 0.0 && self.ds == 0.0
+    }
+}
+
+/// Geodetic datum: an ellipsoid plus its position relative to WGS84
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Datum {
+    /// Identifier in PROJ strings (`+datum=...`)
+    pub proj_id: &'static str,
+    /// English name
+    pub nice_name: &'static str,
+    /// Ellipsoid of the datum
+    pub ellipsoid: Ellipsoid,
+    /// Transformation to WGS84, `None` if it requires a grid shift
+    pub to_wgs84: Option<Helmert>,
+}
+
+impl Datum {
+    /// Returns the datum with the given ellipsoid and transformation to WGS84
+    ///
+    /// NAD83 (GRS80 without a shift) and NAD27 (no transformation) can't be told
+    /// apart from other datums on the same ellipsoid, so only datums with a distinct
+    /// shift (and WGS84 itself) are identified.
+    pub fn identify(ellipsoid: &Ellipsoid, to_wgs84: Option<&Helmert>) -> Option<&'static Datum> {
+        let identifiable = |d: &Datum| {
+            d.proj_id == WGS84_DATUM.proj_id ||
+            d.to_wgs84.is_some_and(|h| h != Helmert::translation(0.0, 0.0, 0.0))
+        };
+
+        DATUMS.iter().cloned().find(|d| {
+            identifiable(d) && d.ellipsoid.a == ellipsoid.a && d.ellipsoid.b == ellipsoid.b &&
+            d.to_wgs84.as_ref() == to_wgs84
+        })
+    }
+}
+
+/// The datums known to PROJ.4
+pub const DATUMS: [&Datum; 9] = [
+    &WGS84_DATUM,
+    &GGRS87_DATUM,
+    &NAD83_DATUM,
+    &NAD27_DATUM,
+    &POTSDAM_DATUM,
+    &HERMANNSKOGEL_DATUM,
+    &IRE65_DATUM,
+    &NZGD49_DATUM,
+    &OSGB36_DATUM,
+];
+
+/// World Geodetic System 1984
+pub const WGS84_DATUM: Datum = Datum {
+    proj_id: "WGS84",
+    nice_name: "World Geodetic System 1984",
+    ellipsoid: WGS_1984_ELLIPSOID,
+    to_wgs84: Some(Helmert::translation(0.0, 0.0, 0.0)),
+};
+
+/// Greek Geodetic Reference System 1987
+pub const GGRS87_DATUM: Datum = Datum {
+    proj_id: "GGRS87",
+    nice_name: "Greek Geodetic Reference System 1987",
+    ellipsoid: GRS_1980_ELLIPSOID,
+    to_wgs84: Some(Helmert::translation(-199.87, 74.79, 246.62)),
+};
+
+/// North American Datum 1983
+pub const NAD83_DATUM: Datum = Datum {
+    proj_id: "NAD83",
+    nice_name: "North American Datum 1983",
+    ellipsoid: GRS_1980_ELLIPSOID,
+    to_wgs84: Some(Helmert::translation(0.0, 0.0, 0.0)),
+};
+
+/// North American Datum 1927 (requires the NADCON grid shift)
+pub const NAD27_DATUM: Datum = Datum {
+    proj_id: "NAD27",
+    nice_name: "North American Datum 1927",
+    ellipsoid: CLARKE_1866_ELLIPSOID,
+    to_wgs84: None,
+};
+
+/// Potsdam Rauenberg 1950 DHDN
+pub const POTSDAM_DATUM: Datum = Datum {
+    proj_id: "potsdam",
+    nice_name: "Potsdam Rauenberg 1950 DHDN",
+    ellipsoid: BESSEL_1841_ELLIPSOID,
+    to_wgs84: Some(Helmert { dx: 598.1, dy: 73.7, dz: 418.2, rx: 0.202, ry: 0.045, rz: -2.455, ds: 6.7 }),
+};
+
+/// Hermannskogel
+pub const HERMANNSKOGEL_DATUM: Datum = Datum {
+    proj_id: "hermannskogel",
+    nice_name: "Hermannskogel",
+    ellipsoid: BESSEL_1841_ELLIPSOID,
+    to_wgs84: Some(Helmert { dx: 577.326, dy: 90.129, dz: 463.919, rx: 5.137, ry: 1.474, rz: 5.297, ds: 2.4232 }),
+};
+
+/// Ireland 1965
+pub const IRE65_DATUM: Datum = Datum {
+    proj_id: "ire65",
+    nice_name: "Ireland 1965",
+    ellipsoid: MODIFIED_AIRY_ELLIPSOID,
+    to_wgs84: Some(Helmert { dx: 482.530, dy: -130.596, dz: 564.557, rx: -1.042, ry: -0.214, rz: -0.631, ds: 8.15 }),
+};
+
+/// New Zealand Geodetic Datum 1949
+pub const NZGD49_DATUM: Datum = Datum {
+    proj_id: "nzgd49",
+    nice_name: "New Zealand Geodetic Datum 1949",
+    ellipsoid: INTERNATIONAL_1924_ELLIPSOID,
+    to_wgs84: Some(Helmert { dx: 59.47, dy: -5.04, dz: 187.44, rx: 0.47, ry: -0.1, rz: 1.024, ds: -4.5993 }),
+};
+
+/// Ordnance Survey of Great Britain 1936
+pub const OSGB36_DATUM: Datum = Datum {
+    proj_id: "OSGB36",
+    nice_name: "Ordnance Survey of Great Britain 1936",
+    ellipsoid: AIRY_1830_ELLIPSOID,
+    to_wgs84: Some(Helmert { dx: 446.448, dy: -125.157, dz: 542.060, rx: 0.1502, ry: 0.2470, rz: 0.8421, ds: -20.4894 }),
+};
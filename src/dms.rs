@@ -0,0 +1,409 @@
+//! Degrees, minutes and seconds: parsing and formatting of typed coordinates
+//!
+//! `parse_coordinate` reads a latitude / longitude pair as users type it and
+//! returns it as `(longitude, latitude)` in decimal degrees, ready for a `LonLatBuf`:
+//!
+//! - `48°08'14.5"N 11°34'31.2"E`, also with `º`, `′`, `″`, `’`, `”` or `''`
+//! - `N 48 08.241 E 011 34.520` (decimal minutes, hemisphere letters in front)
+//! - `48.137361N, 11.575333E`, `-33.8688 151.2093`, `48:08:14.5 11:34:31.2`
+//!
+//! Hemisphere letters decide which value is the latitude. Without letters, a
+//! value beyond ±90° must be the longitude, otherwise the latitude is taken to
+//! come first, as in ISO 6709 and most map applications. `parse_latitude`,
+//! `parse_longitude` and `parse_angle` read single values.
+//!
+//! `format_latitude`, `format_longitude` and `format_coordinate` write degrees,
+//! minutes and seconds (or degrees and decimal minutes) as configured by a
+//! `DmsFormat`, or return `OutOfRange` for NaN and values beyond the range of
+//! the axis. The value is rounded once, in units of the last component, so
+//! that carries propagate and the output never shows 60 seconds or 60 minutes.
+//!
+//! ```rust
+//! extern crate proj5;
+//!
+//! use proj5::dms::*;
+//!
+//! fn main() {
+//!     let (lon, lat) = parse_coordinate("48°08'14.5\"N 11°34'31.2\"E").unwrap();
+//!     assert!((lat - 48.137361).abs() < 1e-6 && (lon - 11.575333).abs() < 1e-6);
+//!
+//!     assert_eq!(parse_coordinate("N 48 08.241 E 011 34.520"), parse_coordinate("11°34.520'E 48°08.241'N"));
+//!     assert_eq!(parse_coordinate("-33.8688, 151.2093"), Ok((151.2093, -33.8688)));
+//!
+//!     assert_eq!(format_coordinate((lon, lat), &DmsFormat::default()).unwrap(), "48°08′14.5″N 011°34′31.2″E");
+//!
+//!     let format = DmsFormat { style: DmsStyle::DegreesMinutes, decimals: 3, ..DmsFormat::default() };
+//!     assert_eq!(format_latitude(lat, &format).unwrap(), "48°08.242′N");
+//!
+//!     // 7°59′59.99999″ rounds up to the next degree
+//!     assert_eq!(format_longitude(-(7.0 + 59.0 / 60.0 + 59.99999 / 3600.0), &DmsFormat::default()).unwrap(), "008°00′00.0″W");
+//!
+//!     // NaN and values beyond ±90° / ±180° can't be formatted
+//!     assert!(format_longitude(f64::NAN, &DmsFormat::default()).is_err());
+//! }
+//! ```
+
+use std::error::Error;
+use std::fmt;
+
+/// Which coordinate a value is, as told by its hemisphere letter
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Axis {
+    Latitude,
+    Longitude,
+}
+
+/// Error that can occur when reading an angle or a coordinate
+#[derive(Debug, Clone, PartialEq)]
+pub enum DmsError {
+    /// The text can't be read as an angle or coordinate pair
+    InvalidFormat(String),
+    /// Minutes or seconds of 60 or more, a latitude beyond ±90° or a longitude
+    /// beyond ±180°, NaN or an infinite value
+    OutOfRange(f64),
+    /// The hemisphere letters give two latitudes or two longitudes, or the wrong axis for a single value
+    WrongAxis(Axis),
+}
+
+impl fmt::Display for DmsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::DmsError::*;
+        match *self {
+            InvalidFormat(ref s) => write!(f, "invalid angle or coordinate: \"{}\"", s),
+            OutOfRange(value) => write!(f, "value out of range: {}", value),
+            WrongAxis(axis) => write!(f, "unexpected {:?}", axis),
+        }
+    }
+}
+
+impl Error for DmsError { }
+
+/// Components of a formatted angle
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DmsStyle {
+    /// Degrees, minutes and decimal seconds, e.g. `48°08′14.5″`
+    DegreesMinutesSeconds,
+    /// Degrees and decimal minutes, e.g. `48°08.241′`
+    DegreesMinutes,
+    /// Decimal degrees, e.g. `48.1374°`
+    Degrees,
+}
+
+/// Configuration of the formatters
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct DmsFormat {
+    pub style: DmsStyle,
+    /// Decimal places of the last component
+    pub decimals: usize,
+    /// `°′″` if true, the ASCII `°'"` otherwise
+    pub unicode: bool,
+    /// A hemisphere letter (N / S, E / W) after the value if true, a leading minus sign otherwise
+    pub hemisphere: bool,
+    /// Pads the degrees to two (latitude) or three (longitude) digits and the minutes and seconds to two
+    pub zero_pad: bool,
+    /// Puts a space between the components
+    pub spaced: bool,
+}
+
+impl Default for DmsFormat {
+    /// Degrees, minutes and seconds with one decimal, e.g. `48°08′14.5″N`
+    fn default() -> Self {
+        DmsFormat {
+            style: DmsStyle::DegreesMinutesSeconds,
+            decimals: 1,
+            unicode: true,
+            hemisphere: true,
+            zero_pad: true,
+            spaced: false,
+        }
+    }
+}
+
+/// Parts of the text, see `tokenize`
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Token {
+    /// A number, whether it has decimals and the component of its symbol (0 degrees, 1 minutes, 2 seconds)
+    Number(f64, bool, Option<usize>),
+    Hemisphere(Axis, bool),
+    Minus,
+    /// `,`, `;` or `/` between latitude and longitude
+    Separator,
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, DmsError> {
+    let invalid = || DmsError::InvalidFormat(s.to_string());
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() || c == ':' || c == '+' => continue,
+            '0'..='9' | '.' => {
+                let mut number = c.to_string();
+                while let Some(&next) = chars.peek().filter(|&&n| n.is_ascii_digit() || n == '.') {
+                    number.push(next);
+                    chars.next();
+                }
+                let value = number.parse::<f64>().map_err(|_| invalid())?;
+                let component = match chars.peek().cloned() {
+                    Some('°') | Some('º') | Some('˚') => { chars.next(); Some(0) },
+                    Some('\'') | Some('′') | Some('’') => {
+                        chars.next();
+                        // two apostrophes stand for seconds
+                        if chars.peek() == Some(&'\'') { chars.next(); Some(2) } else { Some(1) }
+                    },
+                    Some('"') | Some('″') | Some('”') => { chars.next(); Some(2) },
+                    _ => None,
+                };
+                Token::Number(value, number.contains('.'), component)
+            },
+            '-' | '−' => Token::Minus,
+            ',' | ';' | '/' => Token::Separator,
+            'N' | 'n' => Token::Hemisphere(Axis::Latitude, true),
+            'S' | 's' => Token::Hemisphere(Axis::Latitude, false),
+            'E' | 'e' => Token::Hemisphere(Axis::Longitude, true),
+            'W' | 'w' => Token::Hemisphere(Axis::Longitude, false),
+            _ => return Err(invalid()),
+        };
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+/// Reads the tokens of one angle, returns the value in degrees and the axis of its hemisphere letter
+fn read_angle(tokens: &[Token], s: &str) -> Result<(f64, Option<Axis>), DmsError> {
+    let invalid = || DmsError::InvalidFormat(s.to_string());
+    let mut sign = 1.0;
+    let mut axis = None;
+    let mut components = [0.0; 3];
+    let mut last: Option<usize> = None;
+    let mut has_fraction = false;
+
+    for (i, &token) in tokens.iter().enumerate() {
+        match token {
+            // a minus sign must come first, and can't be combined with a hemisphere letter
+            Token::Minus if i == 0 => sign = -1.0,
+            Token::Hemisphere(a, positive) if axis.is_none() && (i == 0 || i == tokens.len() - 1) => {
+                if sign < 0.0 {
+                    return Err(invalid());
+                }
+                axis = Some(a);
+                sign = if positive { 1.0 } else { -1.0 };
+            },
+            Token::Number(value, fraction, component) => {
+                // only the last component can have decimals
+                let component = component.unwrap_or_else(|| last.map_or(0, |l| l + 1));
+                if has_fraction || component > 2 || last.is_some_and(|l| component <= l) {
+                    return Err(invalid());
+                }
+                if component > 0 && value >= 60.0 {
+                    return Err(DmsError::OutOfRange(value));
+                }
+                components[component] = value;
+                last = Some(component);
+                has_fraction = fraction;
+            },
+            _ => return Err(invalid()),
+        }
+    }
+
+    if last.is_none() {
+        return Err(invalid());
+    }
+    Ok((sign * (components[0] + components[1] / 60.0 + components[2] / 3600.0), axis))
+}
+
+/// Splits the tokens of a coordinate pair into the two angles
+fn split_pair(tokens: &[Token]) -> Option<(&[Token], &[Token])> {
+    let is_letter = |t: &Token| matches!(*t, Token::Hemisphere(..));
+    let split = |index: usize| Some((&tokens[..index], &tokens[index..]));
+
+    if let Some(index) = tokens.iter().position(|t| *t == Token::Separator) {
+        return Some((&tokens[..index], &tokens[index + 1..]));
+    }
+
+    let letters: Vec<usize> = tokens.iter().enumerate().filter(|&(_, t)| is_letter(t)).map(|(i, _)| i).collect();
+    if letters.len() == 2 {
+        // letters either all in front of or all after the numbers
+        return if letters[0] == 0 { split(letters[1]) } else { split(letters[0] + 1) };
+    }
+
+    let numbers: Vec<usize> = tokens.iter().enumerate().filter(|&(_, t)| matches!(*t, Token::Number(..))).map(|(i, _)| i).collect();
+    let second_degrees = numbers.iter().skip(1).find(|&&i| matches!(tokens[i], Token::Number(_, _, Some(0))));
+    let start = match second_degrees {
+        Some(&index) => index,
+        None if numbers.len() % 2 == 0 && !numbers.is_empty() => numbers[numbers.len() / 2],
+        None => return None,
+    };
+
+    // a minus sign belongs to the second angle
+    if start > 0 && tokens[start - 1] == Token::Minus {
+        split(start - 1)
+    } else {
+        split(start)
+    }
+}
+
+fn check_range(value: f64, axis: Axis) -> Result<f64, DmsError> {
+    let limit = if axis == Axis::Latitude { 90.0 } else { 180.0 };
+    if value.is_nan() || value.abs() > limit {
+        return Err(DmsError::OutOfRange(value));
+    }
+    Ok(value)
+}
+
+/// Reads an angle in decimal degrees or degrees, minutes and seconds, in degrees
+///
+/// A hemisphere letter is accepted for either axis and gives the sign.
+pub fn parse_angle(s: &str) -> Result<f64, DmsError> {
+    read_angle(&tokenize(s)?, s).map(|(value, _)| value)
+}
+
+fn parse_single(s: &str, expected: Axis) -> Result<f64, DmsError> {
+    let (value, axis) = read_angle(&tokenize(s)?, s)?;
+    if let Some(axis) = axis.filter(|&a| a != expected) {
+        return Err(DmsError::WrongAxis(axis));
+    }
+    check_range(value, expected)
+}
+
+/// Reads a latitude, e.g. `48°08'14.5"N`, `-33.8688` or `S 33 52.128`
+pub fn parse_latitude(s: &str) -> Result<f64, DmsError> {
+    parse_single(s, Axis::Latitude)
+}
+
+/// Reads a longitude, e.g. `11°34'31.2"E`, `151.2093` or `E 151 12.558`
+pub fn parse_longitude(s: &str) -> Result<f64, DmsError> {
+    parse_single(s, Axis::Longitude)
+}
+
+/// Reads a latitude / longitude pair in either order, returns `(longitude, latitude)`
+pub fn parse_coordinate(s: &str) -> Result<(f64, f64), DmsError> {
+    let tokens = tokenize(s)?;
+    let (first, second) = split_pair(&tokens).ok_or_else(|| DmsError::InvalidFormat(s.to_string()))?;
+    let (a, a_axis) = read_angle(first, s)?;
+    let (b, b_axis) = read_angle(second, s)?;
+
+    let latitude_first = match (a_axis, b_axis) {
+        (Some(x), Some(y)) if x == y => return Err(DmsError::WrongAxis(x)),
+        (Some(x), _) => x == Axis::Latitude,
+        (None, Some(y)) => y == Axis::Longitude,
+        (None, None) => a.abs() <= 90.0,
+    };
+
+    let (lat, lon) = if latitude_first { (a, b) } else { (b, a) };
+    Ok((check_range(lon, Axis::Longitude)?, check_range(lat, Axis::Latitude)?))
+}
+
+/// Formats the absolute value of an angle
+fn format_angle(value: f64, format: &DmsFormat, degree_digits: usize) -> String {
+    let (degree_symbol, minute_symbol, second_symbol) = if format.unicode { ("°", "′", "″") } else { ("°", "'", "\"") };
+    let separator = if format.spaced { " " } else { "" };
+    let pad = |digits: usize| if format.zero_pad { digits } else { 1 };
+
+    // the value is rounded once, in units of the last component
+    let decimals = format.decimals.min(15);
+    let scale = 10u128.pow(decimals as u32);
+    let units_per_degree = match format.style {
+        DmsStyle::DegreesMinutesSeconds => 3600,
+        DmsStyle::DegreesMinutes => 60,
+        DmsStyle::Degrees => 1,
+    };
+    let total = (value.abs() * (units_per_degree as f64) * scale as f64).round() as u128;
+
+    // the last component with its decimals, the integer part padded to `digits`
+    let last = |units: u128, digits: usize| {
+        let integer = format!("{:0width$}", units / scale, width = digits);
+        if decimals == 0 {
+            integer
+        } else {
+            format!("{}.{:0width$}", integer, units % scale, width = decimals)
+        }
+    };
+
+    match format.style {
+        DmsStyle::DegreesMinutesSeconds => {
+            let seconds = total % (60 * scale);
+            let minutes = (total / (60 * scale)) % 60;
+            let degrees = total / (3600 * scale);
+            format!("{:0dw$}{}{}{:0mw$}{}{}{}{}", degrees, degree_symbol, separator, minutes, minute_symbol, separator,
+                    last(seconds, pad(2)), second_symbol, dw = pad(degree_digits), mw = pad(2))
+        },
+        DmsStyle::DegreesMinutes => {
+            let minutes = total % (60 * scale);
+            let degrees = total / (60 * scale);
+            format!("{:0dw$}{}{}{}{}", degrees, degree_symbol, separator, last(minutes, pad(2)), minute_symbol, dw = pad(degree_digits))
+        },
+        DmsStyle::Degrees => format!("{}{}", last(total, pad(degree_digits)), degree_symbol),
+    }
+}
+
+fn format_signed(value: f64, format: &DmsFormat, axis: Axis) -> Result<String, DmsError> {
+    let value = check_range(value, axis)?;
+    let (degree_digits, positive, negative) = match axis {
+        Axis::Latitude => (2, 'N', 'S'),
+        Axis::Longitude => (3, 'E', 'W'),
+    };
+    let angle = format_angle(value, format, degree_digits);
+
+    // values that round to zero have no sign
+    let is_zero = !angle.chars().any(|c| c.is_ascii_digit() && c != '0');
+    let negative_value = value < 0.0 && !is_zero;
+
+    Ok(match (format.hemisphere, negative_value) {
+        (true, _) => format!("{}{}{}", angle, if format.spaced { " " } else { "" }, if negative_value { negative } else { positive }),
+        (false, true) => format!("-{}", angle),
+        (false, false) => angle,
+    })
+}
+
+/// Formats a latitude in degrees, e.g. `48°08′14.5″N`
+///
+/// Returns `OutOfRange` for NaN and values beyond ±90°.
+pub fn format_latitude(value: f64, format: &DmsFormat) -> Result<String, DmsError> {
+    format_signed(value, format, Axis::Latitude)
+}
+
+/// Formats a longitude in degrees, e.g. `011°34′31.2″E`
+///
+/// Returns `OutOfRange` for NaN and values beyond ±180°.
+pub fn format_longitude(value: f64, format: &DmsFormat) -> Result<String, DmsError> {
+    format_signed(value, format, Axis::Longitude)
+}
+
+/// Formats a `(longitude, latitude)` pair as latitude, then longitude, separated by a space
+pub fn format_coordinate((lon, lat): (f64, f64), format: &DmsFormat) -> Result<String, DmsError> {
+    Ok(format!("{} {}", format_latitude(lat, format)?, format_longitude(lon, format)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_non_finite() {
+        let format = DmsFormat::default();
+        for &value in &[f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert!(format_latitude(value, &format).is_err());
+            assert!(format_longitude(value, &format).is_err());
+        }
+        assert_eq!(format_longitude(1e300, &format), Err(DmsError::OutOfRange(1e300)));
+        assert_eq!(format_latitude(90.5, &format), Err(DmsError::OutOfRange(90.5)));
+        assert!(format_coordinate((f64::NAN, 48.0), &format).is_err());
+    }
+
+    #[test]
+    fn format_range_limits() {
+        let format = DmsFormat::default();
+        assert_eq!(format_longitude(-180.0, &format).unwrap(), "180°00′00.0″W");
+        assert_eq!(format_latitude(90.0, &format).unwrap(), "90°00′00.0″N");
+        assert_eq!(format_latitude(-0.00001, &format).unwrap(), "00°00′00.0″N");
+    }
+
+    #[test]
+    fn parse_non_finite() {
+        assert!(parse_latitude("NaN").is_err());
+        assert!(parse_longitude("inf").is_err());
+    }
+}
@@ -22,11 +22,109 @@ pub struct Ellipsoid {
     pub scaling: f64,
 }
 
+impl Ellipsoid {
+    /// Square of the first eccentricity, calculated from a and b
+    #[inline]
+    pub fn eccentricity_squared(&self) -> f64 {
+        let temp = self.b / self.a;
+        1.0 - (temp * temp)
+    }
+
+    // q(phi) from Snyder (3-12), used for the authalic latitude
+    #[inline]
+    fn authalic_q(&self, phi: f64) -> f64 {
+        let e2 = self.eccentricity_squared();
+        let sinphi = phi.sin();
+
+        if e2 < 1e-15 {
+            return 2.0 * sinphi;
+        }
+
+        let e = e2.sqrt();
+        let con = e * sinphi;
+        (1.0 - e2) * (sinphi / (1.0 - con * con) - (1.0 / (2.0 * e)) * ((1.0 - con) / (1.0 + con)).ln())
+    }
+
+    /// Radius of the sphere that has the same surface area as the ellipsoid, in meters
+    #[inline]
+    pub fn authalic_radius(&self) -> f64 {
+        self.a * (self.authalic_q(::std::f64::consts::FRAC_PI_2) / 2.0).sqrt()
+    }
+
+    /// Converts a geodetic latitude to the authalic latitude (both in radians),
+    /// the latitude on the sphere with the `authalic_radius()` that preserves areas
+    #[inline]
+    pub fn authalic_latitude(&self, phi: f64) -> f64 {
+        let ratio = self.authalic_q(phi) / self.authalic_q(::std::f64::consts::FRAC_PI_2);
+        ratio.clamp(-1.0, 1.0).asin()
+    }
+
+    /// Converts an authalic latitude back to the geodetic latitude (both in radians)
+    ///
+    /// Reference: Snyder, J. P., 1987, Map Projections - A Working Manual, p. 16, (3-18) and (3-16)
+    pub fn geodetic_latitude(&self, beta: f64) -> f64 {
+        let e2 = self.eccentricity_squared();
+
+        if e2 < 1e-15 {
+            return beta;
+        }
+
+        let e4 = e2 * e2;
+        let e6 = e4 * e2;
+
+        // Series approximation ...
+        let mut phi = beta +
+            (e2 / 3.0 + 31.0 * e4 / 180.0 + 517.0 * e6 / 5040.0) * (2.0 * beta).sin() +
+            (23.0 * e4 / 360.0 + 251.0 * e6 / 3780.0) * (4.0 * beta).sin() +
+            (761.0 * e6 / 45360.0) * (6.0 * beta).sin();
+
+        // ... refined with Newton's method, except at the poles
+        let e = e2.sqrt();
+        let q = self.authalic_q(::std::f64::consts::FRAC_PI_2) * beta.sin();
+
+        for _ in 0..3 {
+            let cosphi = phi.cos();
+            if cosphi.abs() < 1e-12 { break; }
+            let sinphi = phi.sin();
+            let con = e * sinphi;
+            let com = 1.0 - con * con;
+            phi += com * com / (2.0 * cosphi) *
+                (q / (1.0 - e2) - sinphi / com + (1.0 / (2.0 * e)) * ((1.0 - con) / (1.0 + con)).ln());
+        }
+
+        phi
+    }
+
+    /// Creates a user-defined ellipsoid from its semi-major and semi-minor axes (in meters),
+    /// `a == b` creates a sphere
+    pub fn from_axes(a: f64, b: f64) -> Self {
+        Ellipsoid {
+            a,
+            b,
+            flattening: if a == b { 0.0 } else { a / (a - b) },
+            nice_name: "User-defined",
+            usage_region: "",
+            offset: 0.0,
+            rotation: 0.0,
+            scaling: 0.0,
+        }
+    }
+
+    /// Area of a lon / lat rectangle (in degrees) on the ellipsoid, in square meters
+    #[inline]
+    pub fn lonlat_rectangle_area(&self, min_lon: f64, min_lat: f64, max_lon: f64, max_lat: f64) -> f64 {
+        let radius = self.authalic_radius();
+        let beta_min = self.authalic_latitude(min_lat.to_radians());
+        let beta_max = self.authalic_latitude(max_lat.to_radians());
+        radius * radius * (max_lon - min_lon).to_radians() * (beta_max.sin() - beta_min.sin())
+    }
+}
+
 // Note: It doesn't really make sense to put ellipsoids in seperate files,
 // they are too simple. So we just put them here.
 
 /// The 24 standard ellipsoids
-pub const ELLIPSOIDS: [&'static Ellipsoid; 24] = [
+pub const ELLIPSOIDS: [&Ellipsoid; 24] = [
     &MAUPERTUIS_1738_ELLIPSOID,
     &PLESSIS_1817_ELLIPSOID,
     &EVEREST_1830_ELLIPSOID,
@@ -68,7 +166,7 @@ pub const MAUPERTUIS_1738_ELLIPSOID: Ellipsoid = Ellipsoid {
 /// Plessis (1817)
 pub const PLESSIS_1817_ELLIPSOID: Ellipsoid = Ellipsoid {
     a: 6_376_523.0,
-    b: 6_355_862.9333,
+    b: 6_355_862.933_3,
     flattening: 308.64,
     usage_region: "France",
     nice_name: "Plessis (1817)",
@@ -92,7 +190,7 @@ pub const EVEREST_1830_ELLIPSOID: Ellipsoid = Ellipsoid {
 /// Everest 1830 Modified (1967)
 pub const EVEREST_1967_MODIFIED_ELLIPSOID: Ellipsoid = Ellipsoid {
     a: 6_377_304.063,
-    b: 6_356_103.0390,
+    b: 6_356_103.039_0,
     flattening: 300.8017,
     usage_region: "West Malaysia & Singapore",
     nice_name: "Everest 1830 Modified (1967)",
@@ -125,6 +223,18 @@ pub const AIRY_1830_ELLIPSOID: Ellipsoid = Ellipsoid {
     scaling: 0.0,
 };
 
+/// Airy Modified (1849), used by the Irish Grid
+pub const MODIFIED_AIRY_ELLIPSOID: Ellipsoid = Ellipsoid {
+    a: 6_377_340.189,
+    b: 6_356_034.447_9,
+    flattening: 299.3249646,
+    usage_region: "Ireland",
+    nice_name: "Airy Modified (1849)",
+    offset: 0.0,
+    rotation: 0.0,
+    scaling: 0.0,
+};
+
 /// Bessel (1841)
 pub const BESSEL_1841_ELLIPSOID: Ellipsoid = Ellipsoid {
     a: 6_377_397.155,
@@ -296,7 +406,7 @@ pub const WGS_1972_ELLIPSOID: Ellipsoid = Ellipsoid {
 /// GRS-80 (1979)
 pub const GRS_1980_ELLIPSOID: Ellipsoid = Ellipsoid {
     a: 6_378_137.0,
-    b: 6_356_752.3141,
+    b: 6_356_752.314_1,
     flattening: 298.257222101,
     usage_region: "Global ITRS",
     nice_name: "GRS-80 (1979)",
@@ -0,0 +1,307 @@
+//! Embedded subset of the EPSG registry
+//!
+//! `from_epsg` returns a ready-to-use CRS with its ellipsoid, transformation to
+//! WGS84, name, area of use and axis order. `to_epsg` does the opposite lookup,
+//! e.g. to write the EPSG code of a `UTMSystem` into file metadata.
+//!
+//! Covered codes:
+//!
+//! - geographic CRS: 4326 (WGS 84), 4258 (ETRS89), 4269 (NAD83), 4267 (NAD27),
+//!   4277 (OSGB36), 4171 (RGF93), 4314 (DHDN), 4312 (MGI), 4300 (TM65),
+//!   4272 (NZGD49), 4121 (GGRS87), 4156 (S-JTSK)
+//! - UTM zones: 32601 - 32660 and 32701 - 32760 (WGS 84), 25828 - 25838 (ETRS89),
+//!   26901 - 26923 (NAD83), 26701 - 26722 (NAD27)
+//! - 3857 (Pseudo-Mercator), 3395 (World Mercator), 5041 / 5042 and 32661 / 32761 (UPS)
+//! - national grids: 27700 (British National Grid), 29902 (Irish Grid),
+//!   2154 (Lambert-93), 3942 - 3950 (CC42 - CC50), 2100 (Greek Grid),
+//!   31466 - 31469 (German Gauss-Krüger zones 2 - 5), 31254 - 31256 (Austria GK),
+//!   5513 / 5514 (S-JTSK / Krovak)
+//!
+//! The `axis_order` is the one of the EPSG definition, e.g. latitude first for
+//! EPSG:4326. proj5 itself always uses (x, y) = (easting, northing) or
+//! (longitude, latitude), so swap the coordinates if a format requires the EPSG order.
+//!
+//! ```rust
+//! extern crate proj5;
+//!
+//! use proj5::prelude::*;
+//! use proj5::epsg::*;
+//!
+//! fn main() {
+//!     let etrs89 = from_epsg(25832).unwrap();
+//!     assert_eq!(etrs89.name, "ETRS89 / UTM zone 32N");
+//!     assert_eq!(etrs89.ellipsoid, GRS_1980_ELLIPSOID);
+//!
+//!     let utm = UTMSystem { utm_zone: 33, hemisphere: Hemisphere::South };
+//!     assert_eq!(to_epsg(&utm, &WGS_1984_ELLIPSOID, None), Some(32733));
+//!     assert_eq!(to_epsg(&*etrs89.crs, &etrs89.ellipsoid, etrs89.to_wgs84.as_ref()), Some(25832));
+//!
+//!     // RGF93 and ETRS89 share the ellipsoid and the transformation
+//!     let lambert_93 = from_epsg(2154).unwrap();
+//!     assert_eq!(to_epsg(&*lambert_93.crs, &lambert_93.ellipsoid, None), Some(2154));
+//!     assert_eq!(to_epsg(&LonLatSystem, &lambert_93.ellipsoid, None), Some(4258));
+//! }
+//! ```
+
+use prelude::*;
+use coordinate_systems::adjusted::AxisDirection;
+use coordinate_systems::adjusted::AxisDirection::*;
+use coordinate_systems::description::Description;
+
+/// A CRS of the EPSG registry
+#[derive(Debug)]
+pub struct EpsgCrs {
+    /// EPSG code, e.g. 25832
+    pub code: u32,
+    /// EPSG name, e.g. "ETRS89 / UTM zone 32N"
+    pub name: String,
+    /// Description of the area of use
+    pub area_of_use: String,
+    /// Directions of the first and second axis in the EPSG definition
+    pub axis_order: (AxisDirection, AxisDirection),
+    /// The coordinate system
+    pub crs: Box<dyn Crs>,
+    /// The ellipsoid of the datum
+    pub ellipsoid: Ellipsoid,
+    /// Transformation of the datum to WGS84, `None` if it requires a grid shift
+    pub to_wgs84: Option<Helmert>,
+}
+
+impl Clone for EpsgCrs {
+    fn clone(&self) -> Self {
+        EpsgCrs {
+            code: self.code,
+            name: self.name.clone(),
+            area_of_use: self.area_of_use.clone(),
+            axis_order: self.axis_order,
+            crs: Crs::clone(&*self.crs),
+            ellipsoid: self.ellipsoid,
+            to_wgs84: self.to_wgs84,
+        }
+    }
+}
+
+/// Geographic CRS, the base of the projected ones
+struct GeographicEntry {
+    code: u32,
+    name: &'static str,
+    area_of_use: &'static str,
+    ellipsoid: &'static Ellipsoid,
+    to_wgs84: Option<Helmert>,
+}
+
+/// Datums on the same ellipsoid are ordered by importance, `to_epsg` returns the first match
+const GEOGRAPHIC_CRS: [GeographicEntry; 12] = [
+    GeographicEntry { code: 4326, name: "WGS 84", area_of_use: "World", ellipsoid: &WGS_1984_ELLIPSOID, to_wgs84: WGS84_DATUM.to_wgs84 },
+    GeographicEntry { code: 4258, name: "ETRS89", area_of_use: "Europe", ellipsoid: &GRS_1980_ELLIPSOID, to_wgs84: Some(Helmert::translation(0.0, 0.0, 0.0)) },
+    GeographicEntry { code: 4269, name: "NAD83", area_of_use: "North America", ellipsoid: &GRS_1980_ELLIPSOID, to_wgs84: NAD83_DATUM.to_wgs84 },
+    GeographicEntry { code: 4267, name: "NAD27", area_of_use: "North America", ellipsoid: &CLARKE_1866_ELLIPSOID, to_wgs84: NAD27_DATUM.to_wgs84 },
+    GeographicEntry { code: 4277, name: "OSGB36", area_of_use: "United Kingdom", ellipsoid: &AIRY_1830_ELLIPSOID, to_wgs84: OSGB36_DATUM.to_wgs84 },
+    GeographicEntry { code: 4171, name: "RGF93 v1", area_of_use: "France", ellipsoid: &GRS_1980_ELLIPSOID, to_wgs84: Some(Helmert::translation(0.0, 0.0, 0.0)) },
+    GeographicEntry { code: 4314, name: "DHDN", area_of_use: "Germany", ellipsoid: &BESSEL_1841_ELLIPSOID, to_wgs84: POTSDAM_DATUM.to_wgs84 },
+    GeographicEntry { code: 4312, name: "MGI", area_of_use: "Austria", ellipsoid: &BESSEL_1841_ELLIPSOID, to_wgs84: HERMANNSKOGEL_DATUM.to_wgs84 },
+    GeographicEntry { code: 4300, name: "TM65", area_of_use: "Ireland", ellipsoid: &MODIFIED_AIRY_ELLIPSOID, to_wgs84: IRE65_DATUM.to_wgs84 },
+    GeographicEntry { code: 4272, name: "NZGD49", area_of_use: "New Zealand", ellipsoid: &INTERNATIONAL_1924_ELLIPSOID, to_wgs84: NZGD49_DATUM.to_wgs84 },
+    GeographicEntry { code: 4121, name: "GGRS87", area_of_use: "Greece", ellipsoid: &GRS_1980_ELLIPSOID, to_wgs84: GGRS87_DATUM.to_wgs84 },
+    GeographicEntry { code: 4156, name: "S-JTSK", area_of_use: "Czechia and Slovakia", ellipsoid: &BESSEL_1841_ELLIPSOID, to_wgs84: Some(Helmert::translation(589.0, 76.0, 480.0)) },
+];
+
+/// Consecutive codes of UTM zones on one datum
+struct UtmRange {
+    /// Code of the first zone
+    first_code: u32,
+    /// Code of the geographic CRS
+    base: u32,
+    first_zone: u8,
+    last_zone: u8,
+    hemisphere: Hemisphere,
+    /// Region for the area of use, `None` for the whole hemisphere
+    region: Option<&'static str>,
+}
+
+const UTM_RANGES: [UtmRange; 5] = [
+    UtmRange { first_code: 32601, base: 4326, first_zone: 1, last_zone: 60, hemisphere: Hemisphere::North, region: None },
+    UtmRange { first_code: 32701, base: 4326, first_zone: 1, last_zone: 60, hemisphere: Hemisphere::South, region: None },
+    UtmRange { first_code: 25828, base: 4258, first_zone: 28, last_zone: 38, hemisphere: Hemisphere::North, region: Some("Europe") },
+    UtmRange { first_code: 26901, base: 4269, first_zone: 1, last_zone: 23, hemisphere: Hemisphere::North, region: Some("North America") },
+    UtmRange { first_code: 26701, base: 4267, first_zone: 1, last_zone: 22, hemisphere: Hemisphere::North, region: Some("North America") },
+];
+
+/// Projected CRS other than the UTM zones
+struct ProjectedEntry {
+    code: u32,
+    name: &'static str,
+    area_of_use: &'static str,
+    /// Code of the geographic CRS
+    base: u32,
+    axis_order: (AxisDirection, AxisDirection),
+    projection: Description,
+}
+
+const fn gauss_kruger(lon_0: f64, false_easting: f64, false_northing: f64) -> Description {
+    Description::TransverseMercator(TransverseMercatorSystem { lat_0: 0.0, lon_0, scale_factor: 1.0, false_easting, false_northing })
+}
+
+const fn gauss_kruger_scaled(lon_0: f64, scale_factor: f64, false_easting: f64) -> Description {
+    Description::TransverseMercator(TransverseMercatorSystem { lat_0: 0.0, lon_0, scale_factor, false_easting, false_northing: 0.0 })
+}
+
+const PROJECTED_CRS: [ProjectedEntry; 28] = [
+    ProjectedEntry { code: 3857, name: "WGS 84 / Pseudo-Mercator", area_of_use: "World between 85.06°S and 85.06°N",
+                     base: 4326, axis_order: (East, North), projection: Description::WebMercator },
+    ProjectedEntry { code: 3395, name: "WGS 84 / World Mercator", area_of_use: "World between 80°S and 84°N",
+                     base: 4326, axis_order: (East, North), projection: Description::Mercator },
+    ProjectedEntry { code: 5041, name: "WGS 84 / UPS North (E,N)", area_of_use: "Northern hemisphere north of 60°N",
+                     base: 4326, axis_order: (East, North), projection: Description::Ups(UPSSystem { hemisphere: Hemisphere::North }) },
+    ProjectedEntry { code: 5042, name: "WGS 84 / UPS South (E,N)", area_of_use: "Southern hemisphere south of 60°S",
+                     base: 4326, axis_order: (East, North), projection: Description::Ups(UPSSystem { hemisphere: Hemisphere::South }) },
+    ProjectedEntry { code: 32661, name: "WGS 84 / UPS North (N,E)", area_of_use: "Northern hemisphere north of 60°N",
+                     base: 4326, axis_order: (North, East), projection: Description::Ups(UPSSystem { hemisphere: Hemisphere::North }) },
+    ProjectedEntry { code: 32761, name: "WGS 84 / UPS South (N,E)", area_of_use: "Southern hemisphere south of 60°S",
+                     base: 4326, axis_order: (North, East), projection: Description::Ups(UPSSystem { hemisphere: Hemisphere::South }) },
+    ProjectedEntry { code: 27700, name: "OSGB36 / British National Grid", area_of_use: "United Kingdom",
+                     base: 4277, axis_order: (East, North), projection: Description::TransverseMercator(BRITISH_NATIONAL_GRID) },
+    ProjectedEntry { code: 29902, name: "TM65 / Irish Grid", area_of_use: "Ireland",
+                     base: 4300, axis_order: (East, North), projection: Description::TransverseMercator(IRISH_GRID) },
+    ProjectedEntry { code: 2154, name: "RGF93 v1 / Lambert-93", area_of_use: "France",
+                     base: 4171, axis_order: (East, North), projection: Description::LambertConic(LAMBERT_93) },
+    ProjectedEntry { code: 3942, name: "RGF93 v1 / CC42", area_of_use: "France between 41°N and 43°N",
+                     base: 4171, axis_order: (East, North), projection: Description::LambertConic(CC42) },
+    ProjectedEntry { code: 3943, name: "RGF93 v1 / CC43", area_of_use: "France between 42°N and 44°N",
+                     base: 4171, axis_order: (East, North), projection: Description::LambertConic(CC43) },
+    ProjectedEntry { code: 3944, name: "RGF93 v1 / CC44", area_of_use: "France between 43°N and 45°N",
+                     base: 4171, axis_order: (East, North), projection: Description::LambertConic(CC44) },
+    ProjectedEntry { code: 3945, name: "RGF93 v1 / CC45", area_of_use: "France between 44°N and 46°N",
+                     base: 4171, axis_order: (East, North), projection: Description::LambertConic(CC45) },
+    ProjectedEntry { code: 3946, name: "RGF93 v1 / CC46", area_of_use: "France between 45°N and 47°N",
+                     base: 4171, axis_order: (East, North), projection: Description::LambertConic(CC46) },
+    ProjectedEntry { code: 3947, name: "RGF93 v1 / CC47", area_of_use: "France between 46°N and 48°N",
+                     base: 4171, axis_order: (East, North), projection: Description::LambertConic(CC47) },
+    ProjectedEntry { code: 3948, name: "RGF93 v1 / CC48", area_of_use: "France between 47°N and 49°N",
+                     base: 4171, axis_order: (East, North), projection: Description::LambertConic(CC48) },
+    ProjectedEntry { code: 3949, name: "RGF93 v1 / CC49", area_of_use: "France between 48°N and 50°N",
+                     base: 4171, axis_order: (East, North), projection: Description::LambertConic(CC49) },
+    ProjectedEntry { code: 3950, name: "RGF93 v1 / CC50", area_of_use: "France between 49°N and 51°N",
+                     base: 4171, axis_order: (East, North), projection: Description::LambertConic(CC50) },
+    ProjectedEntry { code: 2100, name: "GGRS87 / Greek Grid", area_of_use: "Greece",
+                     base: 4121, axis_order: (East, North), projection: gauss_kruger_scaled(24.0, 0.9996, 500_000.0) },
+    ProjectedEntry { code: 31466, name: "DHDN / 3-degree Gauss-Kruger zone 2", area_of_use: "Germany west of 7°30'E",
+                     base: 4314, axis_order: (North, East), projection: gauss_kruger(6.0, 2_500_000.0, 0.0) },
+    ProjectedEntry { code: 31467, name: "DHDN / 3-degree Gauss-Kruger zone 3", area_of_use: "Germany between 7°30'E and 10°30'E",
+                     base: 4314, axis_order: (North, East), projection: gauss_kruger(9.0, 3_500_000.0, 0.0) },
+    ProjectedEntry { code: 31468, name: "DHDN / 3-degree Gauss-Kruger zone 4", area_of_use: "Germany between 10°30'E and 13°30'E",
+                     base: 4314, axis_order: (North, East), projection: gauss_kruger(12.0, 4_500_000.0, 0.0) },
+    ProjectedEntry { code: 31469, name: "DHDN / 3-degree Gauss-Kruger zone 5", area_of_use: "Germany east of 13°30'E",
+                     base: 4314, axis_order: (North, East), projection: gauss_kruger(15.0, 5_500_000.0, 0.0) },
+    ProjectedEntry { code: 31254, name: "MGI / Austria GK West", area_of_use: "Austria west of 11°50'E",
+                     base: 4312, axis_order: (North, East), projection: gauss_kruger(10.0 + 20.0 / 60.0, 0.0, -5_000_000.0) },
+    ProjectedEntry { code: 31255, name: "MGI / Austria GK Central", area_of_use: "Austria between 11°50'E and 14°50'E",
+                     base: 4312, axis_order: (North, East), projection: gauss_kruger(13.0 + 20.0 / 60.0, 0.0, -5_000_000.0) },
+    ProjectedEntry { code: 31256, name: "MGI / Austria GK East", area_of_use: "Austria east of 14°50'E",
+                     base: 4312, axis_order: (North, East), projection: gauss_kruger(16.0 + 20.0 / 60.0, 0.0, -5_000_000.0) },
+    ProjectedEntry { code: 5513, name: "S-JTSK / Krovak", area_of_use: "Czechia and Slovakia",
+                     base: 4156, axis_order: (South, West),
+                     projection: Description::Krovak(KrovakSystem { variant: KrovakVariant::Standard, axis_order: KrovakAxisOrder::WestingSouthing }) },
+    ProjectedEntry { code: 5514, name: "S-JTSK / Krovak East North", area_of_use: "Czechia and Slovakia",
+                     base: 4156, axis_order: (East, North),
+                     projection: Description::Krovak(KrovakSystem { variant: KrovakVariant::Standard, axis_order: KrovakAxisOrder::EastingNorthing }) },
+];
+
+fn geographic(code: u32) -> Option<&'static GeographicEntry> {
+    GEOGRAPHIC_CRS.iter().find(|g| g.code == code)
+}
+
+/// Formats a longitude for the area of use, e.g. "6°E"
+fn format_longitude(lon: i32) -> String {
+    format!("{}°{}", lon.abs(), if lon < 0 { 'W' } else { 'E' })
+}
+
+/// Returns the CRS with the given EPSG code, `None` if it isn't part of the
+/// embedded registry (see the module documentation)
+pub fn from_epsg(code: u32) -> Option<EpsgCrs> {
+    if let Some(g) = geographic(code) {
+        return Some(EpsgCrs {
+            code,
+            name: g.name.to_string(),
+            area_of_use: g.area_of_use.to_string(),
+            axis_order: (North, East),
+            crs: Box::new(LonLatSystem),
+            ellipsoid: *g.ellipsoid,
+            to_wgs84: g.to_wgs84,
+        });
+    }
+
+    for range in UTM_RANGES.iter() {
+        let last_code = range.first_code + u32::from(range.last_zone - range.first_zone);
+        if !(range.first_code..=last_code).contains(&code) {
+            continue;
+        }
+
+        let base = geographic(range.base)?;
+        let zone = range.first_zone + (code - range.first_code) as u8;
+        let west = format_longitude(i32::from(zone) * 6 - 186);
+        let east = format_longitude(i32::from(zone) * 6 - 180);
+        let area_of_use = match (range.region, range.hemisphere) {
+            (Some(region), _) => format!("{} between {} and {}", region, west, east),
+            (None, Hemisphere::North) => format!("Between {} and {}, northern hemisphere between equator and 84°N", west, east),
+            (None, Hemisphere::South) => format!("Between {} and {}, southern hemisphere between 80°S and equator", west, east),
+        };
+
+        return Some(EpsgCrs {
+            code,
+            name: format!("{} / UTM zone {}{}", base.name, zone, if range.hemisphere == Hemisphere::North { 'N' } else { 'S' }),
+            area_of_use,
+            axis_order: (East, North),
+            crs: Box::new(UTMSystem { utm_zone: zone, hemisphere: range.hemisphere }),
+            ellipsoid: *base.ellipsoid,
+            to_wgs84: base.to_wgs84,
+        });
+    }
+
+    let entry = PROJECTED_CRS.iter().find(|p| p.code == code)?;
+    let base = geographic(entry.base)?;
+
+    Some(EpsgCrs {
+        code,
+        name: entry.name.to_string(),
+        area_of_use: entry.area_of_use.to_string(),
+        axis_order: entry.axis_order,
+        crs: entry.projection.to_crs(),
+        ellipsoid: *base.ellipsoid,
+        to_wgs84: base.to_wgs84,
+    })
+}
+
+/// Returns the EPSG code of the CRS on the ellipsoid, `None` if the registry has no such CRS
+///
+/// If `to_wgs84` is given, it must match the transformation of the EPSG datum.
+/// Otherwise several datums on the same ellipsoid may match (e.g. ETRS89 and
+/// NAD83 on GRS80) and the most important one is returned. Coordinate systems
+/// with non-metric units or a different axis order (`AdjustedSystem`) are not identified.
+pub fn to_epsg(crs: &dyn Crs, ellipsoid: &Ellipsoid, to_wgs84: Option<&Helmert>) -> Option<u32> {
+    let datum_matches = |g: &GeographicEntry| {
+        g.ellipsoid.a == ellipsoid.a && g.ellipsoid.b == ellipsoid.b &&
+        to_wgs84.is_none_or(|h| g.to_wgs84.as_ref() == Some(h))
+    };
+    let base_matches = |code: u32| geographic(code).is_some_and(datum_matches);
+
+    match Description::of(crs)? {
+        Description::LonLat => GEOGRAPHIC_CRS.iter().find(|g| datum_matches(g)).map(|g| g.code),
+        Description::Utm(utm) => UTM_RANGES.iter()
+            .find(|r| r.hemisphere == utm.hemisphere && (r.first_zone..=r.last_zone).contains(&utm.utm_zone) && base_matches(r.base))
+            .map(|r| r.first_code + u32::from(utm.utm_zone - r.first_zone)),
+        description => PROJECTED_CRS.iter()
+            .find(|p| p.projection == description && base_matches(p.base))
+            .map(|p| p.code),
+    }
+}
+
+/// Returns all EPSG codes of the embedded registry
+pub fn epsg_codes() -> Vec<u32> {
+    let mut codes: Vec<u32> = GEOGRAPHIC_CRS.iter().map(|g| g.code).collect();
+    for range in UTM_RANGES.iter() {
+        codes.extend((range.first_zone..=range.last_zone).map(|zone| range.first_code + u32::from(zone - range.first_zone)));
+    }
+    codes.extend(PROJECTED_CRS.iter().map(|p| p.code));
+    codes
+}
@@ -0,0 +1,377 @@
+//! Geohash encoding and decoding
+//!
+//! Implementation based on:
+//!
+//! Niemeyer, G., 2008, geohash.org - http://geohash.org/site/tips.html
+//!
+//! A geohash is a base32 string that identifies a rectangular cell on the
+//! lon / lat grid. Every character adds 5 bits, alternating between longitude
+//! and latitude (starting with longitude), so a hash with 12 characters has
+//! a cell size of about 3.7 cm x 1.9 cm at the equator.
+//!
+//! Bounding boxes are `(min_lon, min_lat, max_lon, max_lat)` in degrees.
+
+use prelude::*;
+use std::error::Error;
+use std::fmt;
+
+/// Base32 alphabet used by geohashes (without a, i, l and o)
+const BASE32: &[u8; 32] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+
+/// Maximum number of characters of a geohash, more don't fit into an f64
+pub const MAX_PRECISION: usize = 12;
+
+/// Error that can occur when encoding or decoding a geohash
+#[derive(Debug, Clone, PartialEq)]
+pub enum GeohashError {
+    /// Precision must be between 1 and `MAX_PRECISION`
+    InvalidPrecision(usize),
+    /// Longitude must be between -180 and 180, latitude between -90 and 90
+    InvalidCoordinate(f64, f64),
+    /// The geohash contains a character that is not part of the base32 alphabet
+    InvalidCharacter(char),
+    /// The geohash is empty or longer than `MAX_PRECISION`
+    InvalidLength(usize),
+    /// The southern edge of a bounding box is north of its northern edge
+    InvalidBoundingBox(f64, f64, f64, f64),
+}
+
+impl fmt::Display for GeohashError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::GeohashError::*;
+        match *self {
+            InvalidPrecision(precision) => write!(f, "invalid geohash precision: {} (must be 1 to {})", precision, MAX_PRECISION),
+            InvalidCoordinate(lon, lat) => write!(f, "invalid coordinate: ({}, {})", lon, lat),
+            InvalidCharacter(c) => write!(f, "invalid geohash character: '{}'", c),
+            InvalidLength(len) => write!(f, "invalid geohash length: {} (must be 1 to {})", len, MAX_PRECISION),
+            InvalidBoundingBox(min_lon, min_lat, max_lon, max_lat) => write!(f, "invalid bounding box: ({}, {}, {}, {}) (min_lat must not exceed max_lat)", min_lon, min_lat, max_lon, max_lat),
+        }
+    }
+}
+
+impl Error for GeohashError { }
+
+/// Direction of a neighbouring cell
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Direction {
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+}
+
+impl Direction {
+    /// All directions, clockwise starting with `North`
+    pub const ALL: [Direction; 8] = [
+        Direction::North, Direction::NorthEast, Direction::East, Direction::SouthEast,
+        Direction::South, Direction::SouthWest, Direction::West, Direction::NorthWest,
+    ];
+
+    /// Offset of the neighbouring cell, in cells (lon, lat)
+    fn offset(self) -> (f64, f64) {
+        use self::Direction::*;
+        match self {
+            North => (0.0, 1.0),
+            NorthEast => (1.0, 1.0),
+            East => (1.0, 0.0),
+            SouthEast => (1.0, -1.0),
+            South => (0.0, -1.0),
+            SouthWest => (-1.0, -1.0),
+            West => (-1.0, 0.0),
+            NorthWest => (-1.0, 1.0),
+        }
+    }
+}
+
+/// Size of a cell with the given number of characters, in degrees (width, height)
+#[inline]
+pub fn cell_size(precision: usize)
+                 -> (f64, f64)
+{
+    let bits = 5 * precision as i32;
+    let lon_bits = (bits + 1) / 2;
+    let lat_bits = bits / 2;
+    (360.0 / 2.0_f64.powi(lon_bits), 180.0 / 2.0_f64.powi(lat_bits))
+}
+
+/// Encodes a single lon / lat coordinate (in degrees) into a geohash
+/// with `precision` characters
+pub fn encode(lon: f64, lat: f64, precision: usize)
+              -> Result<String, GeohashError>
+{
+    if precision == 0 || precision > MAX_PRECISION {
+        return Err(GeohashError::InvalidPrecision(precision));
+    }
+
+    if !(-180.0..=180.0).contains(&lon) || !(-90.0..=90.0).contains(&lat) {
+        return Err(GeohashError::InvalidCoordinate(lon, lat));
+    }
+
+    let mut lon_range = (-180.0, 180.0);
+    let mut lat_range = (-90.0, 90.0);
+    let mut hash = String::with_capacity(precision);
+    let mut even_bit = true;
+
+    for _ in 0..precision {
+        let mut index = 0;
+        for _ in 0..5 {
+            let (value, range) = if even_bit { (lon, &mut lon_range) } else { (lat, &mut lat_range) };
+            let mid = (range.0 + range.1) / 2.0;
+            index <<= 1;
+            if value >= mid {
+                index |= 1;
+                range.0 = mid;
+            } else {
+                range.1 = mid;
+            }
+            even_bit = !even_bit;
+        }
+        hash.push(BASE32[index] as char);
+    }
+
+    Ok(hash)
+}
+
+/// Decodes a geohash into the bounding box of its cell
+pub fn decode_bbox(hash: &str)
+                   -> Result<(f64, f64, f64, f64), GeohashError>
+{
+    let len = hash.chars().count();
+    if len == 0 || len > MAX_PRECISION {
+        return Err(GeohashError::InvalidLength(len));
+    }
+
+    let mut lon_range = (-180.0, 180.0);
+    let mut lat_range = (-90.0, 90.0);
+    let mut even_bit = true;
+
+    for c in hash.chars() {
+        let index = BASE32.iter().position(|&b| b as char == c.to_ascii_lowercase())
+            .ok_or(GeohashError::InvalidCharacter(c))?;
+
+        for bit in (0..5).rev() {
+            let range = if even_bit { &mut lon_range } else { &mut lat_range };
+            let mid = (range.0 + range.1) / 2.0;
+            if (index >> bit) & 1 == 1 {
+                range.0 = mid;
+            } else {
+                range.1 = mid;
+            }
+            even_bit = !even_bit;
+        }
+    }
+
+    Ok((lon_range.0, lat_range.0, lon_range.1, lat_range.1))
+}
+
+/// Decodes a geohash into the center of its cell (lon / lat, in degrees)
+pub fn decode(hash: &str)
+              -> Result<(f64, f64), GeohashError>
+{
+    let (min_lon, min_lat, max_lon, max_lat) = decode_bbox(hash)?;
+    Ok(((min_lon + max_lon) / 2.0, (min_lat + max_lat) / 2.0))
+}
+
+/// Returns the neighbouring cell of a geohash in the given direction,
+/// with the same precision
+///
+/// Cells wrap around the antimeridian. Returns `None` for the northern
+/// neighbours of cells at the north pole (and vice versa for the south pole).
+pub fn neighbour(hash: &str, direction: Direction)
+                 -> Result<Option<String>, GeohashError>
+{
+    let (lon, lat) = decode(hash)?;
+    let (width, height) = cell_size(hash.chars().count());
+    let (dx, dy) = direction.offset();
+
+    let lat = lat + dy * height;
+    if !(-90.0..=90.0).contains(&lat) {
+        return Ok(None);
+    }
+
+    let lon = (lon + dx * width + 180.0).rem_euclid(360.0) - 180.0;
+    encode(lon, lat, hash.chars().count()).map(Some)
+}
+
+/// Returns all (up to 8) neighbouring cells of a geohash, clockwise starting with north
+pub fn neighbours(hash: &str)
+                  -> Result<Vec<(Direction, String)>, GeohashError>
+{
+    let mut neighbours = Vec::with_capacity(8);
+
+    for &direction in Direction::ALL.iter() {
+        if let Some(neighbour) = neighbour(hash, direction)? {
+            neighbours.push((direction, neighbour));
+        }
+    }
+
+    Ok(neighbours)
+}
+
+/// Returns all cells with `precision` characters that intersect the given
+/// bounding box, row by row from south-west to north-east
+///
+/// If `min_lon > max_lon`, the bounding box crosses the antimeridian,
+/// `min_lat > max_lat` is an error. Note that the number of cells grows by a factor of 32 with each
+/// additional character of precision.
+pub fn covering(bbox: (f64, f64, f64, f64), precision: usize)
+                -> Result<Vec<String>, GeohashError>
+{
+    let (min_lon, min_lat, max_lon, max_lat) = bbox;
+
+    if precision == 0 || precision > MAX_PRECISION {
+        return Err(GeohashError::InvalidPrecision(precision));
+    }
+
+    for &(lon, lat) in [(min_lon, min_lat), (max_lon, max_lat)].iter() {
+        if !(-180.0..=180.0).contains(&lon) || !(-90.0..=90.0).contains(&lat) {
+            return Err(GeohashError::InvalidCoordinate(lon, lat));
+        }
+    }
+
+    if min_lat > max_lat {
+        return Err(GeohashError::InvalidBoundingBox(min_lon, min_lat, max_lon, max_lat));
+    }
+
+    if min_lon > max_lon {
+        let mut cells = covering((min_lon, min_lat, 180.0, max_lat), precision)?;
+        cells.extend(covering((-180.0, min_lat, max_lon, max_lat), precision)?);
+        return Ok(cells);
+    }
+
+    let (width, height) = cell_size(precision);
+    let columns = 2.0_f64.powi(((5 * precision as i32) + 1) / 2);
+    let rows = 2.0_f64.powi((5 * precision as i32) / 2);
+
+    let column_range = |min: f64, max: f64| {
+        let first = ((min + 180.0) / width).floor().min(columns - 1.0) as u64;
+        let last = ((max + 180.0) / width).floor().min(columns - 1.0) as u64;
+        (first, last)
+    };
+    let (first_column, last_column) = column_range(min_lon, max_lon);
+    let first_row = ((min_lat + 90.0) / height).floor().min(rows - 1.0) as u64;
+    let last_row = ((max_lat + 90.0) / height).floor().min(rows - 1.0) as u64;
+
+    let mut cells = Vec::with_capacity(((last_column - first_column + 1) * (last_row - first_row + 1)) as usize);
+
+    for row in first_row..=last_row {
+        let lat = -90.0 + (row as f64 + 0.5) * height;
+        for column in first_column..=last_column {
+            let lon = -180.0 + (column as f64 + 0.5) * width;
+            cells.push(encode(lon, lat, precision)?);
+        }
+    }
+
+    Ok(cells)
+}
+
+/// Encodes all coordinates of a `LonLatBuf` into geohashes with `precision` characters
+///
+/// Coordinates that are out of range are returned as `None`.
+pub fn encode_lonlat_buf(buf: &LonLatBuf, precision: usize, strategy: &mut MultithreadingStrategy)
+                         -> Result<Vec<Option<String>>, GeohashError>
+{
+    if precision == 0 || precision > MAX_PRECISION {
+        return Err(GeohashError::InvalidPrecision(precision));
+    }
+
+    let mut hashes = vec![None; buf.data.len()];
+
+    match *strategy {
+        SingleCore => {
+            for (&(lon, lat), hash) in buf.data.iter().zip(hashes.iter_mut()) {
+                *hash = encode(lon, lat, precision).ok();
+            }
+        },
+        #[cfg(all(not(target_arch = "wasm32"), feature = "scoped_threadpool"))]
+        MultiCore(ref mut thread_pool) => {
+            thread_pool.scoped(|scoped| {
+                for (&(lon, lat), hash) in buf.data.iter().zip(hashes.iter_mut()) {
+                    scoped.execute(move || {
+                        *hash = encode(lon, lat, precision).ok();
+                    });
+                }
+            });
+        },
+    }
+
+    Ok(hashes)
+}
+
+/// Decodes geohashes into a `LonLatBuf` with the center of each cell
+///
+/// Invalid geohashes are decoded to (NaN, NaN).
+pub fn decode_to_lonlat_buf<S: AsRef<str> + Sync>(hashes: &[S], ellipsoid: &Ellipsoid, strategy: &mut MultithreadingStrategy)
+                                                  -> LonLatBuf
+{
+    let mut data = vec![(f64::NAN, f64::NAN); hashes.len()];
+
+    match *strategy {
+        SingleCore => {
+            for (hash, coordinate) in hashes.iter().zip(data.iter_mut()) {
+                *coordinate = decode(hash.as_ref()).unwrap_or((f64::NAN, f64::NAN));
+            }
+        },
+        #[cfg(all(not(target_arch = "wasm32"), feature = "scoped_threadpool"))]
+        MultiCore(ref mut thread_pool) => {
+            thread_pool.scoped(|scoped| {
+                for (hash, coordinate) in hashes.iter().zip(data.iter_mut()) {
+                    scoped.execute(move || {
+                        *coordinate = decode(hash.as_ref()).unwrap_or((f64::NAN, f64::NAN));
+                    });
+                }
+            });
+        },
+    }
+
+    LonLatBuf {
+        data,
+        ellipsoid: *ellipsoid,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_decode() {
+        assert_eq!(encode(-5.6, 42.6, 5).unwrap(), "ezs42");
+        assert_eq!(decode_bbox("ezs42").unwrap(), (-5.625, 42.583_007_812_5, -5.581_054_687_5, 42.626_953_125));
+        assert_eq!(decode_bbox("EZS42").unwrap(), decode_bbox("ezs42").unwrap());
+        assert_eq!(encode(10.40744, 57.64911, 11).unwrap(), "u4pruydqqvj");
+
+        let (lon, lat) = decode("u4pruydqqvj").unwrap();
+        assert!((lon - 10.40744).abs() < 1e-5 && (lat - 57.64911).abs() < 1e-5);
+
+        assert_eq!(encode(0.0, 0.0, 0), Err(GeohashError::InvalidPrecision(0)));
+        assert_eq!(encode(181.0, 0.0, 5), Err(GeohashError::InvalidCoordinate(181.0, 0.0)));
+        assert_eq!(decode("ezs4a"), Err(GeohashError::InvalidCharacter('a')));
+    }
+
+    #[test]
+    fn neighbours_of_a_cell() {
+        let expected = ["dqcjw", "dqcjx", "dqcjr", "dqcjp", "dqcjn", "dqcjj", "dqcjm", "dqcjt"];
+        let found = neighbours("dqcjq").unwrap();
+        assert_eq!(found.len(), 8);
+        for (&(direction, ref hash), &expected) in found.iter().zip(expected.iter()) {
+            assert_eq!(hash, expected, "{:?}", direction);
+        }
+
+        // wraps around the antimeridian, stops at the poles
+        assert_eq!(neighbour("xbpb", Direction::East).unwrap(), Some("8000".to_string()));
+        assert_eq!(neighbour("zzzz", Direction::North).unwrap(), None);
+    }
+
+    #[test]
+    fn covering_bbox() {
+        assert_eq!(covering((10.0, 48.0, 11.0, 49.0), 3).unwrap(), vec!["u0x"]);
+        assert_eq!(covering((179.9, 0.0, -179.9, 0.1), 4).unwrap().len(), 2);
+        assert_eq!(covering((0.0, 50.0, 1.0, 40.0), 3),
+                   Err(GeohashError::InvalidBoundingBox(0.0, 50.0, 1.0, 40.0)));
+    }
+}
@@ -0,0 +1,473 @@
+//! GeoJSON (RFC 7946) reprojection
+//!
+//! `parse_geojson` reads any GeoJSON object: a `FeatureCollection`, a `Feature`
+//! or a geometry. `GeoJson::reproject` gathers the positions of every `Point`,
+//! `LineString`, `Polygon`, their `Multi*` variants and `GeometryCollection`s,
+//! projects them in a single batched `CoordinateSource::project` call and puts
+//! them back in place. Properties, ids, foreign members, the order of the members
+//! and the height of three-dimensional positions are written back unchanged,
+//! existing `bbox` members are recalculated. Features with positions that can't
+//! be projected are reported and get a `null` geometry.
+//!
+//! RFC 7946 only allows WGS 84 longitude / latitude, but projected GeoJSON is
+//! common in practice: `set_crs` writes the `crs` member of the 2008 GeoJSON
+//! specification, e.g. `urn:ogc:def:crs:EPSG::32632`, which GDAL and QGIS read.
+//!
+//! ```rust
+//! extern crate proj5;
+//!
+//! use proj5::prelude::*;
+//! use proj5::geojson::*;
+//!
+//! fn main() {
+//!     let mut geojson = parse_geojson(r#"{
+//!       "type": "FeatureCollection",
+//!       "features": [
+//!         {"type": "Feature", "properties": {"name": "Stuttgart"},
+//!          "geometry": {"type": "Point", "coordinates": [9.0, 48.0, 245.0]}},
+//!         {"type": "Feature", "properties": null,
+//!          "geometry": {"type": "Polygon", "coordinates": [[[8, 47], [10, 47], [9, 49], [8, 47]]]}}
+//!       ]
+//!     }"#).unwrap();
+//!     assert_eq!(geojson.epsg_code(), Some(4326));
+//!
+//!     let utm = UTMSystem { utm_zone: 32, hemisphere: Hemisphere::North };
+//!     let mut strategy = MultithreadingStrategy::SingleCore;
+//!     let not_projectable = geojson.reproject(&LonLatSystem, &WGS_1984_ELLIPSOID, &utm, &WGS_1984_ELLIPSOID, &mut strategy);
+//!     assert!(not_projectable.is_empty());
+//!     geojson.set_crs(Some("urn:ogc:def:crs:EPSG::32632"));
+//!     geojson.set_bbox();
+//!
+//!     let point = geojson.coordinates()[0];
+//!     assert!((point.0 - 500_000.0).abs() < 1e-6);
+//!     assert_eq!(geojson.epsg_code(), Some(32632));
+//!
+//!     let written = geojson.to_string();
+//!     assert!(written.contains(r#""properties":{"name":"Stuttgart"}"#));
+//!     assert!(written.contains(",245.0]"));
+//! }
+//! ```
+
+use prelude::*;
+use json::Json;
+use coordinate_buf::project_batch;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// A GeoJSON object: `FeatureCollection`, `Feature` or geometry
+///
+/// The document is kept as a whole, so that everything but the coordinates is
+/// written back as it was read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoJson {
+    root: Json,
+}
+
+/// Error that can occur when reading GeoJSON
+#[derive(Debug, Clone, PartialEq)]
+pub enum GeoJsonError {
+    /// The text isn't valid JSON, the field is the byte offset of the error
+    InvalidJson(usize),
+    /// The `type` member isn't a GeoJSON type
+    UnsupportedType(String),
+    /// A required member is missing
+    MissingMember(String),
+    /// The member has the wrong JSON type, or a position has less than two numbers
+    InvalidMember(String),
+}
+
+impl fmt::Display for GeoJsonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::GeoJsonError::*;
+        match *self {
+            InvalidJson(position) => write!(f, "invalid JSON at byte {}", position),
+            UnsupportedType(ref kind) => write!(f, "unsupported GeoJSON type: {}", kind),
+            MissingMember(ref member) => write!(f, "missing GeoJSON member: {}", member),
+            InvalidMember(ref member) => write!(f, "invalid GeoJSON member: {}", member),
+        }
+    }
+}
+
+impl Error for GeoJsonError { }
+
+impl GeoJson {
+    /// Returns the horizontal coordinates of all positions, in document order
+    pub fn coordinates(&self) -> Vec<(f64, f64)> {
+        let mut positions = Vec::new();
+        // the document has been validated when it was read
+        let _ = collect_positions(&self.root, &mut positions);
+        positions.iter().map(|p| (p[0].as_f64().unwrap_or(0.0), p[1].as_f64().unwrap_or(0.0))).collect()
+    }
+
+    /// Replaces the horizontal coordinates of all positions, in document order
+    ///
+    /// # Panics
+    ///
+    /// If `data` doesn't have one coordinate per position, see `coordinates`
+    pub fn set_coordinates(&mut self, data: Vec<(f64, f64)>) {
+        let mut positions = Vec::new();
+        collect_positions_mut(&mut self.root, &mut positions);
+        assert_eq!(positions.len(), data.len(), "one coordinate per position is required");
+
+        for (position, (x, y)) in positions.into_iter().zip(data) {
+            position[0] = Json::Number(x);
+            position[1] = Json::Number(y);
+        }
+    }
+
+    /// Projects all positions from the source to the target CRS in one batch,
+    /// then recalculates the existing `bbox` members
+    ///
+    /// Returns the indices of the features with positions whose projection isn't
+    /// finite, e.g. outside the area of the CRS. Their geometry is written as
+    /// `null`, since JSON has no numbers for them. A single `Feature` or geometry
+    /// has the index 0; a geometry without a feature is left unchanged.
+    pub fn reproject(&mut self,
+                     source_crs: &dyn Crs, source_ellipsoid: &Ellipsoid,
+                     target_crs: &dyn Crs, target_ellipsoid: &Ellipsoid,
+                     strategy: &mut MultithreadingStrategy)
+                     -> Vec<usize>
+    {
+        let mut data = project_batch(self.coordinates(), source_crs, source_ellipsoid, target_crs, target_ellipsoid, strategy);
+
+        let mut not_projectable = Vec::new();
+        let mut start = 0;
+        for (index, count) in feature_position_counts(&self.root).into_iter().enumerate() {
+            let range = start..start + count;
+            start += count;
+            if data[range.clone()].iter().any(|&(x, y)| !x.is_finite() || !y.is_finite()) {
+                data[range].iter_mut().for_each(|xy| *xy = (f64::NAN, f64::NAN));
+                not_projectable.push(index);
+            }
+        }
+
+        let mut positions = Vec::new();
+        collect_positions_mut(&mut self.root, &mut positions);
+        for (position, (x, y)) in positions.into_iter().zip(data).filter(|&(_, (x, y))| x.is_finite() && y.is_finite()) {
+            position[0] = Json::Number(x);
+            position[1] = Json::Number(y);
+        }
+
+        let kind = object_type(&self.root).unwrap_or("").to_string();
+        for &index in &not_projectable {
+            match (kind.as_str(), self.root.get_mut("features")) {
+                ("FeatureCollection", Some(&mut Json::Array(ref mut features))) => features[index].set("geometry", Json::Null),
+                ("Feature", _) => self.root.set("geometry", Json::Null),
+                _ => { },
+            }
+        }
+        update_bboxes(&mut self.root);
+        not_projectable
+    }
+
+    /// Returns the name of the `crs` member, e.g. `urn:ogc:def:crs:EPSG::32632`
+    pub fn crs(&self) -> Option<&str> {
+        self.root.get("crs")?.get("properties")?.get("name")?.as_str()
+    }
+
+    /// Writes a named `crs` member, or removes it for `None`
+    pub fn set_crs(&mut self, name: Option<&str>) {
+        match name {
+            Some(name) => self.root.set("crs", Json::object(vec![
+                ("type", Json::string("name")),
+                ("properties", Json::object(vec![("name", Json::string(name))])),
+            ])),
+            None => self.root.remove("crs"),
+        }
+    }
+
+    /// Returns the EPSG code of the `crs` member (`urn:ogc:def:crs:EPSG::...` or
+    /// `EPSG:...`), or 4326 if there is none, as RFC 7946 requires
+    pub fn epsg_code(&self) -> Option<u32> {
+        let name = match self.crs() {
+            None => return Some(4326),
+            Some(name) => name,
+        };
+
+        if name == "urn:ogc:def:crs:OGC:1.3:CRS84" || name == "urn:ogc:def:crs:OGC::CRS84" {
+            return Some(4326);
+        }
+        name.strip_prefix("urn:ogc:def:crs:EPSG:")
+            .and_then(|rest| rest.rsplit(':').next())
+            .or_else(|| name.strip_prefix("EPSG:"))
+            .and_then(|code| code.parse().ok())
+    }
+
+    /// Adds or replaces the two-dimensional `bbox` member of the top-level object
+    pub fn set_bbox(&mut self) {
+        match bbox(&self.root, 2) {
+            Some(bbox) => self.root.set("bbox", bbox),
+            None => self.root.remove("bbox"),
+        }
+    }
+
+    /// Writes the GeoJSON with two spaces of indentation
+    pub fn to_pretty_string(&self) -> String {
+        self.root.to_pretty_string()
+    }
+}
+
+/// Compact GeoJSON without whitespace
+impl fmt::Display for GeoJson {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.root)
+    }
+}
+
+impl FromStr for GeoJson {
+    type Err = GeoJsonError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_geojson(s)
+    }
+}
+
+/// Reads a GeoJSON object and checks the structure of its geometries
+pub fn parse_geojson(s: &str) -> Result<GeoJson, GeoJsonError> {
+    let root = Json::parse(s).map_err(GeoJsonError::InvalidJson)?;
+    collect_positions(&root, &mut Vec::new())?;
+    Ok(GeoJson { root })
+}
+
+/// Nesting depth of the positions in the `coordinates` of a geometry type
+fn coordinates_depth(kind: &str) -> Option<usize> {
+    match kind {
+        "Point" => Some(0),
+        "MultiPoint" | "LineString" => Some(1),
+        "MultiLineString" | "Polygon" => Some(2),
+        "MultiPolygon" => Some(3),
+        _ => None,
+    }
+}
+
+fn object_type(value: &Json) -> Result<&str, GeoJsonError> {
+    value.get("type").ok_or_else(|| GeoJsonError::MissingMember("type".to_string()))?
+        .as_str().ok_or_else(|| GeoJsonError::InvalidMember("type".to_string()))
+}
+
+fn array_member<'a>(value: &'a Json, key: &str) -> Result<&'a [Json], GeoJsonError> {
+    value.get(key).ok_or_else(|| GeoJsonError::MissingMember(key.to_string()))?
+        .as_array().ok_or_else(|| GeoJsonError::InvalidMember(key.to_string()))
+}
+
+/// Collects the positions of a GeoJSON object, checking its structure
+fn collect_positions<'a>(value: &'a Json, positions: &mut Vec<&'a [Json]>) -> Result<(), GeoJsonError> {
+    match object_type(value)? {
+        "FeatureCollection" => {
+            for feature in array_member(value, "features")? {
+                if object_type(feature)? != "Feature" {
+                    return Err(GeoJsonError::InvalidMember("features".to_string()));
+                }
+                collect_positions(feature, positions)?;
+            }
+            Ok(())
+        },
+        "Feature" => match value.get("geometry") {
+            None => Err(GeoJsonError::MissingMember("geometry".to_string())),
+            Some(&Json::Null) => Ok(()),
+            Some(geometry) => collect_geometry_positions(geometry, positions),
+        },
+        _ => collect_geometry_positions(value, positions),
+    }
+}
+
+fn collect_geometry_positions<'a>(value: &'a Json, positions: &mut Vec<&'a [Json]>) -> Result<(), GeoJsonError> {
+    let kind = object_type(value)?;
+    if kind == "GeometryCollection" {
+        for geometry in array_member(value, "geometries")? {
+            collect_geometry_positions(geometry, positions)?;
+        }
+        return Ok(());
+    }
+
+    let depth = coordinates_depth(kind).ok_or_else(|| GeoJsonError::UnsupportedType(kind.to_string()))?;
+    let coordinates = value.get("coordinates").ok_or_else(|| GeoJsonError::MissingMember("coordinates".to_string()))?;
+    collect_coordinates(coordinates, depth, positions)
+}
+
+fn collect_coordinates<'a>(value: &'a Json, depth: usize, positions: &mut Vec<&'a [Json]>) -> Result<(), GeoJsonError> {
+    let values = value.as_array().ok_or_else(|| GeoJsonError::InvalidMember("coordinates".to_string()))?;
+    if depth > 0 {
+        for value in values {
+            collect_coordinates(value, depth - 1, positions)?;
+        }
+    } else if values.len() >= 2 && values.iter().all(|v| v.as_f64().is_some()) {
+        positions.push(values);
+    } else {
+        return Err(GeoJsonError::InvalidMember("coordinates".to_string()));
+    }
+    Ok(())
+}
+
+/// Number of positions of each feature of a validated GeoJSON object, a single
+/// `Feature` or geometry counts as one feature
+fn feature_position_counts(value: &Json) -> Vec<usize> {
+    let count = |value: &Json| {
+        let mut positions = Vec::new();
+        let _ = collect_positions(value, &mut positions);
+        positions.len()
+    };
+    match value.get("features").and_then(Json::as_array) {
+        Some(features) if object_type(value).ok() == Some("FeatureCollection") => features.iter().map(count).collect(),
+        _ => vec![count(value)],
+    }
+}
+
+/// Member with the child objects of a collection or feature
+fn child_member(kind: &str) -> Option<&'static str> {
+    match kind {
+        "FeatureCollection" => Some("features"),
+        "GeometryCollection" => Some("geometries"),
+        "Feature" => Some("geometry"),
+        _ => None,
+    }
+}
+
+/// Calls `f` for the child objects of a collection or feature, `null` geometries included
+fn for_each_child<F: FnMut(&mut Json)>(value: &mut Json, mut f: F) {
+    let kind = value.get("type").and_then(Json::as_str).unwrap_or("").to_string();
+    match child_member(&kind).and_then(|member| value.get_mut(member)) {
+        Some(&mut Json::Array(ref mut values)) => values.iter_mut().for_each(f),
+        Some(geometry) => f(geometry),
+        None => { },
+    }
+}
+
+/// Collects the positions of a validated GeoJSON object, in the same order as `collect_positions`
+fn collect_positions_mut<'a>(value: &'a mut Json, positions: &mut Vec<&'a mut Vec<Json>>) {
+    let kind = value.get("type").and_then(Json::as_str).unwrap_or("").to_string();
+    match (child_member(&kind), coordinates_depth(&kind)) {
+        (Some(member), _) => match value.get_mut(member) {
+            Some(&mut Json::Array(ref mut values)) => {
+                for value in values {
+                    collect_positions_mut(value, positions);
+                }
+            },
+            Some(geometry) => collect_positions_mut(geometry, positions),
+            None => { },
+        },
+        (None, Some(depth)) => {
+            if let Some(coordinates) = value.get_mut("coordinates") {
+                collect_coordinates_mut(coordinates, depth, positions);
+            }
+        },
+        (None, None) => { },
+    }
+}
+
+fn collect_coordinates_mut<'a>(value: &'a mut Json, depth: usize, positions: &mut Vec<&'a mut Vec<Json>>) {
+    if let Json::Array(ref mut values) = *value {
+        if depth == 0 {
+            positions.push(values);
+        } else {
+            for value in values {
+                collect_coordinates_mut(value, depth - 1, positions);
+            }
+        }
+    }
+}
+
+/// Bounding box over the first `dimensions` coordinates of the positions,
+/// `None` if the object has no positions
+fn bbox(value: &Json, dimensions: usize) -> Option<Json> {
+    let mut positions = Vec::new();
+    collect_positions(value, &mut positions).ok()?;
+
+    let mut min = vec![f64::INFINITY; dimensions];
+    let mut max = vec![f64::NEG_INFINITY; dimensions];
+    for position in positions {
+        for (i, value) in position.iter().take(dimensions).enumerate() {
+            let value = value.as_f64().unwrap_or(f64::NAN);
+            min[i] = min[i].min(value);
+            max[i] = max[i].max(value);
+        }
+    }
+
+    // drop the height if not all positions have one
+    let found = min.iter().take_while(|v| v.is_finite()).count();
+    if found < 2 {
+        return None;
+    }
+    min.truncate(found);
+    max.truncate(found);
+    Some(Json::Array(min.into_iter().chain(max).map(Json::Number).collect()))
+}
+
+/// Recalculates every `bbox` member, keeping its number of dimensions
+fn update_bboxes(value: &mut Json) {
+    for_each_child(value, update_bboxes);
+
+    let dimensions = match value.get("bbox").and_then(Json::as_array) {
+        Some(values) => (values.len() / 2).max(2),
+        None => return,
+    };
+    match bbox(value, dimensions) {
+        Some(bbox) => value.set("bbox", bbox),
+        None => value.remove("bbox"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COLLECTION: &str = r#"{"type":"FeatureCollection","bbox":[0,0,0,0],"features":[
+        {"type":"Feature","properties":{"name":"Greenwich"},"geometry":{"type":"Point","coordinates":[0.0,51.5]}},
+        {"type":"Feature","properties":{"name":"Honolulu"},"geometry":{"type":"Point","coordinates":[-157.9,21.3]}},
+        {"type":"Feature","properties":null,"geometry":{"type":"LineString","coordinates":[[10,0],[170,0]]}}
+    ]}"#;
+
+    fn meteosat() -> GeostationarySystem {
+        GeostationarySystem { satellite_height: 35_785_831.0, sub_satellite_lon: 0.0, sweep_axis: SweepAxis::Y }
+    }
+
+    #[test]
+    fn not_projectable_round_trip() {
+        let mut geojson = parse_geojson(COLLECTION).unwrap();
+        let mut strategy = MultithreadingStrategy::SingleCore;
+        let not_projectable = geojson.reproject(&LonLatSystem, &WGS_1984_ELLIPSOID, &meteosat(), &WGS_1984_ELLIPSOID, &mut strategy);
+        assert_eq!(not_projectable, vec![1, 2]);
+
+        let written = geojson.to_string();
+        assert!(!written.contains("[null") && !written.contains("null]"));
+        assert!(written.contains(r#""properties":{"name":"Honolulu"},"geometry":null"#));
+
+        let read = parse_geojson(&written).unwrap();
+        assert_eq!(read.to_string(), written);
+        let coordinates = read.coordinates();
+        assert_eq!(coordinates.len(), 1);
+        assert!(coordinates[0].0.abs() < 1e-6 && coordinates[0].1 > 0.0);
+        let bbox = read.root.get("bbox").and_then(Json::as_array).unwrap();
+        assert_eq!(bbox[0].as_f64(), bbox[2].as_f64());
+    }
+
+    #[test]
+    fn not_projectable_single_objects() {
+        let mut strategy = MultithreadingStrategy::SingleCore;
+
+        let mut feature = parse_geojson(r#"{"type":"Feature","properties":null,"geometry":{"type":"Point","coordinates":[180,0]}}"#).unwrap();
+        assert_eq!(feature.reproject(&LonLatSystem, &WGS_1984_ELLIPSOID, &meteosat(), &WGS_1984_ELLIPSOID, &mut strategy), vec![0]);
+        assert_eq!(feature.to_string(), r#"{"type":"Feature","properties":null,"geometry":null}"#);
+
+        let source = r#"{"type":"MultiPoint","coordinates":[[0,0],[180,0]]}"#;
+        let mut geometry = parse_geojson(source).unwrap();
+        assert_eq!(geometry.reproject(&LonLatSystem, &WGS_1984_ELLIPSOID, &meteosat(), &WGS_1984_ELLIPSOID, &mut strategy), vec![0]);
+        assert_eq!(geometry.to_string(), source);
+    }
+
+    #[test]
+    fn reproject_round_trip() {
+        let mut geojson = parse_geojson(COLLECTION).unwrap();
+        let mut strategy = MultithreadingStrategy::SingleCore;
+        let mercator = WebMercatorSystem;
+        assert!(geojson.reproject(&LonLatSystem, &WGS_1984_ELLIPSOID, &mercator, &WGS_1984_ELLIPSOID, &mut strategy).is_empty());
+
+        let mut projected = parse_geojson(&geojson.to_string()).unwrap();
+        assert!(projected.reproject(&mercator, &WGS_1984_ELLIPSOID, &LonLatSystem, &WGS_1984_ELLIPSOID, &mut strategy).is_empty());
+        let expected = parse_geojson(COLLECTION).unwrap().coordinates();
+        for (a, b) in projected.coordinates().iter().zip(expected) {
+            assert!((a.0 - b.0).abs() < 1e-9 && (a.1 - b.1).abs() < 1e-9, "{:?} != {:?}", a, b);
+        }
+    }
+}
@@ -0,0 +1,218 @@
+//! Global Area Reference System (GARS)
+//!
+//! Implementation based on:
+//!
+//! NGA, Global Area Reference System (GARS), https://earth-info.nga.mil/index.php?dir=coordsys&action=gars-20x20-dloads
+//!
+//! A GARS cell such as "006AG39" consists of:
+//!
+//! - a 3 digit longitude band (001 to 720, 30' each, starting at 180°W)
+//! - a 2 letter latitude band (AA to QZ without I and O, 30' each, starting at 90°S)
+//! - optionally a 15' quadrant (1 to 4, numbered from north-west to south-east)
+//! - optionally a 5' keypad cell (1 to 9, numbered like a telephone keypad)
+
+use grid_references::bbox_to_polygon;
+use std::error::Error;
+use std::fmt;
+
+/// Letters of the latitude bands (without I and O)
+const LETTERS: &str = "ABCDEFGHJKLMNPQRSTUVWXYZ";
+
+/// Size of a cell
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum GarsPrecision {
+    /// 30' x 30' cell, e.g. "006AG"
+    ThirtyMinutes,
+    /// 15' x 15' quadrant, e.g. "006AG3"
+    FifteenMinutes,
+    /// 5' x 5' keypad cell, e.g. "006AG39"
+    FiveMinutes,
+}
+
+/// Error that can occur when creating or parsing a GARS cell
+#[derive(Debug, Clone, PartialEq)]
+pub enum GarsError {
+    /// Longitude or latitude isn't a valid number
+    InvalidCoordinate(f64, f64),
+    /// The GARS cell is malformed
+    InvalidCell(String),
+}
+
+impl fmt::Display for GarsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GarsError::InvalidCoordinate(lon, lat) => write!(f, "invalid coordinate: ({}, {})", lon, lat),
+            GarsError::InvalidCell(ref s) => write!(f, "invalid GARS cell: \"{}\"", s),
+        }
+    }
+}
+
+impl Error for GarsError { }
+
+/// Converts a lon / lat coordinate (in degrees) into the GARS cell containing it
+pub fn lonlat_to_gars(lon: f64, lat: f64, precision: GarsPrecision)
+                      -> Result<String, GarsError>
+{
+    if !lon.is_finite() || !lat.is_finite() || !(-90.0..=90.0).contains(&lat) {
+        return Err(GarsError::InvalidCoordinate(lon, lat));
+    }
+
+    // Position in 5' units from the south-west corner
+    let lon = (lon + 180.0).rem_euclid(360.0);
+    let x = ((lon * 12.0).floor() as u32).min(360 * 12 - 1);
+    let y = (((lat + 90.0) * 12.0).floor() as u32).min(180 * 12 - 1);
+
+    let lon_band = x / 6 + 1;
+    let lat_band = (y / 6) as usize;
+    let letters = LETTERS.as_bytes();
+
+    let mut cell = format!("{:03}{}{}", lon_band, letters[lat_band / 24] as char, letters[lat_band % 24] as char);
+
+    // Position within the 30' cell, rows counted from the north
+    let column = x % 6;
+    let row = 5 - y % 6;
+
+    if precision != GarsPrecision::ThirtyMinutes {
+        let quadrant = (row / 3) * 2 + column / 3 + 1;
+        cell.push_str(&quadrant.to_string());
+    }
+
+    if precision == GarsPrecision::FiveMinutes {
+        let keypad = (row % 3) * 3 + column % 3 + 1;
+        cell.push_str(&keypad.to_string());
+    }
+
+    Ok(cell)
+}
+
+/// Parses a GARS cell and returns its bounding box (min_lon, min_lat, max_lon, max_lat) in degrees
+pub fn gars_to_bbox(cell: &str)
+                    -> Result<(f64, f64, f64, f64), GarsError>
+{
+    let invalid = || GarsError::InvalidCell(cell.to_string());
+    let trimmed = cell.trim().to_ascii_uppercase();
+
+    if !trimmed.is_ascii() || trimmed.len() < 5 || trimmed.len() > 7 {
+        return Err(invalid());
+    }
+
+    let lon_band: u32 = trimmed[..3].parse().map_err(|_| invalid())?;
+    if !(1..=720).contains(&lon_band) || !trimmed[..3].bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid());
+    }
+
+    let mut letters = trimmed[3..5].chars();
+    let first = letters.next().and_then(|c| LETTERS.find(c)).ok_or_else(invalid)?;
+    let second = letters.next().and_then(|c| LETTERS.find(c)).ok_or_else(invalid)?;
+    let lat_band = first * 24 + second;
+    if lat_band >= 360 {
+        return Err(invalid());
+    }
+
+    let mut min_lon = -180.0 + f64::from(lon_band - 1) * 0.5;
+    let mut max_lat = -90.0 + (lat_band + 1) as f64 * 0.5;
+    let mut size = 0.5;
+
+    let mut digits = trimmed[5..].chars();
+
+    if let Some(c) = digits.next() {
+        let quadrant = c.to_digit(10).filter(|d| (1..=4).contains(d)).ok_or_else(invalid)? - 1;
+        size /= 2.0;
+        min_lon += f64::from(quadrant % 2) * size;
+        max_lat -= f64::from(quadrant / 2) * size;
+    }
+
+    if let Some(c) = digits.next() {
+        let keypad = c.to_digit(10).filter(|d| (1..=9).contains(d)).ok_or_else(invalid)? - 1;
+        size /= 3.0;
+        min_lon += f64::from(keypad % 3) * size;
+        max_lat -= f64::from(keypad / 3) * size;
+    }
+
+    Ok((min_lon, max_lat - size, min_lon + size, max_lat))
+}
+
+/// Parses a GARS cell and returns its outline as a closed,
+/// counter-clockwise ring of lon / lat coordinates (in degrees)
+pub fn gars_to_polygon(cell: &str)
+                       -> Result<Vec<(f64, f64)>, GarsError>
+{
+    gars_to_bbox(cell).map(bbox_to_polygon)
+}
+
+/// Parses a GARS cell and returns its center (lon / lat, in degrees)
+pub fn gars_to_lonlat(cell: &str)
+                      -> Result<(f64, f64), GarsError>
+{
+    let (min_lon, min_lat, max_lon, max_lat) = gars_to_bbox(cell)?;
+    Ok(((min_lon + max_lon) / 2.0, (min_lat + max_lat) / 2.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_bbox_eq(actual: (f64, f64, f64, f64), expected: (f64, f64, f64, f64)) {
+        let (a, e) = ([actual.0, actual.1, actual.2, actual.3], [expected.0, expected.1, expected.2, expected.3]);
+        for (a, e) in a.iter().zip(e.iter()) {
+            assert!((a - e).abs() < 1e-9, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn reference_cells() {
+        // NGA example cell "006AG39"
+        assert_eq!(lonlat_to_gars(-177.3, -86.95, GarsPrecision::ThirtyMinutes), Ok("006AG".to_string()));
+        assert_eq!(lonlat_to_gars(-177.3, -86.95, GarsPrecision::FifteenMinutes), Ok("006AG3".to_string()));
+        assert_eq!(lonlat_to_gars(-177.3, -86.95, GarsPrecision::FiveMinutes), Ok("006AG39".to_string()));
+
+        assert_eq!(lonlat_to_gars(13.41, 52.52, GarsPrecision::FiveMinutes), Ok("387MX48".to_string()));
+        assert_eq!(lonlat_to_gars(-74.0, 40.75, GarsPrecision::FiveMinutes), Ok("213LX17".to_string()));
+    }
+
+    #[test]
+    fn edges_of_the_world() {
+        assert_eq!(lonlat_to_gars(-180.0, -90.0, GarsPrecision::FiveMinutes), Ok("001AA37".to_string()));
+        assert_eq!(lonlat_to_gars(179.99, 89.99, GarsPrecision::FiveMinutes), Ok("720QZ23".to_string()));
+        // 180° wraps around to 180°W, 90°N falls into the northernmost row
+        assert_eq!(lonlat_to_gars(180.0, 90.0, GarsPrecision::FiveMinutes), Ok("001QZ11".to_string()));
+    }
+
+    #[test]
+    fn bounding_boxes() {
+        assert_bbox_eq(gars_to_bbox("006AG").unwrap(), (-177.5, -87.0, -177.0, -86.5));
+        assert_bbox_eq(gars_to_bbox("006AG3").unwrap(), (-177.5, -87.0, -177.25, -86.75));
+        assert_bbox_eq(gars_to_bbox("006AG39").unwrap(), (-177.5 + 2.0 / 12.0, -87.0, -177.25, -87.0 + 1.0 / 12.0));
+        assert_bbox_eq(gars_to_bbox(" 006ag39 ").unwrap(), gars_to_bbox("006AG39").unwrap());
+        assert_bbox_eq(gars_to_bbox("720QZ").unwrap(), (179.5, 89.5, 180.0, 90.0));
+
+        let polygon = gars_to_polygon("006AG").unwrap();
+        assert_eq!(polygon.len(), 5);
+        assert_eq!(polygon.first(), polygon.last());
+    }
+
+    #[test]
+    fn round_trip() {
+        let precisions = [GarsPrecision::ThirtyMinutes, GarsPrecision::FifteenMinutes, GarsPrecision::FiveMinutes];
+        for &(lon, lat) in &[(-177.3, -86.95), (13.41, 52.52), (-74.0, 40.75), (151.2093, -33.8688)] {
+            for &precision in &precisions {
+                let cell = lonlat_to_gars(lon, lat, precision).unwrap();
+                let (min_lon, min_lat, max_lon, max_lat) = gars_to_bbox(&cell).unwrap();
+                assert!(min_lon <= lon && lon < max_lon && min_lat <= lat && lat < max_lat, "{} {} {}", cell, lon, lat);
+
+                let (center_lon, center_lat) = gars_to_lonlat(&cell).unwrap();
+                assert_eq!(lonlat_to_gars(center_lon, center_lat, precision), Ok(cell));
+            }
+        }
+    }
+
+    #[test]
+    fn errors() {
+        assert!(lonlat_to_gars(0.0, 90.5, GarsPrecision::ThirtyMinutes).is_err());
+        assert!(lonlat_to_gars(f64::NAN, 0.0, GarsPrecision::ThirtyMinutes).is_err());
+
+        for cell in &["", "006A", "000AG", "721AG", "+06AG", "006AI", "006RA", "006AG0", "006AG5", "006AG30", "006AG391"] {
+            assert_eq!(gars_to_bbox(cell), Err(GarsError::InvalidCell(cell.to_string())), "{}", cell);
+        }
+    }
+}
@@ -0,0 +1,245 @@
+//! World Geographic Reference System (GEOREF)
+//!
+//! Implementation based on:
+//!
+//! DMA Technical Manual 8358.1, 1990, Datums, Ellipsoids, Grids and Grid
+//! Reference Systems, Chapter 3.9
+//!
+//! A GEOREF such as "GJPJ5753" consists of:
+//!
+//! - a 15° quadrangle: a longitude letter (A to Z without I and O) and a
+//!   latitude letter (A to M without I), starting at 180°W / 90°S
+//! - a 1° quadrangle: two letters (A to Q without I and O)
+//! - optionally the minutes of longitude and latitude within the 1° quadrangle,
+//!   with the same number of digits each: 2 (1'), 3 (0.1'), 4 (0.01'), ...
+//!
+//! The precision of a reference is given as in GeographicLib:
+//! -1 (15°), 0 (1°), 1 (1'), 2 (0.1'), ... up to `MAX_PRECISION`.
+
+use grid_references::bbox_to_polygon;
+use std::error::Error;
+use std::fmt;
+
+/// Longitude letters of the 15° quadrangles
+const LON_LETTERS: &str = "ABCDEFGHJKLMNPQRSTUVWXYZ";
+/// Latitude letters of the 15° quadrangles
+const LAT_LETTERS: &str = "ABCDEFGHJKLM";
+/// Letters of the 1° quadrangles
+const DEGREE_LETTERS: &str = "ABCDEFGHJKLMNPQ";
+
+/// Highest precision (0.000 000 000 1')
+pub const MAX_PRECISION: i8 = 11;
+
+/// Error that can occur when creating or parsing a GEOREF
+#[derive(Debug, Clone, PartialEq)]
+pub enum GeorefError {
+    /// Precision must be between -1 and `MAX_PRECISION`
+    InvalidPrecision(i8),
+    /// Longitude or latitude isn't a valid number
+    InvalidCoordinate(f64, f64),
+    /// The GEOREF is malformed
+    InvalidGeoref(String),
+}
+
+impl fmt::Display for GeorefError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GeorefError::InvalidPrecision(precision) => write!(f, "invalid GEOREF precision: {} (must be -1 to {})", precision, MAX_PRECISION),
+            GeorefError::InvalidCoordinate(lon, lat) => write!(f, "invalid coordinate: ({}, {})", lon, lat),
+            GeorefError::InvalidGeoref(ref s) => write!(f, "invalid GEOREF: \"{}\"", s),
+        }
+    }
+}
+
+impl Error for GeorefError { }
+
+/// Number of units per degree for the given precision
+/// (1 for precision 0 and below, 60 for 1 (minutes), 600 for 2, ...)
+#[inline]
+fn units_per_degree(precision: i8) -> i64 {
+    if precision <= 0 { 1 } else { 60 * 10_i64.pow(precision as u32 - 1) }
+}
+
+#[inline]
+fn letter(letters: &str, index: i64) -> char {
+    letters.as_bytes()[index as usize] as char
+}
+
+/// Converts a lon / lat coordinate (in degrees) into a GEOREF with the given precision
+pub fn lonlat_to_georef(lon: f64, lat: f64, precision: i8)
+                        -> Result<String, GeorefError>
+{
+    if !(-1..=MAX_PRECISION).contains(&precision) {
+        return Err(GeorefError::InvalidPrecision(precision));
+    }
+
+    if !lon.is_finite() || !lat.is_finite() || !(-90.0..=90.0).contains(&lat) {
+        return Err(GeorefError::InvalidCoordinate(lon, lat));
+    }
+
+    let units = units_per_degree(precision);
+    let lon = (lon + 180.0).rem_euclid(360.0);
+
+    // Position in units from the south-west corner, 90°N falls into the last row
+    let x = ((lon * units as f64).floor() as i64).min(360 * units - 1);
+    let y = (((lat + 90.0) * units as f64).floor() as i64).min(180 * units - 1);
+
+    let (lon_degrees, lat_degrees) = (x / units, y / units);
+
+    let mut georef = String::new();
+    georef.push(letter(LON_LETTERS, lon_degrees / 15));
+    georef.push(letter(LAT_LETTERS, lat_degrees / 15));
+
+    if precision >= 0 {
+        georef.push(letter(DEGREE_LETTERS, lon_degrees % 15));
+        georef.push(letter(DEGREE_LETTERS, lat_degrees % 15));
+    }
+
+    if precision >= 1 {
+        let width = precision as usize + 1;
+        georef.push_str(&format!("{:0width$}{:0width$}", x % units, y % units, width = width));
+    }
+
+    Ok(georef)
+}
+
+/// Parses a GEOREF and returns its bounding box (min_lon, min_lat, max_lon, max_lat) in degrees
+pub fn georef_to_bbox(georef: &str)
+                      -> Result<(f64, f64, f64, f64), GeorefError>
+{
+    let invalid = || GeorefError::InvalidGeoref(georef.to_string());
+    let trimmed = georef.trim().to_ascii_uppercase();
+
+    if !trimmed.is_ascii() || trimmed.len() < 2 {
+        return Err(invalid());
+    }
+
+    let mut chars = trimmed.chars();
+    let lon_quad = chars.next().and_then(|c| LON_LETTERS.find(c)).ok_or_else(invalid)? as i64;
+    let lat_quad = chars.next().and_then(|c| LAT_LETTERS.find(c)).ok_or_else(invalid)? as i64;
+
+    if trimmed.len() == 2 {
+        let (min_lon, min_lat) = ((lon_quad * 15 - 180) as f64, (lat_quad * 15 - 90) as f64);
+        return Ok((min_lon, min_lat, min_lon + 15.0, min_lat + 15.0));
+    }
+
+    if trimmed.len() < 4 {
+        return Err(invalid());
+    }
+
+    let lon_degree = chars.next().and_then(|c| DEGREE_LETTERS.find(c)).ok_or_else(invalid)? as i64;
+    let lat_degree = chars.next().and_then(|c| DEGREE_LETTERS.find(c)).ok_or_else(invalid)? as i64;
+
+    let digits = &trimmed[4..];
+    if digits.len() % 2 == 1 || digits.len() == 2 || digits.len() > 2 * (MAX_PRECISION as usize + 1) ||
+       !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid());
+    }
+
+    let precision = if digits.is_empty() { 0 } else { (digits.len() / 2 - 1) as i8 };
+    let units = units_per_degree(precision);
+
+    let (lon_units, lat_units) = if digits.is_empty() {
+        (0, 0)
+    } else {
+        let (lon_digits, lat_digits) = digits.split_at(digits.len() / 2);
+        (lon_digits.parse::<i64>().map_err(|_| invalid())?, lat_digits.parse::<i64>().map_err(|_| invalid())?)
+    };
+
+    // Minutes must be less than 60
+    if lon_units >= units || lat_units >= units {
+        return Err(invalid());
+    }
+
+    let x = (lon_quad * 15 + lon_degree) * units + lon_units;
+    let y = (lat_quad * 15 + lat_degree) * units + lat_units;
+    let units = units as f64;
+
+    let min_lon = x as f64 / units - 180.0;
+    let min_lat = y as f64 / units - 90.0;
+
+    Ok((min_lon, min_lat, min_lon + 1.0 / units, min_lat + 1.0 / units))
+}
+
+/// Parses a GEOREF and returns its outline as a closed,
+/// counter-clockwise ring of lon / lat coordinates (in degrees)
+pub fn georef_to_polygon(georef: &str)
+                         -> Result<Vec<(f64, f64)>, GeorefError>
+{
+    georef_to_bbox(georef).map(bbox_to_polygon)
+}
+
+/// Parses a GEOREF and returns its center (lon / lat, in degrees)
+pub fn georef_to_lonlat(georef: &str)
+                        -> Result<(f64, f64), GeorefError>
+{
+    let (min_lon, min_lat, max_lon, max_lat) = georef_to_bbox(georef)?;
+    Ok(((min_lon + max_lon) / 2.0, (min_lat + max_lat) / 2.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_bbox_eq(actual: (f64, f64, f64, f64), expected: (f64, f64, f64, f64)) {
+        let (a, e) = ([actual.0, actual.1, actual.2, actual.3], [expected.0, expected.1, expected.2, expected.3]);
+        for (a, e) in a.iter().zip(e.iter()) {
+            assert!((a - e).abs() < 1e-9, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn reference_georefs() {
+        // GeographicLib example
+        let (lon, lat) = (10.40744, 57.64911);
+        let expected = ["NK", "NKLN", "NKLN2438", "NKLN244389", "NKLN24443894", "NKLN2444638946"];
+        for (precision, expected) in (-1..).zip(expected.iter()) {
+            assert_eq!(lonlat_to_georef(lon, lat, precision), Ok(expected.to_string()));
+        }
+    }
+
+    #[test]
+    fn edges_of_the_world() {
+        assert_eq!(lonlat_to_georef(-180.0, -90.0, 2), Ok("AAAA000000".to_string()));
+        // 180° wraps around to 180°W, 90°N falls into the northernmost row
+        assert_eq!(lonlat_to_georef(180.0, 90.0, 1), Ok("AMAQ0059".to_string()));
+    }
+
+    #[test]
+    fn bounding_boxes() {
+        assert_bbox_eq(georef_to_bbox("NK").unwrap(), (0.0, 45.0, 15.0, 60.0));
+        assert_bbox_eq(georef_to_bbox("NKLN").unwrap(), (10.0, 57.0, 11.0, 58.0));
+        assert_bbox_eq(georef_to_bbox("NKLN2438").unwrap(), (10.4, 57.0 + 38.0 / 60.0, 10.0 + 25.0 / 60.0, 57.0 + 39.0 / 60.0));
+        assert_bbox_eq(georef_to_bbox("nkln2438").unwrap(), georef_to_bbox("NKLN2438").unwrap());
+
+        let (lon, lat) = georef_to_lonlat("NKLN2444638946").unwrap();
+        assert!((lon - 10.40744).abs() < 1e-5 && (lat - 57.64911).abs() < 1e-5);
+
+        let polygon = georef_to_polygon("NKLN").unwrap();
+        assert_eq!(polygon.len(), 5);
+        assert_eq!(polygon.first(), polygon.last());
+    }
+
+    #[test]
+    fn round_trip() {
+        for &(lon, lat) in &[(-84.5, 34.05), (13.41, 52.52), (151.2093, -33.8688), (-0.0001, -0.0001)] {
+            for precision in -1..=MAX_PRECISION {
+                let georef = lonlat_to_georef(lon, lat, precision).unwrap();
+                let (min_lon, min_lat, max_lon, max_lat) = georef_to_bbox(&georef).unwrap();
+                assert!(min_lon <= lon + 1e-9 && lon < max_lon + 1e-9 && min_lat <= lat + 1e-9 && lat < max_lat + 1e-9,
+                        "{} {} {}", georef, lon, lat);
+            }
+        }
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(lonlat_to_georef(0.0, 0.0, -2), Err(GeorefError::InvalidPrecision(-2)));
+        assert_eq!(lonlat_to_georef(0.0, 0.0, MAX_PRECISION + 1), Err(GeorefError::InvalidPrecision(MAX_PRECISION + 1)));
+        assert!(lonlat_to_georef(0.0, -90.5, 0).is_err());
+
+        for georef in &["", "N", "NKL", "IK", "NN", "NKLR", "NKLN24", "NKLN243", "NKLN6000", "NKLN00060", "NKLN24x8"] {
+            assert_eq!(georef_to_bbox(georef), Err(GeorefError::InvalidGeoref(georef.to_string())), "{}", georef);
+        }
+    }
+}
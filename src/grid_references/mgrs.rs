@@ -0,0 +1,415 @@
+//! Military Grid Reference System (MGRS) and U.S. National Grid (USNG)
+//!
+//! Implementation based on:
+//!
+//! NGA.SIG.0012_2.0.0_UTMUPS, 2014, Universal Grids and Grid Reference Systems,
+//! National Geospatial-Intelligence Agency, Chapter 11
+//!
+//! An MGRS reference consists of the UTM zone and latitude band ("33U"),
+//! the 100 km square identifier ("XP") and an even number of digits for the
+//! easting and northing within the square ("0410053410" = 1 m precision).
+//! North of 84°N and south of 80°S, the polar UPS grid is used instead,
+//! with the bands A / B (south) and Y / Z (north) and no zone number.
+//!
+//! References are truncated (not rounded) to the requested precision,
+//! decoding a reference returns the center of the referenced square.
+//!
+//! USNG uses the same grid, but writes the components separated by spaces
+//! ("18S UJ 23487 06483").
+
+use prelude::*;
+use coordinate_systems::utm::UtmZoneError;
+use coordinate_systems::utm::utils::{LATITUDE_BANDS, lonlat_to_utm_xy, utm_xy_to_lonlat};
+use coordinate_systems::ups::utils::{lonlat_to_ups_xy, ups_xy_to_lonlat};
+use std::error::Error;
+use std::fmt;
+
+/// Column letters of the 100 km squares, the set depends on `(zone - 1) % 3`
+const UTM_COLUMN_LETTERS: [&str; 3] = ["ABCDEFGH", "JKLMNPQR", "STUVWXYZ"];
+
+/// Row letters of the 100 km squares, repeating every 2000 km
+const UTM_ROW_LETTERS: &str = "ABCDEFGHJKLMNPQRSTUV";
+
+/// Minimum northing of each latitude band (in the same order as `LATITUDE_BANDS`),
+/// used to resolve the 2000 km ambiguity of the row letters
+const BAND_MINIMUM_NORTHINGS: [f64; 20] = [
+    1100000.0, 2000000.0, 2800000.0, 3700000.0, 4600000.0,
+    5500000.0, 6400000.0, 7300000.0, 8200000.0, 9100000.0,
+    0.0, 800000.0, 1700000.0, 2600000.0, 3500000.0,
+    4400000.0, 5300000.0, 6200000.0, 7000000.0, 7900000.0,
+];
+
+/// Column letters and index of the first column (in 100 km) of the UPS bands A, B, Y and Z
+const UPS_COLUMNS: [(char, &str, u32); 4] = [
+    ('A', "JKLPQRSTUXYZ", 8),
+    ('B', "ABCFGHJKLPQR", 20),
+    ('Y', "RSTUXYZ", 13),
+    ('Z', "ABCFGHJ", 20),
+];
+
+/// Row letters and index of the first row (in 100 km) of the southern and northern UPS area
+const UPS_ROWS_SOUTH: (&str, u32) = ("ABCDEFGHJKLMNPQRSTUVWXYZ", 8);
+const UPS_ROWS_NORTH: (&str, u32) = ("ABCDEFGHJKLMNP", 13);
+
+/// Lettering scheme of the 100 km square identifiers
+///
+/// Which scheme is used depends on the ellipsoid of the underlying datum.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Lettering {
+    /// "New" scheme (MGRS-New, AA), used for WGS84, GRS80 and most modern ellipsoids
+    AA,
+    /// "Old" scheme (MGRS-Old, AL), used for Clarke 1866, Clarke 1880 and Bessel 1841
+    AL,
+}
+
+impl Lettering {
+    /// Returns the lettering scheme that is used for the given ellipsoid
+    pub fn for_ellipsoid(ellipsoid: &Ellipsoid) -> Self {
+        if *ellipsoid == CLARKE_1866_ELLIPSOID ||
+           *ellipsoid == CLARKE_1880_ELLIPSOID ||
+           *ellipsoid == BESSEL_1841_ELLIPSOID {
+            Lettering::AL
+        } else {
+            Lettering::AA
+        }
+    }
+
+    /// Offset of the row letters, odd zones start at A (AA) or L (AL),
+    /// even zones are shifted by another 5 letters
+    fn row_offset(self, zone: u8) -> usize {
+        let offset = match self {
+            Lettering::AA => 0,
+            Lettering::AL => 10,
+        };
+        if zone % 2 == 0 { offset + 5 } else { offset }
+    }
+}
+
+/// Error that can occur when creating or parsing an MGRS / USNG reference
+#[derive(Debug, Clone, PartialEq)]
+pub enum MgrsError {
+    /// Longitude or latitude isn't a valid number
+    InvalidCoordinate(f64, f64),
+    /// Precision must be between 0 (100 km) and 5 (1 m)
+    InvalidPrecision(u8),
+    /// The reference is malformed
+    InvalidFormat(String),
+    /// The zone number isn't between 1 and 60, or the zone / band combination doesn't exist
+    InvalidZone(String),
+    /// The latitude band letter isn't valid
+    InvalidBand(char),
+    /// The 100 km square identifier doesn't exist in the given zone / band
+    InvalidSquare(String),
+}
+
+impl fmt::Display for MgrsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::MgrsError::*;
+        match *self {
+            InvalidCoordinate(lon, lat) => write!(f, "invalid coordinate: ({}, {})", lon, lat),
+            InvalidPrecision(precision) => write!(f, "invalid precision: {} (must be 0 to 5)", precision),
+            InvalidFormat(ref s) => write!(f, "invalid grid reference: \"{}\"", s),
+            InvalidZone(ref s) => write!(f, "invalid grid zone designator: \"{}\"", s),
+            InvalidBand(band) => write!(f, "invalid latitude band: '{}'", band),
+            InvalidSquare(ref s) => write!(f, "invalid 100 km square identifier: \"{}\"", s),
+        }
+    }
+}
+
+impl Error for MgrsError { }
+
+/// Components of a grid reference, before formatting
+struct GridReference {
+    /// Zone number, 0 for UPS
+    zone: u8,
+    band: char,
+    square: (char, char),
+    easting: f64,
+    northing: f64,
+}
+
+impl GridReference {
+    fn format(&self, precision: u8, separator: &str) -> String {
+        let divisor = 10_u32.pow(5 - u32::from(precision)) as f64;
+        let easting = (self.easting.rem_euclid(100000.0) / divisor).floor() as u32;
+        let northing = (self.northing.rem_euclid(100000.0) / divisor).floor() as u32;
+
+        let mut gzd = String::new();
+        if self.zone != 0 {
+            gzd.push_str(&format!("{:02}", self.zone));
+        }
+        gzd.push(self.band);
+
+        let mut result = format!("{}{}{}{}", gzd, separator, self.square.0, self.square.1);
+        if precision > 0 {
+            let width = usize::from(precision);
+            result.push_str(&format!("{}{:0width$}{}{:0width$}", separator, easting, separator, northing, width = width));
+        }
+        result
+    }
+}
+
+/// Letter at the given index of a letter set
+#[inline]
+fn letter(set: &str, index: usize) -> char {
+    set.as_bytes()[index] as char
+}
+
+fn grid_reference(lon: f64, lat: f64, ellipsoid: &Ellipsoid, lettering: Lettering)
+                  -> Result<GridReference, MgrsError>
+{
+    if !lon.is_finite() || !lat.is_finite() || !(-90.0..=90.0).contains(&lat) {
+        return Err(MgrsError::InvalidCoordinate(lon, lat));
+    }
+
+    // Normalize to [-180, 180)
+    let lon = (lon + 180.0).rem_euclid(360.0) - 180.0;
+
+    if (-80.0..84.0).contains(&lat) {
+        let utm_zone = UtmZone::from_lon_lat(lon, lat).ok_or(MgrsError::InvalidCoordinate(lon, lat))?;
+        let zone = utm_zone.number();
+        let (easting, northing) = lonlat_to_utm_xy(lon, lat, ellipsoid, zone, utm_zone.hemisphere());
+
+        let columns = UTM_COLUMN_LETTERS[usize::from(zone - 1) % 3];
+        let column = ((easting / 100000.0).floor() as usize).clamp(1, 8) - 1;
+        let row = ((northing / 100000.0).floor() as usize + lettering.row_offset(zone)) % 20;
+
+        Ok(GridReference {
+            zone,
+            band: utm_zone.band(),
+            square: (letter(columns, column), letter(UTM_ROW_LETTERS, row)),
+            easting,
+            northing,
+        })
+    } else {
+        let hemisphere = if lat < 0.0 { Hemisphere::South } else { Hemisphere::North };
+        let (easting, northing) = lonlat_to_ups_xy(lon, lat, ellipsoid, hemisphere);
+
+        let band = match (hemisphere, lon < 0.0) {
+            (Hemisphere::South, true) => 'A',
+            (Hemisphere::South, false) => 'B',
+            (Hemisphere::North, true) => 'Y',
+            (Hemisphere::North, false) => 'Z',
+        };
+
+        let &(_, columns, first_column) = UPS_COLUMNS.iter().find(|&&(b, _, _)| b == band).unwrap();
+        let (rows, first_row) = match hemisphere {
+            Hemisphere::South => UPS_ROWS_SOUTH,
+            Hemisphere::North => UPS_ROWS_NORTH,
+        };
+
+        // Points on the boundary of the UPS area can be just outside the lettered squares
+        let column = ((easting / 100000.0).floor() as i64 - i64::from(first_column)).clamp(0, columns.len() as i64 - 1);
+        let row = ((northing / 100000.0).floor() as i64 - i64::from(first_row)).clamp(0, rows.len() as i64 - 1);
+
+        Ok(GridReference {
+            zone: 0,
+            band,
+            square: (letter(columns, column as usize), letter(rows, row as usize)),
+            easting,
+            northing,
+        })
+    }
+}
+
+/// Converts a lon / lat coordinate (in degrees) to an MGRS reference such as "33UXP0410053410"
+///
+/// `precision` is the number of digits per easting / northing: 0 (100 km),
+/// 1 (10 km), 2 (1 km), 3 (100 m), 4 (10 m) or 5 (1 m)
+pub fn lonlat_to_mgrs(lon: f64, lat: f64, ellipsoid: &Ellipsoid, precision: u8, lettering: Lettering)
+                      -> Result<String, MgrsError>
+{
+    if precision > 5 {
+        return Err(MgrsError::InvalidPrecision(precision));
+    }
+
+    Ok(grid_reference(lon, lat, ellipsoid, lettering)?.format(precision, ""))
+}
+
+/// Converts a lon / lat coordinate (in degrees) to a USNG reference such as "18S UJ 23487 06483"
+///
+/// See `lonlat_to_mgrs` for the meaning of `precision`
+pub fn lonlat_to_usng(lon: f64, lat: f64, ellipsoid: &Ellipsoid, precision: u8, lettering: Lettering)
+                      -> Result<String, MgrsError>
+{
+    if precision > 5 {
+        return Err(MgrsError::InvalidPrecision(precision));
+    }
+
+    Ok(grid_reference(lon, lat, ellipsoid, lettering)?.format(precision, " "))
+}
+
+/// Converts an MGRS reference to the lon / lat coordinate (in degrees)
+/// of the center of the referenced square
+///
+/// Whitespace and lowercase letters are accepted.
+pub fn mgrs_to_lonlat(reference: &str, ellipsoid: &Ellipsoid, lettering: Lettering)
+                      -> Result<(f64, f64), MgrsError>
+{
+    let compact: String = reference.chars().filter(|c| !c.is_whitespace()).collect::<String>().to_uppercase();
+    let invalid_format = || MgrsError::InvalidFormat(reference.to_string());
+
+    if !compact.is_ascii() {
+        return Err(invalid_format());
+    }
+
+    // Grid zone designator: zone number (one or two digits, none for UPS) and band letter
+    let zone_len = compact.bytes().take_while(|b| b.is_ascii_digit()).count();
+    if zone_len > 2 || compact.len() < zone_len + 3 {
+        return Err(invalid_format());
+    }
+
+    let (gzd, rest) = compact.split_at(zone_len + 1);
+    let mut letters = rest.chars();
+    let band = gzd.chars().last().ok_or_else(invalid_format)?;
+    let square = (letters.next().ok_or_else(invalid_format)?, letters.next().ok_or_else(invalid_format)?);
+    let digits = letters.as_str();
+
+    if digits.len() % 2 != 0 || digits.len() > 10 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid_format());
+    }
+
+    // Offset within the 100 km square, pointing to the center of the referenced cell
+    let precision = digits.len() / 2;
+    let cell_size = 10_u32.pow(5 - precision as u32) as f64;
+    let (easting_digits, northing_digits) = digits.split_at(precision);
+    let offset = |digits: &str| digits.parse::<f64>().unwrap_or(0.0) * cell_size + cell_size / 2.0;
+    let easting_offset = offset(easting_digits);
+    let northing_offset = offset(northing_digits);
+
+    let invalid_square = || MgrsError::InvalidSquare(format!("{}{}", square.0, square.1));
+
+    if zone_len == 0 {
+        // UPS
+        let &(_, columns, first_column) = UPS_COLUMNS.iter().find(|&&(b, _, _)| b == band)
+            .ok_or(MgrsError::InvalidBand(band))?;
+        let (hemisphere, (rows, first_row)) = if band == 'A' || band == 'B' {
+            (Hemisphere::South, UPS_ROWS_SOUTH)
+        } else {
+            (Hemisphere::North, UPS_ROWS_NORTH)
+        };
+
+        let column = columns.find(square.0).ok_or_else(invalid_square)?;
+        let row = rows.find(square.1).ok_or_else(invalid_square)?;
+
+        let easting = (column as f64 + f64::from(first_column)) * 100000.0 + easting_offset;
+        let northing = (row as f64 + f64::from(first_row)) * 100000.0 + northing_offset;
+
+        return Ok(ups_xy_to_lonlat(easting, northing, ellipsoid, hemisphere));
+    }
+
+    let utm_zone: UtmZone = gzd.parse().map_err(|e| match e {
+        UtmZoneError::InvalidBand(band) => MgrsError::InvalidBand(band),
+        _ => MgrsError::InvalidZone(gzd.to_string()),
+    })?;
+    let zone = utm_zone.number();
+    let band_index = LATITUDE_BANDS.find(band).unwrap_or(0);
+
+    let columns = UTM_COLUMN_LETTERS[usize::from(zone - 1) % 3];
+    let column = columns.find(square.0).ok_or_else(invalid_square)?;
+    let row = UTM_ROW_LETTERS.find(square.1).ok_or_else(invalid_square)?;
+
+    let easting = (column + 1) as f64 * 100000.0 + easting_offset;
+
+    // The row letters only give the northing modulo 2000 km
+    let row = (row + 20 - lettering.row_offset(zone)) % 20;
+    let mut northing = row as f64 * 100000.0;
+    let minimum_northing = BAND_MINIMUM_NORTHINGS[band_index];
+    while northing < minimum_northing {
+        northing += 2000000.0;
+    }
+    northing += northing_offset;
+
+    Ok(utm_xy_to_lonlat(easting, northing, ellipsoid, zone, utm_zone.hemisphere()))
+}
+
+/// Converts a USNG reference to the lon / lat coordinate (in degrees)
+/// of the center of the referenced square
+///
+/// USNG references are MGRS references with spaces between the components,
+/// so this is the same as `mgrs_to_lonlat`
+pub fn usng_to_lonlat(reference: &str, ellipsoid: &Ellipsoid, lettering: Lettering)
+                      -> Result<(f64, f64), MgrsError>
+{
+    mgrs_to_lonlat(reference, ellipsoid, lettering)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // (lon, lat, AA reference, AL reference), computed with the Krüger series
+    // of the transverse Mercator projection and the lettering rules of NGA.SIG.0012
+    const REFERENCES: [(f64, f64, &str, &str); 13] = [
+        (-77.035258, 38.889469, "18SUJ2348106479", "18SUU2348106479"),
+        (13.41, 52.52, "33UUU9211820064", "33UUJ9211820064"),
+        (151.2093, -33.8688, "56HLH3436850948", "56HLT3436850948"),
+        (-0.1276, 51.5072, "30UXC9933010142", "30UXN9933010142"),
+        (5.0, 60.0, "32VKM7697958157", "32VKB7697958157"),
+        (10.0, 78.0, "33XUG8408563320", "33XUS8408563320"),
+        (-70.0, -79.5, "19CDM7965674046", "19CDB7965674046"),
+        (2.0, 0.5, "31NCA8872355273", "31NCL8872355273"),
+        (170.0, -5.0, "59MLQ8914047251", "59MLE8914047251"),
+        (0.0, 90.0, "ZAH0000000000", "ZAH0000000000"),
+        (0.0, -90.0, "BAN0000000000", "BAN0000000000"),
+        (45.0, 86.0, "ZFD1414585854", "ZFD1414585854"),
+        (-135.0, -85.0, "AUJ0723207232", "AUJ0723207232"),
+    ];
+
+    #[test]
+    fn encode() {
+        for &(lon, lat, aa, al) in &REFERENCES {
+            assert_eq!(lonlat_to_mgrs(lon, lat, &WGS_1984_ELLIPSOID, 5, Lettering::AA).unwrap(), aa);
+            assert_eq!(lonlat_to_mgrs(lon, lat, &WGS_1984_ELLIPSOID, 5, Lettering::AL).unwrap(), al);
+        }
+
+        assert_eq!(lonlat_to_mgrs(-77.035258, 38.889469, &WGS_1984_ELLIPSOID, 0, Lettering::AA).unwrap(), "18SUJ");
+        assert_eq!(lonlat_to_mgrs(-77.035258, 38.889469, &WGS_1984_ELLIPSOID, 2, Lettering::AA).unwrap(), "18SUJ2306");
+        assert_eq!(lonlat_to_usng(-77.035258, 38.889469, &WGS_1984_ELLIPSOID, 5, Lettering::AA).unwrap(), "18S UJ 23481 06479");
+        assert_eq!(lonlat_to_mgrs(-77.035258 + 360.0, 38.889469, &WGS_1984_ELLIPSOID, 5, Lettering::AA).unwrap(), "18SUJ2348106479");
+    }
+
+    #[test]
+    fn decode() {
+        for &(lon, lat, aa, al) in &REFERENCES {
+            for &(reference, lettering) in &[(aa, Lettering::AA), (al, Lettering::AL)] {
+                let (plon, plat) = mgrs_to_lonlat(reference, &WGS_1984_ELLIPSOID, lettering).unwrap();
+                assert_eq!(lonlat_to_mgrs(plon, plat, &WGS_1984_ELLIPSOID, 5, lettering).unwrap(), reference);
+                assert!((plat - lat).abs() < 1e-4, "{}: ({}, {})", reference, plon, plat);
+                if lat.abs() < 89.0 {
+                    assert!((plon - lon).abs() < 1e-4, "{}: ({}, {})", reference, plon, plat);
+                }
+            }
+        }
+
+        // the center of the referenced square, in any case and with spaces
+        let (lon, lat) = usng_to_lonlat("18s uj 23 06", &WGS_1984_ELLIPSOID, Lettering::AA).unwrap();
+        let (x, y) = lonlat_to_utm_xy(lon, lat, &WGS_1984_ELLIPSOID, 18, Hemisphere::North);
+        assert!((x - 323_500.0).abs() < 1e-6 && (y - 4_306_500.0).abs() < 1e-6, "({}, {})", x, y);
+    }
+
+    #[test]
+    fn lettering() {
+        assert_eq!(Lettering::for_ellipsoid(&WGS_1984_ELLIPSOID), Lettering::AA);
+        assert_eq!(Lettering::for_ellipsoid(&GRS_1980_ELLIPSOID), Lettering::AA);
+        assert_eq!(Lettering::for_ellipsoid(&CLARKE_1866_ELLIPSOID), Lettering::AL);
+        assert_eq!(Lettering::for_ellipsoid(&BESSEL_1841_ELLIPSOID), Lettering::AL);
+    }
+
+    #[test]
+    fn errors() {
+        let wgs84 = &WGS_1984_ELLIPSOID;
+        assert_eq!(lonlat_to_mgrs(0.0, 0.0, wgs84, 6, Lettering::AA), Err(MgrsError::InvalidPrecision(6)));
+        assert_eq!(lonlat_to_mgrs(0.0, 91.0, wgs84, 5, Lettering::AA), Err(MgrsError::InvalidCoordinate(0.0, 91.0)));
+        assert!(lonlat_to_mgrs(f64::NAN, 0.0, wgs84, 5, Lettering::AA).is_err());
+
+        assert_eq!(mgrs_to_lonlat("18SUJ234", wgs84, Lettering::AA), Err(MgrsError::InvalidFormat("18SUJ234".to_string())));
+        assert_eq!(mgrs_to_lonlat("18S", wgs84, Lettering::AA), Err(MgrsError::InvalidFormat("18S".to_string())));
+        assert_eq!(mgrs_to_lonlat("61SUJ", wgs84, Lettering::AA), Err(MgrsError::InvalidZone("61S".to_string())));
+        assert_eq!(mgrs_to_lonlat("18IUJ", wgs84, Lettering::AA), Err(MgrsError::InvalidBand('I')));
+        assert_eq!(mgrs_to_lonlat("18SAJ", wgs84, Lettering::AA), Err(MgrsError::InvalidSquare("AJ".to_string())));
+        assert_eq!(mgrs_to_lonlat("18SUW", wgs84, Lettering::AA), Err(MgrsError::InvalidSquare("UW".to_string())));
+        assert_eq!(mgrs_to_lonlat("ZXH", wgs84, Lettering::AA), Err(MgrsError::InvalidSquare("XH".to_string())));
+        assert_eq!(mgrs_to_lonlat("CAH", wgs84, Lettering::AA), Err(MgrsError::InvalidBand('C')));
+    }
+}
@@ -0,0 +1,17 @@
+pub(crate) mod mgrs;
+pub(crate) mod gars;
+pub(crate) mod georef;
+pub(crate) mod os_grid;
+
+/// Converts a bounding box (min_lon, min_lat, max_lon, max_lat) into a closed,
+/// counter-clockwise ring of lon / lat coordinates, starting in the south-west corner
+pub(crate) fn bbox_to_polygon(bbox: (f64, f64, f64, f64)) -> Vec<(f64, f64)> {
+    let (min_lon, min_lat, max_lon, max_lat) = bbox;
+    vec![
+        (min_lon, min_lat),
+        (max_lon, min_lat),
+        (max_lon, max_lat),
+        (min_lon, max_lat),
+        (min_lon, min_lat),
+    ]
+}
@@ -0,0 +1,379 @@
+//! Ordnance Survey grid references: British National Grid and Irish Grid
+//!
+//! Implementation based on:
+//!
+//! Ordnance Survey, 2020, A Guide to Coordinate Systems in Great Britain,
+//! Chapter 7 (The National Grid reference system)
+//!
+//! A British National Grid reference such as "TQ 30080 80887" consists of two
+//! letters for the 100 km square and an even number of digits for the easting
+//! and northing within the square ("30080 80887" = 1 m precision). The first
+//! letter selects a 500 km square and the second letter a 100 km square within
+//! it, both from a 5 x 5 grid of the letters A to Z without I, starting in the
+//! north-west corner. The false origin of the grid is in the south-west corner
+//! of square "SV".
+//!
+//! Irish Grid references such as "O 15904 34671" use a single letter from the
+//! same 5 x 5 grid for the 100 km square, "V" is the south-west square.
+//!
+//! The lon / lat coordinates are OSGB36 (on the `AIRY_1830_ELLIPSOID`) for the
+//! British National Grid and TM65 (on the `MODIFIED_AIRY_ELLIPSOID`) for the
+//! Irish Grid. There is no datum transformation to or from WGS84 / ETRS89,
+//! which differ by up to about 120 m.
+//!
+//! References are truncated (not rounded) to the requested precision,
+//! decoding a reference returns the center of the referenced square.
+
+use prelude::*;
+use coordinate_systems::tmerc::{BRITISH_NATIONAL_GRID, IRISH_GRID};
+use coordinate_systems::tmerc::utils::{lonlat_to_tmerc_xy, tmerc_xy_to_lonlat};
+use std::error::Error;
+use std::fmt;
+
+/// Letters of the 5 x 5 square grids, row by row from the north-west corner
+const SQUARE_LETTERS: &str = "ABCDEFGHJKLMNOPQRSTUVWXYZ";
+
+/// Extent of the British National Grid in meters (min_x, min_y, max_x, max_y)
+pub const BNG_BOUNDS: (f64, f64, f64, f64) = (0.0, 0.0, 700_000.0, 1_300_000.0);
+
+/// Extent of the Irish Grid in meters (min_x, min_y, max_x, max_y)
+pub const IRISH_GRID_BOUNDS: (f64, f64, f64, f64) = (0.0, 0.0, 500_000.0, 500_000.0);
+
+/// Error that can occur when creating or parsing a grid reference
+#[derive(Debug, Clone, PartialEq)]
+pub enum OsGridError {
+    /// Longitude / latitude or easting / northing isn't a valid number
+    InvalidCoordinate(f64, f64),
+    /// Precision must be between 0 (100 km) and 5 (1 m)
+    InvalidPrecision(u8),
+    /// The easting / northing is outside of the lettered squares of the grid
+    OutsideGrid(f64, f64),
+    /// The reference is malformed
+    InvalidFormat(String),
+    /// The 100 km square identifier isn't part of the grid
+    InvalidSquare(String),
+}
+
+impl fmt::Display for OsGridError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::OsGridError::*;
+        match *self {
+            InvalidCoordinate(x, y) => write!(f, "invalid coordinate: ({}, {})", x, y),
+            InvalidPrecision(precision) => write!(f, "invalid precision: {} (must be 0 to 5)", precision),
+            OutsideGrid(x, y) => write!(f, "coordinate outside of the grid: ({}, {})", x, y),
+            InvalidFormat(ref s) => write!(f, "invalid grid reference: \"{}\"", s),
+            InvalidSquare(ref s) => write!(f, "invalid 100 km square identifier: \"{}\"", s),
+        }
+    }
+}
+
+impl Error for OsGridError { }
+
+/// Letter at the given column / row of a 5 x 5 square grid,
+/// counted from the south-west corner
+#[inline]
+fn square_letter(column: i64, row: i64) -> char {
+    SQUARE_LETTERS.as_bytes()[((4 - row) * 5 + column) as usize] as char
+}
+
+/// Column / row of a letter in a 5 x 5 square grid, counted from the south-west corner
+#[inline]
+fn square_position(letter: char) -> Option<(i64, i64)> {
+    SQUARE_LETTERS.find(letter).map(|index| ((index % 5) as i64, 4 - (index / 5) as i64))
+}
+
+/// Formats the easting / northing as a reference: the square letters,
+/// followed by the digits of the easting and northing within the square
+fn format_reference(easting: f64, northing: f64, precision: u8, bounds: (f64, f64, f64, f64), square: &dyn Fn(i64, i64) -> String)
+                    -> Result<String, OsGridError>
+{
+    if precision > 5 {
+        return Err(OsGridError::InvalidPrecision(precision));
+    }
+
+    if !easting.is_finite() || !northing.is_finite() {
+        return Err(OsGridError::InvalidCoordinate(easting, northing));
+    }
+
+    let (min_x, min_y, max_x, max_y) = bounds;
+    if !(min_x..max_x).contains(&easting) || !(min_y..max_y).contains(&northing) {
+        return Err(OsGridError::OutsideGrid(easting, northing));
+    }
+
+    let column = (easting / 100000.0).floor() as i64;
+    let row = (northing / 100000.0).floor() as i64;
+    let mut result = square(column, row);
+
+    if precision > 0 {
+        let divisor = 10_u32.pow(5 - u32::from(precision)) as f64;
+        let easting = (easting.rem_euclid(100000.0) / divisor).floor() as u32;
+        let northing = (northing.rem_euclid(100000.0) / divisor).floor() as u32;
+        let width = usize::from(precision);
+        result.push_str(&format!(" {:0width$} {:0width$}", easting, northing, width = width));
+    }
+
+    Ok(result)
+}
+
+/// Parses a reference into the bounds of the referenced square in meters
+/// (min_x, min_y, max_x, max_y), given the number of square letters and a function
+/// that returns the south-west corner of the 100 km square for these letters
+fn parse_reference(reference: &str, letter_count: usize, bounds: (f64, f64, f64, f64), square: &dyn Fn(&str) -> Option<(f64, f64)>)
+                   -> Result<(f64, f64, f64, f64), OsGridError>
+{
+    let invalid_format = || OsGridError::InvalidFormat(reference.to_string());
+
+    let upper = reference.trim().to_uppercase();
+    if !upper.is_ascii() || upper.len() < letter_count {
+        return Err(invalid_format());
+    }
+
+    let (letters, digits) = upper.split_at(letter_count);
+    if !letters.bytes().all(|b| b.is_ascii_uppercase()) {
+        return Err(invalid_format());
+    }
+
+    // The digits are either one block or an easting and northing block of the same length
+    let blocks: Vec<&str> = digits.split_whitespace().collect();
+    let (easting_digits, northing_digits) = match blocks.len() {
+        0 => ("", ""),
+        1 if blocks[0].len() % 2 == 0 => blocks[0].split_at(blocks[0].len() / 2),
+        2 if blocks[0].len() == blocks[1].len() => (blocks[0], blocks[1]),
+        _ => return Err(invalid_format()),
+    };
+
+    if easting_digits.len() > 5 || !easting_digits.bytes().chain(northing_digits.bytes()).all(|b| b.is_ascii_digit()) {
+        return Err(invalid_format());
+    }
+
+    let (square_x, square_y) = square(letters).ok_or_else(|| OsGridError::InvalidSquare(letters.to_string()))?;
+
+    let (min_x, min_y, max_x, max_y) = bounds;
+    if !(min_x..max_x).contains(&square_x) || !(min_y..max_y).contains(&square_y) {
+        return Err(OsGridError::InvalidSquare(letters.to_string()));
+    }
+
+    let cell_size = 10_u32.pow(5 - easting_digits.len() as u32) as f64;
+    let offset = |digits: &str| digits.parse::<f64>().unwrap_or(0.0) * cell_size;
+    let x = square_x + offset(easting_digits);
+    let y = square_y + offset(northing_digits);
+
+    Ok((x, y, x + cell_size, y + cell_size))
+}
+
+#[inline]
+fn bbox_center(bbox: (f64, f64, f64, f64)) -> (f64, f64) {
+    let (min_x, min_y, max_x, max_y) = bbox;
+    ((min_x + max_x) / 2.0, (min_y + max_y) / 2.0)
+}
+
+fn bng_square(column: i64, row: i64) -> String {
+    // The false origin is in the south-west corner of the 500 km square "S",
+    // which is in column 2 / row 1 of the 500 km grid
+    let first = square_letter(column / 5 + 2, row / 5 + 1);
+    let second = square_letter(column % 5, row % 5);
+    format!("{}{}", first, second)
+}
+
+fn bng_square_origin(letters: &str) -> Option<(f64, f64)> {
+    let mut chars = letters.chars();
+    let (first_column, first_row) = square_position(chars.next()?)?;
+    let (second_column, second_row) = square_position(chars.next()?)?;
+
+    let column = (first_column - 2) * 5 + second_column;
+    let row = (first_row - 1) * 5 + second_row;
+    Some((column as f64 * 100000.0, row as f64 * 100000.0))
+}
+
+fn irish_grid_square(column: i64, row: i64) -> String {
+    square_letter(column, row).to_string()
+}
+
+fn irish_grid_square_origin(letters: &str) -> Option<(f64, f64)> {
+    let (column, row) = square_position(letters.chars().next()?)?;
+    Some((column as f64 * 100000.0, row as f64 * 100000.0))
+}
+
+/// Converts a British National Grid easting / northing (in meters)
+/// to a grid reference such as "TQ 30080 80887"
+///
+/// `precision` is the number of digits per easting / northing: 0 (100 km),
+/// 1 (10 km), 2 (1 km), 3 (100 m), 4 (10 m) or 5 (1 m)
+pub fn xy_to_bng(easting: f64, northing: f64, precision: u8)
+                 -> Result<String, OsGridError>
+{
+    format_reference(easting, northing, precision, BNG_BOUNDS, &bng_square)
+}
+
+/// Converts a British National Grid reference to the bounds of the referenced
+/// square in meters (min_x, min_y, max_x, max_y)
+///
+/// Whitespace between the letters and the digits and lowercase letters are accepted,
+/// as well as the digits without a space between easting and northing ("TQ3008080887").
+pub fn bng_to_bounds(reference: &str)
+                     -> Result<(f64, f64, f64, f64), OsGridError>
+{
+    parse_reference(reference, 2, BNG_BOUNDS, &bng_square_origin)
+}
+
+/// Converts a British National Grid reference to the easting / northing
+/// (in meters) of the center of the referenced square
+pub fn bng_to_xy(reference: &str)
+                 -> Result<(f64, f64), OsGridError>
+{
+    bng_to_bounds(reference).map(bbox_center)
+}
+
+/// Converts an OSGB36 lon / lat coordinate (in degrees) to a British National Grid reference
+///
+/// See `xy_to_bng` for the meaning of `precision`
+pub fn lonlat_to_bng(lon: f64, lat: f64, precision: u8)
+                     -> Result<String, OsGridError>
+{
+    if !lon.is_finite() || !lat.is_finite() || !(-90.0..=90.0).contains(&lat) {
+        return Err(OsGridError::InvalidCoordinate(lon, lat));
+    }
+
+    let (easting, northing) = lonlat_to_tmerc_xy(lon, lat, &AIRY_1830_ELLIPSOID, &BRITISH_NATIONAL_GRID);
+    xy_to_bng(easting, northing, precision)
+}
+
+/// Converts a British National Grid reference to the OSGB36 lon / lat coordinate
+/// (in degrees) of the center of the referenced square
+pub fn bng_to_lonlat(reference: &str)
+                     -> Result<(f64, f64), OsGridError>
+{
+    let (easting, northing) = bng_to_xy(reference)?;
+    Ok(tmerc_xy_to_lonlat(easting, northing, &AIRY_1830_ELLIPSOID, &BRITISH_NATIONAL_GRID))
+}
+
+/// Converts an Irish Grid easting / northing (in meters)
+/// to a grid reference such as "O 15904 34671"
+///
+/// See `xy_to_bng` for the meaning of `precision`
+pub fn xy_to_irish_grid(easting: f64, northing: f64, precision: u8)
+                        -> Result<String, OsGridError>
+{
+    format_reference(easting, northing, precision, IRISH_GRID_BOUNDS, &irish_grid_square)
+}
+
+/// Converts an Irish Grid reference to the bounds of the referenced
+/// square in meters (min_x, min_y, max_x, max_y)
+///
+/// The same formats as in `bng_to_bounds` are accepted.
+pub fn irish_grid_to_bounds(reference: &str)
+                            -> Result<(f64, f64, f64, f64), OsGridError>
+{
+    parse_reference(reference, 1, IRISH_GRID_BOUNDS, &irish_grid_square_origin)
+}
+
+/// Converts an Irish Grid reference to the easting / northing
+/// (in meters) of the center of the referenced square
+pub fn irish_grid_to_xy(reference: &str)
+                        -> Result<(f64, f64), OsGridError>
+{
+    irish_grid_to_bounds(reference).map(bbox_center)
+}
+
+/// Converts a TM65 lon / lat coordinate (in degrees) to an Irish Grid reference
+///
+/// See `xy_to_bng` for the meaning of `precision`
+pub fn lonlat_to_irish_grid(lon: f64, lat: f64, precision: u8)
+                            -> Result<String, OsGridError>
+{
+    if !lon.is_finite() || !lat.is_finite() || !(-90.0..=90.0).contains(&lat) {
+        return Err(OsGridError::InvalidCoordinate(lon, lat));
+    }
+
+    let (easting, northing) = lonlat_to_tmerc_xy(lon, lat, &MODIFIED_AIRY_ELLIPSOID, &IRISH_GRID);
+    xy_to_irish_grid(easting, northing, precision)
+}
+
+/// Converts an Irish Grid reference to the TM65 lon / lat coordinate
+/// (in degrees) of the center of the referenced square
+pub fn irish_grid_to_lonlat(reference: &str)
+                            -> Result<(f64, f64), OsGridError>
+{
+    let (easting, northing) = irish_grid_to_xy(reference)?;
+    Ok(tmerc_xy_to_lonlat(easting, northing, &MODIFIED_AIRY_ELLIPSOID, &IRISH_GRID))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Worked example of "A Guide to Coordinate Systems in Great Britain" (OSGB36)
+    const GUIDE_LONLAT: (f64, f64) = (1.0 + 43.0 / 60.0 + 4.5177 / 3600.0, 52.0 + 39.0 / 60.0 + 27.2531 / 3600.0);
+    const GUIDE_XY: (f64, f64) = (651_409.903, 313_177.270);
+
+    #[test]
+    fn british_national_grid() {
+        assert_eq!(xy_to_bng(GUIDE_XY.0, GUIDE_XY.1, 5), Ok("TG 51409 13177".to_string()));
+        assert_eq!(lonlat_to_bng(GUIDE_LONLAT.0, GUIDE_LONLAT.1, 5), Ok("TG 51409 13177".to_string()));
+
+        let expected = ["TG", "TG 5 1", "TG 51 13", "TG 514 131", "TG 5140 1317", "TG 51409 13177"];
+        for (precision, expected) in (0..).zip(expected.iter()) {
+            assert_eq!(xy_to_bng(GUIDE_XY.0, GUIDE_XY.1, precision).as_ref().map(|s| s.as_str()), Ok(*expected));
+        }
+
+        assert_eq!(bng_to_bounds("TG 51409 13177"), Ok((651_409.0, 313_177.0, 651_410.0, 313_178.0)));
+        assert_eq!(bng_to_bounds("tg5140913177"), bng_to_bounds("TG 51409 13177"));
+        assert_eq!(bng_to_xy("TG 51409 13177"), Ok((651_409.5, 313_177.5)));
+        assert_eq!(bng_to_xy("TQ 30080 80887"), Ok((530_080.5, 180_887.5)));
+        assert_eq!(bng_to_bounds("TG 5140"), Ok((651_000.0, 340_000.0, 652_000.0, 341_000.0)));
+        assert_eq!(bng_to_bounds("TG"), Ok((600_000.0, 300_000.0, 700_000.0, 400_000.0)));
+
+        // corners of the grid
+        assert_eq!(xy_to_bng(0.0, 0.0, 0), Ok("SV".to_string()));
+        assert_eq!(xy_to_bng(699_999.0, 1_299_999.0, 0), Ok("JM".to_string()));
+        assert_eq!(bng_to_bounds("HP"), Ok((400_000.0, 1_200_000.0, 500_000.0, 1_300_000.0)));
+    }
+
+    #[test]
+    fn round_trip() {
+        let (x, y) = lonlat_to_tmerc_xy(GUIDE_LONLAT.0, GUIDE_LONLAT.1, &AIRY_1830_ELLIPSOID, &BRITISH_NATIONAL_GRID);
+        assert!((x - GUIDE_XY.0).abs() < 1e-3 && (y - GUIDE_XY.1).abs() < 1e-3, "{} {}", x, y);
+
+        let (lon, lat) = bng_to_lonlat("TG 51409 13177").unwrap();
+        let (x, y) = lonlat_to_tmerc_xy(lon, lat, &AIRY_1830_ELLIPSOID, &BRITISH_NATIONAL_GRID);
+        assert!((x - 651_409.5).abs() < 1e-3 && (y - 313_177.5).abs() < 1e-3);
+
+        // the center of the square is 0.6 m north-east of the example point
+        assert!((lon - GUIDE_LONLAT.0).abs() < 1e-5 && (lat - GUIDE_LONLAT.1).abs() < 1e-5);
+    }
+
+    #[test]
+    fn irish_grid() {
+        assert_eq!(xy_to_irish_grid(315_904.0, 234_671.0, 5), Ok("O 15904 34671".to_string()));
+        assert_eq!(irish_grid_to_bounds("O 15904 34671"), Ok((315_904.0, 234_671.0, 315_905.0, 234_672.0)));
+        assert_eq!(irish_grid_to_bounds("o1590434671"), irish_grid_to_bounds("O 15904 34671"));
+        assert_eq!(irish_grid_to_xy("O 159 346"), Ok((315_950.0, 234_650.0)));
+        assert_eq!(xy_to_irish_grid(0.0, 0.0, 0), Ok("V".to_string()));
+        assert_eq!(xy_to_irish_grid(499_999.0, 499_999.0, 0), Ok("E".to_string()));
+
+        // the natural origin of the Irish Grid (53°30'N, 8°W) is at (200 000, 250 000)
+        assert_eq!(lonlat_to_irish_grid(-8.0, 53.5, 5), Ok("N 00000 50000".to_string()));
+
+        let (lon, lat) = irish_grid_to_lonlat("N 00000 50000").unwrap();
+        let (x, y) = lonlat_to_tmerc_xy(lon, lat, &MODIFIED_AIRY_ELLIPSOID, &IRISH_GRID);
+        assert!((x - 200_000.5).abs() < 1e-3 && (y - 250_000.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(xy_to_bng(0.0, 0.0, 6), Err(OsGridError::InvalidPrecision(6)));
+        assert_eq!(xy_to_bng(-1.0, 0.0, 1), Err(OsGridError::OutsideGrid(-1.0, 0.0)));
+        assert_eq!(xy_to_bng(700_000.0, 0.0, 1), Err(OsGridError::OutsideGrid(700_000.0, 0.0)));
+        assert!(matches!(xy_to_irish_grid(f64::NAN, 0.0, 1), Err(OsGridError::InvalidCoordinate(..))));
+        assert_eq!(lonlat_to_bng(0.0, 91.0, 1), Err(OsGridError::InvalidCoordinate(0.0, 91.0)));
+
+        assert_eq!(bng_to_bounds("TG 514 13177"), Err(OsGridError::InvalidFormat("TG 514 13177".to_string())));
+        assert_eq!(bng_to_bounds("TG 51409a13177"), Err(OsGridError::InvalidFormat("TG 51409a13177".to_string())));
+        assert_eq!(bng_to_bounds("TG 514091 131771"), Err(OsGridError::InvalidFormat("TG 514091 131771".to_string())));
+        assert_eq!(bng_to_bounds("T1 51409 13177"), Err(OsGridError::InvalidFormat("T1 51409 13177".to_string())));
+        assert_eq!(bng_to_bounds("TI 51409 13177"), Err(OsGridError::InvalidSquare("TI".to_string())));
+        // "AA" is outside of the grid
+        assert_eq!(bng_to_bounds("AA"), Err(OsGridError::InvalidSquare("AA".to_string())));
+        assert_eq!(irish_grid_to_bounds("I 1 1"), Err(OsGridError::InvalidSquare("I".to_string())));
+    }
+}
@@ -0,0 +1,1470 @@
+//! Hexagonal hierarchical cell indexes, compatible with H3
+//!
+//! Implementation based on:
+//!
+//! https://h3geo.org/docs/core-library/overview
+//! https://github.com/uber/h3 (reference implementation)
+//!
+//! The sphere is projected onto the 20 faces of an icosahedron (gnomonic
+//! projection, Dymaxion orientation). Each face is covered by hexagons,
+//! with 12 pentagons at the vertices of the icosahedron. Every resolution
+//! (0 to 15) has cells with about 1/7 of the area of its parent resolution.
+//!
+//! An `H3Index` is a 64 bit number: the resolution, one of the 122
+//! resolution 0 base cells and a 3 bit digit (0 to 6) for every finer
+//! resolution. Indexes are written as 15 hexadecimal digits, e.g.
+//! "8928308280fffff".
+//!
+//! Coordinates are treated as spherical, like in the H3 library: latitudes
+//! are used as-is, no matter on which ellipsoid they are defined.
+
+mod tables;
+
+use prelude::*;
+use self::tables::*;
+use std::collections::HashSet;
+use std::error::Error;
+use std::f64::consts::PI;
+use std::fmt;
+use std::str::FromStr;
+
+/// Highest resolution
+pub const MAX_RESOLUTION: u8 = 15;
+
+const NUM_ICOSA_FACES: usize = 20;
+const NUM_BASE_CELLS: usize = 122;
+const INVALID_BASE_CELL: u8 = 0xff;
+
+/// Base cells that are pentagons
+const PENTAGON_BASE_CELLS: [u8; 12] = [4, 14, 24, 38, 49, 58, 63, 72, 83, 97, 107, 117];
+
+/// Base cells that are pentagons centered on the poles
+const POLAR_PENTAGON_BASE_CELLS: [u8; 2] = [4, 117];
+
+/// Scaling factor from the resolution 0 gnomonic unit length to the hex grid
+const RES0_U_GNOMONIC: f64 = 0.381966011250105;
+
+/// Rotation angle between class II and class III resolution axes (asin(sqrt(3 / 28)))
+const AP7_ROT_RADS: f64 = 0.3334731722518321;
+
+const SQRT7: f64 = 2.6457513110645907;
+const SQRT3_2: f64 = 0.8660254037844386;
+const EPSILON: f64 = 1e-16;
+
+/// Maximum ijk coordinate sum on a face at class II resolutions
+const MAX_DIM_BY_CII_RES: [i32; 17] = [2, -1, 14, -1, 98, -1, 686, -1, 4802, -1, 33614, -1, 235298, -1, 1647086, -1, 11529602];
+
+/// Distance between two resolution 0 cells in cells of class II resolutions
+const UNIT_SCALE_BY_CII_RES: [i32; 17] = [1, -1, 7, -1, 49, -1, 343, -1, 2401, -1, 16807, -1, 117649, -1, 823543, -1, 5764801];
+
+// Digits (directions) of an index
+const CENTER_DIGIT: u8 = 0;
+const K_AXES_DIGIT: u8 = 1;
+const JK_AXES_DIGIT: u8 = 3;
+const IK_AXES_DIGIT: u8 = 5;
+const INVALID_DIGIT: u8 = 7;
+
+/// Unit ijk vectors of the digits
+const UNIT_VECS: [[i32; 3]; 7] = [[0, 0, 0], [0, 0, 1], [0, 1, 0], [0, 1, 1], [1, 0, 0], [1, 0, 1], [1, 1, 0]];
+
+/// New digit when moving in a direction, on class II resolutions
+const NEW_DIGIT_II: [[u8; 7]; 7] = [
+    [0, 1, 2, 3, 4, 5, 6],
+    [1, 4, 3, 6, 5, 2, 0],
+    [2, 3, 1, 4, 6, 0, 5],
+    [3, 6, 4, 5, 0, 1, 2],
+    [4, 5, 6, 0, 2, 3, 1],
+    [5, 2, 0, 1, 3, 6, 4],
+    [6, 0, 5, 2, 1, 4, 3],
+];
+
+/// Direction to move in at the parent resolution, on class II resolutions
+const NEW_ADJUSTMENT_II: [[u8; 7]; 7] = [
+    [0, 0, 0, 0, 0, 0, 0],
+    [0, 1, 0, 1, 0, 5, 0],
+    [0, 0, 2, 3, 0, 0, 2],
+    [0, 1, 3, 3, 0, 0, 0],
+    [0, 0, 0, 0, 4, 4, 6],
+    [0, 5, 0, 0, 4, 5, 0],
+    [0, 0, 2, 0, 6, 0, 6],
+];
+
+/// New digit when moving in a direction, on class III resolutions
+const NEW_DIGIT_III: [[u8; 7]; 7] = [
+    [0, 1, 2, 3, 4, 5, 6],
+    [1, 2, 3, 4, 5, 6, 0],
+    [2, 3, 4, 5, 6, 0, 1],
+    [3, 4, 5, 6, 0, 1, 2],
+    [4, 5, 6, 0, 1, 2, 3],
+    [5, 6, 0, 1, 2, 3, 4],
+    [6, 0, 1, 2, 3, 4, 5],
+];
+
+/// Direction to move in at the parent resolution, on class III resolutions
+const NEW_ADJUSTMENT_III: [[u8; 7]; 7] = [
+    [0, 0, 0, 0, 0, 0, 0],
+    [0, 1, 0, 3, 0, 1, 0],
+    [0, 0, 2, 2, 0, 0, 6],
+    [0, 3, 2, 3, 0, 0, 0],
+    [0, 0, 0, 0, 4, 5, 4],
+    [0, 1, 0, 0, 5, 5, 0],
+    [0, 0, 6, 0, 4, 0, 6],
+];
+
+/// Error that can occur when creating or parsing an index
+#[derive(Debug, Clone, PartialEq)]
+pub enum H3Error {
+    /// Resolution must be between 0 and `MAX_RESOLUTION`
+    InvalidResolution(u8),
+    /// Longitude or latitude isn't a valid number
+    InvalidCoordinate(f64, f64),
+    /// The number is not a valid cell index
+    InvalidIndex(u64),
+    /// The string is not a hexadecimal index
+    InvalidFormat(String),
+}
+
+impl fmt::Display for H3Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::H3Error::*;
+        match *self {
+            InvalidResolution(res) => write!(f, "invalid H3 resolution: {} (must be 0 to {})", res, MAX_RESOLUTION),
+            InvalidCoordinate(lon, lat) => write!(f, "invalid coordinate: ({}, {})", lon, lat),
+            InvalidIndex(index) => write!(f, "invalid H3 index: {:x}", index),
+            InvalidFormat(ref s) => write!(f, "invalid H3 index: \"{}\"", s),
+        }
+    }
+}
+
+impl Error for H3Error { }
+
+/// A cell of the H3 grid
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct H3Index(u64);
+
+// --- ijk coordinates ------------------------------------------------------------------
+
+/// Hexagon coordinates along three axes 120° apart
+type Ijk = [i32; 3];
+
+/// Removes the common part of the three coordinates, so that all are
+/// non-negative and at least one is zero
+fn ijk_normalize(c: &mut Ijk) {
+    if c[0] < 0 { c[1] -= c[0]; c[2] -= c[0]; c[0] = 0; }
+    if c[1] < 0 { c[0] -= c[1]; c[2] -= c[1]; c[1] = 0; }
+    if c[2] < 0 { c[0] -= c[2]; c[1] -= c[2]; c[2] = 0; }
+
+    let min = c[0].min(c[1]).min(c[2]);
+    if min > 0 {
+        c[0] -= min; c[1] -= min; c[2] -= min;
+    }
+}
+
+#[inline]
+fn ijk_add(a: Ijk, b: Ijk) -> Ijk {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+#[inline]
+fn ijk_sub(a: Ijk, b: Ijk) -> Ijk {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+#[inline]
+fn ijk_scale(a: Ijk, factor: i32) -> Ijk {
+    [a[0] * factor, a[1] * factor, a[2] * factor]
+}
+
+/// Expresses the coordinate in the basis (i_vec, j_vec, k_vec)
+fn ijk_transform(c: &mut Ijk, i_vec: Ijk, j_vec: Ijk, k_vec: Ijk) {
+    *c = ijk_add(ijk_add(ijk_scale(i_vec, c[0]), ijk_scale(j_vec, c[1])), ijk_scale(k_vec, c[2]));
+    ijk_normalize(c);
+}
+
+/// Center of the aperture 7 child (counter-clockwise rotated grid)
+fn down_ap7(c: &mut Ijk) {
+    ijk_transform(c, [3, 0, 1], [1, 3, 0], [0, 1, 3]);
+}
+
+/// Center of the aperture 7 child (clockwise rotated grid)
+fn down_ap7r(c: &mut Ijk) {
+    ijk_transform(c, [3, 1, 0], [0, 3, 1], [1, 0, 3]);
+}
+
+/// Center of the aperture 3 child (counter-clockwise rotated grid)
+fn down_ap3(c: &mut Ijk) {
+    ijk_transform(c, [2, 0, 1], [1, 2, 0], [0, 1, 2]);
+}
+
+/// Center of the aperture 3 child (clockwise rotated grid)
+fn down_ap3r(c: &mut Ijk) {
+    ijk_transform(c, [2, 1, 0], [0, 2, 1], [1, 0, 2]);
+}
+
+/// Parent of an aperture 7 grid (counter-clockwise rotated grid)
+fn up_ap7(c: &mut Ijk) {
+    let (i, j) = (f64::from(c[0] - c[2]), f64::from(c[1] - c[2]));
+    *c = [((3.0 * i - j) / 7.0).round() as i32, ((i + 2.0 * j) / 7.0).round() as i32, 0];
+    ijk_normalize(c);
+}
+
+/// Parent of an aperture 7 grid (clockwise rotated grid)
+fn up_ap7r(c: &mut Ijk) {
+    let (i, j) = (f64::from(c[0] - c[2]), f64::from(c[1] - c[2]));
+    *c = [((2.0 * i + j) / 7.0).round() as i32, ((3.0 * j - i) / 7.0).round() as i32, 0];
+    ijk_normalize(c);
+}
+
+fn ijk_rotate60ccw(c: &mut Ijk) {
+    ijk_transform(c, [1, 1, 0], [0, 1, 1], [1, 0, 1]);
+}
+
+fn ijk_rotate60cw(c: &mut Ijk) {
+    ijk_transform(c, [1, 0, 1], [1, 1, 0], [0, 1, 1]);
+}
+
+/// Moves to the neighbouring cell in the direction of a digit
+fn ijk_neighbor(c: &mut Ijk, digit: u8) {
+    if digit > CENTER_DIGIT && digit < INVALID_DIGIT {
+        *c = ijk_add(*c, UNIT_VECS[digit as usize]);
+        ijk_normalize(c);
+    }
+}
+
+/// Digit of a unit vector
+fn unit_ijk_to_digit(mut c: Ijk) -> u8 {
+    ijk_normalize(&mut c);
+    UNIT_VECS.iter().position(|&v| v == c).map(|d| d as u8).unwrap_or(INVALID_DIGIT)
+}
+
+/// Converts ijk coordinates into 2D coordinates of the hex grid
+fn ijk_to_hex2d(c: Ijk) -> (f64, f64) {
+    let i = f64::from(c[0] - c[2]);
+    let j = f64::from(c[1] - c[2]);
+    (i - 0.5 * j, j * SQRT3_2)
+}
+
+/// Returns the ijk coordinates of the hexagon containing a 2D point of the hex grid
+fn hex2d_to_ijk(x: f64, y: f64) -> Ijk {
+    let a1 = x.abs();
+    let a2 = y.abs();
+
+    // First do a reverse conversion
+    let x2 = a2 / SQRT3_2;
+    let x1 = a1 + x2 / 2.0;
+
+    // Check if we have the center of a hex
+    let m1 = x1 as i32;
+    let m2 = x2 as i32;
+
+    // Otherwise round correctly
+    let r1 = x1 - f64::from(m1);
+    let r2 = x2 - f64::from(m2);
+
+    let (mut i, mut j);
+
+    if r1 < 0.5 {
+        if r1 < 1.0 / 3.0 {
+            i = m1;
+            j = if r2 < (1.0 + r1) / 2.0 { m2 } else { m2 + 1 };
+        } else {
+            j = if r2 < 1.0 - r1 { m2 } else { m2 + 1 };
+            i = if 1.0 - r1 <= r2 && r2 < 2.0 * r1 { m1 + 1 } else { m1 };
+        }
+    } else if r1 < 2.0 / 3.0 {
+        j = if r2 < 1.0 - r1 { m2 } else { m2 + 1 };
+        i = if 2.0 * r1 - 1.0 < r2 && r2 < 1.0 - r1 { m1 } else { m1 + 1 };
+    } else {
+        i = m1 + 1;
+        j = if r2 < r1 / 2.0 { m2 } else { m2 + 1 };
+    }
+
+    // Fold across the axes if necessary
+    if x < 0.0 {
+        if j % 2 == 0 {
+            let axis_i = j / 2;
+            i -= 2 * (i - axis_i);
+        } else {
+            let axis_i = (j + 1) / 2;
+            i -= 2 * (i - axis_i) + 1;
+        }
+    }
+
+    if y < 0.0 {
+        i -= (2 * j + 1) / 2;
+        j = -j;
+    }
+
+    let mut c = [i, j, 0];
+    ijk_normalize(&mut c);
+    c
+}
+
+// --- spherical geometry --------------------------------------------------------------
+
+/// Normalizes an angle to [0, 2 PI)
+#[inline]
+fn pos_angle(a: f64) -> f64 {
+    let a = if a < 0.0 { a + 2.0 * PI } else { a };
+    if a >= 2.0 * PI { a - 2.0 * PI } else { a }
+}
+
+/// Normalizes a longitude (in radians) to [-PI, PI]
+#[inline]
+fn constrain_lon(mut lon: f64) -> f64 {
+    while lon > PI { lon -= 2.0 * PI; }
+    while lon < -PI { lon += 2.0 * PI; }
+    lon
+}
+
+/// Azimuth from point 1 to point 2 (lat / lon in radians)
+fn azimuth(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    (lat2.cos() * (lon2 - lon1).sin())
+        .atan2(lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * (lon2 - lon1).cos())
+}
+
+/// Point at an azimuth and distance (in radians) from a point (lat / lon in radians)
+fn az_distance(lat1: f64, lon1: f64, az: f64, distance: f64) -> (f64, f64) {
+    if distance < EPSILON {
+        return (lat1, lon1);
+    }
+
+    let az = pos_angle(az);
+    let pole = |lat: f64| if (lat - PI / 2.0).abs() < EPSILON {
+        Some((PI / 2.0, 0.0))
+    } else if (lat + PI / 2.0).abs() < EPSILON {
+        Some((-PI / 2.0, 0.0))
+    } else {
+        None
+    };
+
+    // Due north or south
+    if az < EPSILON || (az - PI).abs() < EPSILON {
+        let lat2 = if az < EPSILON { lat1 + distance } else { lat1 - distance };
+        return pole(lat2).unwrap_or((lat2, constrain_lon(lon1)));
+    }
+
+    let sin_lat = (lat1.sin() * distance.cos() + lat1.cos() * distance.sin() * az.cos()).clamp(-1.0, 1.0);
+    let lat2 = sin_lat.asin();
+
+    if let Some(p) = pole(lat2) {
+        return p;
+    }
+
+    let inv_cos_lat2 = 1.0 / lat2.cos();
+    let sin_lon = (az.sin() * distance.sin() * inv_cos_lat2).clamp(-1.0, 1.0);
+    let cos_lon = ((distance.cos() - lat1.sin() * lat2.sin()) / lat1.cos() * inv_cos_lat2).clamp(-1.0, 1.0);
+
+    (lat2, constrain_lon(lon1 + sin_lon.atan2(cos_lon)))
+}
+
+#[inline]
+fn is_class_iii(res: usize) -> bool {
+    res % 2 == 1
+}
+
+/// Projects a point (lat / lon in radians) onto the 2D hex grid of the nearest face
+fn geo_to_hex2d(lat: f64, lon: f64, res: usize) -> (usize, f64, f64) {
+    let p = [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()];
+
+    let mut face = 0;
+    let mut sqd = 5.0;
+    for (f, c) in FACE_CENTER_POINT.iter().enumerate() {
+        let d = (p[0] - c[0]).powi(2) + (p[1] - c[1]).powi(2) + (p[2] - c[2]).powi(2);
+        if d < sqd {
+            face = f;
+            sqd = d;
+        }
+    }
+
+    // Cosine of the angle from the face center
+    let r = (1.0 - sqd / 2.0).acos();
+    if r < EPSILON {
+        return (face, 0.0, 0.0);
+    }
+
+    // Counter-clockwise angle from the class II i axis
+    let (center_lat, center_lon) = FACE_CENTER_GEO[face];
+    let mut theta = pos_angle(FACE_AXES_AZ_RADS[face] - pos_angle(azimuth(center_lat, center_lon, lat, lon)));
+
+    if is_class_iii(res) {
+        theta = pos_angle(theta - AP7_ROT_RADS);
+    }
+
+    // Gnomonic scaling of r, then scaling for the resolution
+    let r = r.tan() / RES0_U_GNOMONIC * SQRT7.powi(res as i32);
+
+    (face, r * theta.cos(), r * theta.sin())
+}
+
+/// Converts a 2D point of the hex grid of a face into lat / lon (in radians)
+///
+/// `substrate` is set for the vertex grid, which is 3 times finer (and
+/// unrotated on class III resolutions).
+fn hex2d_to_geo(x: f64, y: f64, face: usize, res: usize, substrate: bool) -> (f64, f64) {
+    let mut r = x.hypot(y);
+
+    if r < EPSILON {
+        return FACE_CENTER_GEO[face];
+    }
+
+    let mut theta = y.atan2(x);
+
+    r /= SQRT7.powi(res as i32);
+
+    if substrate {
+        r /= 3.0;
+        if is_class_iii(res) {
+            r /= SQRT7;
+        }
+    }
+
+    r = (r * RES0_U_GNOMONIC).atan();
+
+    if !substrate && is_class_iii(res) {
+        theta = pos_angle(theta + AP7_ROT_RADS);
+    }
+
+    theta = pos_angle(FACE_AXES_AZ_RADS[face] - theta);
+
+    let (center_lat, center_lon) = FACE_CENTER_GEO[face];
+    az_distance(center_lat, center_lon, theta, r)
+}
+
+// --- faces ---------------------------------------------------------------------------
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Overage {
+    /// The coordinate is on its face
+    None,
+    /// The coordinate is on the edge of the face (substrate grids only)
+    FaceEdge,
+    /// The coordinate was moved to an adjacent face
+    NewFace,
+}
+
+/// Direction (1 = IJ, 2 = KI, 3 = JK) from one face to an adjacent face
+fn adjacent_face_dir(from: usize, to: usize) -> Option<usize> {
+    FACE_NEIGHBORS[from].iter().position(|n| n.0 as usize == to)
+}
+
+/// Moves a class II coordinate that lies beyond the edge of its face onto the adjacent face
+fn adjust_overage_class_ii(face: &mut usize, c: &mut Ijk, res: usize, pent_leading_4: bool, substrate: bool) -> Overage {
+    let mut max_dim = MAX_DIM_BY_CII_RES[res];
+    if substrate {
+        max_dim *= 3;
+    }
+
+    let sum = c[0] + c[1] + c[2];
+
+    if substrate && sum == max_dim {
+        return Overage::FaceEdge;
+    }
+
+    if sum <= max_dim {
+        return Overage::None;
+    }
+
+    let direction = if c[2] > 0 {
+        if c[1] > 0 {
+            3
+        } else {
+            // Adjust for the pentagonal missing sequence
+            if pent_leading_4 {
+                let origin = [max_dim, 0, 0];
+                let mut tmp = ijk_sub(*c, origin);
+                ijk_rotate60cw(&mut tmp);
+                *c = ijk_add(tmp, origin);
+            }
+            2
+        }
+    } else {
+        1
+    };
+
+    let (new_face, translate, ccw_rot60) = FACE_NEIGHBORS[*face][direction];
+    *face = new_face as usize;
+
+    for _ in 0..ccw_rot60 {
+        ijk_rotate60ccw(c);
+    }
+
+    let mut unit_scale = UNIT_SCALE_BY_CII_RES[res];
+    if substrate {
+        unit_scale *= 3;
+    }
+
+    *c = ijk_add(*c, ijk_scale(translate, unit_scale));
+    ijk_normalize(c);
+
+    if substrate && c[0] + c[1] + c[2] == max_dim {
+        Overage::FaceEdge
+    } else {
+        Overage::NewFace
+    }
+}
+
+// --- index bits ------------------------------------------------------------------------
+
+const MODE_CELL: u64 = 1;
+const MODE_OFFSET: u32 = 59;
+const RES_OFFSET: u32 = 52;
+const BASE_CELL_OFFSET: u32 = 45;
+
+#[inline]
+fn get_resolution(h: u64) -> usize {
+    ((h >> RES_OFFSET) & 0xf) as usize
+}
+
+#[inline]
+fn get_base_cell(h: u64) -> u8 {
+    ((h >> BASE_CELL_OFFSET) & 0x7f) as u8
+}
+
+#[inline]
+fn set_base_cell(h: u64, base_cell: u8) -> u64 {
+    (h & !(0x7f << BASE_CELL_OFFSET)) | (u64::from(base_cell) << BASE_CELL_OFFSET)
+}
+
+#[inline]
+fn get_digit(h: u64, res: usize) -> u8 {
+    ((h >> ((MAX_RESOLUTION as usize - res) * 3)) & 7) as u8
+}
+
+#[inline]
+fn set_digit(h: u64, res: usize, digit: u8) -> u64 {
+    let offset = (MAX_RESOLUTION as usize - res) * 3;
+    (h & !(7 << offset)) | (u64::from(digit) << offset)
+}
+
+/// Index at a resolution with base cell 0 and all digits unset
+#[inline]
+fn init_index(res: usize) -> u64 {
+    (MODE_CELL << MODE_OFFSET) | ((res as u64) << RES_OFFSET) | 0x1fff_ffff_ffff
+}
+
+#[inline]
+fn is_pentagon_base_cell(base_cell: u8) -> bool {
+    PENTAGON_BASE_CELLS.contains(&base_cell)
+}
+
+/// Returns true if a face is a clockwise offset face of a pentagon base cell
+fn is_cw_offset(base_cell: u8, face: usize) -> bool {
+    match BASE_CELL_DATA[base_cell as usize].2 {
+        Some((a, b)) => a as usize == face || b as usize == face,
+        None => false,
+    }
+}
+
+/// First non-zero digit of an index
+fn leading_non_zero_digit(h: u64) -> u8 {
+    (1..=get_resolution(h)).map(|r| get_digit(h, r)).find(|&d| d != CENTER_DIGIT).unwrap_or(CENTER_DIGIT)
+}
+
+#[inline]
+fn rotate_digit60ccw(digit: u8) -> u8 {
+    match digit {
+        1 => 5, 5 => 4, 4 => 6, 6 => 2, 2 => 3, 3 => 1,
+        _ => digit,
+    }
+}
+
+#[inline]
+fn rotate_digit60cw(digit: u8) -> u8 {
+    match digit {
+        1 => 3, 3 => 2, 2 => 6, 6 => 4, 4 => 5, 5 => 1,
+        _ => digit,
+    }
+}
+
+fn rotate60ccw(mut h: u64) -> u64 {
+    for r in 1..=get_resolution(h) {
+        h = set_digit(h, r, rotate_digit60ccw(get_digit(h, r)));
+    }
+    h
+}
+
+fn rotate60cw(mut h: u64) -> u64 {
+    for r in 1..=get_resolution(h) {
+        h = set_digit(h, r, rotate_digit60cw(get_digit(h, r)));
+    }
+    h
+}
+
+/// Rotates a pentagon index, skipping the deleted K subsequence
+fn rotate_pent60ccw(mut h: u64) -> u64 {
+    let mut found_first_non_zero_digit = false;
+
+    for r in 1..=get_resolution(h) {
+        h = set_digit(h, r, rotate_digit60ccw(get_digit(h, r)));
+
+        if !found_first_non_zero_digit && get_digit(h, r) != CENTER_DIGIT {
+            found_first_non_zero_digit = true;
+            if leading_non_zero_digit(h) == K_AXES_DIGIT {
+                h = rotate60ccw(h);
+            }
+        }
+    }
+
+    h
+}
+
+// --- conversions -----------------------------------------------------------------------
+
+/// Converts face ijk coordinates at a resolution into an index
+fn face_ijk_to_h3(face: usize, mut c: Ijk, res: usize) -> Option<u64> {
+    let mut h = init_index(res);
+
+    // Build the index from the finest resolution up
+    for r in (0..res).rev() {
+        let last = c;
+        let mut last_center;
+
+        if is_class_iii(r + 1) {
+            up_ap7(&mut c);
+            last_center = c;
+            down_ap7(&mut last_center);
+        } else {
+            up_ap7r(&mut c);
+            last_center = c;
+            down_ap7r(&mut last_center);
+        }
+
+        h = set_digit(h, r + 1, unit_ijk_to_digit(ijk_sub(last, last_center)));
+    }
+
+    if c.iter().any(|&v| v > 2) {
+        return None;
+    }
+
+    let (base_cell, rotations) = FACE_IJK_BASE_CELLS[face][c[0] as usize][c[1] as usize][c[2] as usize];
+    h = set_base_cell(h, base_cell);
+
+    if is_pentagon_base_cell(base_cell) {
+        // Force rotation out of the missing K axes subsequence
+        if leading_non_zero_digit(h) == K_AXES_DIGIT {
+            h = if is_cw_offset(base_cell, face) { rotate60cw(h) } else { rotate60ccw(h) };
+        }
+
+        for _ in 0..rotations {
+            h = rotate_pent60ccw(h);
+        }
+    } else {
+        for _ in 0..rotations {
+            h = rotate60ccw(h);
+        }
+    }
+
+    Some(h)
+}
+
+/// Converts an index into face ijk coordinates on the home face of its base cell,
+/// returns true if the coordinates may lie beyond the edge of that face
+fn h3_to_face_ijk_on_home_face(h: u64, c: &mut Ijk) -> bool {
+    let res = get_resolution(h);
+    let possible_overage = is_pentagon_base_cell(get_base_cell(h)) || (res != 0 && *c != [0, 0, 0]);
+
+    for r in 1..=res {
+        if is_class_iii(r) { down_ap7(c); } else { down_ap7r(c); }
+        ijk_neighbor(c, get_digit(h, r));
+    }
+
+    possible_overage
+}
+
+/// Converts an index into the face ijk coordinates of its center
+fn h3_to_face_ijk(mut h: u64) -> (usize, Ijk) {
+    let base_cell = get_base_cell(h);
+
+    // Adjust for the pentagonal missing sequence, all of the
+    // sub-sequence 5 needs to be adjusted
+    if is_pentagon_base_cell(base_cell) && leading_non_zero_digit(h) == IK_AXES_DIGIT {
+        h = rotate60cw(h);
+    }
+
+    let (home_face, home_ijk, _) = BASE_CELL_DATA[base_cell as usize];
+    let mut face = home_face as usize;
+    let mut c = home_ijk;
+
+    if !h3_to_face_ijk_on_home_face(h, &mut c) {
+        return (face, c);
+    }
+
+    // The cell may lie on an adjacent face
+    let original = c;
+    let mut res = get_resolution(h);
+
+    // Overage is determined on the class II grid
+    if is_class_iii(res) {
+        down_ap7r(&mut c);
+        res += 1;
+    }
+
+    let pent_leading_4 = is_pentagon_base_cell(base_cell) && leading_non_zero_digit(h) == 4;
+
+    if adjust_overage_class_ii(&mut face, &mut c, res, pent_leading_4, false) != Overage::None {
+        // A pentagon base cell with a leading 4 digit requires another adjustment
+        if is_pentagon_base_cell(base_cell) {
+            while adjust_overage_class_ii(&mut face, &mut c, res, false, false) != Overage::None { }
+        }
+
+        if res != get_resolution(h) {
+            up_ap7r(&mut c);
+        }
+    } else if res != get_resolution(h) {
+        c = original;
+    }
+
+    (face, c)
+}
+
+/// Intersection of the lines through (p0, p1) and (p2, p3)
+fn intersect(p0: (f64, f64), p1: (f64, f64), p2: (f64, f64), p3: (f64, f64)) -> (f64, f64) {
+    let s1 = (p1.0 - p0.0, p1.1 - p0.1);
+    let s2 = (p3.0 - p2.0, p3.1 - p2.1);
+    let t = (s2.0 * (p0.1 - p2.1) - s2.1 * (p0.0 - p2.0)) / (-s2.0 * s1.1 + s1.0 * s2.1);
+    (p0.0 + t * s1.0, p0.1 + t * s1.1)
+}
+
+/// Edge of a face (in 2D coordinates of the substrate grid) in a direction
+fn face_edge(res: usize, direction: Option<usize>) -> ((f64, f64), (f64, f64)) {
+    let max_dim = f64::from(MAX_DIM_BY_CII_RES[res]);
+    let v0 = (3.0 * max_dim, 0.0);
+    let v1 = (-1.5 * max_dim, 3.0 * SQRT3_2 * max_dim);
+    let v2 = (-1.5 * max_dim, -3.0 * SQRT3_2 * max_dim);
+
+    match direction {
+        Some(1) => (v0, v1),
+        Some(3) => (v1, v2),
+        _ => (v2, v0),
+    }
+}
+
+/// Vertices of a cell on the substrate grid, also returns the (class II) resolution of that grid
+fn face_ijk_to_vertices(face: usize, mut c: Ijk, res: usize, pentagon: bool) -> (Vec<(usize, Ijk)>, usize) {
+    const VERTS_CII: [Ijk; 6] = [[2, 1, 0], [1, 2, 0], [0, 2, 1], [0, 1, 2], [1, 0, 2], [2, 0, 1]];
+    const VERTS_CIII: [Ijk; 6] = [[5, 4, 0], [1, 5, 0], [0, 5, 4], [0, 1, 5], [4, 0, 5], [5, 0, 1]];
+
+    let verts = if is_class_iii(res) { &VERTS_CIII } else { &VERTS_CII };
+    let count = if pentagon { 5 } else { 6 };
+
+    // Center on the aperture 3 substrate grid
+    down_ap3(&mut c);
+    down_ap3r(&mut c);
+
+    // Class III resolutions use the next (class II) resolution
+    let mut adjusted_res = res;
+    if is_class_iii(res) {
+        down_ap7r(&mut c);
+        adjusted_res += 1;
+    }
+
+    let vertices = verts[..count].iter().map(|&v| {
+        let mut vertex = ijk_add(c, v);
+        ijk_normalize(&mut vertex);
+        (face, vertex)
+    }).collect();
+
+    (vertices, adjusted_res)
+}
+
+/// Boundary of a hexagon (lat / lon in radians)
+fn hexagon_boundary(center_face: usize, c: Ijk, res: usize) -> Vec<(f64, f64)> {
+    let (vertices, adjusted_res) = face_ijk_to_vertices(center_face, c, res, false);
+    let mut boundary = Vec::with_capacity(10);
+    let mut last_face = None;
+    let mut last_overage = Overage::None;
+
+    // One additional iteration to check the edge between the last and the first vertex
+    for vert in 0..7 {
+        let v = vert % 6;
+        let (mut face, mut vertex) = vertices[v];
+        let overage = adjust_overage_class_ii(&mut face, &mut vertex, adjusted_res, false, true);
+
+        // Class III edges may cross a face edge, which adds a distortion vertex
+        if let Some(last_face) = last_face {
+            if is_class_iii(res) && face != last_face && last_overage != Overage::FaceEdge {
+                let last_v = (v + 5) % 6;
+                let orig0 = ijk_to_hex2d(vertices[last_v].1);
+                let orig1 = ijk_to_hex2d(vertices[v].1);
+
+                let other_face = if last_face == center_face { face } else { last_face };
+                let (edge0, edge1) = face_edge(adjusted_res, adjacent_face_dir(center_face, other_face));
+                let inter = intersect(orig0, orig1, edge0, edge1);
+
+                // The intersection may be one of the vertices
+                let almost_equal = |a: (f64, f64), b: (f64, f64)| {
+                    (a.0 - b.0).abs() < f64::from(f32::EPSILON) && (a.1 - b.1).abs() < f64::from(f32::EPSILON)
+                };
+
+                if !almost_equal(orig0, inter) && !almost_equal(orig1, inter) {
+                    boundary.push(hex2d_to_geo(inter.0, inter.1, center_face, adjusted_res, true));
+                }
+            }
+        }
+
+        if vert < 6 {
+            let (x, y) = ijk_to_hex2d(vertex);
+            boundary.push(hex2d_to_geo(x, y, face, adjusted_res, true));
+        }
+
+        last_face = Some(face);
+        last_overage = overage;
+    }
+
+    boundary
+}
+
+/// Boundary of a pentagon (lat / lon in radians)
+fn pentagon_boundary(center_face: usize, c: Ijk, res: usize) -> Vec<(f64, f64)> {
+    let (vertices, adjusted_res) = face_ijk_to_vertices(center_face, c, res, true);
+    let mut boundary = Vec::with_capacity(10);
+    let mut last: Option<(usize, Ijk)> = None;
+
+    for vert in 0..6 {
+        let v = vert % 5;
+        let (mut face, mut vertex) = vertices[v];
+
+        // Pentagon vertices may have to be moved several faces
+        while adjust_overage_class_ii(&mut face, &mut vertex, adjusted_res, false, true) == Overage::NewFace { }
+
+        // All class III pentagon edges cross icosahedron edges
+        if let Some((last_face, last_vertex)) = last {
+            if is_class_iii(res) {
+                let orig0 = ijk_to_hex2d(last_vertex);
+
+                // Express the current vertex in the coordinate system of the last one
+                let direction = adjacent_face_dir(face, last_face).unwrap_or(0);
+                let (tmp_face, translate, ccw_rot60) = FACE_NEIGHBORS[face][direction];
+                let mut tmp = vertex;
+                for _ in 0..ccw_rot60 {
+                    ijk_rotate60ccw(&mut tmp);
+                }
+                tmp = ijk_add(tmp, ijk_scale(translate, UNIT_SCALE_BY_CII_RES[adjusted_res] * 3));
+                ijk_normalize(&mut tmp);
+                let orig1 = ijk_to_hex2d(tmp);
+
+                let tmp_face = tmp_face as usize;
+                let (edge0, edge1) = face_edge(adjusted_res, adjacent_face_dir(tmp_face, face));
+                let inter = intersect(orig0, orig1, edge0, edge1);
+                boundary.push(hex2d_to_geo(inter.0, inter.1, tmp_face, adjusted_res, true));
+            }
+        }
+
+        if vert < 5 {
+            let (x, y) = ijk_to_hex2d(vertex);
+            boundary.push(hex2d_to_geo(x, y, face, adjusted_res, true));
+        }
+
+        last = Some((face, vertex));
+    }
+
+    boundary
+}
+
+/// Returns the neighbouring index in a direction, `None` if the direction is the
+/// deleted K direction of a pentagon
+///
+/// `rotations` is the number of 60° ccw rotations of the direction, it is
+/// updated when moving across the edge of a face.
+fn neighbor_rotations(origin: u64, direction: u8, rotations: &mut u8) -> Option<u64> {
+    let mut current = origin;
+    let mut direction = direction;
+
+    for _ in 0..*rotations % 6 {
+        direction = rotate_digit60ccw(direction);
+    }
+
+    let old_base_cell = get_base_cell(origin);
+    let old_leading_digit = leading_non_zero_digit(origin);
+
+    // Adjust the digits and, if needed, the base cell
+    let mut r = get_resolution(origin);
+    let mut new_rotations = 0;
+
+    loop {
+        if r == 0 {
+            let neighbor = BASE_CELL_NEIGHBORS[old_base_cell as usize][direction as usize];
+
+            if neighbor == INVALID_BASE_CELL {
+                // Moving into the deleted K direction of a pentagon base cell,
+                // the edge borders the IK neighbour instead
+                current = set_base_cell(current, BASE_CELL_NEIGHBORS[old_base_cell as usize][IK_AXES_DIGIT as usize]);
+                new_rotations = BASE_CELL_NEIGHBOR_60CCW_ROTS[old_base_cell as usize][IK_AXES_DIGIT as usize];
+                current = rotate60ccw(current);
+                *rotations += 1;
+            } else {
+                current = set_base_cell(current, neighbor);
+                new_rotations = BASE_CELL_NEIGHBOR_60CCW_ROTS[old_base_cell as usize][direction as usize];
+            }
+            break;
+        }
+
+        let old_digit = get_digit(current, r) as usize;
+        let next_direction;
+
+        if is_class_iii(r) {
+            current = set_digit(current, r, NEW_DIGIT_II[old_digit][direction as usize]);
+            next_direction = NEW_ADJUSTMENT_II[old_digit][direction as usize];
+        } else {
+            current = set_digit(current, r, NEW_DIGIT_III[old_digit][direction as usize]);
+            next_direction = NEW_ADJUSTMENT_III[old_digit][direction as usize];
+        }
+
+        if next_direction == CENTER_DIGIT {
+            break;
+        }
+
+        direction = next_direction;
+        r -= 1;
+    }
+
+    let new_base_cell = get_base_cell(current);
+
+    if is_pentagon_base_cell(new_base_cell) {
+        let mut already_adjusted_k_subsequence = false;
+
+        // Force rotation out of the missing K axes subsequence
+        if leading_non_zero_digit(current) == K_AXES_DIGIT {
+            if old_base_cell != new_base_cell {
+                // Moved into the deleted K subsequence of a pentagon base cell
+                if is_cw_offset(new_base_cell, BASE_CELL_DATA[old_base_cell as usize].0 as usize) {
+                    current = rotate60cw(current);
+                } else {
+                    current = rotate60ccw(current);
+                }
+                already_adjusted_k_subsequence = true;
+            } else if old_leading_digit == CENTER_DIGIT {
+                // Moved into the deleted K subsequence from the center of the pentagon
+                return None;
+            } else if old_leading_digit == JK_AXES_DIGIT {
+                current = rotate60ccw(current);
+                *rotations += 1;
+            } else if old_leading_digit == IK_AXES_DIGIT {
+                current = rotate60cw(current);
+                *rotations += 5;
+            }
+        }
+
+        for _ in 0..new_rotations {
+            current = rotate_pent60ccw(current);
+        }
+
+        // Account for the differing orientation of the base cells
+        if old_base_cell != new_base_cell {
+            if POLAR_PENTAGON_BASE_CELLS.contains(&new_base_cell) {
+                // Polar pentagons have all I neighbours
+                if old_base_cell != 118 && old_base_cell != 8 && leading_non_zero_digit(current) != JK_AXES_DIGIT {
+                    *rotations += 1;
+                }
+            } else if leading_non_zero_digit(current) == IK_AXES_DIGIT && !already_adjusted_k_subsequence {
+                // Account for the distortion introduced by the deleted K subsequence
+                *rotations += 1;
+            }
+        }
+    } else {
+        for _ in 0..new_rotations {
+            current = rotate60ccw(current);
+        }
+    }
+
+    *rotations = (*rotations + new_rotations) % 6;
+    Some(current)
+}
+
+// --- public API ----------------------------------------------------------------------
+
+impl H3Index {
+    /// Creates an index from its 64 bit representation
+    pub fn new(index: u64) -> Result<Self, H3Error> {
+        let cell = H3Index(index);
+        if cell.is_valid() { Ok(cell) } else { Err(H3Error::InvalidIndex(index)) }
+    }
+
+    /// Returns the cell at the given resolution that contains the lon / lat coordinate (in degrees)
+    pub fn from_lon_lat(lon: f64, lat: f64, res: u8) -> Result<Self, H3Error> {
+        if res > MAX_RESOLUTION {
+            return Err(H3Error::InvalidResolution(res));
+        }
+
+        if !lon.is_finite() || !lat.is_finite() || !(-90.0..=90.0).contains(&lat) {
+            return Err(H3Error::InvalidCoordinate(lon, lat));
+        }
+
+        let (face, x, y) = geo_to_hex2d(lat.to_radians(), lon.to_radians(), res as usize);
+
+        face_ijk_to_h3(face, hex2d_to_ijk(x, y), res as usize)
+            .map(H3Index)
+            .ok_or(H3Error::InvalidCoordinate(lon, lat))
+    }
+
+    /// Returns true if the number is a valid cell index
+    fn is_valid(self) -> bool {
+        let h = self.0;
+
+        if h >> 63 != 0 || (h >> MODE_OFFSET) & 0xf != MODE_CELL || (h >> 56) & 7 != 0 {
+            return false;
+        }
+
+        let base_cell = get_base_cell(h);
+        if base_cell as usize >= NUM_BASE_CELLS {
+            return false;
+        }
+
+        let res = get_resolution(h);
+        let digits_valid = (1..=MAX_RESOLUTION as usize).all(|r| {
+            let digit = get_digit(h, r);
+            if r <= res { digit < INVALID_DIGIT } else { digit == INVALID_DIGIT }
+        });
+
+        digits_valid && !(is_pentagon_base_cell(base_cell) && leading_non_zero_digit(h) == K_AXES_DIGIT)
+    }
+
+    /// The 64 bit representation of the index
+    pub fn id(&self) -> u64 {
+        self.0
+    }
+
+    /// The resolution of the cell (0 to `MAX_RESOLUTION`)
+    pub fn resolution(&self) -> u8 {
+        get_resolution(self.0) as u8
+    }
+
+    /// The resolution 0 base cell of the cell (0 to 121)
+    pub fn base_cell(&self) -> u8 {
+        get_base_cell(self.0)
+    }
+
+    /// Returns true if the cell is one of the 12 pentagons of its resolution
+    pub fn is_pentagon(&self) -> bool {
+        is_pentagon_base_cell(self.base_cell()) && leading_non_zero_digit(self.0) == CENTER_DIGIT
+    }
+
+    /// Returns the cell at a lower resolution that contains the center of this cell,
+    /// `None` if `res` is higher than the resolution of the cell
+    pub fn parent(&self, res: u8) -> Option<H3Index> {
+        if res > self.resolution() {
+            return None;
+        }
+
+        let mut h = (self.0 & !(0xf << RES_OFFSET)) | (u64::from(res) << RES_OFFSET);
+        for r in res as usize + 1..=MAX_RESOLUTION as usize {
+            h = set_digit(h, r, INVALID_DIGIT);
+        }
+
+        Some(H3Index(h))
+    }
+
+    /// Returns the cells at the next resolution (7 for hexagons, 6 for pentagons),
+    /// `None` at the highest resolution
+    pub fn children(&self) -> Option<Vec<H3Index>> {
+        if self.resolution() >= MAX_RESOLUTION {
+            return None;
+        }
+
+        let res = self.resolution() as usize + 1;
+        let h = (self.0 & !(0xf << RES_OFFSET)) | ((res as u64) << RES_OFFSET);
+        let pentagon = self.is_pentagon();
+
+        Some((CENTER_DIGIT..INVALID_DIGIT)
+            .filter(|&digit| !(pentagon && digit == K_AXES_DIGIT))
+            .map(|digit| H3Index(set_digit(h, res, digit)))
+            .collect())
+    }
+
+    /// Returns the center of the cell (lon / lat, in degrees)
+    pub fn center(&self) -> (f64, f64) {
+        let (face, c) = h3_to_face_ijk(self.0);
+        let (x, y) = ijk_to_hex2d(c);
+        let (lat, lon) = hex2d_to_geo(x, y, face, get_resolution(self.0), false);
+        (lon.to_degrees(), lat.to_degrees())
+    }
+
+    /// Returns the vertices of the cell (lon / lat, in degrees), counter-clockwise
+    ///
+    /// Hexagons have 6 vertices, pentagons 5. Cells whose edges cross an edge
+    /// of the icosahedron have additional vertices (up to 10) on that edge.
+    pub fn boundary(&self) -> Vec<(f64, f64)> {
+        let (face, c) = h3_to_face_ijk(self.0);
+        let res = get_resolution(self.0);
+
+        let boundary = if self.is_pentagon() {
+            pentagon_boundary(face, c, res)
+        } else {
+            hexagon_boundary(face, c, res)
+        };
+
+        boundary.iter().map(|&(lat, lon)| (lon.to_degrees(), lat.to_degrees())).collect()
+    }
+
+    /// Returns the outline of the cell as a closed, counter-clockwise ring of
+    /// lon / lat coordinates (in degrees)
+    pub fn polygon(&self) -> Vec<(f64, f64)> {
+        let mut polygon = self.boundary();
+        polygon.push(polygon[0]);
+        polygon
+    }
+
+    /// Returns the neighbouring cells (6 for hexagons, 5 for pentagons)
+    pub fn neighbours(&self) -> Vec<H3Index> {
+        // Pentagons have no neighbour in the deleted K direction
+        let first_direction = if self.is_pentagon() { JK_AXES_DIGIT - 1 } else { K_AXES_DIGIT };
+
+        (first_direction..INVALID_DIGIT)
+            .filter_map(|direction| neighbor_rotations(self.0, direction, &mut 0))
+            .map(H3Index)
+            .collect()
+    }
+
+    /// Returns all cells within `k` steps of this cell together with their
+    /// distance, ordered by distance (the cell itself comes first)
+    pub fn k_ring_distances(&self, k: u32) -> Vec<(H3Index, u32)> {
+        let mut seen = HashSet::new();
+        seen.insert(*self);
+
+        let mut cells = vec![(*self, 0)];
+        let mut ring_start = 0;
+
+        for distance in 1..=k {
+            let ring_end = cells.len();
+
+            for n in ring_start..ring_end {
+                for neighbour in cells[n].0.neighbours() {
+                    if seen.insert(neighbour) {
+                        cells.push((neighbour, distance));
+                    }
+                }
+            }
+
+            ring_start = ring_end;
+        }
+
+        cells
+    }
+
+    /// Returns all cells within `k` steps of this cell, ordered by distance
+    /// (the cell itself comes first)
+    pub fn k_ring(&self, k: u32) -> Vec<H3Index> {
+        self.k_ring_distances(k).into_iter().map(|(cell, _)| cell).collect()
+    }
+}
+
+impl fmt::Display for H3Index {
+    /// Formats the index as lowercase hexadecimal, e.g. "8928308280fffff"
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:x}", self.0)
+    }
+}
+
+impl FromStr for H3Index {
+    type Err = H3Error;
+
+    /// Parses an index from hexadecimal, e.g. "8928308280fffff"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let trimmed = s.trim();
+
+        if trimmed.is_empty() || trimmed.len() > 16 || !trimmed.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(H3Error::InvalidFormat(s.to_string()));
+        }
+
+        let index = u64::from_str_radix(trimmed, 16).map_err(|_| H3Error::InvalidFormat(s.to_string()))?;
+        H3Index::new(index)
+    }
+}
+
+/// Returns true if a point lies within a ring (lon / lat, in degrees, even-odd rule)
+fn ring_contains(ring: &[(f64, f64)], lon: f64, lat: f64) -> bool {
+    let mut inside = false;
+
+    for (k, &(lon1, lat1)) in ring.iter().enumerate() {
+        let (lon2, lat2) = ring[(k + 1) % ring.len()];
+
+        if (lat1 > lat) != (lat2 > lat) && lon < (lon2 - lon1) * (lat - lat1) / (lat2 - lat1) + lon1 {
+            inside = !inside;
+        }
+    }
+
+    inside
+}
+
+/// Returns all cells at the given resolution whose centers lie within a polygon
+///
+/// The polygon is given as an outer ring and optional holes of lon / lat
+/// coordinates (in degrees, closing the rings is optional). Edges are
+/// straight lines in lon / lat, polygons crossing the antimeridian are not
+/// supported. The cells are sorted by index.
+pub fn polyfill(exterior: &[(f64, f64)], holes: &[Vec<(f64, f64)>], res: u8)
+                -> Result<Vec<H3Index>, H3Error>
+{
+    if res > MAX_RESOLUTION {
+        return Err(H3Error::InvalidResolution(res));
+    }
+
+    for &(lon, lat) in exterior.iter().chain(holes.iter().flat_map(|hole| hole.iter())) {
+        if !(-180.0..=180.0).contains(&lon) || !(-90.0..=90.0).contains(&lat) {
+            return Err(H3Error::InvalidCoordinate(lon, lat));
+        }
+    }
+
+    if exterior.len() < 3 {
+        return Ok(Vec::new());
+    }
+
+    let contains = |(lon, lat): (f64, f64)| {
+        ring_contains(exterior, lon, lat) && !holes.iter().any(|hole| hole.len() >= 3 && ring_contains(hole, lon, lat))
+    };
+
+    // Cells along the edges, sampled at a quarter of the (approximate) shortest edge length
+    let step = 10.0_f64.to_radians() / SQRT7.powi(i32::from(res)) / 4.0;
+    let mut edge_cells = HashSet::new();
+
+    for ring in ::std::iter::once(exterior).chain(holes.iter().map(|hole| hole.as_slice())) {
+        for (k, &(lon1, lat1)) in ring.iter().enumerate() {
+            let (lon2, lat2) = ring[(k + 1) % ring.len()];
+            let length = (lon2 - lon1).abs().to_radians() + (lat2 - lat1).abs().to_radians();
+            let samples = (length / step).ceil().max(1.0) as usize;
+
+            for s in 0..=samples {
+                let t = s as f64 / samples as f64;
+                edge_cells.insert(H3Index::from_lon_lat(lon1 + t * (lon2 - lon1), lat1 + t * (lat2 - lat1), res)?);
+            }
+        }
+    }
+
+    // Flood fill the interior, starting from the edges
+    let mut visited: HashSet<H3Index> = edge_cells.iter().cloned().collect();
+    let mut queue: Vec<H3Index> = edge_cells.iter().cloned().collect();
+
+    while let Some(cell) = queue.pop() {
+        for neighbour in cell.neighbours() {
+            if !visited.contains(&neighbour) && contains(neighbour.center()) {
+                visited.insert(neighbour);
+                queue.push(neighbour);
+            }
+        }
+    }
+
+    let mut cells: Vec<H3Index> = visited.into_iter().filter(|cell| contains(cell.center())).collect();
+    cells.sort();
+    Ok(cells)
+}
+
+/// Returns the cell at the given resolution of every coordinate of a `LonLatBuf`
+///
+/// Invalid coordinates are returned as `None`.
+pub fn lonlat_buf_to_indexes(buf: &LonLatBuf, res: u8, strategy: &mut MultithreadingStrategy)
+                             -> Result<Vec<Option<H3Index>>, H3Error>
+{
+    if res > MAX_RESOLUTION {
+        return Err(H3Error::InvalidResolution(res));
+    }
+
+    let mut cells = vec![None; buf.data.len()];
+
+    match *strategy {
+        SingleCore => {
+            for (&(lon, lat), cell) in buf.data.iter().zip(cells.iter_mut()) {
+                *cell = H3Index::from_lon_lat(lon, lat, res).ok();
+            }
+        },
+        #[cfg(all(not(target_arch = "wasm32"), feature = "scoped_threadpool"))]
+        MultiCore(ref mut thread_pool) => {
+            thread_pool.scoped(|scoped| {
+                for (&(lon, lat), cell) in buf.data.iter().zip(cells.iter_mut()) {
+                    scoped.execute(move || {
+                        *cell = H3Index::from_lon_lat(lon, lat, res).ok();
+                    });
+                }
+            });
+        },
+    }
+
+    Ok(cells)
+}
+
+/// Converts cells into a `LonLatBuf` with the center of each cell
+pub fn indexes_to_lonlat_buf(cells: &[H3Index], ellipsoid: &Ellipsoid, strategy: &mut MultithreadingStrategy)
+                             -> LonLatBuf
+{
+    let mut data = vec![(f64::NAN, f64::NAN); cells.len()];
+
+    match *strategy {
+        SingleCore => {
+            for (cell, coordinate) in cells.iter().zip(data.iter_mut()) {
+                *coordinate = cell.center();
+            }
+        },
+        #[cfg(all(not(target_arch = "wasm32"), feature = "scoped_threadpool"))]
+        MultiCore(ref mut thread_pool) => {
+            thread_pool.scoped(|scoped| {
+                for (cell, coordinate) in cells.iter().zip(data.iter_mut()) {
+                    scoped.execute(move || {
+                        *coordinate = cell.center();
+                    });
+                }
+            });
+        },
+    }
+
+    LonLatBuf {
+        data,
+        ellipsoid: *ellipsoid,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reference_indexes() {
+        // examples of the H3 documentation
+        assert_eq!(H3Index::from_lon_lat(-122.388_903, 37.769_377, 9).map(|cell| cell.to_string()), Ok("89283082e73ffff".to_string()));
+        assert_eq!(H3Index::from_lon_lat(-74.044_444, 40.689_167, 10).map(|cell| cell.to_string()), Ok("8a2a1072b59ffff".to_string()));
+
+        let cell = H3Index::from_lon_lat(-122.055_323_8, 37.361_559_3, 7).unwrap();
+        assert_eq!(cell.to_string(), "87283472bffffff");
+        let (lon, lat) = cell.center();
+        assert!((lon - -122.050_325_652_639_46).abs() < 1e-9 && (lat - 37.351_718_201_832_72).abs() < 1e-9);
+    }
+
+    #[test]
+    fn resolution_9_cell() {
+        let cell: H3Index = "8928308280fffff".parse().unwrap();
+        assert_eq!(cell.id(), 0x0892_8308_280f_ffff);
+        assert_eq!(cell.resolution(), 9);
+        assert_eq!(cell.base_cell(), 20);
+        assert!(!cell.is_pentagon());
+
+        let (lon, lat) = cell.center();
+        assert!((lon - -122.418_459_323_183_11).abs() < 1e-9 && (lat - 37.776_702_349_435_67).abs() < 1e-9);
+        assert_eq!(H3Index::from_lon_lat(lon, lat, 9), Ok(cell));
+
+        let boundary = cell.boundary();
+        assert_eq!(boundary.len(), 6);
+        assert!(ring_contains(&boundary, lon, lat));
+
+        let polygon = cell.polygon();
+        assert_eq!(polygon.len(), 7);
+        assert_eq!(polygon.first(), polygon.last());
+    }
+
+    #[test]
+    fn k_rings() {
+        let cell: H3Index = "8928308280fffff".parse().unwrap();
+
+        let sizes: Vec<usize> = (0..4).map(|k| cell.k_ring(k).len()).collect();
+        assert_eq!(sizes, [1, 7, 19, 37]);
+
+        // test case of the H3 library
+        let mut neighbours: Vec<String> = cell.neighbours().iter().map(|n| n.to_string()).collect();
+        neighbours.sort();
+        assert_eq!(neighbours, ["89283082803ffff", "89283082807ffff", "8928308280bffff",
+                                "8928308283bffff", "89283082873ffff", "89283082877ffff"]);
+
+        let distances = cell.k_ring_distances(2);
+        assert_eq!(distances[0], (cell, 0));
+        assert_eq!(distances.iter().filter(|&&(_, distance)| distance == 1).count(), 6);
+        assert_eq!(distances.iter().filter(|&&(_, distance)| distance == 2).count(), 12);
+    }
+
+    #[test]
+    fn hierarchy() {
+        let cell: H3Index = "8928308280fffff".parse().unwrap();
+        assert_eq!(cell.parent(5).map(|parent| parent.to_string()), Some("85283083fffffff".to_string()));
+        assert_eq!(cell.parent(9), Some(cell));
+        assert_eq!(cell.parent(10), None);
+
+        let children = cell.children().unwrap();
+        assert_eq!(children.len(), 7);
+        assert!(children.iter().all(|child| child.resolution() == 10 && child.parent(9) == Some(cell)));
+
+        let leaf = H3Index::from_lon_lat(-122.418, 37.776, MAX_RESOLUTION).unwrap();
+        assert_eq!(leaf.children(), None);
+    }
+
+    #[test]
+    fn pentagons() {
+        let pentagons: Vec<H3Index> = PENTAGON_BASE_CELLS.iter()
+            .map(|&base_cell| H3Index::new(0x0800_1fff_ffff_ffff | (u64::from(base_cell) << 45)).unwrap())
+            .collect();
+
+        for pentagon in &pentagons {
+            assert!(pentagon.is_pentagon());
+            assert_eq!(pentagon.boundary().len(), 5);
+            assert_eq!(pentagon.neighbours().len(), 5);
+            assert_eq!(pentagon.k_ring(1).len(), 6);
+
+            let children = pentagon.children().unwrap();
+            assert_eq!(children.len(), 6);
+            assert_eq!(children.iter().filter(|child| child.is_pentagon()).count(), 1);
+
+            let (lon, lat) = pentagon.center();
+            assert_eq!(H3Index::from_lon_lat(lon, lat, 0), Ok(*pentagon));
+        }
+
+        // resolution 0 has 110 hexagons and 12 pentagons
+        let base_cells: HashSet<H3Index> = (0..NUM_BASE_CELLS as u64)
+            .filter_map(|base_cell| H3Index::new(0x0800_1fff_ffff_ffff | (base_cell << 45)).ok())
+            .collect();
+        assert_eq!(base_cells.len(), 122);
+        assert_eq!(base_cells.iter().filter(|cell| cell.is_pentagon()).count(), 12);
+
+        // the north pole lies in base cell 0
+        assert_eq!(H3Index::from_lon_lat(0.0, 90.0, 0).map(|cell| cell.to_string()), Ok("8001fffffffffff".to_string()));
+    }
+
+    #[test]
+    fn polyfills() {
+        let cell: H3Index = "8928308280fffff".parse().unwrap();
+        assert_eq!(polyfill(&cell.boundary(), &[], 9), Ok(vec![cell]));
+
+        let exterior = [(-122.43, 37.77), (-122.40, 37.77), (-122.40, 37.79), (-122.43, 37.79)];
+        let cells = polyfill(&exterior, &[], 9).unwrap();
+        assert!(cells.len() > 10);
+        assert!(cells.iter().all(|cell| ring_contains(&exterior, cell.center().0, cell.center().1)));
+        assert!(cells.windows(2).all(|pair| pair[0] < pair[1]));
+
+        let hole = vec![(-122.42, 37.775), (-122.41, 37.775), (-122.41, 37.785), (-122.42, 37.785)];
+        let with_hole = polyfill(&exterior, ::std::slice::from_ref(&hole), 9).unwrap();
+        assert!(with_hole.len() < cells.len());
+        assert!(with_hole.iter().all(|cell| !ring_contains(&hole, cell.center().0, cell.center().1)));
+
+        assert_eq!(polyfill(&exterior[..2], &[], 9), Ok(Vec::new()));
+        assert_eq!(polyfill(&exterior, &[], 16), Err(H3Error::InvalidResolution(16)));
+        assert_eq!(polyfill(&[(0.0, 0.0), (0.0, 91.0), (1.0, 0.0)], &[], 1), Err(H3Error::InvalidCoordinate(0.0, 91.0)));
+    }
+
+    #[test]
+    fn buffers() {
+        let buf = LonLatBuf {
+            data: vec![(-122.418_459_323_183_11, 37.776_702_349_435_67), (f64::NAN, 0.0)],
+            ellipsoid: WGS_1984_ELLIPSOID,
+        };
+
+        let mut strategy = MultithreadingStrategy::SingleCore;
+        let cells = lonlat_buf_to_indexes(&buf, 9, &mut strategy).unwrap();
+        assert_eq!(cells, vec!["8928308280fffff".parse().ok(), None]);
+        assert_eq!(lonlat_buf_to_indexes(&buf, 16, &mut strategy), Err(H3Error::InvalidResolution(16)));
+
+        let centers = indexes_to_lonlat_buf(&[cells[0].unwrap()], &WGS_1984_ELLIPSOID, &mut strategy);
+        assert_eq!(centers.data, vec![cells[0].unwrap().center()]);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(H3Index::from_lon_lat(0.0, 0.0, 16), Err(H3Error::InvalidResolution(16)));
+        assert_eq!(H3Index::from_lon_lat(0.0, 90.5, 1), Err(H3Error::InvalidCoordinate(0.0, 90.5)));
+        assert_eq!(H3Index::new(0), Err(H3Error::InvalidIndex(0)));
+        // base cell 122 doesn't exist
+        assert_eq!(H3Index::new(0x0800_1fff_ffff_ffff | (122 << 45)), Err(H3Error::InvalidIndex(0x0800_1fff_ffff_ffff | (122 << 45))));
+        // digit below the resolution must be 7
+        assert_eq!(H3Index::new(0x0892_8308_280f_fff0), Err(H3Error::InvalidIndex(0x0892_8308_280f_fff0)));
+        // pentagons have no K axes sub-cell
+        assert_eq!(H3Index::new(0x0810_87ff_ffff_ffff), Err(H3Error::InvalidIndex(0x0810_87ff_ffff_ffff)));
+
+        for s in &["", "xyz", "8928308280fffff0f", " "] {
+            assert_eq!(s.parse::<H3Index>(), Err(H3Error::InvalidFormat(s.to_string())), "{}", s);
+        }
+    }
+}
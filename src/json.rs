@@ -0,0 +1,389 @@
+//! Minimal JSON values, as needed by the PROJJSON and GeoJSON readers and writers
+//!
+//! Objects keep the order of their members, so that written documents
+//! follow the member order of the respective specification.
+
+use std::fmt;
+
+/// Maximum nesting of arrays and objects, so that deep documents can't overflow the stack
+const MAX_DEPTH: usize = 128;
+
+/// A JSON value
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    /// A number read from a document, with its original text so that it is
+    /// written back unchanged (e.g. integers above 2^53)
+    ParsedNumber(f64, String),
+    String(String),
+    Array(Vec<Json>),
+    /// Members in document order
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Parses a JSON document, the error is the byte offset of the first invalid character
+    ///
+    /// Arrays and objects nested deeper than `MAX_DEPTH` levels and numbers
+    /// outside the range of `f64` are invalid.
+    pub(crate) fn parse(s: &str) -> Result<Json, usize> {
+        let mut parser = Parser { bytes: s.as_bytes(), position: 0, depth: 0 };
+        parser.skip_whitespace();
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.position != s.len() {
+            return Err(parser.position);
+        }
+        Ok(value)
+    }
+
+    /// Creates an object from `(key, value)` pairs
+    pub(crate) fn object<I: IntoIterator<Item = (&'static str, Json)>>(members: I) -> Json {
+        Json::Object(members.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+    }
+
+    /// Creates a string value
+    pub(crate) fn string(s: &str) -> Json {
+        Json::String(s.to_string())
+    }
+
+    /// Returns the member of an object
+    pub(crate) fn get(&self, key: &str) -> Option<&Json> {
+        match *self {
+            Json::Object(ref members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn get_mut(&mut self, key: &str) -> Option<&mut Json> {
+        match *self {
+            Json::Object(ref mut members) => members.iter_mut().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Replaces the member of an object, or appends it if there is none
+    pub(crate) fn set(&mut self, key: &str, value: Json) {
+        if let Json::Object(ref mut members) = *self {
+            match members.iter_mut().find(|(k, _)| k == key) {
+                Some(member) => member.1 = value,
+                None => members.push((key.to_string(), value)),
+            }
+        }
+    }
+
+    pub(crate) fn remove(&mut self, key: &str) {
+        if let Json::Object(ref mut members) = *self {
+            members.retain(|(k, _)| k != key);
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> Option<&str> {
+        match *self {
+            Json::String(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Json::Number(n) | Json::ParsedNumber(n, _) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_array(&self) -> Option<&[Json]> {
+        match *self {
+            Json::Array(ref values) => Some(values),
+            _ => None,
+        }
+    }
+
+    /// Writes the value with two spaces of indentation per level, like PROJ does
+    pub(crate) fn to_pretty_string(&self) -> String {
+        let mut out = String::new();
+        self.write_pretty(&mut out, 0);
+        out
+    }
+
+    fn write_pretty(&self, out: &mut String, indent: usize) {
+        let newline = |out: &mut String, indent: usize| {
+            out.push('\n');
+            out.extend(std::iter::repeat_n(' ', indent));
+        };
+
+        match *self {
+            Json::Array(ref values) if !values.is_empty() => {
+                out.push('[');
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 { out.push(','); }
+                    newline(out, indent + 2);
+                    value.write_pretty(out, indent + 2);
+                }
+                newline(out, indent);
+                out.push(']');
+            },
+            Json::Object(ref members) if !members.is_empty() => {
+                out.push('{');
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i > 0 { out.push(','); }
+                    newline(out, indent + 2);
+                    write_string(out, key);
+                    out.push_str(": ");
+                    value.write_pretty(out, indent + 2);
+                }
+                newline(out, indent);
+                out.push('}');
+            },
+            _ => out.push_str(&self.to_string()),
+        }
+    }
+}
+
+fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Compact JSON without whitespace
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(b) => write!(f, "{}", b),
+            // JSON has no NaN or infinity, avoid "-0"
+            Json::Number(n) if !n.is_finite() => write!(f, "null"),
+            Json::Number(0.0) => write!(f, "0"),
+            // exponents for very small and large numbers, e.g. "4.84813681109536e-6"
+            Json::Number(n) if n.abs() < 1e-5 || n.abs() >= 1e21 => write!(f, "{:e}", n),
+            Json::Number(n) => write!(f, "{}", n),
+            Json::ParsedNumber(_, ref text) => write!(f, "{}", text),
+            Json::String(ref s) => {
+                let mut out = String::new();
+                write_string(&mut out, s);
+                write!(f, "{}", out)
+            },
+            Json::Array(ref values) => {
+                write!(f, "[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 { write!(f, ",")?; }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, "]")
+            },
+            Json::Object(ref members) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i > 0 { write!(f, ",")?; }
+                    write!(f, "{}:{}", Json::String(key.clone()), value)?;
+                }
+                write!(f, "}}")
+            },
+        }
+    }
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    /// Number of arrays and objects that enclose the position
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.position).cloned()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|b| b == b' ' || b == b'\t' || b == b'\n' || b == b'\r') {
+            self.position += 1;
+        }
+    }
+
+    fn expect(&mut self, literal: &str) -> Result<(), usize> {
+        if self.bytes[self.position..].starts_with(literal.as_bytes()) {
+            self.position += literal.len();
+            Ok(())
+        } else {
+            Err(self.position)
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, usize> {
+        match self.peek() {
+            Some(b'{') | Some(b'[') if self.depth == MAX_DEPTH => Err(self.position),
+            Some(b'{') => self.nested(Parser::object),
+            Some(b'[') => self.nested(Parser::array),
+            Some(b'"') => Ok(Json::String(self.string()?)),
+            Some(b't') => self.expect("true").map(|_| Json::Bool(true)),
+            Some(b'f') => self.expect("false").map(|_| Json::Bool(false)),
+            Some(b'n') => self.expect("null").map(|_| Json::Null),
+            Some(b'-') | Some(b'0'..=b'9') => self.number(),
+            _ => Err(self.position),
+        }
+    }
+
+    fn nested(&mut self, parse: fn(&mut Self) -> Result<Json, usize>) -> Result<Json, usize> {
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn object(&mut self) -> Result<Json, usize> {
+        self.position += 1;
+        let mut members = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.position += 1;
+            return Ok(Json::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some(b'"') {
+                return Err(self.position);
+            }
+            let key = self.string()?;
+            self.skip_whitespace();
+            self.expect(":")?;
+            self.skip_whitespace();
+            members.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.position += 1,
+                Some(b'}') => { self.position += 1; return Ok(Json::Object(members)); },
+                _ => return Err(self.position),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, usize> {
+        self.position += 1;
+        let mut values = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.position += 1;
+            return Ok(Json::Array(values));
+        }
+        loop {
+            self.skip_whitespace();
+            values.push(self.value()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.position += 1,
+                Some(b']') => { self.position += 1; return Ok(Json::Array(values)); },
+                _ => return Err(self.position),
+            }
+        }
+    }
+
+    fn hex4(&mut self) -> Result<u32, usize> {
+        let digits = self.bytes.get(self.position..self.position + 4).ok_or(self.position)?;
+        let digits = std::str::from_utf8(digits).map_err(|_| self.position)?;
+        let value = u32::from_str_radix(digits, 16).map_err(|_| self.position)?;
+        self.position += 4;
+        Ok(value)
+    }
+
+    fn string(&mut self) -> Result<String, usize> {
+        self.position += 1;
+        let mut s = String::new();
+        loop {
+            let start = self.position;
+            while self.peek().is_some_and(|b| b != b'"' && b != b'\\' && b >= 0x20) {
+                self.position += 1;
+            }
+            // the input is a &str and the run ends at an ASCII byte, so this is valid UTF-8
+            s.push_str(std::str::from_utf8(&self.bytes[start..self.position]).map_err(|_| start)?);
+
+            match self.peek() {
+                Some(b'"') => { self.position += 1; return Ok(s); },
+                Some(b'\\') => {
+                    self.position += 1;
+                    let escape = self.peek().ok_or(self.position)?;
+                    self.position += 1;
+                    match escape {
+                        b'"' => s.push('"'),
+                        b'\\' => s.push('\\'),
+                        b'/' => s.push('/'),
+                        b'b' => s.push('\u{8}'),
+                        b'f' => s.push('\u{c}'),
+                        b'n' => s.push('\n'),
+                        b'r' => s.push('\r'),
+                        b't' => s.push('\t'),
+                        b'u' => {
+                            let position = self.position;
+                            let mut code = self.hex4()?;
+                            // surrogate pair
+                            if (0xD800..0xDC00).contains(&code) {
+                                self.expect("\\u")?;
+                                let low = self.hex4()?;
+                                if !(0xDC00..0xE000).contains(&low) {
+                                    return Err(position);
+                                }
+                                code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                            }
+                            s.push(std::char::from_u32(code).ok_or(position)?);
+                        },
+                        _ => return Err(self.position - 1),
+                    }
+                },
+                _ => return Err(self.position),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Json, usize> {
+        let start = self.position;
+        let digits = |parser: &mut Parser| {
+            let start = parser.position;
+            while parser.peek().is_some_and(|b| b.is_ascii_digit()) {
+                parser.position += 1;
+            }
+            parser.position > start
+        };
+
+        if self.peek() == Some(b'-') {
+            self.position += 1;
+        }
+        if !digits(self) {
+            return Err(self.position);
+        }
+        if self.peek() == Some(b'.') {
+            self.position += 1;
+            if !digits(self) {
+                return Err(self.position);
+            }
+        }
+        if self.peek().is_some_and(|b| b == b'e' || b == b'E') {
+            self.position += 1;
+            if self.peek().is_some_and(|b| b == b'+' || b == b'-') {
+                self.position += 1;
+            }
+            if !digits(self) {
+                return Err(self.position);
+            }
+        }
+
+        // numbers that overflow to infinity, e.g. "1e400", are invalid
+        let text = std::str::from_utf8(&self.bytes[start..self.position]).map_err(|_| start)?;
+        text.parse().ok()
+            .filter(|n: &f64| n.is_finite())
+            .map(|n| Json::ParsedNumber(n, text.to_string()))
+            .ok_or(start)
+    }
+}
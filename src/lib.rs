@@ -41,6 +41,7 @@
 //!             data: vec![(377299.0, 1483035.0)],
 //!             crs: Box::new(UTMSystem {
 //!                 utm_zone: 48,
+//!                 hemisphere: Hemisphere::North,
 //!             }),
 //!             ellipsoid: ellipsoid,
 //!         }
@@ -134,11 +135,31 @@ extern crate scoped_threadpool;
 // mod math;
 
 mod coordinate_systems;
+mod grid_references;
 mod coordinate_buf;
 mod traits;
 mod lonlat_buf;
 mod ellipsoid;
+mod datum;
 mod multithreading;
+mod json;
+
+pub mod geohash;
+pub mod tiles;
+pub mod plus_codes;
+pub mod maidenhead;
+pub mod s2;
+pub mod h3;
+pub mod proj_string;
+pub mod wkt;
+pub mod epsg;
+pub mod projjson;
+pub mod pipeline;
+pub mod geojson;
+pub mod wkb;
+pub mod csv;
+pub mod dms;
+pub mod shapefile;
 
 pub use traits::{
     ToLonLat,
@@ -151,19 +172,82 @@ pub use scoped_threadpool::Pool as ThreadPool;
 
 pub use multithreading::MultithreadingStrategy;
 pub use ellipsoid::*;
+pub use datum::*;
 pub use lonlat_buf::LonLatBuf;
 pub use coordinate_buf::CoordinateBuf;
 
 pub mod crs {
+    pub use coordinate_systems::longlat::LonLatSystem as LonLatSystem;
+    pub use coordinate_systems::adjusted::AdjustedSystem as AdjustedSystem;
+    pub use coordinate_systems::adjusted::AxisDirection as AxisDirection;
     pub use coordinate_systems::merc::MercatorSystem as MercatorSystem;
+    pub use coordinate_systems::merc::WebMercatorSystem as WebMercatorSystem;
     pub use coordinate_systems::utm::UTMSystem as UTMSystem;
+    pub use coordinate_systems::utm::AutoUTMSystem as AutoUTMSystem;
+    pub use coordinate_systems::utm::Hemisphere as Hemisphere;
+    pub use coordinate_systems::utm::UtmZone as UtmZone;
+    pub use coordinate_systems::utm::UtmZoneError as UtmZoneError;
+    pub use coordinate_systems::ups::UPSSystem as UPSSystem;
+    pub use coordinate_systems::tmerc::TransverseMercatorSystem as TransverseMercatorSystem;
+    pub use coordinate_systems::tmerc::{BRITISH_NATIONAL_GRID, IRISH_GRID};
+    pub use coordinate_systems::geos::GeostationarySystem as GeostationarySystem;
+    pub use coordinate_systems::geos::SweepAxis as SweepAxis;
+    pub use coordinate_systems::krovak::KrovakSystem as KrovakSystem;
+    pub use coordinate_systems::krovak::KrovakVariant as KrovakVariant;
+    pub use coordinate_systems::krovak::KrovakAxisOrder as KrovakAxisOrder;
+    pub use coordinate_systems::igh::GoodeHomolosineSystem as GoodeHomolosineSystem;
+    pub use coordinate_systems::igh::utils::GOODE_HOMOLOSINE_LOBES as GOODE_HOMOLOSINE_LOBES;
+    pub use coordinate_systems::lcc::LambertConformalConicSystem as LambertConformalConicSystem;
+    pub use coordinate_systems::lcc::{LAMBERT_93, CC42, CC43, CC44, CC45, CC46, CC47, CC48, CC49, CC50};
+    pub use coordinate_systems::lcc::CONIQUE_CONFORME_ZONES as CONIQUE_CONFORME_ZONES;
+    pub use coordinate_systems::healpix::HealpixSystem as HealpixSystem;
+    pub use coordinate_systems::healpix::RHealpixSystem as RHealpixSystem;
     // other coordinate systems go here
 
     // utility functions, specific to certain coordinate systems
     pub mod utils {
+        pub mod adjusted {
+            pub use coordinate_systems::adjusted::utils::*;
+        }
+        pub mod merc {
+            pub use coordinate_systems::merc::utils::*;
+        }
         pub mod utm {
             pub use coordinate_systems::utm::utils::*;
         }
+        pub mod ups {
+            pub use coordinate_systems::ups::utils::*;
+        }
+        pub mod mgrs {
+            pub use grid_references::mgrs::*;
+        }
+        pub mod gars {
+            pub use grid_references::gars::*;
+        }
+        pub mod georef {
+            pub use grid_references::georef::*;
+        }
+        pub mod tmerc {
+            pub use coordinate_systems::tmerc::utils::*;
+        }
+        pub mod os_grid {
+            pub use grid_references::os_grid::*;
+        }
+        pub mod geos {
+            pub use coordinate_systems::geos::utils::*;
+        }
+        pub mod krovak {
+            pub use coordinate_systems::krovak::utils::*;
+        }
+        pub mod igh {
+            pub use coordinate_systems::igh::utils::*;
+        }
+        pub mod lcc {
+            pub use coordinate_systems::lcc::utils::*;
+        }
+        pub mod healpix {
+            pub use coordinate_systems::healpix::utils::*;
+        }
     }
 }
 
@@ -173,6 +257,7 @@ pub mod prelude {
     pub use lonlat_buf::LonLatBuf;
     pub use coordinate_buf::CoordinateBuf;
     pub use ellipsoid::*;
+    pub use datum::*;
     pub use crs::*;
     #[cfg(all(not(target_arch = "wasm32"), feature = "scoped_threadpool"))]
     pub use ThreadPool;
@@ -197,15 +282,36 @@ pub mod wasm_reexport {
     //! called from C or WASM.
 
     use prelude::*;
+    use coordinate_systems::utm::utils::{get_utm_system, lonlat_to_utm_xy};
 
     const ELLIPSOID: Ellipsoid = WGS_1984_ELLIPSOID;
 
     /// Reprojects from lon-lat (warning: not latlon, note the order!)
     /// to UTM using the `WGS_1984_ELLIPSOID`.
+    ///
+    /// The UTM zone is negative for the southern hemisphere, the same as
+    /// the zones returned by `lonlat_to_utm_auto`.
     #[no_mangle]
-    pub fn lonlat_to_utm(data: Vec<(f64, f64)>, target_utm_zone: u8) -> Vec<(f64, f64)> {
-        let crs = Box::new(UTMSystem { utm_zone: target_utm_zone });
-        lonlat_to_crs_inner(data, crs)
+    pub fn lonlat_to_utm(data: Vec<(f64, f64)>, target_utm_zone: i8) -> Vec<(f64, f64)> {
+        lonlat_to_crs_inner(data, Box::new(utm_system(target_utm_zone)))
+    }
+
+    /// Reprojects from lon-lat (warning: not latlon, note the order!)
+    /// to UTM, choosing the UTM zone of each point automatically.
+    ///
+    /// Returns (Easting, Northing, UTM zone) per point. The UTM zone is
+    /// negative for points on the southern hemisphere.
+    #[no_mangle]
+    pub fn lonlat_to_utm_auto(data: Vec<(f64, f64)>) -> Vec<(f64, f64, i8)> {
+        data.into_iter().map(|(lon, lat)| {
+            let system = get_utm_system(lon, lat);
+            let (x, y) = lonlat_to_utm_xy(lon, lat, &ELLIPSOID, system.utm_zone, system.hemisphere);
+            let zone = match system.hemisphere {
+                Hemisphere::North => system.utm_zone as i8,
+                Hemisphere::South => -(system.utm_zone as i8),
+            };
+            (x, y, zone)
+        }).collect()
     }
 
     /// Reprojects from lon-lat (warning: not latlon, note the order!) to Mercator
@@ -217,10 +323,11 @@ pub mod wasm_reexport {
 
     /// Reprojects from UTM (Easting, Northing, note the order!) and a given UTM zone
     /// to lat-lon coordinates.
+    ///
+    /// The UTM zone is negative for the southern hemisphere.
     #[no_mangle]
-    pub fn utm_to_lonlat(data: Vec<(f64, f64)>, target_utm_zone: u8) -> Vec<(f64, f64)> {
-        let crs = Box::new(UTMSystem { utm_zone: target_utm_zone });
-        crs_to_lonlat_inner(data, crs)
+    pub fn utm_to_lonlat(data: Vec<(f64, f64)>, target_utm_zone: i8) -> Vec<(f64, f64)> {
+        crs_to_lonlat_inner(data, Box::new(utm_system(target_utm_zone)))
     }
 
     /// Reprojects from Mercator (Easting, Northing, note the order!) to lat-lon coordinates
@@ -230,21 +337,40 @@ pub mod wasm_reexport {
         crs_to_lonlat_inner(data, crs)
     }
 
+    /// Reads one latitude / longitude pair per line, in degrees, minutes and
+    /// seconds or decimal degrees (see `dms::parse_coordinate`), and returns
+    /// them as lon-lat. Lines that can't be read result in `(NaN, NaN)`.
+    #[no_mangle]
+    pub fn dms_to_lonlat(text: String) -> Vec<(f64, f64)> {
+        text.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| ::dms::parse_coordinate(line).unwrap_or((f64::NAN, f64::NAN)))
+            .collect()
+    }
+
+    // Zone number and hemisphere from a signed UTM zone
+    fn utm_system(utm_zone: i8) -> UTMSystem {
+        UTMSystem {
+            utm_zone: utm_zone.unsigned_abs(),
+            hemisphere: if utm_zone < 0 { Hemisphere::South } else { Hemisphere::North },
+        }
+    }
+
     // Rust-only since it uses the
-    fn lonlat_to_crs_inner(data: Vec<(f64, f64)>, crs: Box<Crs>) -> Vec<(f64, f64)> {
+    fn lonlat_to_crs_inner(data: Vec<(f64, f64)>, crs: Box<dyn Crs>) -> Vec<(f64, f64)> {
         let source_len = data.len();
         let mut strategy = MultithreadingStrategy::SingleCore;
 
         let source = CoordinateSource::LonLatBuf(Box::new(
             LonLatBuf {
-                data: data,
+                data,
                 ellipsoid: ELLIPSOID,
             }));
 
         let mut target = CoordinateSource::CoordinateBuf(Box::new(
             CoordinateBuf {
                 data: Vec::with_capacity(source_len),
-                crs: crs,
+                crs,
                 ellipsoid: ELLIPSOID,
             }));
 
@@ -252,7 +378,7 @@ pub mod wasm_reexport {
         target.into_data()
     }
 
-    fn crs_to_lonlat_inner(data: Vec<(f64, f64)>, crs: Box<Crs>) -> Vec<(f64, f64)> {
+    fn crs_to_lonlat_inner(data: Vec<(f64, f64)>, crs: Box<dyn Crs>) -> Vec<(f64, f64)> {
         let source_len = data.len();
         let mut strategy = MultithreadingStrategy::SingleCore;
 
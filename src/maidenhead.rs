@@ -0,0 +1,363 @@
+//! Maidenhead locator system (QTH locators)
+//!
+//! Implementation based on:
+//!
+//! IARU Region 1, VHF Managers Handbook, Maidenhead Locator System
+//!
+//! A locator such as "JN58td" consists of pairs of longitude / latitude
+//! characters, each pair dividing the previous cell further:
+//!
+//! - field: letters A to R (20° x 10°)
+//! - square: digits 0 to 9 (2° x 1°)
+//! - subsquare: letters a to x (5' x 2.5')
+//! - extended square: digits 0 to 9 (30" x 15")
+//! - extended subsquare: letters a to x (1.25" x 0.625")
+//!
+//! Bounding boxes are `(min_lon, min_lat, max_lon, max_lat)` in degrees.
+
+use prelude::*;
+use std::error::Error;
+use std::fmt;
+
+/// Maximum number of character pairs of a locator
+pub const MAX_PAIRS: usize = 5;
+
+/// Error that can occur when encoding, decoding or shortening a locator
+#[derive(Debug, Clone, PartialEq)]
+pub enum MaidenheadError {
+    /// Number of pairs must be between 1 and `MAX_PAIRS`
+    InvalidPrecision(usize),
+    /// Longitude or latitude isn't a valid number
+    InvalidCoordinate(f64, f64),
+    /// The locator is malformed
+    InvalidLocator(String),
+}
+
+impl fmt::Display for MaidenheadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::MaidenheadError::*;
+        match *self {
+            InvalidPrecision(pairs) => write!(f, "invalid locator precision: {} pairs (must be 1 to {})", pairs, MAX_PAIRS),
+            InvalidCoordinate(lon, lat) => write!(f, "invalid coordinate: ({}, {})", lon, lat),
+            InvalidLocator(ref s) => write!(f, "invalid Maidenhead locator: \"{}\"", s),
+        }
+    }
+}
+
+impl Error for MaidenheadError { }
+
+/// Number of divisions of the pair at the given index (0 = field)
+#[inline]
+fn divisions(pair: usize) -> u32 {
+    match pair {
+        0 => 18,
+        _ if pair % 2 == 1 => 10,
+        _ => 24,
+    }
+}
+
+/// Size of a cell with the given number of pairs, in degrees (width, height)
+pub fn cell_size(pairs: usize)
+                 -> (f64, f64)
+{
+    let mut size = (360.0, 180.0);
+
+    for pair in 0..pairs {
+        let n = f64::from(divisions(pair));
+        size = (size.0 / n, size.1 / n);
+    }
+
+    size
+}
+
+/// Character for the value of a pair at the given index
+#[inline]
+fn character(pair: usize, value: u32) -> char {
+    match pair {
+        0 => (b'A' + value as u8) as char,
+        _ if pair % 2 == 1 => (b'0' + value as u8) as char,
+        _ => (b'a' + value as u8) as char,
+    }
+}
+
+/// Value of a character of a pair at the given index
+#[inline]
+fn value(pair: usize, c: char) -> Option<u32> {
+    let value = match pair {
+        _ if pair % 2 == 1 => c.to_digit(10)?,
+        _ if c.is_ascii_alphabetic() => u32::from(c.to_ascii_uppercase() as u8 - b'A'),
+        _ => return None,
+    };
+
+    if value < divisions(pair) { Some(value) } else { None }
+}
+
+/// Encodes a lon / lat coordinate (in degrees) into a locator with
+/// `pairs` character pairs (1 = field, 2 = square, 3 = subsquare, ...)
+///
+/// Longitudes are normalized to [-180, 180).
+pub fn encode(lon: f64, lat: f64, pairs: usize)
+              -> Result<String, MaidenheadError>
+{
+    if pairs == 0 || pairs > MAX_PAIRS {
+        return Err(MaidenheadError::InvalidPrecision(pairs));
+    }
+
+    if !lon.is_finite() || !lat.is_finite() || !(-90.0..=90.0).contains(&lat) {
+        return Err(MaidenheadError::InvalidCoordinate(lon, lat));
+    }
+
+    let mut lon = (lon + 180.0).rem_euclid(360.0);
+    let mut lat = lat + 90.0;
+    let (mut width, mut height) = (360.0, 180.0);
+    let mut locator = String::with_capacity(pairs * 2);
+
+    for pair in 0..pairs {
+        let n = divisions(pair);
+        width /= f64::from(n);
+        height /= f64::from(n);
+
+        // Clamp, so that 90°N falls into the northernmost cell
+        let x = ((lon / width).floor() as u32).min(n - 1);
+        let y = ((lat / height).floor() as u32).min(n - 1);
+
+        locator.push(character(pair, x));
+        locator.push(character(pair, y));
+
+        lon -= f64::from(x) * width;
+        lat -= f64::from(y) * height;
+    }
+
+    Ok(locator)
+}
+
+/// Decodes a locator into the bounding box of its cell
+///
+/// Both uppercase and lowercase letters are accepted.
+pub fn decode_bbox(locator: &str)
+                   -> Result<(f64, f64, f64, f64), MaidenheadError>
+{
+    let invalid = || MaidenheadError::InvalidLocator(locator.to_string());
+    let chars: Vec<char> = locator.trim().chars().collect();
+
+    if chars.is_empty() || chars.len() % 2 == 1 || chars.len() > MAX_PAIRS * 2 {
+        return Err(invalid());
+    }
+
+    let (mut lon, mut lat) = (-180.0, -90.0);
+    let (mut width, mut height) = (360.0, 180.0);
+
+    for (pair, c) in chars.chunks(2).enumerate() {
+        let n = f64::from(divisions(pair));
+        width /= n;
+        height /= n;
+        lon += f64::from(value(pair, c[0]).ok_or_else(invalid)?) * width;
+        lat += f64::from(value(pair, c[1]).ok_or_else(invalid)?) * height;
+    }
+
+    Ok((lon, lat, lon + width, lat + height))
+}
+
+/// Decodes a locator into the center of its cell (lon / lat, in degrees)
+pub fn decode(locator: &str)
+              -> Result<(f64, f64), MaidenheadError>
+{
+    let (min_lon, min_lat, max_lon, max_lat) = decode_bbox(locator)?;
+    Ok(((min_lon + max_lon) / 2.0, (min_lat + max_lat) / 2.0))
+}
+
+/// Area of the cell of a locator on the given ellipsoid, in square meters
+pub fn cell_area(locator: &str, ellipsoid: &Ellipsoid)
+                 -> Result<f64, MaidenheadError>
+{
+    let (min_lon, min_lat, max_lon, max_lat) = decode_bbox(locator)?;
+    Ok(ellipsoid.lonlat_rectangle_area(min_lon, min_lat, max_lon, max_lat))
+}
+
+/// Removes the leading pairs that a locator shares with the locator of the
+/// reference location (in degrees), keeping at least the last pair
+///
+/// For example "JN58te" is shortened to "te" near "JN58td".
+pub fn shorten(locator: &str, ref_lon: f64, ref_lat: f64)
+               -> Result<String, MaidenheadError>
+{
+    let (lon, lat) = decode(locator)?;
+    let pairs = locator.trim().len() / 2;
+    let locator = encode(lon, lat, pairs)?;
+    let reference = encode(ref_lon, ref_lat, pairs)?;
+
+    let shared = (0..pairs - 1)
+        .take_while(|&pair| locator[pair * 2..pair * 2 + 2] == reference[pair * 2..pair * 2 + 2])
+        .count();
+
+    Ok(locator[shared * 2..].to_string())
+}
+
+/// Recovers a full locator with `pairs` character pairs from a locator that was
+/// shortened with `shorten`, choosing the cell that is nearest to the
+/// reference location (in degrees)
+pub fn recover_nearest(short_locator: &str, ref_lon: f64, ref_lat: f64, pairs: usize)
+                       -> Result<String, MaidenheadError>
+{
+    let short_locator = short_locator.trim();
+    let short_pairs = short_locator.len() / 2;
+
+    if short_locator.is_empty() || short_locator.len() % 2 == 1 || short_pairs > pairs {
+        return Err(MaidenheadError::InvalidLocator(short_locator.to_string()));
+    }
+
+    let removed_pairs = pairs - short_pairs;
+    let reference = encode(ref_lon, ref_lat, pairs)?;
+    let (mut lon, mut lat) = decode(&format!("{}{}", &reference[..removed_pairs * 2], short_locator))?;
+
+    if removed_pairs == 0 {
+        return encode(lon, lat, pairs);
+    }
+
+    // Move by one cell of the removed pairs if the reference is closer to the neighbouring cell
+    let (width, height) = cell_size(removed_pairs);
+
+    if ref_lat + height / 2.0 < lat && lat - height >= -90.0 {
+        lat -= height;
+    } else if ref_lat - height / 2.0 > lat && lat + height <= 90.0 {
+        lat += height;
+    }
+
+    if ref_lon + width / 2.0 < lon {
+        lon -= width;
+    } else if ref_lon - width / 2.0 > lon {
+        lon += width;
+    }
+
+    encode(lon, lat, pairs)
+}
+
+/// Encodes all coordinates of a `LonLatBuf` into locators with `pairs` character pairs
+///
+/// Invalid coordinates are returned as `None`.
+pub fn encode_lonlat_buf(buf: &LonLatBuf, pairs: usize, strategy: &mut MultithreadingStrategy)
+                         -> Result<Vec<Option<String>>, MaidenheadError>
+{
+    if pairs == 0 || pairs > MAX_PAIRS {
+        return Err(MaidenheadError::InvalidPrecision(pairs));
+    }
+
+    let mut locators = vec![None; buf.data.len()];
+
+    match *strategy {
+        SingleCore => {
+            for (&(lon, lat), locator) in buf.data.iter().zip(locators.iter_mut()) {
+                *locator = encode(lon, lat, pairs).ok();
+            }
+        },
+        #[cfg(all(not(target_arch = "wasm32"), feature = "scoped_threadpool"))]
+        MultiCore(ref mut thread_pool) => {
+            thread_pool.scoped(|scoped| {
+                for (&(lon, lat), locator) in buf.data.iter().zip(locators.iter_mut()) {
+                    scoped.execute(move || {
+                        *locator = encode(lon, lat, pairs).ok();
+                    });
+                }
+            });
+        },
+    }
+
+    Ok(locators)
+}
+
+/// Decodes locators into a `LonLatBuf` with the center of each cell
+///
+/// Invalid locators are decoded to (NaN, NaN).
+pub fn decode_to_lonlat_buf<S: AsRef<str> + Sync>(locators: &[S], ellipsoid: &Ellipsoid, strategy: &mut MultithreadingStrategy)
+                                                  -> LonLatBuf
+{
+    let mut data = vec![(f64::NAN, f64::NAN); locators.len()];
+
+    match *strategy {
+        SingleCore => {
+            for (locator, coordinate) in locators.iter().zip(data.iter_mut()) {
+                *coordinate = decode(locator.as_ref()).unwrap_or((f64::NAN, f64::NAN));
+            }
+        },
+        #[cfg(all(not(target_arch = "wasm32"), feature = "scoped_threadpool"))]
+        MultiCore(ref mut thread_pool) => {
+            thread_pool.scoped(|scoped| {
+                for (locator, coordinate) in locators.iter().zip(data.iter_mut()) {
+                    scoped.execute(move || {
+                        *coordinate = decode(locator.as_ref()).unwrap_or((f64::NAN, f64::NAN));
+                    });
+                }
+            });
+        },
+    }
+
+    LonLatBuf {
+        data,
+        ellipsoid: *ellipsoid,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOCATORS: [(f64, f64, &str); 6] = [
+        (11.575, 48.137, "JN58sd82xv"),
+        (-72.727, 41.714, "FN31pr21si"),
+        (151.2093, -33.8688, "QF56od51cl"),
+        (-0.1276, 51.5072, "IO91wm41qr"),
+        (179.99, 89.99, "RR99xx87to"),
+        (-180.0, -90.0, "AA00aa00aa"),
+    ];
+
+    #[test]
+    fn encode_all_precisions() {
+        for &(lon, lat, locator) in &LOCATORS {
+            for pairs in 1..=MAX_PAIRS {
+                assert_eq!(encode(lon, lat, pairs).unwrap(), &locator[..2 * pairs]);
+            }
+        }
+        assert_eq!(encode(180.0, 90.0, 3).unwrap(), "AR09ax");
+        assert_eq!(encode(-348.425, 48.137, 3).unwrap(), "JN58sd");
+        assert_eq!(encode(191.575, 48.137, 3).unwrap(), "AN58sd");
+    }
+
+    #[test]
+    fn decode_jn58sc() {
+        let (min_lon, min_lat, max_lon, max_lat) = decode_bbox("JN58sc").unwrap();
+        assert!((min_lon - 11.5).abs() < 1e-12 && (max_lon - (11.5 + 5.0 / 60.0)).abs() < 1e-12);
+        assert!((min_lat - (48.0 + 5.0 / 60.0)).abs() < 1e-12 && (max_lat - 48.125).abs() < 1e-12);
+
+        let (lon, lat) = decode("jn58SC").unwrap();
+        assert!((lon - 11.541_666_666_666_666).abs() < 1e-12 && (lat - 48.104_166_666_666_67).abs() < 1e-12);
+        assert_eq!(encode(lon, lat, 3).unwrap(), "JN58sc");
+
+        assert_eq!(cell_size(3), (5.0 / 60.0, 2.5 / 60.0));
+        let area = cell_area("JN58sc", &WGS_1984_ELLIPSOID).unwrap();
+        assert!(area > 28e6 && area < 29e6, "{}", area);
+    }
+
+    #[test]
+    fn shorten_and_recover() {
+        assert_eq!(shorten("JN58te", 11.575, 48.137).unwrap(), "te");
+        assert_eq!(shorten("JN58te", 13.0, 48.137).unwrap(), "58te");
+        assert_eq!(shorten("JN58sd", 11.575, 48.137).unwrap(), "sd");
+        assert_eq!(recover_nearest("te", 11.575, 48.137, 3).unwrap(), "JN58te");
+
+        // the nearest cell is in the neighbouring square
+        assert_eq!(recover_nearest("aa", 11.99, 48.5, 3).unwrap(), "JN68aa");
+        assert_eq!(recover_nearest("xx", 12.01, 48.01, 3).unwrap(), "JN57xx");
+        assert_eq!(recover_nearest("JN58sc", 0.0, 0.0, 3).unwrap(), "JN58sc");
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(encode(0.0, 0.0, 0), Err(MaidenheadError::InvalidPrecision(0)));
+        assert_eq!(encode(0.0, 0.0, 6), Err(MaidenheadError::InvalidPrecision(6)));
+        assert_eq!(encode(0.0, 90.5, 3), Err(MaidenheadError::InvalidCoordinate(0.0, 90.5)));
+        for locator in &["", "J", "JN5", "SN58", "JNA8", "JN58yc", "JN58sc8a", "JN58sc82xv00"] {
+            assert_eq!(decode(locator), Err(MaidenheadError::InvalidLocator(locator.to_string())));
+        }
+        assert_eq!(recover_nearest("JN58sc", 0.0, 0.0, 2), Err(MaidenheadError::InvalidLocator("JN58sc".to_string())));
+    }
+}
@@ -0,0 +1,592 @@
+//! PROJ pipelines, such as `+proj=pipeline +step +proj=unitconvert +xy_in=deg +xy_out=rad +step +proj=utm +zone=32`
+//!
+//! A `Pipeline` is an ordered list of steps, each of which runs its operation
+//! forward or, with `+inv`, backward. Unlike `CoordinateSource::project`, which
+//! always goes through longitude / latitude on a single ellipsoid, a pipeline can
+//! chain projections, datum shifts, axis swaps and unit conversions in any order.
+//!
+//! Supported operations (`+proj=...`):
+//!
+//! - every projection of `parse_proj_string`, with its ellipsoid and units
+//! - `cart`: geodetic to geocentric coordinates, on the ellipsoid of `+ellps`, `+datum`, `+a`, ...
+//! - `helmert` (`+x`, `+y`, `+z`, `+rx`, `+ry`, `+rz`, `+s`, `+convention`): seven-parameter
+//!   transformation of geocentric coordinates, with small-angle rotations
+//! - `affine` (`+xoff`, `+yoff`, `+zoff`, `+s11` ... `+s33`)
+//! - `axisswap` (`+order`), read as an affine transformation
+//! - `unitconvert` (`+xy_in`, `+xy_out`, `+z_in`, `+z_out`)
+//! - `noop`, which is dropped
+//!
+//! As in PROJ, angular coordinates are in radians between the steps and at both
+//! ends of the pipeline, a `unitconvert` step with `+xy_in=deg +xy_out=rad` lets
+//! the pipeline take degrees. Parameters before the first `+step` apply to every
+//! projection and `cart` step that doesn't set them itself.
+//!
+//! ```rust
+//! extern crate proj5;
+//!
+//! use proj5::prelude::*;
+//! use proj5::pipeline::Pipeline;
+//!
+//! fn main() {
+//!     let pipeline: Pipeline = "+proj=pipeline +ellps=WGS84 \
+//!                               +step +proj=unitconvert +xy_in=deg +xy_out=rad \
+//!                               +step +proj=utm +zone=32".parse().unwrap();
+//!
+//!     let mut strategy = MultithreadingStrategy::SingleCore;
+//!     let projected = pipeline.transform(vec![(9.0, 48.0)], &mut strategy);
+//!     assert!((projected[0].0 - 500_000.0).abs() < 1e-6);
+//!
+//!     let lonlat = pipeline.inverse().transform(projected, &mut strategy);
+//!     assert!((lonlat[0].0 - 9.0).abs() < 1e-9 && (lonlat[0].1 - 48.0).abs() < 1e-9);
+//!
+//!     // datum shift from OSGB36 to WGS84
+//!     let shift: Pipeline = "+proj=pipeline \
+//!                            +step +proj=cart +ellps=airy \
+//!                            +step +proj=helmert +x=446.448 +y=-125.157 +z=542.06 \
+//!                                  +rx=0.15 +ry=0.247 +rz=0.842 +s=-20.489 +convention=position_vector \
+//!                            +step +inv +proj=cart +ellps=WGS84".parse().unwrap();
+//!     let shifted = shift.transform_3d(vec![(-0.0236, 0.8990, 0.0)], &mut strategy);
+//!     let back = shift.inverse().transform_3d(shifted, &mut strategy);
+//!     assert!((back[0].0 + 0.0236).abs() < 1e-12 && (back[0].1 - 0.8990).abs() < 1e-12);
+//! }
+//! ```
+
+use prelude::*;
+use coordinate_systems::adjusted::AdjustedSystem;
+use proj_string::{self, Parameters, ProjStringError, UNITS};
+use wkt::ARC_SECOND;
+use std::f64::consts::PI;
+use std::str::FromStr;
+
+/// Parameters of `+proj=cart`: the ones that describe the ellipsoid
+const CART_KEYS: [&str; 8] = ["proj", "ellps", "datum", "a", "b", "rf", "f", "R"];
+
+const HELMERT_KEYS: [&str; 9] = ["proj", "x", "y", "z", "rx", "ry", "rz", "s", "convention"];
+
+const AFFINE_KEYS: [&str; 13] = [
+    "proj", "xoff", "yoff", "zoff", "s11", "s12", "s13", "s21", "s22", "s23", "s31", "s32", "s33",
+];
+
+const AXISSWAP_KEYS: [&str; 2] = ["proj", "order"];
+
+const UNITCONVERT_KEYS: [&str; 5] = ["proj", "xy_in", "xy_out", "z_in", "z_out"];
+
+/// Angular units of `+proj=unitconvert`, in radians
+const ANGULAR_UNITS: [(&str, f64); 3] = [
+    ("rad", 1.0),
+    ("deg", PI / 180.0),
+    ("grad", PI / 200.0),
+];
+
+const IDENTITY: [[f64; 3]; 3] = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+
+/// Ordered list of operations, see the module documentation
+#[derive(Debug, Clone, Default)]
+pub struct Pipeline {
+    pub steps: Vec<Step>,
+}
+
+/// An operation of a pipeline and its direction
+#[derive(Debug, Clone)]
+pub struct Step {
+    pub operation: Operation,
+    /// Whether the operation runs backward (`+inv`)
+    pub inverse: bool,
+}
+
+/// Operation of a pipeline step, described in the forward direction
+#[derive(Debug)]
+pub enum Operation {
+    /// Radians to the coordinates of the CRS. The output of geographic CRS is in radians, too.
+    Projection { crs: Box<dyn Crs>, ellipsoid: Ellipsoid },
+    /// Longitude, latitude (radians) and ellipsoidal height to geocentric X, Y, Z
+    Cartesian(Ellipsoid),
+    /// Helmert transformation of geocentric coordinates
+    Helmert(Helmert),
+    /// `offset + matrix * (x, y, z)`
+    Affine { offset: [f64; 3], matrix: [[f64; 3]; 3] },
+    /// Multiplies the horizontal and vertical coordinates with the ratio of the units
+    UnitConversion { xy_in: Unit, xy_out: Unit, z_in: f64, z_out: f64 },
+}
+
+impl Clone for Operation {
+    fn clone(&self) -> Self {
+        match *self {
+            Operation::Projection { ref crs, ellipsoid } => Operation::Projection { crs: Crs::clone(&**crs), ellipsoid },
+            Operation::Cartesian(ellipsoid) => Operation::Cartesian(ellipsoid),
+            Operation::Helmert(helmert) => Operation::Helmert(helmert),
+            Operation::Affine { offset, matrix } => Operation::Affine { offset, matrix },
+            Operation::UnitConversion { xy_in, xy_out, z_in, z_out } => Operation::UnitConversion { xy_in, xy_out, z_in, z_out },
+        }
+    }
+}
+
+/// Unit of the horizontal coordinates in a `unitconvert` step
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Unit {
+    /// Length unit, in meters
+    Linear(f64),
+    /// Angle unit, in radians
+    Angular(f64),
+}
+
+impl Pipeline {
+    /// Returns the pipeline that undoes this one: the steps in reverse order, each inverted
+    pub fn inverse(&self) -> Pipeline {
+        Pipeline {
+            steps: self.steps.iter().rev().map(|step| Step {
+                operation: step.operation.clone(),
+                inverse: !step.inverse,
+            }).collect(),
+        }
+    }
+
+    /// Runs the steps over horizontal coordinates, with a height of zero
+    pub fn transform(&self, data: Vec<(f64, f64)>, strategy: &mut MultithreadingStrategy)
+                     -> Vec<(f64, f64)>
+    {
+        let data = data.into_iter().map(|(x, y)| (x, y, 0.0)).collect();
+        self.transform_3d(data, strategy).into_iter().map(|(x, y, _)| (x, y)).collect()
+    }
+
+    /// Runs the steps over three-dimensional coordinates
+    pub fn transform_3d(&self, mut data: Vec<(f64, f64, f64)>, strategy: &mut MultithreadingStrategy)
+                        -> Vec<(f64, f64, f64)>
+    {
+        for step in &self.steps {
+            data = step.operation.apply(data, step.inverse, strategy);
+        }
+        data
+    }
+
+    /// Writes the pipeline as a PROJ string, `+proj=pipeline +step ... +step +inv ...`
+    pub fn to_proj_string(&self) -> Result<String, ProjStringError> {
+        let mut parts = vec!["+proj=pipeline".to_string()];
+        for step in &self.steps {
+            parts.push("+step".to_string());
+            if step.inverse {
+                parts.push("+inv".to_string());
+            }
+            parts.push(step.operation.to_proj_string()?);
+        }
+        Ok(parts.join(" "))
+    }
+}
+
+impl FromStr for Pipeline {
+    type Err = ProjStringError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_pipeline(s)
+    }
+}
+
+impl Operation {
+    fn apply(&self, mut data: Vec<(f64, f64, f64)>, inverse: bool, strategy: &mut MultithreadingStrategy)
+             -> Vec<(f64, f64, f64)>
+    {
+        match *self {
+            Operation::Projection { ref crs, ref ellipsoid } => {
+                let geographic = is_geographic(&**crs);
+                let to_degrees = |(x, y, _): (f64, f64, f64)| (x.to_degrees(), y.to_degrees());
+                let output = if !inverse {
+                    crs.from_lon_lat(data.iter().cloned().map(to_degrees).collect(), ellipsoid, strategy).data
+                } else if geographic {
+                    crs.to_lon_lat(data.iter().cloned().map(to_degrees).collect(), ellipsoid, strategy).data
+                } else {
+                    crs.to_lon_lat(data.iter().map(|&(x, y, _)| (x, y)).collect(), ellipsoid, strategy).data
+                };
+
+                let radians = inverse || geographic;
+                for (c, (x, y)) in data.iter_mut().zip(output) {
+                    *c = if radians { (x.to_radians(), y.to_radians(), c.2) } else { (x, y, c.2) };
+                }
+            },
+            Operation::Cartesian(ref ellipsoid) => {
+                let convert = if inverse { geocentric_to_geodetic } else { geodetic_to_geocentric };
+                for c in data.iter_mut() {
+                    *c = convert(ellipsoid, *c);
+                }
+            },
+            Operation::Helmert(ref helmert) => {
+                let (offset, matrix) = helmert_matrix(helmert);
+                apply_affine(&mut data, &offset, &matrix, inverse);
+            },
+            Operation::Affine { ref offset, ref matrix } => {
+                apply_affine(&mut data, offset, matrix, inverse);
+            },
+            Operation::UnitConversion { xy_in, xy_out, z_in, z_out } => {
+                let (xy_factor, z_factor) = match (xy_in, xy_out) {
+                    (Unit::Linear(a), Unit::Linear(b)) | (Unit::Angular(a), Unit::Angular(b)) => (a / b, z_in / z_out),
+                    // can only be constructed by hand, the parser rejects it
+                    _ => (f64::NAN, z_in / z_out),
+                };
+                let (xy_factor, z_factor) = if inverse { (1.0 / xy_factor, 1.0 / z_factor) } else { (xy_factor, z_factor) };
+                for c in data.iter_mut() {
+                    *c = (c.0 * xy_factor, c.1 * xy_factor, c.2 * z_factor);
+                }
+            },
+        }
+        data
+    }
+
+    fn to_proj_string(&self) -> Result<String, ProjStringError> {
+        let parts = match *self {
+            Operation::Projection { ref crs, ref ellipsoid } => {
+                let s = proj_string::to_proj_string(&**crs, ellipsoid, None)?;
+                return Ok(s.trim_end_matches(" +no_defs").to_string());
+            },
+            Operation::Cartesian(ref ellipsoid) => {
+                let s = proj_string::to_proj_string(&LonLatSystem, ellipsoid, None)?;
+                return Ok(s.trim_end_matches(" +no_defs").replacen("+proj=longlat", "+proj=cart", 1));
+            },
+            Operation::Helmert(ref h) => {
+                let mut parts = vec!["+proj=helmert".to_string(), format!("+x={}", h.dx), format!("+y={}", h.dy), format!("+z={}", h.dz)];
+                if !h.is_translation() {
+                    parts.extend(vec![
+                        format!("+rx={}", h.rx), format!("+ry={}", h.ry), format!("+rz={}", h.rz),
+                        format!("+s={}", h.ds), "+convention=position_vector".to_string(),
+                    ]);
+                }
+                parts
+            },
+            Operation::Affine { ref offset, ref matrix } => {
+                let mut parts = vec!["+proj=affine".to_string()];
+                for (axis, &value) in ["x", "y", "z"].iter().zip(offset) {
+                    if value != 0.0 {
+                        parts.push(format!("+{}off={}", axis, value));
+                    }
+                }
+                for (i, row) in matrix.iter().enumerate() {
+                    for (j, &value) in row.iter().enumerate() {
+                        if value != IDENTITY[i][j] {
+                            parts.push(format!("+s{}{}={}", i + 1, j + 1, value));
+                        }
+                    }
+                }
+                parts
+            },
+            Operation::UnitConversion { xy_in, xy_out, z_in, z_out } => {
+                let mut parts = vec!["+proj=unitconvert".to_string()];
+                if xy_in != xy_out {
+                    parts.push(format!("+xy_in={}", unit_to_string("xy_in", xy_in)?));
+                    parts.push(format!("+xy_out={}", unit_to_string("xy_out", xy_out)?));
+                }
+                if z_in != z_out {
+                    parts.push(format!("+z_in={}", unit_to_string("z_in", Unit::Linear(z_in))?));
+                    parts.push(format!("+z_out={}", unit_to_string("z_out", Unit::Linear(z_out))?));
+                }
+                parts
+            },
+        };
+        Ok(parts.join(" "))
+    }
+}
+
+/// Pipeline from the source to the target CRS through geocentric WGS84 coordinates:
+/// the inverse source projection, both Helmert transformations and the target
+/// projection. Unlike the steps of a parsed pipeline, geographic CRS take and
+/// return degrees, like `CoordinateSource::project`.
+pub(crate) fn through_wgs84(source_crs: &dyn Crs, source_ellipsoid: &Ellipsoid, source_to_wgs84: &Helmert,
+                            target_crs: &dyn Crs, target_ellipsoid: &Ellipsoid, target_to_wgs84: &Helmert)
+                            -> Pipeline
+{
+    let step = |operation, inverse| Step { operation, inverse };
+    let degrees = || Operation::UnitConversion {
+        xy_in: Unit::Angular(PI / 180.0), xy_out: Unit::Angular(1.0), z_in: 1.0, z_out: 1.0,
+    };
+
+    let mut steps = Vec::new();
+    if is_geographic(source_crs) {
+        steps.push(step(degrees(), false));
+    }
+    steps.extend(vec![
+        step(Operation::Projection { crs: Crs::clone(source_crs), ellipsoid: *source_ellipsoid }, true),
+        step(Operation::Cartesian(*source_ellipsoid), false),
+        step(Operation::Helmert(*source_to_wgs84), false),
+        step(Operation::Helmert(*target_to_wgs84), true),
+        step(Operation::Cartesian(*target_ellipsoid), true),
+        step(Operation::Projection { crs: Crs::clone(target_crs), ellipsoid: *target_ellipsoid }, false),
+    ]);
+    if is_geographic(target_crs) {
+        steps.push(step(degrees(), true));
+    }
+    Pipeline { steps }
+}
+
+/// Whether the CRS has angular coordinates
+fn is_geographic(crs: &dyn Crs) -> bool {
+    let inner = crs.as_any().downcast_ref::<AdjustedSystem>().map(|a| &*a.crs).unwrap_or(crs);
+    inner.as_any().is::<LonLatSystem>()
+}
+
+fn geodetic_to_geocentric(ellipsoid: &Ellipsoid, (lon, lat, h): (f64, f64, f64)) -> (f64, f64, f64) {
+    let e2 = ellipsoid.eccentricity_squared();
+    let n = ellipsoid.a / (1.0 - e2 * lat.sin().powi(2)).sqrt();
+    ((n + h) * lat.cos() * lon.cos(), (n + h) * lat.cos() * lon.sin(), (n * (1.0 - e2) + h) * lat.sin())
+}
+
+/// Iterates the latitude until it is stable, which takes a few rounds near the surface
+fn geocentric_to_geodetic(ellipsoid: &Ellipsoid, (x, y, z): (f64, f64, f64)) -> (f64, f64, f64) {
+    let e2 = ellipsoid.eccentricity_squared();
+    let p = x.hypot(y);
+    let mut lat = z.atan2(p * (1.0 - e2));
+    for _ in 0..10 {
+        let n = ellipsoid.a / (1.0 - e2 * lat.sin().powi(2)).sqrt();
+        let next = (z + e2 * n * lat.sin()).atan2(p);
+        let converged = (next - lat).abs() < 1e-14;
+        lat = next;
+        if converged {
+            break;
+        }
+    }
+    let h = p * lat.cos() + z * lat.sin() - ellipsoid.a * (1.0 - e2 * lat.sin().powi(2)).sqrt();
+    (y.atan2(x), lat, h)
+}
+
+/// Translation and matrix of the position vector convention, with small-angle rotations
+fn helmert_matrix(h: &Helmert) -> ([f64; 3], [[f64; 3]; 3]) {
+    let scale = 1.0 + h.ds * 1e-6;
+    let (rx, ry, rz) = (h.rx * ARC_SECOND, h.ry * ARC_SECOND, h.rz * ARC_SECOND);
+    let matrix = [
+        [scale, -rz * scale, ry * scale],
+        [rz * scale, scale, -rx * scale],
+        [-ry * scale, rx * scale, scale],
+    ];
+    ([h.dx, h.dy, h.dz], matrix)
+}
+
+/// Applies `offset + matrix * c`, or its inverse. A singular matrix has no inverse and results in NaN.
+fn apply_affine(data: &mut [(f64, f64, f64)], offset: &[f64; 3], matrix: &[[f64; 3]; 3], inverse: bool) {
+    let m = if inverse { invert(matrix) } else { *matrix };
+    for c in data.iter_mut() {
+        let v = if inverse {
+            [c.0 - offset[0], c.1 - offset[1], c.2 - offset[2]]
+        } else {
+            [c.0, c.1, c.2]
+        };
+        let row = |r: &[f64; 3]| r[0] * v[0] + r[1] * v[1] + r[2] * v[2];
+        *c = if inverse {
+            (row(&m[0]), row(&m[1]), row(&m[2]))
+        } else {
+            (offset[0] + row(&m[0]), offset[1] + row(&m[1]), offset[2] + row(&m[2]))
+        };
+    }
+}
+
+/// Inverse of a 3x3 matrix through its adjugate
+fn invert(m: &[[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    let cofactor = |i: usize, j: usize| {
+        let (r0, r1) = ((i + 1) % 3, (i + 2) % 3);
+        let (c0, c1) = ((j + 1) % 3, (j + 2) % 3);
+        m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0]
+    };
+    let det = m[0][0] * cofactor(0, 0) + m[0][1] * cofactor(0, 1) + m[0][2] * cofactor(0, 2);
+    if det == 0.0 {
+        return [[f64::NAN; 3]; 3];
+    }
+
+    let mut inverse = [[0.0; 3]; 3];
+    for (i, row) in inverse.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = cofactor(j, i) / det;
+        }
+    }
+    inverse
+}
+
+/// Writes the unit by its name, or length units as the number of meters
+fn unit_to_string(key: &str, unit: Unit) -> Result<String, ProjStringError> {
+    match unit {
+        Unit::Linear(m) => Ok(UNITS.iter().find(|&&(_, v)| v == m).map(|&(id, _)| id.to_string()).unwrap_or_else(|| m.to_string())),
+        // PROJ reads numbers as length units, so other angles can't be written
+        Unit::Angular(r) => ANGULAR_UNITS.iter().find(|&&(_, v)| v == r).map(|&(id, _)| id.to_string())
+            .ok_or_else(|| ProjStringError::UnsupportedValue(key.to_string(), r.to_string())),
+    }
+}
+
+/// Returns the key of a `key=value` token
+fn token_key(token: &str) -> &str {
+    token.split('=').next().unwrap_or(token)
+}
+
+/// Parses a PROJ pipeline
+///
+/// A string without `+proj=pipeline` is read as a pipeline with a single step.
+/// The leading `+` of the parameters is optional. See the module documentation
+/// for the supported operations and parameters.
+pub fn parse_pipeline(s: &str) -> Result<Pipeline, ProjStringError> {
+    let tokens: Vec<&str> = s.split_whitespace().map(|t| t.strip_prefix('+').unwrap_or(t)).collect();
+    let mut groups = tokens.split(|&t| t == "step");
+    let mut globals: Vec<&str> = groups.next().unwrap_or(&[]).to_vec();
+
+    match globals.iter().position(|&t| t == "proj=pipeline") {
+        Some(index) => { globals.remove(index); },
+        None if tokens.contains(&"step") => return Err(ProjStringError::UnsupportedKey("step".to_string())),
+        None => return Ok(Pipeline { steps: parse_step(&globals, &[])?.into_iter().collect() }),
+    }
+
+    if let Some(&token) = globals.iter().find(|&&t| token_key(t) == "inv" || token_key(t) == "proj") {
+        return Err(ProjStringError::UnsupportedParameter("pipeline".to_string(), token_key(token).to_string()));
+    }
+
+    let mut steps = Vec::new();
+    for group in groups {
+        steps.extend(parse_step(group, &globals)?);
+    }
+    Ok(Pipeline { steps })
+}
+
+/// Parses the tokens of one step, `None` for `+proj=noop`
+fn parse_step(tokens: &[&str], globals: &[&str]) -> Result<Option<Step>, ProjStringError> {
+    let inverse = tokens.contains(&"inv");
+    let tokens: Vec<&str> = tokens.iter().cloned().filter(|&t| t != "inv").collect();
+    let proj = tokens.iter().find_map(|t| t.strip_prefix("proj=")).ok_or(ProjStringError::MissingProjection)?;
+
+    // the global parameters that the step doesn't override
+    let with_globals = |allowed: Option<&[&str]>| {
+        let mut all = tokens.clone();
+        all.extend(globals.iter().filter(|&&g| {
+            allowed.is_none_or(|keys| keys.contains(&token_key(g))) &&
+            !tokens.iter().any(|&t| token_key(t) == token_key(g))
+        }));
+        all.join(" ")
+    };
+
+    let operation = match proj {
+        "noop" => {
+            Parameters::parse_with_keys(&tokens.join(" "), &["proj"])?;
+            return Ok(None);
+        },
+        "pipeline" => return Err(ProjStringError::UnsupportedProjection("pipeline".to_string())),
+        "cart" => parse_cartesian(&with_globals(Some(&CART_KEYS)))?,
+        "helmert" => parse_helmert(&tokens.join(" "))?,
+        "affine" => parse_affine(&tokens.join(" "))?,
+        "axisswap" => parse_axisswap(&tokens.join(" "))?,
+        "unitconvert" => parse_unitconvert(&tokens.join(" "))?,
+        _ => {
+            // datum shifts are separate steps in a pipeline
+            if tokens.iter().any(|&t| token_key(t) == "towgs84") {
+                return Err(ProjStringError::UnsupportedParameter(proj.to_string(), "towgs84".to_string()));
+            }
+            let definition = proj_string::parse_proj_string(&with_globals(None))?;
+            Operation::Projection { crs: definition.crs, ellipsoid: definition.ellipsoid }
+        },
+    };
+
+    Ok(Some(Step { operation, inverse }))
+}
+
+fn parse_cartesian(s: &str) -> Result<Operation, ProjStringError> {
+    Parameters::parse_with_keys(s, &CART_KEYS)?;
+    let definition = proj_string::parse_proj_string(&s.replacen("proj=cart", "proj=longlat", 1))?;
+    Ok(Operation::Cartesian(definition.ellipsoid))
+}
+
+fn parse_helmert(s: &str) -> Result<Operation, ProjStringError> {
+    let mut params = Parameters::parse_with_keys(s, &HELMERT_KEYS)?;
+    let mut take = |key: &str| params.take_f64(key);
+    let (dx, dy, dz) = (take("x")?.unwrap_or(0.0), take("y")?.unwrap_or(0.0), take("z")?.unwrap_or(0.0));
+    let (rx, ry, rz) = (take("rx")?, take("ry")?, take("rz")?);
+    let ds = take("s")?.unwrap_or(0.0);
+
+    // PROJ requires the convention as soon as a rotation is given
+    let has_rotation = rx.is_some() || ry.is_some() || rz.is_some();
+    let sign = match params.take_str("convention")? {
+        Some("position_vector") => 1.0,
+        Some("coordinate_frame") => -1.0,
+        Some(value) => return Err(ProjStringError::InvalidValue("convention".to_string(), value.to_string())),
+        None if has_rotation => return Err(ProjStringError::MissingParameter("helmert".to_string(), "convention".to_string())),
+        None => 1.0,
+    };
+
+    // adding zero turns the -0.0 of negated zero rotations into 0.0
+    let rotation = |r: Option<f64>| sign * r.unwrap_or(0.0) + 0.0;
+    Ok(Operation::Helmert(Helmert { dx, dy, dz, rx: rotation(rx), ry: rotation(ry), rz: rotation(rz), ds }))
+}
+
+fn parse_affine(s: &str) -> Result<Operation, ProjStringError> {
+    let mut params = Parameters::parse_with_keys(s, &AFFINE_KEYS)?;
+    let mut offset = [0.0; 3];
+    for (value, key) in offset.iter_mut().zip(&["xoff", "yoff", "zoff"]) {
+        *value = params.take_f64(key)?.unwrap_or(0.0);
+    }
+    let mut matrix = IDENTITY;
+    for (i, row) in matrix.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            if let Some(v) = params.take_f64(&format!("s{}{}", i + 1, j + 1))? {
+                *value = v;
+            }
+        }
+    }
+    Ok(Operation::Affine { offset, matrix })
+}
+
+/// Reads `+order`, e.g. "2,1" or "-1,2,3", as a matrix that permutes and negates the axes
+fn parse_axisswap(s: &str) -> Result<Operation, ProjStringError> {
+    let mut params = Parameters::parse_with_keys(s, &AXISSWAP_KEYS)?;
+    let order = params.take_str("order")?
+        .ok_or_else(|| ProjStringError::MissingParameter("axisswap".to_string(), "order".to_string()))?;
+    let invalid = || ProjStringError::InvalidValue("order".to_string(), order.to_string());
+
+    let axes = order.split(',').map(|a| a.trim().parse::<i32>().ok()).collect::<Option<Vec<i32>>>().ok_or_else(invalid)?;
+    if !(axes.len() == 2 || axes.len() == 3) {
+        return Err(invalid());
+    }
+
+    let mut matrix = IDENTITY;
+    let mut used = [false; 3];
+    for (row, &axis) in matrix.iter_mut().zip(&axes) {
+        let index = axis.unsigned_abs() as usize;
+        if !(1..=axes.len()).contains(&index) || used[index - 1] {
+            return Err(invalid());
+        }
+        used[index - 1] = true;
+        *row = [0.0; 3];
+        row[index - 1] = axis.signum() as f64;
+    }
+    Ok(Operation::Affine { offset: [0.0; 3], matrix })
+}
+
+fn parse_unit(key: &str, value: &str) -> Result<Unit, ProjStringError> {
+    if let Some(&(_, m)) = UNITS.iter().find(|&&(id, _)| id == value) {
+        return Ok(Unit::Linear(m));
+    }
+    if let Some(&(_, r)) = ANGULAR_UNITS.iter().find(|&&(id, _)| id == value) {
+        return Ok(Unit::Angular(r));
+    }
+    value.parse::<f64>().ok()
+        .filter(|&m| m > 0.0 && m.is_finite())
+        .map(Unit::Linear)
+        .ok_or_else(|| ProjStringError::InvalidValue(key.to_string(), value.to_string()))
+}
+
+fn parse_unitconvert(s: &str) -> Result<Operation, ProjStringError> {
+    let mut params = Parameters::parse_with_keys(s, &UNITCONVERT_KEYS)?;
+
+    // both units or none
+    let mut take_pair = |key_in: &str, key_out: &str| -> Result<Option<(Unit, Unit)>, ProjStringError> {
+        match (params.take_str(key_in)?, params.take_str(key_out)?) {
+            (None, None) => Ok(None),
+            (Some(_), None) => Err(ProjStringError::MissingParameter("unitconvert".to_string(), key_out.to_string())),
+            (None, Some(_)) => Err(ProjStringError::MissingParameter("unitconvert".to_string(), key_in.to_string())),
+            (Some(a), Some(b)) => {
+                let (unit_in, unit_out) = (parse_unit(key_in, a)?, parse_unit(key_out, b)?);
+                let same_kind = matches!((unit_in, unit_out), (Unit::Linear(_), Unit::Linear(_)) | (Unit::Angular(_), Unit::Angular(_)));
+                if !same_kind {
+                    return Err(ProjStringError::InvalidValue(key_out.to_string(), b.to_string()));
+                }
+                Ok(Some((unit_in, unit_out)))
+            },
+        }
+    };
+
+    let (xy_in, xy_out) = take_pair("xy_in", "xy_out")?.unwrap_or((Unit::Linear(1.0), Unit::Linear(1.0)));
+    let (z_in, z_out) = match take_pair("z_in", "z_out")? {
+        None => (1.0, 1.0),
+        Some((Unit::Linear(a), Unit::Linear(b))) => (a, b),
+        Some((unit, _)) => return Err(ProjStringError::UnsupportedValue("z_in".to_string(), unit_to_string("z_in", unit)?)),
+    };
+
+    Ok(Operation::UnitConversion { xy_in, xy_out, z_in, z_out })
+}
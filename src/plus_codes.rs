@@ -0,0 +1,555 @@
+//! Open Location Code (Plus Codes)
+//!
+//! Implementation ported from the reference implementation of the
+//! Open Location Code specification:
+//!
+//! https://github.com/google/open-location-code/blob/main/docs/specification.md
+//!
+//! A full code such as "8FVC9G8F+6X" has 8 digits before the `+` separator,
+//! encoding pairs of latitude / longitude digits in base 20 (down to a cell
+//! of 0.000125°). Digits after the 10th refine the cell on a 4 x 5 grid.
+//! Codes with less than 8 digits are padded with `0` ("8FVC0000+").
+//!
+//! A short code such as "9G8F+6X" omits the first digits and can only be
+//! recovered with a nearby reference location.
+//!
+//! Bounding boxes are `(min_lon, min_lat, max_lon, max_lat)` in degrees.
+
+use prelude::*;
+use std::error::Error;
+use std::fmt;
+
+/// The 20 digits of a code
+const ALPHABET: &str = "23456789CFGHJMPQRVWX";
+
+/// Separator between the 8th and 9th digit
+pub const SEPARATOR: char = '+';
+
+/// Position of the separator in a full code
+const SEPARATOR_POSITION: usize = 8;
+
+/// Padding character for codes with less than 8 digits
+const PADDING: char = '0';
+
+/// Number of digits encoded as latitude / longitude pairs
+const PAIR_CODE_LENGTH: usize = 10;
+
+/// Maximum number of digits of a code
+pub const MAX_CODE_LENGTH: usize = 15;
+
+/// Default number of digits (cell of about 14 x 14 m)
+pub const DEFAULT_CODE_LENGTH: usize = 10;
+
+/// Minimum number of digits of a code that can be shortened
+const MIN_TRIMMABLE_CODE_LEN: usize = 6;
+
+/// Number of rows / columns of the grid refinement after the 10th digit
+const GRID_ROWS: i64 = 5;
+const GRID_COLUMNS: i64 = 4;
+
+/// Place value of the first latitude / longitude digit in integer units
+/// (1 / 25 000 000 degree latitude, 1 / 8 192 000 degree longitude)
+const LAT_FIRST_PLACE_VALUE: i64 = 500_000_000;
+const LNG_FIRST_PLACE_VALUE: i64 = 163_840_000;
+
+/// Integer units per degree
+const LAT_INTEGER_MULTIPLIER: f64 = 25_000_000.0;
+const LNG_INTEGER_MULTIPLIER: f64 = 8_192_000.0;
+
+/// Error that can occur when encoding, decoding or shortening a code
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlusCodeError {
+    /// Code length must be 2, 4, 6, 8 or 10 to 15
+    InvalidCodeLength(usize),
+    /// Longitude or latitude isn't a valid number
+    InvalidCoordinate(f64, f64),
+    /// The code isn't a valid Open Location Code
+    InvalidCode(String),
+    /// The operation requires a full code
+    NotFullCode(String),
+    /// The operation requires a short code
+    NotShortCode(String),
+    /// Padded codes can't be shortened
+    PaddedCode(String),
+}
+
+impl fmt::Display for PlusCodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::PlusCodeError::*;
+        match *self {
+            InvalidCodeLength(len) => write!(f, "invalid code length: {} (must be 2, 4, 6, 8 or 10 to 15)", len),
+            InvalidCoordinate(lon, lat) => write!(f, "invalid coordinate: ({}, {})", lon, lat),
+            InvalidCode(ref s) => write!(f, "invalid plus code: \"{}\"", s),
+            NotFullCode(ref s) => write!(f, "not a full plus code: \"{}\"", s),
+            NotShortCode(ref s) => write!(f, "not a short plus code: \"{}\"", s),
+            PaddedCode(ref s) => write!(f, "padded plus codes can't be shortened: \"{}\"", s),
+        }
+    }
+}
+
+impl Error for PlusCodeError { }
+
+/// Decoded area of a code
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CodeArea {
+    pub min_lon: f64,
+    pub min_lat: f64,
+    pub max_lon: f64,
+    pub max_lat: f64,
+    /// Number of digits of the code (without separator and padding)
+    pub code_length: usize,
+}
+
+impl CodeArea {
+    /// Center of the area (lon, lat), in degrees
+    pub fn center(&self) -> (f64, f64) {
+        ((self.min_lon + self.max_lon) / 2.0, ((self.min_lat + self.max_lat) / 2.0).min(90.0))
+    }
+
+    /// Bounding box of the area (min_lon, min_lat, max_lon, max_lat), in degrees
+    pub fn bounds(&self) -> (f64, f64, f64, f64) {
+        (self.min_lon, self.min_lat, self.max_lon, self.max_lat)
+    }
+
+    /// Area of the cell on the given ellipsoid, in square meters
+    pub fn area(&self, ellipsoid: &Ellipsoid) -> f64 {
+        ellipsoid.lonlat_rectangle_area(self.min_lon, self.min_lat, self.max_lon, self.max_lat)
+    }
+}
+
+#[inline]
+fn digit_value(c: char) -> Option<usize> {
+    ALPHABET.find(c.to_ascii_uppercase())
+}
+
+#[inline]
+fn digit(value: i64) -> char {
+    ALPHABET.as_bytes()[value as usize] as char
+}
+
+/// Height of a cell with the given number of digits, in degrees
+fn latitude_precision(code_length: usize) -> f64 {
+    if code_length <= PAIR_CODE_LENGTH {
+        20.0_f64.powi(2 - (code_length / 2) as i32)
+    } else {
+        20.0_f64.powi(-3) / (GRID_ROWS as f64).powi((code_length - PAIR_CODE_LENGTH) as i32)
+    }
+}
+
+/// Returns whether the code is a valid (full or short) code
+pub fn is_valid(code: &str) -> bool {
+    if code.is_empty() || !code.is_ascii() {
+        return false;
+    }
+
+    // Exactly one separator, at an even position no later than the 8th digit
+    let separator = match code.find(SEPARATOR) {
+        Some(position) => position,
+        None => return false,
+    };
+    if code.rfind(SEPARATOR) != Some(separator) || separator > SEPARATOR_POSITION || separator % 2 == 1 || code.len() == 1 {
+        return false;
+    }
+
+    // Only full codes are padded. Padding may not start the code, must be
+    // a single run of an even number of characters that ends directly
+    // before the separator, and nothing may follow the separator.
+    if let Some(padding_start) = code.find(PADDING) {
+        if separator < SEPARATOR_POSITION || padding_start == 0 || padding_start > separator {
+            return false;
+        }
+
+        let padding = &code[padding_start..separator];
+        if padding.len() % 2 == 1 || padding.chars().any(|c| c != PADDING) {
+            return false;
+        }
+
+        if code.len() > separator + 1 {
+            return false;
+        }
+    }
+
+    // A single digit after the separator is not allowed
+    if code.len() - separator - 1 == 1 {
+        return false;
+    }
+
+    code.chars().all(|c| c == SEPARATOR || c == PADDING || digit_value(c).is_some())
+}
+
+/// Returns whether the code is a valid short code (less than 8 digits before the separator)
+pub fn is_short(code: &str) -> bool {
+    is_valid(code) && code.find(SEPARATOR).map(|p| p < SEPARATOR_POSITION).unwrap_or(false)
+}
+
+/// Returns whether the code is a valid full code
+pub fn is_full(code: &str) -> bool {
+    if !is_valid(code) || is_short(code) {
+        return false;
+    }
+
+    let mut chars = code.chars();
+
+    // The first latitude digit may not exceed 90°, the first longitude digit not 180°
+    let first_lat = chars.next().and_then(digit_value).unwrap_or(0) * 20;
+    let first_lng = chars.next().and_then(digit_value).unwrap_or(0) * 20;
+    first_lat < 180 && first_lng < 360
+}
+
+/// Encodes a lon / lat coordinate (in degrees) into a full code with
+/// `code_length` digits (2, 4, 6, 8 or 10 to 15)
+///
+/// Latitudes are clipped to ±90°, longitudes are normalized to [-180, 180).
+pub fn encode(lon: f64, lat: f64, code_length: usize)
+              -> Result<String, PlusCodeError>
+{
+    if code_length < 2 || (code_length < PAIR_CODE_LENGTH && code_length % 2 == 1) || code_length > MAX_CODE_LENGTH {
+        return Err(PlusCodeError::InvalidCodeLength(code_length));
+    }
+
+    if !lon.is_finite() || !lat.is_finite() {
+        return Err(PlusCodeError::InvalidCoordinate(lon, lat));
+    }
+
+    let mut lat = lat.clamp(-90.0, 90.0);
+    if lat == 90.0 {
+        lat -= latitude_precision(code_length);
+    }
+    let lon = (lon + 180.0).rem_euclid(360.0) - 180.0;
+
+    // Round to 6 decimal places before truncating, to avoid floating point errors
+    let to_integer = |value: f64, multiplier: f64| ((value * multiplier * 1e6).round() / 1e6).floor() as i64;
+    let mut lat_value = to_integer(lat + 90.0, LAT_INTEGER_MULTIPLIER);
+    let mut lng_value = to_integer(lon + 180.0, LNG_INTEGER_MULTIPLIER);
+
+    let mut reversed = Vec::with_capacity(MAX_CODE_LENGTH + 1);
+
+    if code_length > PAIR_CODE_LENGTH {
+        for _ in 0..(MAX_CODE_LENGTH - PAIR_CODE_LENGTH) {
+            let index = (lat_value % GRID_ROWS) * GRID_COLUMNS + lng_value % GRID_COLUMNS;
+            reversed.push(digit(index));
+            lat_value /= GRID_ROWS;
+            lng_value /= GRID_COLUMNS;
+        }
+    } else {
+        lat_value /= GRID_ROWS.pow(5);
+        lng_value /= GRID_COLUMNS.pow(5);
+    }
+
+    for i in 0..(PAIR_CODE_LENGTH / 2) {
+        reversed.push(digit(lng_value % 20));
+        reversed.push(digit(lat_value % 20));
+        lat_value /= 20;
+        lng_value /= 20;
+
+        if i == 0 {
+            reversed.push(SEPARATOR);
+        }
+    }
+
+    let full: String = reversed.into_iter().rev().collect();
+    let (pairs, rest) = full.split_at(SEPARATOR_POSITION);
+    let rest = &rest[1..];
+
+    if code_length >= SEPARATOR_POSITION {
+        let extra = code_length - SEPARATOR_POSITION;
+        Ok(format!("{}{}{}", pairs, SEPARATOR, &rest[..extra]))
+    } else {
+        let padding: String = ::std::iter::repeat_n(PADDING, SEPARATOR_POSITION - code_length).collect();
+        Ok(format!("{}{}{}", &pairs[..code_length], padding, SEPARATOR))
+    }
+}
+
+/// Decodes a full code into the area it covers
+pub fn decode(code: &str)
+              -> Result<CodeArea, PlusCodeError>
+{
+    if !is_full(code) {
+        return Err(PlusCodeError::NotFullCode(code.to_string()));
+    }
+
+    let digits: Vec<usize> = code.chars()
+        .filter(|&c| c != SEPARATOR && c != PADDING)
+        .take(MAX_CODE_LENGTH)
+        .filter_map(digit_value)
+        .collect();
+
+    let mut lat_value = 0_i64;
+    let mut lng_value = 0_i64;
+    let mut lat_place = LAT_FIRST_PLACE_VALUE;
+    let mut lng_place = LNG_FIRST_PLACE_VALUE;
+
+    for (i, pair) in digits.chunks(2).take(PAIR_CODE_LENGTH / 2).enumerate() {
+        if i > 0 {
+            lat_place /= 20;
+            lng_place /= 20;
+        }
+        lat_value += pair[0] as i64 * lat_place;
+        lng_value += pair.get(1).map(|&d| d as i64).unwrap_or(0) * lng_place;
+    }
+
+    for &d in digits.iter().skip(PAIR_CODE_LENGTH) {
+        lat_place /= GRID_ROWS;
+        lng_place /= GRID_COLUMNS;
+        lat_value += d as i64 / GRID_COLUMNS * lat_place;
+        lng_value += d as i64 % GRID_COLUMNS * lng_place;
+    }
+
+    Ok(CodeArea {
+        min_lon: lng_value as f64 / LNG_INTEGER_MULTIPLIER - 180.0,
+        min_lat: lat_value as f64 / LAT_INTEGER_MULTIPLIER - 90.0,
+        max_lon: (lng_value + lng_place) as f64 / LNG_INTEGER_MULTIPLIER - 180.0,
+        max_lat: (lat_value + lat_place) as f64 / LAT_INTEGER_MULTIPLIER - 90.0,
+        code_length: digits.len(),
+    })
+}
+
+/// Removes as many leading digits from a full code as possible,
+/// so that it can still be recovered with the reference location (in degrees)
+///
+/// The reference location must be within about 0.3 cell sizes
+/// (of the removed digits) of the code's center. Codes with fewer than
+/// 6 digits are rejected with `InvalidCodeLength`.
+pub fn shorten(code: &str, ref_lon: f64, ref_lat: f64)
+               -> Result<String, PlusCodeError>
+{
+    if !is_full(code) {
+        return Err(PlusCodeError::NotFullCode(code.to_string()));
+    }
+
+    if code.contains(PADDING) {
+        return Err(PlusCodeError::PaddedCode(code.to_string()));
+    }
+
+    let code = code.to_ascii_uppercase();
+    let area = decode(&code)?;
+    if area.code_length < MIN_TRIMMABLE_CODE_LEN {
+        return Err(PlusCodeError::InvalidCodeLength(area.code_length));
+    }
+    let (lon, lat) = area.center();
+
+    let ref_lat = ref_lat.clamp(-90.0, 90.0);
+    let ref_lon = (ref_lon + 180.0).rem_euclid(360.0) - 180.0;
+    let range = (lat - ref_lat).abs().max((lon - ref_lon).abs());
+
+    // Resolution of the 2nd, 3rd and 4th pair: 1°, 0.05°, 0.0025°
+    for pair in (1..4).rev() {
+        if range < 20.0_f64.powi(1 - pair as i32) * 0.3 {
+            return Ok(code[(pair + 1) * 2..].to_string());
+        }
+    }
+
+    Ok(code)
+}
+
+/// Recovers the full code of a short code, choosing the cell
+/// that is nearest to the reference location (in degrees)
+///
+/// Full codes are returned unchanged (in uppercase).
+pub fn recover_nearest(short_code: &str, ref_lon: f64, ref_lat: f64)
+                       -> Result<String, PlusCodeError>
+{
+    if is_full(short_code) {
+        return Ok(short_code.to_ascii_uppercase());
+    }
+
+    if !is_short(short_code) {
+        return Err(PlusCodeError::NotShortCode(short_code.to_string()));
+    }
+
+    let ref_lat = ref_lat.clamp(-90.0, 90.0);
+    let ref_lon = (ref_lon + 180.0).rem_euclid(360.0) - 180.0;
+
+    // Number of removed digits and the size of the cell they describe
+    let padding_length = SEPARATOR_POSITION - short_code.find(SEPARATOR).unwrap_or(0);
+    let resolution = 20.0_f64.powi(2 - (padding_length / 2) as i32);
+    let half_resolution = resolution / 2.0;
+
+    let prefix = encode(ref_lon, ref_lat, DEFAULT_CODE_LENGTH)?;
+    let area = decode(&format!("{}{}", &prefix[..padding_length], short_code.to_ascii_uppercase()))?;
+    let (mut lon, mut lat) = area.center();
+
+    // Move by one cell if the reference is closer to the neighbouring cell
+    if ref_lat + half_resolution < lat && lat - resolution >= -90.0 {
+        lat -= resolution;
+    } else if ref_lat - half_resolution > lat && lat + resolution <= 90.0 {
+        lat += resolution;
+    }
+
+    if ref_lon + half_resolution < lon {
+        lon -= resolution;
+    } else if ref_lon - half_resolution > lon {
+        lon += resolution;
+    }
+
+    encode(lon, lat, area.code_length)
+}
+
+/// Encodes all coordinates of a `LonLatBuf` into full codes with `code_length` digits
+///
+/// Invalid coordinates are returned as `None`.
+pub fn encode_lonlat_buf(buf: &LonLatBuf, code_length: usize, strategy: &mut MultithreadingStrategy)
+                         -> Result<Vec<Option<String>>, PlusCodeError>
+{
+    // Validate the code length once
+    encode(0.0, 0.0, code_length)?;
+
+    let mut codes = vec![None; buf.data.len()];
+
+    match *strategy {
+        SingleCore => {
+            for (&(lon, lat), code) in buf.data.iter().zip(codes.iter_mut()) {
+                *code = encode(lon, lat, code_length).ok();
+            }
+        },
+        #[cfg(all(not(target_arch = "wasm32"), feature = "scoped_threadpool"))]
+        MultiCore(ref mut thread_pool) => {
+            thread_pool.scoped(|scoped| {
+                for (&(lon, lat), code) in buf.data.iter().zip(codes.iter_mut()) {
+                    scoped.execute(move || {
+                        *code = encode(lon, lat, code_length).ok();
+                    });
+                }
+            });
+        },
+    }
+
+    Ok(codes)
+}
+
+/// Decodes full codes into a `LonLatBuf` with the center of each cell
+///
+/// Invalid codes are decoded to (NaN, NaN).
+pub fn decode_to_lonlat_buf<S: AsRef<str> + Sync>(codes: &[S], ellipsoid: &Ellipsoid, strategy: &mut MultithreadingStrategy)
+                                                  -> LonLatBuf
+{
+    let mut data = vec![(f64::NAN, f64::NAN); codes.len()];
+
+    match *strategy {
+        SingleCore => {
+            for (code, coordinate) in codes.iter().zip(data.iter_mut()) {
+                *coordinate = decode(code.as_ref()).map(|area| area.center()).unwrap_or((f64::NAN, f64::NAN));
+            }
+        },
+        #[cfg(all(not(target_arch = "wasm32"), feature = "scoped_threadpool"))]
+        MultiCore(ref mut thread_pool) => {
+            thread_pool.scoped(|scoped| {
+                for (code, coordinate) in codes.iter().zip(data.iter_mut()) {
+                    scoped.execute(move || {
+                        *coordinate = decode(code.as_ref()).map(|area| area.center()).unwrap_or((f64::NAN, f64::NAN));
+                    });
+                }
+            });
+        },
+    }
+
+    LonLatBuf {
+        data,
+        ellipsoid: *ellipsoid,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // validityTests.csv of the reference implementation: code, is_valid, is_short, is_full
+    const VALIDITY: [(&str, bool, bool, bool); 21] = [
+        ("8FWC2345+G6", true, false, true),
+        ("8FWC2345+G6G", true, false, true),
+        ("8fwc2345+", true, false, true),
+        ("8FWCX400+", true, false, true),
+        ("WC2345+G6g", true, true, false),
+        ("2345+G6", true, true, false),
+        ("45+G6", true, true, false),
+        ("+G6", true, true, false),
+        ("G+", false, false, false),
+        ("+", false, false, false),
+        ("8FWC2345+G", false, false, false),
+        ("8FWC2_45+G6", false, false, false),
+        ("8FWC2η45+G6", false, false, false),
+        ("8FWC2345+G6+", false, false, false),
+        ("8FWC2345G6+", false, false, false),
+        ("8FWC2300+G6", false, false, false),
+        ("WC2300+G6g", false, false, false),
+        ("WC2345+G", false, false, false),
+        ("WC2300+", false, false, false),
+        // padding after the separator, padded short codes
+        ("8FVC9G8F+0X", false, false, false),
+        ("2200+", false, false, false),
+    ];
+
+    #[test]
+    fn validity() {
+        for &(code, valid, short, full) in VALIDITY.iter() {
+            assert_eq!(is_valid(code), valid, "is_valid({})", code);
+            assert_eq!(is_short(code), short, "is_short({})", code);
+            assert_eq!(is_full(code), full, "is_full({})", code);
+        }
+    }
+
+    #[test]
+    fn padding() {
+        assert!(is_full("8FVC0000+"));
+        assert!(is_full("8FVC9G00+"));
+        assert!(!is_valid("8F0C0000+"));
+        assert!(!is_valid("8FV00000+"));
+        assert!(!is_valid("00000000+"));
+        assert!(!is_valid("8FVC0000+9G"));
+    }
+
+    // encoding.csv of the reference implementation: lat, lon, length, code
+    const ENCODING: [(f64, f64, usize, &str); 8] = [
+        (20.375, 2.775, 6, "7FG49Q00+"),
+        (20.3700625, 2.7821875, 10, "7FG49QCJ+2V"),
+        (20.3701125, 2.782234375, 11, "7FG49QCJ+2VX"),
+        (47.0000625, 8.0000625, 10, "8FVC2222+22"),
+        (-41.2730625, 174.7859375, 10, "4VCPPQGP+Q9"),
+        (0.5, -179.5, 4, "62G20000+"),
+        (-89.5, -179.5, 4, "22220000+"),
+        (20.5, 2.5, 4, "7FG40000+"),
+    ];
+
+    #[test]
+    fn encode_decode() {
+        for &(lat, lon, length, code) in ENCODING.iter() {
+            assert_eq!(encode(lon, lat, length).unwrap(), code);
+            let area = decode(code).unwrap();
+            assert_eq!(area.code_length, length);
+            let (min_lon, min_lat, max_lon, max_lat) = area.bounds();
+            assert!(min_lon <= lon && lon <= max_lon && min_lat <= lat && lat <= max_lat, "decode({})", code);
+        }
+
+        let (lon, lat) = decode("8FVC9G8F+6X").unwrap().center();
+        assert!((lon - 8.5249375).abs() < 1e-9 && (lat - 47.3655625).abs() < 1e-9);
+        assert_eq!(encode(lon, lat, 10).unwrap(), "8FVC9G8F+6X");
+    }
+
+    // shortCodeTests.csv of the reference implementation: code, lat, lon, short code
+    const SHORT_CODES: [(&str, f64, f64, &str); 13] = [
+        ("9C3W9QCJ+2VX", 51.3701125, -1.217765625, "+2VX"),
+        ("9C3W9QCJ+2VX", 51.3708675, -1.217765625, "CJ+2VX"),
+        ("9C3W9QCJ+2VX", 51.3693575, -1.217765625, "CJ+2VX"),
+        ("9C3W9QCJ+2VX", 51.3701125, -1.218520625, "CJ+2VX"),
+        ("9C3W9QCJ+2VX", 51.3701125, -1.217010625, "CJ+2VX"),
+        ("9C3W9QCJ+2VX", 51.3852125, -1.217765625, "9QCJ+2VX"),
+        ("9C3W9QCJ+2VX", 51.3550125, -1.217765625, "9QCJ+2VX"),
+        ("9C3W9QCJ+2VX", 51.3701125, -1.232865625, "9QCJ+2VX"),
+        ("9C3W9QCJ+2VX", 51.3701125, -1.202665625, "9QCJ+2VX"),
+        ("8FVC2GGG+GG", 46.976, 8.526, "2GGG+GG"),
+        ("8FRCXGGG+GG", 47.0, 8.526, "XGGG+GG"),
+        ("8FR9GXGG+GG", 46.526, 8.026, "GXGG+GG"),
+        ("8FRCG2GG+GG", 46.526, 7.976, "G2GG+GG"),
+    ];
+
+    #[test]
+    fn shorten_and_recover() {
+        for &(code, lat, lon, short) in SHORT_CODES.iter() {
+            assert_eq!(shorten(code, lon, lat).unwrap(), short, "shorten({})", code);
+            assert_eq!(recover_nearest(short, lon, lat).unwrap(), code, "recover_nearest({})", short);
+        }
+
+        assert_eq!(shorten("8FVC0000+", 8.5, 47.3), Err(PlusCodeError::PaddedCode("8FVC0000+".to_string())));
+        assert_eq!(shorten("9G8F+6X", 8.5, 47.3), Err(PlusCodeError::NotFullCode("9G8F+6X".to_string())));
+    }
+}
@@ -0,0 +1,768 @@
+//! PROJ.4 strings, such as `+proj=utm +zone=32 +ellps=WGS84 +units=m +no_defs`
+//!
+//! `parse_proj_string` turns a PROJ string into a `ProjDefinition`: the CRS,
+//! the ellipsoid and the optional `+towgs84` transformation of the datum.
+//! `to_proj_string` does the opposite for the coordinate systems listed below.
+//!
+//! Supported projections (`+proj=...`):
+//!
+//! - `longlat` (also `latlong`, `lonlat`, `latlon`): `LonLatSystem`
+//! - `utm` (`+zone`, `+south`): `UTMSystem`
+//! - `ups` (`+south`): `UPSSystem`
+//! - `merc`: `MercatorSystem`, only with the default origin and scale
+//! - `webmerc`: `WebMercatorSystem`
+//! - `tmerc` (`+lat_0`, `+lon_0`, `+k`, `+x_0`, `+y_0`): `TransverseMercatorSystem`
+//! - `lcc` (`+lat_0`, `+lon_0`, `+lat_1`, `+lat_2`, `+k_0`, `+x_0`, `+y_0`): `LambertConformalConicSystem`
+//! - `geos` (`+h`, `+lon_0`, `+sweep`): `GeostationarySystem`
+//! - `healpix` (`+lon_0`) and `rhealpix` (`+lon_0`, `+north_square`, `+south_square`)
+//! - `igh`: `GoodeHomolosineSystem` with the standard lobes
+//!
+//! The ellipsoid is given by `+ellps`, `+datum`, or the `+a`, `+b`, `+rf`, `+f`
+//! and `+R` parameters, and defaults to WGS84 (`+a` alone describes a sphere).
+//! Non-metric `+units` / `+to_meter`, a `+pm` other than Greenwich and an `+axis`
+//! other than `enu` wrap the CRS in an `AdjustedSystem`.
+//!
+//! Parameters that proj5 doesn't know are rejected with `UnsupportedKey`, known
+//! parameters that the projection doesn't use with `UnsupportedParameter`, so
+//! that no part of a definition is silently ignored. Degenerate values, such as
+//! a scale factor or satellite height that isn't positive or `lcc` standard
+//! parallels that are symmetric about the equator, are rejected with `InvalidValue`.
+//!
+//! `+towgs84` and `+datum` are only read into `ProjDefinition::to_wgs84`, they
+//! are never applied: `CoordinateSource::project` converts between two CRS on
+//! the same datum. To shift coordinates between datums, build a `pipeline`
+//! with `cart` and `helmert` steps from the `to_wgs84` of both definitions.
+
+use prelude::*;
+use coordinate_systems::adjusted::{AdjustedSystem, AxisDirection};
+use coordinate_systems::description::{Degenerate, Description};
+use coordinate_systems::igh::utils::GOODE_HOMOLOSINE_LOBES;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// All parameters that are understood by the parser
+const KNOWN_KEYS: [&str; 32] = [
+    "proj", "zone", "south", "ellps", "datum", "towgs84", "lat_0", "lon_0", "lat_1", "lat_2",
+    "lat_ts", "k", "k_0", "x_0", "y_0", "units", "to_meter", "pm", "axis", "no_defs",
+    "a", "b", "rf", "f", "R", "h", "sweep", "north_square", "south_square", "type",
+    "nadgrids", "wktext",
+];
+
+/// Ellipsoid identifiers of PROJ (`+ellps=...`)
+pub(crate) const ELLIPSOID_IDS: [(&str, &Ellipsoid); 19] = [
+    ("WGS84", &WGS_1984_ELLIPSOID),
+    ("GRS80", &GRS_1980_ELLIPSOID),
+    ("WGS72", &WGS_1972_ELLIPSOID),
+    ("WGS66", &WGS_1966_ELLIPSOID),
+    ("GRS67", &GRS_1967_ELLIPSOID),
+    ("airy", &AIRY_1830_ELLIPSOID),
+    ("mod_airy", &MODIFIED_AIRY_ELLIPSOID),
+    ("bessel", &BESSEL_1841_ELLIPSOID),
+    ("clrk66", &CLARKE_1866_ELLIPSOID),
+    ("clrk80", &CLARKE_1880_ELLIPSOID),
+    ("intl", &INTERNATIONAL_1924_ELLIPSOID),
+    ("krass", &KRASSOVSKY_1940_ELLIPSOID),
+    ("helmert", &HELMERT_1906_ELLIPSOID),
+    ("aust_SA", &AUSTRALIAN_1966_ELLIPSOID),
+    ("new_intl", &NEW_INTERNATIONAL_1967_ELLIPSOID),
+    ("evrst48", &EVEREST_1967_MODIFIED_ELLIPSOID),
+    ("evrstSS", &EVEREST_1967_DEFINITION_ELLIPSOID),
+    ("plessis", &PLESSIS_1817_ELLIPSOID),
+    ("mprts", &MAUPERTUIS_1738_ELLIPSOID),
+];
+
+/// Length units of PROJ (`+units=...`), in meters
+pub(crate) const UNITS: [(&str, f64); 21] = [
+    ("m", 1.0),
+    ("km", 1000.0),
+    ("dm", 0.1),
+    ("cm", 0.01),
+    ("mm", 0.001),
+    ("kmi", 1852.0),
+    ("in", 0.0254),
+    ("ft", 0.3048),
+    ("yd", 0.9144),
+    ("mi", 1609.344),
+    ("fath", 1.8288),
+    ("ch", 20.1168),
+    ("link", 0.201168),
+    ("us-in", 100.0 / 3937.0),
+    ("us-ft", 1200.0 / 3937.0),
+    ("us-yd", 3600.0 / 3937.0),
+    ("us-ch", 79200.0 / 3937.0),
+    ("us-mi", 6_336_000.0 / 3937.0),
+    ("ind-yd", 0.914_395_23),
+    ("ind-ft", 0.304_798_41),
+    ("ind-ch", 20.116_695_06),
+];
+
+/// Prime meridians of PROJ (`+pm=...`), in degrees east of Greenwich
+pub(crate) const PRIME_MERIDIANS: [(&str, f64); 14] = [
+    ("greenwich", 0.0),
+    ("lisbon", -9.131_906_111_111),
+    ("paris", 2.337_229_166_667),
+    ("bogota", -74.080_916_666_667),
+    ("madrid", -3.687_938_888_889),
+    ("rome", 12.452_333_333_333),
+    ("bern", 7.439_583_333_333),
+    ("jakarta", 106.807_719_444_444),
+    ("ferro", -17.666_666_666_667),
+    ("brussels", 4.367_975),
+    ("stockholm", 18.058_277_777_778),
+    ("athens", 23.716_337_5),
+    ("oslo", 10.722_916_666_667),
+    ("copenhagen", 12.577_88),
+];
+
+/// A CRS definition read from a PROJ string
+#[derive(Debug)]
+pub struct ProjDefinition {
+    /// The coordinate system
+    pub crs: Box<dyn Crs>,
+    /// The ellipsoid the coordinates refer to
+    pub ellipsoid: Ellipsoid,
+    /// Transformation of the datum to WGS84 (`+towgs84`, or implied by `+datum`)
+    ///
+    /// Only recorded, not applied to any coordinates (see the module docs).
+    pub to_wgs84: Option<Helmert>,
+}
+
+impl Clone for ProjDefinition {
+    fn clone(&self) -> Self {
+        ProjDefinition {
+            crs: Crs::clone(&*self.crs),
+            ellipsoid: self.ellipsoid,
+            to_wgs84: self.to_wgs84,
+        }
+    }
+}
+
+impl ProjDefinition {
+    /// Writes the definition as a PROJ string, see `to_proj_string`
+    pub fn to_proj_string(&self) -> Result<String, ProjStringError> {
+        to_proj_string(&*self.crs, &self.ellipsoid, self.to_wgs84.as_ref())
+    }
+}
+
+impl FromStr for ProjDefinition {
+    type Err = ProjStringError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_proj_string(s)
+    }
+}
+
+/// Error that can occur when reading or writing a PROJ string
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProjStringError {
+    /// A token isn't of the form `+key` or `+key=value`
+    InvalidFormat(String),
+    /// The parameter is given more than once, or together with its alias (`+k` and `+k_0`)
+    DuplicateKey(String),
+    /// proj5 doesn't know the parameter
+    UnsupportedKey(String),
+    /// There is no `+proj` parameter
+    MissingProjection,
+    /// proj5 doesn't implement the projection
+    UnsupportedProjection(String),
+    /// The parameter (second field) isn't used by the projection (first field)
+    UnsupportedParameter(String, String),
+    /// The projection (first field) requires the parameter (second field)
+    MissingParameter(String, String),
+    /// The value (second field) of the parameter (first field) is malformed or unknown
+    InvalidValue(String, String),
+    /// The parameter (first field) is only supported with a different value than the given one (second field)
+    UnsupportedValue(String, String),
+    /// The CRS can't be written as a PROJ string
+    UnsupportedCrs(String),
+}
+
+impl fmt::Display for ProjStringError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::ProjStringError::*;
+        match *self {
+            InvalidFormat(ref s) => write!(f, "invalid PROJ string token: \"{}\"", s),
+            DuplicateKey(ref key) => write!(f, "duplicate parameter: +{}", key),
+            UnsupportedKey(ref key) => write!(f, "unsupported parameter: +{}", key),
+            MissingProjection => write!(f, "missing +proj parameter"),
+            UnsupportedProjection(ref proj) => write!(f, "unsupported projection: +proj={}", proj),
+            UnsupportedParameter(ref proj, ref key) => write!(f, "parameter +{} is not supported by +proj={}", key, proj),
+            MissingParameter(ref proj, ref key) => write!(f, "+proj={} requires the parameter +{}", proj, key),
+            InvalidValue(ref key, ref value) => write!(f, "invalid value for +{}: \"{}\"", key, value),
+            UnsupportedValue(ref key, ref value) => write!(f, "unsupported value for +{}: \"{}\"", key, value),
+            UnsupportedCrs(ref crs) => write!(f, "CRS can't be written as a PROJ string: {}", crs),
+        }
+    }
+}
+
+impl Error for ProjStringError { }
+
+/// The parameters of a PROJ string, removed one by one while building the CRS
+pub(crate) struct Parameters<'a> {
+    values: Vec<(&'a str, Option<&'a str>)>,
+}
+
+impl<'a> Parameters<'a> {
+    fn parse(s: &'a str) -> Result<Self, ProjStringError> {
+        Parameters::parse_with_keys(s, &KNOWN_KEYS)
+    }
+
+    /// Parses the parameters, rejecting keys that aren't in `known_keys`
+    pub(crate) fn parse_with_keys(s: &'a str, known_keys: &[&str]) -> Result<Self, ProjStringError> {
+        let mut values: Vec<(&str, Option<&str>)> = Vec::new();
+
+        for token in s.split_whitespace() {
+            let token_body = token.strip_prefix('+').unwrap_or(token);
+            let (key, value) = match token_body.find('=') {
+                Some(index) => (&token_body[..index], Some(&token_body[index + 1..])),
+                None => (token_body, None),
+            };
+
+            if key.is_empty() || value == Some("") {
+                return Err(ProjStringError::InvalidFormat(token.to_string()));
+            }
+
+            if !known_keys.contains(&key) {
+                return Err(ProjStringError::UnsupportedKey(key.to_string()));
+            }
+
+            if values.iter().any(|&(k, _)| k == key) {
+                return Err(ProjStringError::DuplicateKey(key.to_string()));
+            }
+
+            values.push((key, value));
+        }
+
+        Ok(Parameters { values })
+    }
+
+    /// Removes the parameter, `Some(None)` for flags without a value
+    fn take(&mut self, key: &str) -> Option<Option<&'a str>> {
+        let index = self.values.iter().position(|&(k, _)| k == key)?;
+        Some(self.values.remove(index).1)
+    }
+
+    pub(crate) fn take_flag(&mut self, key: &str) -> Result<bool, ProjStringError> {
+        match self.take(key) {
+            None => Ok(false),
+            Some(None) => Ok(true),
+            Some(Some(value)) => Err(ProjStringError::InvalidValue(key.to_string(), value.to_string())),
+        }
+    }
+
+    pub(crate) fn take_str(&mut self, key: &str) -> Result<Option<&'a str>, ProjStringError> {
+        match self.take(key) {
+            None => Ok(None),
+            Some(None) => Err(ProjStringError::InvalidValue(key.to_string(), String::new())),
+            Some(Some(value)) => Ok(Some(value)),
+        }
+    }
+
+    pub(crate) fn take_f64(&mut self, key: &str) -> Result<Option<f64>, ProjStringError> {
+        match self.take_str(key)? {
+            None => Ok(None),
+            Some(value) => value.parse::<f64>().ok()
+                .filter(|v| v.is_finite())
+                .map(Some)
+                .ok_or_else(|| ProjStringError::InvalidValue(key.to_string(), value.to_string())),
+        }
+    }
+
+    /// Removes a number that must be present
+    fn require_f64(&mut self, proj: &str, key: &str) -> Result<f64, ProjStringError> {
+        self.take_f64(key)?.ok_or_else(|| ProjStringError::MissingParameter(proj.to_string(), key.to_string()))
+    }
+
+    /// Removes a parameter that proj5 only supports with its default value
+    fn take_default(&mut self, key: &str, default: f64) -> Result<(), ProjStringError> {
+        match self.take_f64(key)? {
+            Some(value) if value != default => Err(ProjStringError::UnsupportedValue(key.to_string(), value.to_string())),
+            _ => Ok(()),
+        }
+    }
+
+    fn contains(&self, key: &str) -> bool {
+        self.values.iter().any(|&(k, _)| k == key)
+    }
+
+    /// Removes the scale factor, `+k_0` or its alias `+k` (giving both is a `DuplicateKey`)
+    fn take_scale_factor(&mut self) -> Result<Option<f64>, ProjStringError> {
+        let k_0 = self.take_f64("k_0")?;
+        let k = self.take_f64("k")?;
+        if k_0.is_some() && k.is_some() {
+            return Err(ProjStringError::DuplicateKey("k_0".to_string()));
+        }
+        Ok(k_0.or(k))
+    }
+}
+
+fn find_ellipsoid(id: &str) -> Option<Ellipsoid> {
+    ELLIPSOID_IDS.iter().find(|&&(i, _)| i.eq_ignore_ascii_case(id)).map(|&(_, e)| *e)
+}
+
+fn find_datum(id: &str) -> Option<&'static Datum> {
+    DATUMS.iter().find(|d| d.proj_id.eq_ignore_ascii_case(id)).cloned()
+}
+
+/// Builds the ellipsoid and the transformation to WGS84 from `+datum`, `+ellps`,
+/// `+towgs84` and the ellipsoid parameters
+fn parse_ellipsoid(params: &mut Parameters)
+                   -> Result<(Ellipsoid, Option<Helmert>), ProjStringError>
+{
+    let datum = match params.take_str("datum")? {
+        Some(id) => Some(find_datum(id).ok_or_else(|| ProjStringError::InvalidValue("datum".to_string(), id.to_string()))?),
+        None => None,
+    };
+
+    let base = match params.take_str("ellps")? {
+        Some(id) => Some(find_ellipsoid(id).ok_or_else(|| ProjStringError::InvalidValue("ellps".to_string(), id.to_string()))?),
+        None => datum.map(|d| d.ellipsoid),
+    };
+
+    let radius = params.take_f64("R")?;
+    let a = params.take_f64("a")?;
+    let b = params.take_f64("b")?;
+    let rf = params.take_f64("rf")?;
+    let f = params.take_f64("f")?;
+
+    let ellipsoid = if let Some(radius) = radius {
+        if radius <= 0.0 {
+            return Err(ProjStringError::InvalidValue("R".to_string(), radius.to_string()));
+        }
+        Ellipsoid::from_axes(radius, radius)
+    } else if a.is_some() || b.is_some() || rf.is_some() || f.is_some() {
+        let a = a.unwrap_or_else(|| base.unwrap_or(WGS_1984_ELLIPSOID).a);
+
+        let b = if let Some(b) = b {
+            b
+        } else if let Some(rf) = rf.filter(|&rf| rf != 0.0) {
+            a * (1.0 - 1.0 / rf)
+        } else if let Some(f) = f {
+            a * (1.0 - f)
+        } else if rf.is_some() {
+            a
+        } else {
+            base.map(|base| a * base.b / base.a).unwrap_or(a)
+        };
+
+        if !(a > 0.0 && b > 0.0 && b <= a) {
+            return Err(ProjStringError::InvalidValue("b".to_string(), b.to_string()));
+        }
+
+        Ellipsoid::from_axes(a, b)
+    } else {
+        base.unwrap_or(WGS_1984_ELLIPSOID)
+    };
+
+    let to_wgs84 = match params.take_str("towgs84")? {
+        Some(value) => {
+            let invalid = || ProjStringError::InvalidValue("towgs84".to_string(), value.to_string());
+            let values = value.split(',')
+                .map(|v| v.trim().parse::<f64>().ok().filter(|v| v.is_finite()))
+                .collect::<Option<Vec<f64>>>()
+                .ok_or_else(invalid)?;
+            Some(Helmert::from_towgs84(&values).ok_or_else(invalid)?)
+        },
+        None => datum.and_then(|d| d.to_wgs84),
+    };
+
+    Ok((ellipsoid, to_wgs84))
+}
+
+/// Parses the `+axis` parameter, e.g. "enu" or "neu"
+fn parse_axis(value: &str) -> Option<(AxisDirection, AxisDirection)> {
+    use self::AxisDirection::*;
+
+    let mut chars = value.chars();
+    let first = AxisDirection::from_char(chars.next()?)?;
+    let second = AxisDirection::from_char(chars.next()?)?;
+    let vertical = chars.next()?;
+
+    let is_east_west = |d: AxisDirection| d == East || d == West;
+    if is_east_west(first) == is_east_west(second) || !(vertical == 'u' || vertical == 'd') || chars.next().is_some() {
+        return None;
+    }
+
+    Some((first, second))
+}
+
+/// Builds the CRS of the `+proj` parameter from its specific parameters.
+/// Returns the CRS and whether it is geographic (longitude / latitude).
+fn parse_projection(proj: &str, params: &mut Parameters)
+                    -> Result<(Box<dyn Crs>, bool), ProjStringError>
+{
+    let invalid_value = |key: &str, value: f64| ProjStringError::InvalidValue(key.to_string(), value.to_string());
+
+    // Parameters that are reported if the projection is undefined for them
+    let scale_key = if params.contains("k_0") { "k_0" } else { "k" };
+    let parallel_key = if params.contains("lat_2") { "lat_2" } else { "lat_1" };
+
+    let crs: Box<dyn Crs> = match proj {
+        "longlat" | "latlong" | "lonlat" | "latlon" => {
+            return Ok((Box::new(LonLatSystem), true));
+        },
+        "utm" => {
+            let zone = params.require_f64(proj, "zone")?;
+            if zone.fract() != 0.0 || !(1.0..=60.0).contains(&zone) {
+                return Err(invalid_value("zone", zone));
+            }
+            let hemisphere = if params.take_flag("south")? { Hemisphere::South } else { Hemisphere::North };
+            Box::new(UTMSystem { utm_zone: zone as u8, hemisphere })
+        },
+        "ups" => {
+            let hemisphere = if params.take_flag("south")? { Hemisphere::South } else { Hemisphere::North };
+            Box::new(UPSSystem { hemisphere })
+        },
+        "merc" | "webmerc" => {
+            params.take_default("lat_ts", 0.0)?;
+            params.take_default("lon_0", 0.0)?;
+            params.take_default("x_0", 0.0)?;
+            params.take_default("y_0", 0.0)?;
+            if let Some(k) = params.take_scale_factor()?.filter(|&k| k != 1.0) {
+                return Err(ProjStringError::UnsupportedValue("k".to_string(), k.to_string()));
+            }
+            if proj == "merc" { Box::new(MercatorSystem) } else { Box::new(WebMercatorSystem) }
+        },
+        "tmerc" => {
+            Box::new(TransverseMercatorSystem {
+                lat_0: params.take_f64("lat_0")?.unwrap_or(0.0),
+                lon_0: params.take_f64("lon_0")?.unwrap_or(0.0),
+                scale_factor: params.take_scale_factor()?.unwrap_or(1.0),
+                false_easting: params.take_f64("x_0")?.unwrap_or(0.0),
+                false_northing: params.take_f64("y_0")?.unwrap_or(0.0),
+            })
+        },
+        "lcc" => {
+            let lat_1 = params.require_f64(proj, "lat_1")?;
+            let lat_2 = params.take_f64("lat_2")?.unwrap_or(lat_1);
+            Box::new(LambertConformalConicSystem {
+                lat_0: params.take_f64("lat_0")?.unwrap_or(0.0),
+                lon_0: params.take_f64("lon_0")?.unwrap_or(0.0),
+                lat_1,
+                lat_2,
+                scale_factor: params.take_scale_factor()?.unwrap_or(1.0),
+                false_easting: params.take_f64("x_0")?.unwrap_or(0.0),
+                false_northing: params.take_f64("y_0")?.unwrap_or(0.0),
+            })
+        },
+        "geos" => {
+            let satellite_height = params.require_f64(proj, "h")?;
+            let sweep_axis = match params.take_str("sweep")? {
+                None | Some("y") => SweepAxis::Y,
+                Some("x") => SweepAxis::X,
+                Some(other) => return Err(ProjStringError::InvalidValue("sweep".to_string(), other.to_string())),
+            };
+            params.take_default("x_0", 0.0)?;
+            params.take_default("y_0", 0.0)?;
+            Box::new(GeostationarySystem {
+                satellite_height,
+                sub_satellite_lon: params.take_f64("lon_0")?.unwrap_or(0.0),
+                sweep_axis,
+            })
+        },
+        "healpix" => {
+            params.take_default("x_0", 0.0)?;
+            params.take_default("y_0", 0.0)?;
+            Box::new(HealpixSystem { lon_0: params.take_f64("lon_0")?.unwrap_or(0.0) })
+        },
+        "rhealpix" => {
+            let mut square = |key: &str| -> Result<u8, ProjStringError> {
+                match params.take_f64(key)? {
+                    None => Ok(0),
+                    Some(value) if value.fract() == 0.0 && (0.0..=3.0).contains(&value) => Ok(value as u8),
+                    Some(value) => Err(invalid_value(key, value)),
+                }
+            };
+            let north_square = square("north_square")?;
+            let south_square = square("south_square")?;
+            params.take_default("x_0", 0.0)?;
+            params.take_default("y_0", 0.0)?;
+            Box::new(RHealpixSystem { lon_0: params.take_f64("lon_0")?.unwrap_or(0.0), north_square, south_square })
+        },
+        "igh" => {
+            params.take_default("lon_0", 0.0)?;
+            params.take_default("x_0", 0.0)?;
+            params.take_default("y_0", 0.0)?;
+            Box::new(GoodeHomolosineSystem { lobes: &GOODE_HOMOLOSINE_LOBES })
+        },
+        _ => return Err(ProjStringError::UnsupportedProjection(proj.to_string())),
+    };
+
+    if let Some(description) = Description::of(&*crs) {
+        description.validate().map_err(|degenerate| match degenerate {
+            Degenerate::ScaleFactor(k) => invalid_value(scale_key, k),
+            Degenerate::SatelliteHeight(h) => invalid_value("h", h),
+            Degenerate::StandardParallels(_, lat_2) => invalid_value(parallel_key, lat_2),
+        })?;
+    }
+
+    Ok((crs, false))
+}
+
+/// Parses a PROJ string such as `+proj=utm +zone=32 +ellps=WGS84 +units=m +no_defs`
+///
+/// The leading `+` of the parameters is optional. See the module documentation
+/// for the supported projections and parameters.
+pub fn parse_proj_string(s: &str)
+                         -> Result<ProjDefinition, ProjStringError>
+{
+    let mut params = Parameters::parse(s)?;
+
+    let proj = params.take_str("proj")?.ok_or(ProjStringError::MissingProjection)?;
+    let (crs, geographic) = parse_projection(proj, &mut params)?;
+    let (ellipsoid, to_wgs84) = parse_ellipsoid(&mut params)?;
+
+    // Units only apply to projected coordinates, geographic coordinates are always in degrees
+    let mut to_meter = 1.0;
+    if !geographic {
+        if let Some(units) = params.take_str("units")? {
+            to_meter = UNITS.iter().find(|&&(id, _)| id == units).map(|&(_, m)| m)
+                .ok_or_else(|| ProjStringError::InvalidValue("units".to_string(), units.to_string()))?;
+        }
+        if let Some(value) = params.take_f64("to_meter")? {
+            if value <= 0.0 {
+                return Err(ProjStringError::InvalidValue("to_meter".to_string(), value.to_string()));
+            }
+            to_meter = value;
+        }
+    }
+
+    let prime_meridian = match params.take_str("pm")? {
+        None => 0.0,
+        Some(pm) => match PRIME_MERIDIANS.iter().find(|&&(id, _)| id == pm) {
+            Some(&(_, lon)) => lon,
+            None => pm.parse::<f64>().ok().filter(|v| v.is_finite())
+                .ok_or_else(|| ProjStringError::InvalidValue("pm".to_string(), pm.to_string()))?,
+        },
+    };
+
+    let axis = match params.take_str("axis")? {
+        None => (AxisDirection::East, AxisDirection::North),
+        Some(value) => parse_axis(value).ok_or_else(|| ProjStringError::InvalidValue("axis".to_string(), value.to_string()))?,
+    };
+
+    params.take_flag("no_defs")?;
+    params.take_flag("wktext")?;
+    if let Some(value) = params.take_str("type")?.filter(|&v| v != "crs") {
+        return Err(ProjStringError::UnsupportedValue("type".to_string(), value.to_string()));
+    }
+    if let Some(value) = params.take_str("nadgrids")?.filter(|&v| v != "@null") {
+        return Err(ProjStringError::UnsupportedValue("nadgrids".to_string(), value.to_string()));
+    }
+
+    if let Some(&(key, _)) = params.values.first() {
+        return Err(ProjStringError::UnsupportedParameter(proj.to_string(), key.to_string()));
+    }
+
+    let crs: Box<dyn Crs> = if to_meter != 1.0 || prime_meridian != 0.0 || axis != (AxisDirection::East, AxisDirection::North) {
+        Box::new(AdjustedSystem { crs, to_meter, prime_meridian, axis })
+    } else {
+        crs
+    };
+
+    Ok(ProjDefinition { crs, ellipsoid, to_wgs84 })
+}
+
+/// Returns the `+proj` parameter and the projection-specific parameters of the CRS,
+/// plus whether it is geographic
+fn projection_parameters(crs: &dyn Crs)
+                         -> Result<(Vec<String>, bool), ProjStringError>
+{
+    let unsupported = || ProjStringError::UnsupportedCrs(format!("{:?}", crs));
+    let mut parts = Vec::new();
+
+    match Description::of(crs).ok_or_else(unsupported)? {
+        Description::LonLat => {
+            parts.push("+proj=longlat".to_string());
+            return Ok((parts, true));
+        },
+        Description::Utm(system) => {
+            parts.push("+proj=utm".to_string());
+            parts.push(format!("+zone={}", system.utm_zone));
+            if system.hemisphere == Hemisphere::South {
+                parts.push("+south".to_string());
+            }
+        },
+        Description::Ups(system) => {
+            parts.push("+proj=ups".to_string());
+            if system.hemisphere == Hemisphere::South {
+                parts.push("+south".to_string());
+            }
+        },
+        Description::Mercator => parts.push("+proj=merc".to_string()),
+        Description::WebMercator => parts.push("+proj=webmerc".to_string()),
+        Description::TransverseMercator(system) => {
+            parts.push("+proj=tmerc".to_string());
+            parts.push(format!("+lat_0={}", system.lat_0));
+            parts.push(format!("+lon_0={}", system.lon_0));
+            parts.push(format!("+k={}", system.scale_factor));
+            parts.push(format!("+x_0={}", system.false_easting));
+            parts.push(format!("+y_0={}", system.false_northing));
+        },
+        Description::LambertConic(system) => {
+            parts.push("+proj=lcc".to_string());
+            parts.push(format!("+lat_0={}", system.lat_0));
+            parts.push(format!("+lon_0={}", system.lon_0));
+            parts.push(format!("+lat_1={}", system.lat_1));
+            parts.push(format!("+lat_2={}", system.lat_2));
+            if system.scale_factor != 1.0 {
+                parts.push(format!("+k_0={}", system.scale_factor));
+            }
+            parts.push(format!("+x_0={}", system.false_easting));
+            parts.push(format!("+y_0={}", system.false_northing));
+        },
+        Description::Geostationary(system) => {
+            parts.push("+proj=geos".to_string());
+            parts.push(format!("+h={}", system.satellite_height));
+            parts.push(format!("+lon_0={}", system.sub_satellite_lon));
+            if system.sweep_axis == SweepAxis::X {
+                parts.push("+sweep=x".to_string());
+            }
+        },
+        Description::Healpix(system) => {
+            parts.push("+proj=healpix".to_string());
+            parts.push(format!("+lon_0={}", system.lon_0));
+        },
+        Description::RHealpix(system) => {
+            parts.push("+proj=rhealpix".to_string());
+            parts.push(format!("+lon_0={}", system.lon_0));
+            parts.push(format!("+north_square={}", system.north_square));
+            parts.push(format!("+south_square={}", system.south_square));
+        },
+        Description::GoodeHomolosine => parts.push("+proj=igh".to_string()),
+        // parse_proj_string doesn't read +proj=krovak
+        Description::Krovak(_) => return Err(unsupported()),
+    }
+
+    Ok((parts, false))
+}
+
+/// Returns the parameters that describe the ellipsoid and the datum
+fn ellipsoid_parameters(ellipsoid: &Ellipsoid, to_wgs84: Option<&Helmert>)
+                        -> Vec<String>
+{
+    let same_axes = |e: &Ellipsoid| e.a == ellipsoid.a && e.b == ellipsoid.b;
+
+    if let Some(datum) = Datum::identify(ellipsoid, to_wgs84) {
+        return vec![format!("+datum={}", datum.proj_id)];
+    }
+
+    let mut parts = Vec::new();
+
+    if let Some(&(id, _)) = ELLIPSOID_IDS.iter().find(|&&(_, e)| same_axes(e)) {
+        parts.push(format!("+ellps={}", id));
+    } else if ellipsoid.a == ellipsoid.b {
+        parts.push(format!("+R={}", ellipsoid.a));
+    } else {
+        parts.push(format!("+a={}", ellipsoid.a));
+        parts.push(format!("+b={}", ellipsoid.b));
+    }
+
+    if let Some(helmert) = to_wgs84 {
+        let values: Vec<String> = helmert.to_towgs84().iter().map(|v| v.to_string()).collect();
+        parts.push(format!("+towgs84={}", values.join(",")));
+    }
+
+    parts
+}
+
+/// Writes the CRS, ellipsoid and transformation to WGS84 as a PROJ string,
+/// e.g. `+proj=utm +zone=32 +datum=WGS84 +units=m +no_defs`
+///
+/// If the ellipsoid and the transformation identify one of the `DATUMS`, `+datum`
+/// is written instead of `+ellps` and `+towgs84`. Returns `UnsupportedCrs` for
+/// coordinate systems that have no PROJ equivalent in `parse_proj_string`.
+pub fn to_proj_string(crs: &dyn Crs, ellipsoid: &Ellipsoid, to_wgs84: Option<&Helmert>)
+                      -> Result<String, ProjStringError>
+{
+    let adjusted = crs.as_any().downcast_ref::<AdjustedSystem>();
+    let inner = adjusted.map(|a| &*a.crs).unwrap_or(crs);
+
+    let (mut parts, geographic) = projection_parameters(inner)?;
+    parts.extend(ellipsoid_parameters(ellipsoid, to_wgs84));
+
+    let (to_meter, prime_meridian, axis) = match adjusted {
+        Some(a) => (a.to_meter, a.prime_meridian, a.axis),
+        None => (1.0, 0.0, (AxisDirection::East, AxisDirection::North)),
+    };
+
+    if prime_meridian != 0.0 {
+        match PRIME_MERIDIANS.iter().find(|&&(_, lon)| lon == prime_meridian) {
+            Some(&(id, _)) => parts.push(format!("+pm={}", id)),
+            None => parts.push(format!("+pm={}", prime_meridian)),
+        }
+    }
+
+    if !geographic {
+        match UNITS.iter().find(|&&(_, m)| m == to_meter) {
+            Some(&(id, _)) => parts.push(format!("+units={}", id)),
+            None => parts.push(format!("+to_meter={}", to_meter)),
+        }
+    } else if to_meter != 1.0 {
+        return Err(ProjStringError::UnsupportedCrs(format!("{:?}", crs)));
+    }
+
+    if axis != (AxisDirection::East, AxisDirection::North) {
+        parts.push(format!("+axis={}{}u", axis.0.to_char(), axis.1.to_char()));
+    }
+
+    parts.push("+no_defs".to_string());
+
+    Ok(parts.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn describe(s: &str) -> Option<Description> {
+        Description::of(&*parse_proj_string(s).unwrap().crs)
+    }
+
+    #[test]
+    fn projections() {
+        assert_eq!(describe("+proj=utm +zone=32 +ellps=WGS84 +units=m +no_defs"),
+                   Some(Description::Utm(UTMSystem { utm_zone: 32, hemisphere: Hemisphere::North })));
+        assert_eq!(describe("proj=utm zone=33 south datum=WGS84"),
+                   Some(Description::Utm(UTMSystem { utm_zone: 33, hemisphere: Hemisphere::South })));
+        assert_eq!(describe("+proj=longlat +datum=WGS84 +no_defs"), Some(Description::LonLat));
+        assert_eq!(describe("+proj=lcc +lat_0=46.5 +lon_0=3 +lat_1=49 +lat_2=44 +x_0=700000 +y_0=6600000 +ellps=GRS80"),
+                   Some(Description::LambertConic(LAMBERT_93)));
+        assert_eq!(describe("+proj=igh"), Some(Description::GoodeHomolosine));
+    }
+
+    #[test]
+    fn british_national_grid() {
+        let s = "+proj=tmerc +lat_0=49 +lon_0=-2 +k=0.9996012717 +x_0=400000 +y_0=-100000 +ellps=airy \
+                 +towgs84=446.448,-125.157,542.06,0.15,0.247,0.842,-20.489 +units=m +no_defs";
+        let definition = parse_proj_string(s).unwrap();
+        assert_eq!(Description::of(&*definition.crs), Some(Description::TransverseMercator(BRITISH_NATIONAL_GRID)));
+        assert_eq!(definition.ellipsoid, AIRY_1830_ELLIPSOID);
+        assert_eq!(definition.to_wgs84.unwrap().to_towgs84()[0], 446.448);
+
+        let written = definition.to_proj_string().unwrap();
+        let read = parse_proj_string(&written).unwrap();
+        assert_eq!(Description::of(&*read.crs), Some(Description::TransverseMercator(BRITISH_NATIONAL_GRID)));
+        assert_eq!(read.to_wgs84, definition.to_wgs84);
+    }
+
+    #[test]
+    fn invalid_parameters() {
+        let error = |s: &str| parse_proj_string(s).map(|_| ()).unwrap_err();
+        let invalid = |key: &str, value: &str| ProjStringError::InvalidValue(key.to_string(), value.to_string());
+
+        assert_eq!(error("+proj=merc +R=0"), invalid("R", "0"));
+        assert_eq!(error("+proj=merc +R=-5"), invalid("R", "-5"));
+        assert_eq!(error("+proj=merc +a=6378137 +b=-1"), invalid("b", "-1"));
+        assert_eq!(error("+proj=tmerc +k=0.9996 +k_0=0.9996"), ProjStringError::DuplicateKey("k_0".to_string()));
+        assert_eq!(error("+proj=tmerc +k=0"), invalid("k", "0"));
+        assert_eq!(error("+proj=geos +h=0"), invalid("h", "0"));
+        assert_eq!(error("+proj=lcc +lat_1=30 +lat_2=-30"), invalid("lat_2", "-30"));
+        assert_eq!(error("+proj=utm +zone=61"), invalid("zone", "61"));
+        assert_eq!(error("+proj=utm +zone=32 +zone=33"), ProjStringError::DuplicateKey("zone".to_string()));
+        assert_eq!(error("+proj=utm"), ProjStringError::MissingParameter("utm".to_string(), "zone".to_string()));
+        assert_eq!(error("+zone=32"), ProjStringError::MissingProjection);
+        assert_eq!(error("+proj=utm +zone=32 +foo=1"), ProjStringError::UnsupportedKey("foo".to_string()));
+    }
+}
@@ -0,0 +1,1191 @@
+//! PROJJSON CRS definitions
+//!
+//! PROJJSON is the JSON encoding of WKT2:2019 written by PROJ (`projinfo -o PROJJSON`),
+//! and the CRS format of GeoParquet and STAC. `parse_projjson` reads a
+//! `GeographicCRS`, a `ProjectedCRS`, a `BoundCRS` to WGS 84 or the horizontal
+//! part of a `CompoundCRS` into a `ProjJsonDefinition`, `to_projjson` writes one.
+//!
+//! Every coordinate system of `crs` except the `AutoUTMSystem` can be written:
+//!
+//! - `LonLatSystem`: `GeographicCRS` in degrees
+//! - `UTMSystem` and `TransverseMercatorSystem`: Transverse Mercator
+//! - `MercatorSystem`: Mercator (variant A) with the default origin and scale
+//! - `WebMercatorSystem`: Popular Visualisation Pseudo Mercator
+//! - `UPSSystem`: Polar Stereographic (variant A) with the UPS parameters
+//! - `LambertConformalConicSystem`: Lambert Conic Conformal (1SP), (2SP) and, for
+//!   two standard parallels with a scale factor, (2SP Michigan)
+//! - `GeostationarySystem`: Geostationary Satellite (Sweep X) / (Sweep Y)
+//! - `KrovakSystem`: Krovak, Krovak (North Orientated), Krovak Modified and
+//!   Krovak Modified (North Orientated)
+//! - `GoodeHomolosineSystem` with the standard lobes: Interrupted Goode Homolosine
+//! - `HealpixSystem` and `RHealpixSystem`: the "HEALPix" and "rHEALPix" methods
+//!   (with "North square" and "South square" parameters), which have no EPSG code
+//!
+//! Like in `wkt`, non-metric units and prime meridians other than Greenwich wrap
+//! the CRS in an `AdjustedSystem`, and the axis directions of the coordinate
+//! system are not applied when reading: the PROJJSON of EPSG:4326 declares
+//! latitude first, but is read as (longitude, latitude). Ellipsoids that proj5
+//! doesn't know are written with their semi-minor axis, so that every written
+//! definition is read back to the same CRS, ellipsoid and transformation.
+//!
+//! Single ellipsoids and datums are converted by `ellipsoid_to_projjson`,
+//! `parse_projjson_ellipsoid`, `datum_to_projjson` and `parse_projjson_datum`.
+//!
+//! ```rust
+//! extern crate proj5;
+//!
+//! use proj5::prelude::*;
+//! use proj5::projjson::*;
+//! use proj5::proj_string::to_proj_string;
+//!
+//! fn main() {
+//!     // As written by `projinfo -o PROJJSON EPSG:32632` (shortened)
+//!     let json = r#"{
+//!       "type": "ProjectedCRS",
+//!       "name": "WGS 84 / UTM zone 32N",
+//!       "base_crs": {
+//!         "name": "WGS 84",
+//!         "datum_ensemble": {
+//!           "name": "World Geodetic System 1984 ensemble",
+//!           "members": [{"name": "World Geodetic System 1984 (Transit)"}],
+//!           "ellipsoid": {"name": "WGS 84", "semi_major_axis": 6378137, "inverse_flattening": 298.257223563},
+//!           "accuracy": "2.0"
+//!         },
+//!         "coordinate_system": {
+//!           "subtype": "ellipsoidal",
+//!           "axis": [
+//!             {"name": "Geodetic latitude", "abbreviation": "Lat", "direction": "north", "unit": "degree"},
+//!             {"name": "Geodetic longitude", "abbreviation": "Lon", "direction": "east", "unit": "degree"}
+//!           ]
+//!         }
+//!       },
+//!       "conversion": {
+//!         "name": "UTM zone 32N",
+//!         "method": {"name": "Transverse Mercator", "id": {"authority": "EPSG", "code": 9807}},
+//!         "parameters": [
+//!           {"name": "Latitude of natural origin", "value": 0, "unit": "degree", "id": {"authority": "EPSG", "code": 8801}},
+//!           {"name": "Longitude of natural origin", "value": 9, "unit": "degree", "id": {"authority": "EPSG", "code": 8802}},
+//!           {"name": "Scale factor at natural origin", "value": 0.9996, "unit": "unity", "id": {"authority": "EPSG", "code": 8805}},
+//!           {"name": "False easting", "value": 500000, "unit": "metre", "id": {"authority": "EPSG", "code": 8806}},
+//!           {"name": "False northing", "value": 0, "unit": "metre", "id": {"authority": "EPSG", "code": 8807}}
+//!         ]
+//!       },
+//!       "coordinate_system": {
+//!         "subtype": "Cartesian",
+//!         "axis": [
+//!           {"name": "Easting", "abbreviation": "E", "direction": "east", "unit": "metre"},
+//!           {"name": "Northing", "abbreviation": "N", "direction": "north", "unit": "metre"}
+//!         ]
+//!       },
+//!       "id": {"authority": "EPSG", "code": 32632}
+//!     }"#;
+//!
+//!     let definition = parse_projjson(json).unwrap();
+//!     assert_eq!(definition.name, "WGS 84 / UTM zone 32N");
+//!     assert_eq!(definition.datum(), Some(&WGS84_DATUM));
+//!     assert_eq!(to_proj_string(&*definition.crs, &definition.ellipsoid, None).unwrap(),
+//!                "+proj=utm +zone=32 +ellps=WGS84 +units=m +no_defs");
+//!
+//!     // The metadata of a buffer is re-created without losses
+//!     let buf = CoordinateBuf {
+//!         data: vec![(3_500_000.0, 5_400_000.0)],
+//!         crs: Box::new(TransverseMercatorSystem {
+//!             lat_0: 0.0,
+//!             lon_0: 9.0,
+//!             scale_factor: 1.0,
+//!             false_easting: 3_500_000.0,
+//!             false_northing: 0.0,
+//!         }),
+//!         ellipsoid: BESSEL_1841_ELLIPSOID,
+//!     };
+//!     let json = buf.crs_to_projjson().unwrap();
+//!     let read = CoordinateBuf::from_projjson(buf.data.clone(), &json).unwrap();
+//!     assert_eq!(read.ellipsoid, buf.ellipsoid);
+//!     assert_eq!(to_proj_string(&*read.crs, &read.ellipsoid, None),
+//!                to_proj_string(&*buf.crs, &buf.ellipsoid, None));
+//!
+//!     // Datums and ellipsoids on their own
+//!     assert_eq!(parse_projjson_datum(&datum_to_projjson(&OSGB36_DATUM)).unwrap(), &OSGB36_DATUM);
+//!     let sphere = Ellipsoid::from_axes(6_371_000.0, 6_371_000.0);
+//!     assert_eq!(parse_projjson_ellipsoid(&ellipsoid_to_projjson(&sphere)).unwrap(), sphere);
+//! }
+//! ```
+
+use prelude::*;
+use coordinate_systems::adjusted::{AdjustedSystem, AxisDirection};
+use coordinate_systems::description::{Degenerate, Description};
+use coordinate_systems::igh::utils::GOODE_HOMOLOSINE_LOBES;
+use coordinate_systems::krovak::utils as krovak;
+use json::Json;
+use proj_string::{ELLIPSOID_IDS, PRIME_MERIDIANS};
+use wkt::{ARC_SECOND, DATUM_NAMES, ELLIPSOID_NAMES, LENGTH_UNITS, adjust, find_datum_names, find_ellipsoid,
+          name_key, normalize, snap, unit_to_degrees, unit_to_meters};
+use std::error::Error;
+use std::f64::consts::PI;
+use std::fmt;
+use std::str::FromStr;
+
+/// The PROJJSON schema that written documents refer to
+const SCHEMA: &str = "https://proj.org/schemas/v0.7/projjson.schema.json";
+
+/// A CRS definition read from or written to PROJJSON
+#[derive(Debug)]
+pub struct ProjJsonDefinition {
+    /// Name of the CRS, e.g. "WGS 84 / UTM zone 32N"
+    pub name: String,
+    /// Name of the geographic CRS (the base CRS of projected ones), e.g. "WGS 84"
+    pub geographic_name: String,
+    /// Name of the datum, e.g. "World Geodetic System 1984"
+    pub datum_name: String,
+    /// The coordinate system
+    pub crs: Box<dyn Crs>,
+    /// The ellipsoid the coordinates refer to
+    pub ellipsoid: Ellipsoid,
+    /// Transformation of the datum to WGS84 (`BoundCRS`)
+    pub to_wgs84: Option<Helmert>,
+}
+
+impl Clone for ProjJsonDefinition {
+    fn clone(&self) -> Self {
+        ProjJsonDefinition {
+            name: self.name.clone(),
+            geographic_name: self.geographic_name.clone(),
+            datum_name: self.datum_name.clone(),
+            crs: Crs::clone(&*self.crs),
+            ellipsoid: self.ellipsoid,
+            to_wgs84: self.to_wgs84,
+        }
+    }
+}
+
+impl ProjJsonDefinition {
+    /// Creates a definition, naming the CRS and the datum after the datum
+    /// identified by the ellipsoid and the transformation (see `Datum::identify`),
+    /// "unknown" otherwise
+    pub fn new(crs: Box<dyn Crs>, ellipsoid: Ellipsoid, to_wgs84: Option<Helmert>) -> Self {
+        let names = Datum::identify(&ellipsoid, to_wgs84.as_ref())
+            .and_then(|datum| DATUM_NAMES.iter().find(|n| n.proj_id == datum.proj_id));
+        let geographic_name = names.map(|n| n.crs).unwrap_or("unknown").to_string();
+        let datum_name = names.map(|n| n.wkt2).unwrap_or("unknown").to_string();
+
+        let adjusted = crs.as_any().downcast_ref::<AdjustedSystem>();
+        let inner = adjusted.map(|a| &*a.crs).unwrap_or(&*crs);
+        let name = match describe_conversion(inner) {
+            Ok(Some(conversion)) => format!("{} / {}", geographic_name, conversion.name),
+            _ => geographic_name.clone(),
+        };
+
+        ProjJsonDefinition { name, geographic_name, datum_name, crs, ellipsoid, to_wgs84 }
+    }
+
+    /// Returns the datum, known by its name or identified by the ellipsoid and
+    /// the transformation to WGS84
+    pub fn datum(&self) -> Option<&'static Datum> {
+        find_datum_names(&self.datum_name)
+            .and_then(|names| DATUMS.iter().cloned().find(|d| d.proj_id == names.proj_id))
+            .or_else(|| Datum::identify(&self.ellipsoid, self.to_wgs84.as_ref()))
+    }
+
+    /// Writes the definition as PROJJSON
+    ///
+    /// Returns `UnsupportedCrs` for the `AutoUTMSystem`, Goode Homolosine lobes
+    /// other than the standard ones and flipped or swapped axes.
+    pub fn to_projjson(&self) -> Result<String, ProjJsonError> {
+        write_crs(self).map(document)
+    }
+}
+
+impl FromStr for ProjJsonDefinition {
+    type Err = ProjJsonError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_projjson(s)
+    }
+}
+
+/// Error that can occur when reading or writing PROJJSON
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProjJsonError {
+    /// The text isn't valid JSON, the field is the byte offset of the error
+    InvalidJson(usize),
+    /// The type of the object (its `type` member) isn't supported, e.g. `VerticalCRS`
+    UnsupportedType(String),
+    /// A required member is missing
+    MissingMember(String),
+    /// The member has the wrong JSON type or an invalid value
+    InvalidMember(String),
+    /// proj5 doesn't implement the projection method
+    UnsupportedProjection(String),
+    /// The parameter (second field) isn't used by the projection method (first field)
+    UnsupportedParameter(String, String),
+    /// The parameter (first field) is only supported with a different value than the given one (second field)
+    UnsupportedValue(String, String),
+    /// The projection is undefined for the value (second field) of the parameter (first field)
+    InvalidValue(String, String),
+    /// The CRS can't be written as PROJJSON
+    UnsupportedCrs(String),
+}
+
+impl fmt::Display for ProjJsonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::ProjJsonError::*;
+        match *self {
+            InvalidJson(position) => write!(f, "invalid JSON at byte {}", position),
+            UnsupportedType(ref kind) => write!(f, "unsupported PROJJSON type: {}", kind),
+            MissingMember(ref member) => write!(f, "missing PROJJSON member: {}", member),
+            InvalidMember(ref member) => write!(f, "invalid PROJJSON member: {}", member),
+            UnsupportedProjection(ref method) => write!(f, "unsupported projection: {}", method),
+            UnsupportedParameter(ref method, ref parameter) => write!(f, "parameter {} is not supported by {}", parameter, method),
+            UnsupportedValue(ref parameter, ref value) => write!(f, "unsupported value for {}: {}", parameter, value),
+            InvalidValue(ref parameter, ref value) => write!(f, "invalid value for {}: {}", parameter, value),
+            UnsupportedCrs(ref crs) => write!(f, "CRS can't be written as PROJJSON: {}", crs),
+        }
+    }
+}
+
+impl Error for ProjJsonError { }
+
+/// Kind of a unit of measure
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum UnitKind {
+    Angle,
+    Length,
+    Scale,
+}
+
+/// EPSG name, EPSG code and unit of a conversion parameter
+type ParameterName = (&'static str, Option<u32>, UnitKind);
+
+const LATITUDE_OF_NATURAL_ORIGIN: ParameterName = ("Latitude of natural origin", Some(8801), UnitKind::Angle);
+const LONGITUDE_OF_NATURAL_ORIGIN: ParameterName = ("Longitude of natural origin", Some(8802), UnitKind::Angle);
+const SCALE_FACTOR_AT_NATURAL_ORIGIN: ParameterName = ("Scale factor at natural origin", Some(8805), UnitKind::Scale);
+const FALSE_EASTING: ParameterName = ("False easting", Some(8806), UnitKind::Length);
+const FALSE_NORTHING: ParameterName = ("False northing", Some(8807), UnitKind::Length);
+const LATITUDE_OF_FALSE_ORIGIN: ParameterName = ("Latitude of false origin", Some(8821), UnitKind::Angle);
+const LONGITUDE_OF_FALSE_ORIGIN: ParameterName = ("Longitude of false origin", Some(8822), UnitKind::Angle);
+const LATITUDE_OF_1ST_STANDARD_PARALLEL: ParameterName = ("Latitude of 1st standard parallel", Some(8823), UnitKind::Angle);
+const LATITUDE_OF_2ND_STANDARD_PARALLEL: ParameterName = ("Latitude of 2nd standard parallel", Some(8824), UnitKind::Angle);
+const EASTING_AT_FALSE_ORIGIN: ParameterName = ("Easting at false origin", Some(8826), UnitKind::Length);
+const NORTHING_AT_FALSE_ORIGIN: ParameterName = ("Northing at false origin", Some(8827), UnitKind::Length);
+const ELLIPSOID_SCALING_FACTOR: ParameterName = ("Ellipsoid scaling factor", Some(1038), UnitKind::Scale);
+const SATELLITE_HEIGHT: ParameterName = ("Satellite Height", None, UnitKind::Length);
+const LATITUDE_OF_PROJECTION_CENTRE: ParameterName = ("Latitude of projection centre", Some(8811), UnitKind::Angle);
+const LONGITUDE_OF_ORIGIN: ParameterName = ("Longitude of origin", Some(8833), UnitKind::Angle);
+const CO_LATITUDE_OF_CONE_AXIS: ParameterName = ("Co-latitude of cone axis", Some(1036), UnitKind::Angle);
+const LATITUDE_OF_PSEUDO_STANDARD_PARALLEL: ParameterName = ("Latitude of pseudo standard parallel", Some(8818), UnitKind::Angle);
+const SCALE_FACTOR_ON_PSEUDO_STANDARD_PARALLEL: ParameterName = ("Scale factor on pseudo standard parallel", Some(8819), UnitKind::Scale);
+const ORDINATE_1_OF_EVALUATION_POINT: ParameterName = ("Ordinate 1 of evaluation point", Some(8617), UnitKind::Length);
+const ORDINATE_2_OF_EVALUATION_POINT: ParameterName = ("Ordinate 2 of evaluation point", Some(8618), UnitKind::Length);
+const NORTH_SQUARE: ParameterName = ("North square", None, UnitKind::Scale);
+const SOUTH_SQUARE: ParameterName = ("South square", None, UnitKind::Scale);
+
+/// Coefficients C1 - C10 of the modified Krovak projection
+const KROVAK_COEFFICIENTS: [ParameterName; 10] = [
+    ("C1", Some(1026), UnitKind::Scale),
+    ("C2", Some(1027), UnitKind::Scale),
+    ("C3", Some(1028), UnitKind::Scale),
+    ("C4", Some(1029), UnitKind::Scale),
+    ("C5", Some(1030), UnitKind::Scale),
+    ("C6", Some(1031), UnitKind::Scale),
+    ("C7", Some(1032), UnitKind::Scale),
+    ("C8", Some(1033), UnitKind::Scale),
+    ("C9", Some(1034), UnitKind::Scale),
+    ("C10", Some(1035), UnitKind::Scale),
+];
+
+fn member<'a>(json: &'a Json, key: &str) -> Result<&'a Json, ProjJsonError> {
+    json.get(key).ok_or_else(|| ProjJsonError::MissingMember(key.to_string()))
+}
+
+fn text<'a>(json: &'a Json, key: &str) -> Result<&'a str, ProjJsonError> {
+    member(json, key)?.as_str().ok_or_else(|| ProjJsonError::InvalidMember(key.to_string()))
+}
+
+fn parse_document(s: &str) -> Result<Json, ProjJsonError> {
+    Json::parse(s).map_err(ProjJsonError::InvalidJson)
+}
+
+/// Checks the `type` member of an object
+fn expect_type(json: &Json, types: &[&str]) -> Result<(), ProjJsonError> {
+    let kind = text(json, "type")?;
+    if types.contains(&kind) { Ok(()) } else { Err(ProjJsonError::UnsupportedType(kind.to_string())) }
+}
+
+/// Reads a unit, returns its kind and its size in radians, meters or unity
+fn read_unit(unit: &Json) -> Result<(UnitKind, f64), ProjJsonError> {
+    use self::UnitKind::*;
+    match *unit {
+        Json::String(ref name) => match name.as_str() {
+            "degree" => Ok((Angle, PI / 180.0)),
+            "grad" => Ok((Angle, PI / 200.0)),
+            "radian" => Ok((Angle, 1.0)),
+            "arc-second" => Ok((Angle, ARC_SECOND)),
+            "unity" => Ok((Scale, 1.0)),
+            "parts per million" => Ok((Scale, 1e-6)),
+            name => LENGTH_UNITS.iter().find(|&&(wkt, _, _)| wkt == name).map(|&(_, _, meters)| (Length, meters))
+                .ok_or_else(|| ProjJsonError::UnsupportedValue("unit".to_string(), name.to_string())),
+        },
+        Json::Object(_) => {
+            let kind = match text(unit, "type")? {
+                "AngularUnit" => Angle,
+                "LinearUnit" => Length,
+                "ScaleUnit" => Scale,
+                other => return Err(ProjJsonError::UnsupportedValue("unit".to_string(), other.to_string())),
+            };
+            let factor = member(unit, "conversion_factor")?.as_f64().filter(|&f| f > 0.0)
+                .ok_or_else(|| ProjJsonError::InvalidMember("conversion_factor".to_string()))?;
+            Ok((kind, factor))
+        },
+        _ => Err(ProjJsonError::InvalidMember("unit".to_string())),
+    }
+}
+
+/// Converts a value in the given unit to degrees, meters or unity
+fn convert(value: f64, unit: (UnitKind, f64)) -> f64 {
+    match unit {
+        (UnitKind::Angle, radians) => value * unit_to_degrees(radians),
+        (UnitKind::Length, meters) => value * unit_to_meters(meters),
+        (UnitKind::Scale, factor) => value * factor,
+    }
+}
+
+/// Reads a member that is either a number in the default unit or a
+/// `{"value": ..., "unit": ...}` object, in degrees or meters
+fn read_measure(json: &Json, key: &str, default: (UnitKind, f64)) -> Result<f64, ProjJsonError> {
+    let measure = member(json, key)?;
+    let invalid = || ProjJsonError::InvalidMember(key.to_string());
+    match *measure {
+        Json::Number(value) | Json::ParsedNumber(value, _) => Ok(convert(value, default)),
+        Json::Object(_) => {
+            let value = member(measure, "value")?.as_f64().ok_or_else(invalid)?;
+            let unit = match measure.get("unit") {
+                Some(unit) => read_unit(unit)?,
+                None => default,
+            };
+            Ok(convert(value, unit))
+        },
+        _ => Err(invalid()),
+    }
+}
+
+/// Returns the unit of the first axis of the coordinate system, if any
+fn read_axis_unit(crs: &Json) -> Result<Option<(UnitKind, f64)>, ProjJsonError> {
+    let unit = crs.get("coordinate_system")
+        .and_then(|cs| cs.get("axis"))
+        .and_then(Json::as_array)
+        .and_then(|axes| axes.first())
+        .and_then(|axis| axis.get("unit"));
+    match unit {
+        Some(unit) => read_unit(unit).map(Some),
+        None => Ok(None),
+    }
+}
+
+const METRE: (UnitKind, f64) = (UnitKind::Length, 1.0);
+const DEGREE: (UnitKind, f64) = (UnitKind::Angle, PI / 180.0);
+
+fn read_ellipsoid(json: &Json) -> Result<Ellipsoid, ProjJsonError> {
+    let invalid = || ProjJsonError::InvalidMember("ellipsoid".to_string());
+
+    if json.get("radius").is_some() {
+        let radius = read_measure(json, "radius", METRE)?;
+        return if radius > 0.0 { Ok(find_ellipsoid(radius, 0.0)) } else { Err(invalid()) };
+    }
+
+    let a = read_measure(json, "semi_major_axis", METRE)?;
+    if !(a > 0.0 && a.is_finite()) {
+        return Err(invalid());
+    }
+
+    if json.get("semi_minor_axis").is_some() {
+        let b = read_measure(json, "semi_minor_axis", METRE)?;
+        if !(b > 0.0 && b <= a) {
+            return Err(invalid());
+        }
+        let known = ELLIPSOID_IDS.iter().map(|&(_, e)| e)
+            .chain(ELLIPSOIDS.iter().cloned())
+            .find(|e| (e.a - a).abs() < 1e-6 && (e.b - b).abs() < 1e-6);
+        return Ok(known.cloned().unwrap_or_else(|| Ellipsoid::from_axes(a, b)));
+    }
+
+    let inverse_flattening = member(json, "inverse_flattening")?.as_f64()
+        .filter(|&rf| rf == 0.0 || rf > 1.0)
+        .ok_or_else(|| ProjJsonError::InvalidMember("inverse_flattening".to_string()))?;
+    Ok(find_ellipsoid(a, inverse_flattening))
+}
+
+/// The geographic part of a CRS definition
+struct Geographic {
+    name: String,
+    datum_name: String,
+    ellipsoid: Ellipsoid,
+    /// Longitude of the prime meridian, in degrees
+    prime_meridian: f64,
+    /// Size of the angular unit, in degrees
+    angle_unit: f64,
+}
+
+fn read_geographic(json: &Json) -> Result<Geographic, ProjJsonError> {
+    let datum = json.get("datum").or_else(|| json.get("datum_ensemble"))
+        .ok_or_else(|| ProjJsonError::MissingMember("datum".to_string()))?;
+    let ellipsoid = read_ellipsoid(member(datum, "ellipsoid")?)?;
+
+    let prime_meridian = match datum.get("prime_meridian") {
+        Some(prime_meridian) => read_measure(prime_meridian, "longitude", DEGREE)?,
+        None => 0.0,
+    };
+
+    let angle_unit = match read_axis_unit(json)? {
+        Some((UnitKind::Angle, radians)) => unit_to_degrees(radians),
+        Some(_) => return Err(ProjJsonError::InvalidMember("coordinate_system".to_string())),
+        None => 1.0,
+    };
+
+    Ok(Geographic {
+        name: text(json, "name")?.to_string(),
+        datum_name: text(datum, "name")?.to_string(),
+        ellipsoid,
+        prime_meridian,
+        angle_unit,
+    })
+}
+
+fn read_geographic_crs(json: &Json) -> Result<ProjJsonDefinition, ProjJsonError> {
+    let subtype = json.get("coordinate_system").and_then(|cs| cs.get("subtype")).and_then(Json::as_str);
+    if subtype == Some("Cartesian") {
+        return Err(ProjJsonError::UnsupportedType(format!("{} (geocentric)", text(json, "type")?)));
+    }
+
+    let geographic = read_geographic(json)?;
+    if geographic.angle_unit != 1.0 {
+        return Err(ProjJsonError::UnsupportedValue("unit".to_string(), geographic.angle_unit.to_string()));
+    }
+
+    Ok(ProjJsonDefinition {
+        name: geographic.name.clone(),
+        geographic_name: geographic.name,
+        datum_name: geographic.datum_name,
+        crs: adjust(Box::new(LonLatSystem), 1.0, geographic.prime_meridian),
+        ellipsoid: geographic.ellipsoid,
+        to_wgs84: None,
+    })
+}
+
+/// The parameters of a conversion, in degrees, meters and unity
+struct Parameters {
+    method: String,
+    /// Name, EPSG code and value
+    values: Vec<(String, Option<u32>, f64)>,
+}
+
+impl Parameters {
+    fn read(method: &str, conversion: &Json) -> Result<Self, ProjJsonError> {
+        let mut values = Vec::new();
+        let parameters = match conversion.get("parameters") {
+            Some(parameters) => parameters.as_array().ok_or_else(|| ProjJsonError::InvalidMember("parameters".to_string()))?,
+            None => &[],
+        };
+        for parameter in parameters {
+            let name = text(parameter, "name")?;
+            let value = member(parameter, "value")?.as_f64()
+                .ok_or_else(|| ProjJsonError::InvalidMember("value".to_string()))?;
+            let value = match parameter.get("unit") {
+                Some(unit) => convert(value, read_unit(unit)?),
+                None => value,
+            };
+            let code = parameter.get("id").and_then(|id| id.get("code")).and_then(Json::as_f64).map(|c| c as u32);
+            values.push((name.to_string(), code, value));
+        }
+        Ok(Parameters { method: method.to_string(), values })
+    }
+
+    /// Removes the parameter, found by its EPSG code or its name
+    fn take(&mut self, parameter: ParameterName) -> Option<f64> {
+        let (name, code, _) = parameter;
+        let key = normalize(name);
+        let index = self.values.iter().position(|&(ref n, c, _)| (code.is_some() && c == code) || normalize(n) == key)?;
+        Some(self.values.remove(index).2)
+    }
+
+    /// Removes a parameter that proj5 only supports with the given value
+    fn take_fixed(&mut self, parameter: ParameterName, value: f64) -> Result<(), ProjJsonError> {
+        match self.take(parameter) {
+            // tolerate rounding, e.g. of the Krovak longitude of origin (24°50')
+            Some(v) if (v - value).abs() > 1e-12 * value.abs() =>
+                Err(ProjJsonError::UnsupportedValue(parameter.0.to_string(), v.to_string())),
+            _ => Ok(()),
+        }
+    }
+
+    /// Fails on the first parameter that wasn't taken
+    fn finish(&self) -> Result<(), ProjJsonError> {
+        match self.values.first() {
+            Some((name, _, _)) => Err(ProjJsonError::UnsupportedParameter(self.method.clone(), name.clone())),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Builds the CRS of a projection method from its parameters
+fn build_projection(parameters: &mut Parameters) -> Result<Box<dyn Crs>, ProjJsonError> {
+    let method = parameters.method.clone();
+    let normalized = normalize(&method);
+
+    let crs: Box<dyn Crs> = match &*normalized {
+        "transverse_mercator" => {
+            let system = TransverseMercatorSystem {
+                lat_0: parameters.take(LATITUDE_OF_NATURAL_ORIGIN).unwrap_or(0.0),
+                lon_0: parameters.take(LONGITUDE_OF_NATURAL_ORIGIN).unwrap_or(0.0),
+                scale_factor: parameters.take(SCALE_FACTOR_AT_NATURAL_ORIGIN).unwrap_or(1.0),
+                false_easting: parameters.take(FALSE_EASTING).unwrap_or(0.0),
+                false_northing: parameters.take(FALSE_NORTHING).unwrap_or(0.0),
+            };
+            let zone = (system.lon_0 + 183.0) / 6.0;
+            let is_utm = system.lat_0 == 0.0 && system.scale_factor == 0.9996 && system.false_easting == 500_000.0 &&
+                (system.false_northing == 0.0 || system.false_northing == 10_000_000.0) &&
+                zone.fract() == 0.0 && (1.0..=60.0).contains(&zone);
+            if is_utm {
+                let hemisphere = if system.false_northing == 0.0 { Hemisphere::North } else { Hemisphere::South };
+                Box::new(UTMSystem { utm_zone: zone as u8, hemisphere })
+            } else {
+                Box::new(system)
+            }
+        },
+        "mercator_(variant_a)" | "popular_visualisation_pseudo_mercator" => {
+            parameters.take_fixed(LATITUDE_OF_NATURAL_ORIGIN, 0.0)?;
+            parameters.take_fixed(LONGITUDE_OF_NATURAL_ORIGIN, 0.0)?;
+            parameters.take_fixed(SCALE_FACTOR_AT_NATURAL_ORIGIN, 1.0)?;
+            parameters.take_fixed(FALSE_EASTING, 0.0)?;
+            parameters.take_fixed(FALSE_NORTHING, 0.0)?;
+            if normalized.starts_with("mercator") { Box::new(MercatorSystem) } else { Box::new(WebMercatorSystem) }
+        },
+        "lambert_conic_conformal_(1sp)" => {
+            let lat_0 = parameters.take(LATITUDE_OF_NATURAL_ORIGIN).unwrap_or(0.0);
+            Box::new(LambertConformalConicSystem {
+                lat_0,
+                lon_0: parameters.take(LONGITUDE_OF_NATURAL_ORIGIN).unwrap_or(0.0),
+                lat_1: lat_0,
+                lat_2: lat_0,
+                scale_factor: parameters.take(SCALE_FACTOR_AT_NATURAL_ORIGIN).unwrap_or(1.0),
+                false_easting: parameters.take(FALSE_EASTING).unwrap_or(0.0),
+                false_northing: parameters.take(FALSE_NORTHING).unwrap_or(0.0),
+            })
+        },
+        "lambert_conic_conformal_(2sp)" | "lambert_conic_conformal_(2sp_michigan)" => {
+            let lat_1 = parameters.take(LATITUDE_OF_1ST_STANDARD_PARALLEL).unwrap_or(0.0);
+            Box::new(LambertConformalConicSystem {
+                lat_0: parameters.take(LATITUDE_OF_FALSE_ORIGIN).unwrap_or(0.0),
+                lon_0: parameters.take(LONGITUDE_OF_FALSE_ORIGIN).unwrap_or(0.0),
+                lat_1,
+                lat_2: parameters.take(LATITUDE_OF_2ND_STANDARD_PARALLEL).unwrap_or(lat_1),
+                scale_factor: parameters.take(ELLIPSOID_SCALING_FACTOR).unwrap_or(1.0),
+                false_easting: parameters.take(EASTING_AT_FALSE_ORIGIN).unwrap_or(0.0),
+                false_northing: parameters.take(NORTHING_AT_FALSE_ORIGIN).unwrap_or(0.0),
+            })
+        },
+        "polar_stereographic_(variant_a)" => {
+            let lat_0 = parameters.take(LATITUDE_OF_NATURAL_ORIGIN).unwrap_or(90.0);
+            if lat_0.abs() != 90.0 {
+                return Err(ProjJsonError::UnsupportedValue(LATITUDE_OF_NATURAL_ORIGIN.0.to_string(), lat_0.to_string()));
+            }
+            parameters.take_fixed(LONGITUDE_OF_NATURAL_ORIGIN, 0.0)?;
+            parameters.take_fixed(SCALE_FACTOR_AT_NATURAL_ORIGIN, 0.994)?;
+            parameters.take_fixed(FALSE_EASTING, 2_000_000.0)?;
+            parameters.take_fixed(FALSE_NORTHING, 2_000_000.0)?;
+            let hemisphere = if lat_0 > 0.0 { Hemisphere::North } else { Hemisphere::South };
+            Box::new(UPSSystem { hemisphere })
+        },
+        "geostationary_satellite_(sweep_x)" | "geostationary_satellite_(sweep_y)" => {
+            let satellite_height = parameters.take(SATELLITE_HEIGHT)
+                .ok_or_else(|| ProjJsonError::MissingMember(SATELLITE_HEIGHT.0.to_string()))?;
+            parameters.take_fixed(FALSE_EASTING, 0.0)?;
+            parameters.take_fixed(FALSE_NORTHING, 0.0)?;
+            Box::new(GeostationarySystem {
+                satellite_height,
+                sub_satellite_lon: parameters.take(LONGITUDE_OF_NATURAL_ORIGIN).unwrap_or(0.0),
+                sweep_axis: if normalized.ends_with("x)") { SweepAxis::X } else { SweepAxis::Y },
+            })
+        },
+        "krovak" | "krovak_(north_orientated)" | "krovak_modified" | "krovak_modified_(north_orientated)" => {
+            let variant = if normalized.contains("modified") { KrovakVariant::Modified } else { KrovakVariant::Standard };
+            let axis_order = if normalized.contains("north") { KrovakAxisOrder::EastingNorthing } else { KrovakAxisOrder::WestingSouthing };
+            parameters.take_fixed(LATITUDE_OF_PROJECTION_CENTRE, krovak::LATITUDE_OF_CENTRE)?;
+            parameters.take_fixed(LONGITUDE_OF_ORIGIN, krovak::LONGITUDE_OF_ORIGIN)?;
+            parameters.take_fixed(CO_LATITUDE_OF_CONE_AXIS, krovak::CONE_AXIS_COLATITUDE)?;
+            parameters.take_fixed(LATITUDE_OF_PSEUDO_STANDARD_PARALLEL, krovak::PSEUDO_STANDARD_PARALLEL)?;
+            parameters.take_fixed(SCALE_FACTOR_ON_PSEUDO_STANDARD_PARALLEL, krovak::SCALE_FACTOR)?;
+            if variant == KrovakVariant::Modified {
+                parameters.take_fixed(FALSE_EASTING, krovak::MODIFIED_FALSE_EASTING)?;
+                parameters.take_fixed(FALSE_NORTHING, krovak::MODIFIED_FALSE_NORTHING)?;
+                parameters.take_fixed(ORDINATE_1_OF_EVALUATION_POINT, krovak::MODIFIED_ORDINATE_1)?;
+                parameters.take_fixed(ORDINATE_2_OF_EVALUATION_POINT, krovak::MODIFIED_ORDINATE_2)?;
+                for (&coefficient, &value) in KROVAK_COEFFICIENTS.iter().zip(krovak::MODIFIED_COEFFICIENTS.iter()) {
+                    parameters.take_fixed(coefficient, value)?;
+                }
+            } else {
+                parameters.take_fixed(FALSE_EASTING, 0.0)?;
+                parameters.take_fixed(FALSE_NORTHING, 0.0)?;
+            }
+            Box::new(KrovakSystem { variant, axis_order })
+        },
+        "interrupted_goode_homolosine" => {
+            parameters.take_fixed(LONGITUDE_OF_NATURAL_ORIGIN, 0.0)?;
+            parameters.take_fixed(FALSE_EASTING, 0.0)?;
+            parameters.take_fixed(FALSE_NORTHING, 0.0)?;
+            Box::new(GoodeHomolosineSystem { lobes: &GOODE_HOMOLOSINE_LOBES })
+        },
+        "healpix" | "rhealpix" => {
+            let lon_0 = parameters.take(LONGITUDE_OF_NATURAL_ORIGIN).unwrap_or(0.0);
+            parameters.take_fixed(FALSE_EASTING, 0.0)?;
+            parameters.take_fixed(FALSE_NORTHING, 0.0)?;
+            if normalized == "healpix" {
+                Box::new(HealpixSystem { lon_0 })
+            } else {
+                let mut square = |parameter: ParameterName| match parameters.take(parameter) {
+                    None => Ok(0),
+                    Some(value) if value.fract() == 0.0 && (0.0..=3.0).contains(&value) => Ok(value as u8),
+                    Some(value) => Err(ProjJsonError::UnsupportedValue(parameter.0.to_string(), value.to_string())),
+                };
+                let north_square = square(NORTH_SQUARE)?;
+                let south_square = square(SOUTH_SQUARE)?;
+                Box::new(RHealpixSystem { lon_0, north_square, south_square })
+            }
+        },
+        _ => return Err(ProjJsonError::UnsupportedProjection(method)),
+    };
+
+    parameters.finish()?;
+
+    if let Some(description) = Description::of(&*crs) {
+        let two_parallels = normalized.contains("(2sp");
+        let invalid = |parameter: ParameterName, value: f64| ProjJsonError::InvalidValue(parameter.0.to_string(), value.to_string());
+        description.validate().map_err(|degenerate| match degenerate {
+            Degenerate::ScaleFactor(k) if two_parallels => invalid(ELLIPSOID_SCALING_FACTOR, k),
+            Degenerate::ScaleFactor(k) => invalid(SCALE_FACTOR_AT_NATURAL_ORIGIN, k),
+            Degenerate::SatelliteHeight(h) => invalid(SATELLITE_HEIGHT, h),
+            Degenerate::StandardParallels(_, lat_2) if two_parallels => invalid(LATITUDE_OF_2ND_STANDARD_PARALLEL, lat_2),
+            Degenerate::StandardParallels(_, lat_0) => invalid(LATITUDE_OF_NATURAL_ORIGIN, lat_0),
+        })?;
+    }
+
+    Ok(crs)
+}
+
+fn read_projected_crs(json: &Json) -> Result<ProjJsonDefinition, ProjJsonError> {
+    let geographic = read_geographic(member(json, "base_crs")?)?;
+
+    let conversion = member(json, "conversion")?;
+    let method = text(member(conversion, "method")?, "name")?;
+    let mut parameters = Parameters::read(method, conversion)?;
+    let crs = build_projection(&mut parameters)?;
+
+    let to_meter = match read_axis_unit(json)? {
+        Some((UnitKind::Length, meters)) => unit_to_meters(meters),
+        Some(_) => return Err(ProjJsonError::InvalidMember("coordinate_system".to_string())),
+        None => 1.0,
+    };
+
+    Ok(ProjJsonDefinition {
+        name: text(json, "name")?.to_string(),
+        geographic_name: geographic.name,
+        datum_name: geographic.datum_name,
+        crs: adjust(crs, to_meter, geographic.prime_meridian),
+        ellipsoid: geographic.ellipsoid,
+        to_wgs84: None,
+    })
+}
+
+/// Reads a `BoundCRS` with a Helmert transformation to WGS 84
+fn read_bound_crs(json: &Json) -> Result<ProjJsonDefinition, ProjJsonError> {
+    let mut definition = read_crs(member(json, "source_crs")?)?;
+
+    let target = member(json, "target_crs")?;
+    if read_geographic(target)?.ellipsoid != WGS_1984_ELLIPSOID {
+        return Err(ProjJsonError::UnsupportedValue("target_crs".to_string(), text(target, "name")?.to_string()));
+    }
+
+    let transformation = member(json, "transformation")?;
+    let method = text(member(transformation, "method")?, "name")?;
+
+    // The coordinate frame convention has the opposite sign of the rotations
+    let normalized = normalize(method);
+    let rotation_sign = if normalized.starts_with("coordinate_frame") {
+        -1.0
+    } else if normalized.starts_with("position_vector") || normalized.starts_with("geocentric_translations") {
+        1.0
+    } else {
+        return Err(ProjJsonError::UnsupportedValue("method".to_string(), method.to_string()));
+    };
+
+    let parameters = match transformation.get("parameters") {
+        Some(parameters) => parameters.as_array().ok_or_else(|| ProjJsonError::InvalidMember("parameters".to_string()))?,
+        None => &[],
+    };
+
+    let mut helmert = Helmert::translation(0.0, 0.0, 0.0);
+    for parameter in parameters {
+        let name = text(parameter, "name")?;
+        let value = member(parameter, "value")?.as_f64()
+            .ok_or_else(|| ProjJsonError::InvalidMember("value".to_string()))?;
+        let factor = match parameter.get("unit") {
+            Some(unit) => Some(read_unit(unit)?.1),
+            None => None,
+        };
+        let meters = || value * factor.unwrap_or(1.0);
+        let arc_seconds = || value * factor.map(|f| snap(f / ARC_SECOND, 1.0)).unwrap_or(1.0);
+        let ppm = || value * factor.map(|f| snap(f / 1e-6, 1.0)).unwrap_or(1.0);
+
+        match &*normalize(name) {
+            "x_axis_translation" => helmert.dx = meters(),
+            "y_axis_translation" => helmert.dy = meters(),
+            "z_axis_translation" => helmert.dz = meters(),
+            "x_axis_rotation" => helmert.rx = rotation_sign * arc_seconds(),
+            "y_axis_rotation" => helmert.ry = rotation_sign * arc_seconds(),
+            "z_axis_rotation" => helmert.rz = rotation_sign * arc_seconds(),
+            "scale_difference" => helmert.ds = ppm(),
+            _ => return Err(ProjJsonError::UnsupportedParameter(method.to_string(), name.to_string())),
+        }
+    }
+
+    definition.to_wgs84 = Some(helmert);
+    Ok(definition)
+}
+
+fn read_crs(json: &Json) -> Result<ProjJsonDefinition, ProjJsonError> {
+    match text(json, "type")? {
+        "GeographicCRS" | "GeodeticCRS" => read_geographic_crs(json),
+        "ProjectedCRS" => read_projected_crs(json),
+        "BoundCRS" => read_bound_crs(json),
+        "CompoundCRS" => {
+            let components = member(json, "components")?.as_array()
+                .ok_or_else(|| ProjJsonError::InvalidMember("components".to_string()))?;
+            let horizontal = components.iter()
+                .find(|c| c.get("type").and_then(Json::as_str)
+                    .is_some_and(|t| ["GeographicCRS", "GeodeticCRS", "ProjectedCRS", "BoundCRS"].contains(&t)))
+                .ok_or_else(|| ProjJsonError::MissingMember("ProjectedCRS".to_string()))?;
+            let mut definition = read_crs(horizontal)?;
+            definition.name = text(json, "name")?.to_string();
+            Ok(definition)
+        },
+        kind => Err(ProjJsonError::UnsupportedType(kind.to_string())),
+    }
+}
+
+/// Parses a PROJJSON CRS definition
+///
+/// Identifiers, usages, remarks and the axes of coordinate systems are ignored.
+/// See the module documentation for the supported coordinate systems.
+pub fn parse_projjson(s: &str)
+                      -> Result<ProjJsonDefinition, ProjJsonError>
+{
+    read_crs(&parse_document(s)?)
+}
+
+/// Parses a PROJJSON `Ellipsoid`, given by its semi-major axis and the inverse
+/// flattening, the semi-minor axis or as a sphere with a radius
+pub fn parse_projjson_ellipsoid(s: &str)
+                                -> Result<Ellipsoid, ProjJsonError>
+{
+    let json = parse_document(s)?;
+    expect_type(&json, &["Ellipsoid"])?;
+    read_ellipsoid(&json)
+}
+
+/// Parses a PROJJSON `GeodeticReferenceFrame` or `DatumEnsemble` of one of the
+/// `DATUMS`, recognized by its name
+pub fn parse_projjson_datum(s: &str)
+                            -> Result<&'static Datum, ProjJsonError>
+{
+    let json = parse_document(s)?;
+    expect_type(&json, &["GeodeticReferenceFrame", "DatumEnsemble"])?;
+    let name = text(&json, "name")?;
+    let datum = find_datum_names(name)
+        .and_then(|names| DATUMS.iter().cloned().find(|d| d.proj_id == names.proj_id))
+        .ok_or_else(|| ProjJsonError::UnsupportedValue("datum".to_string(), name.to_string()))?;
+    if read_ellipsoid(member(&json, "ellipsoid")?)? != datum.ellipsoid {
+        return Err(ProjJsonError::UnsupportedValue("ellipsoid".to_string(), name.to_string()));
+    }
+    Ok(datum)
+}
+
+/// A projection method with its parameters, in degrees, meters and unity
+struct Conversion {
+    /// Name of the conversion, e.g. "UTM zone 32N"
+    name: String,
+    /// EPSG name and code of the method
+    method: (&'static str, Option<u32>),
+    parameters: Vec<(ParameterName, f64)>,
+}
+
+impl Conversion {
+    fn new(method: &'static str, code: Option<u32>, parameters: Vec<(ParameterName, f64)>) -> Self {
+        Conversion { name: method.to_string(), method: (method, code), parameters }
+    }
+}
+
+/// Returns the conversion of the CRS, `None` for geographic coordinates
+fn describe_conversion(crs: &dyn Crs) -> Result<Option<Conversion>, ProjJsonError> {
+    let natural_origin = |lat_0: f64, lon_0: f64, scale_factor: f64, false_easting: f64, false_northing: f64| vec![
+        (LATITUDE_OF_NATURAL_ORIGIN, lat_0),
+        (LONGITUDE_OF_NATURAL_ORIGIN, lon_0),
+        (SCALE_FACTOR_AT_NATURAL_ORIGIN, scale_factor),
+        (FALSE_EASTING, false_easting),
+        (FALSE_NORTHING, false_northing),
+    ];
+
+    let description = Description::of(crs).ok_or_else(|| ProjJsonError::UnsupportedCrs(format!("{:?}", crs)))?;
+    let conversion = match description {
+        Description::LonLat => return Ok(None),
+        Description::Utm(system) => {
+            let north = system.hemisphere == Hemisphere::North;
+            Conversion {
+                name: format!("UTM zone {}{}", system.utm_zone, if north { "N" } else { "S" }),
+                ..Conversion::new("Transverse Mercator", Some(9807), natural_origin(
+                    0.0, f64::from(system.utm_zone) * 6.0 - 183.0, 0.9996, 500_000.0, if north { 0.0 } else { 10_000_000.0 }))
+            }
+        },
+        Description::Ups(system) => {
+            let north = system.hemisphere == Hemisphere::North;
+            Conversion {
+                name: format!("Universal Polar Stereographic {}", if north { "North" } else { "South" }),
+                ..Conversion::new("Polar Stereographic (variant A)", Some(9810), natural_origin(
+                    if north { 90.0 } else { -90.0 }, 0.0, 0.994, 2_000_000.0, 2_000_000.0))
+            }
+        },
+        Description::Mercator => Conversion::new("Mercator (variant A)", Some(9804), natural_origin(0.0, 0.0, 1.0, 0.0, 0.0)),
+        Description::WebMercator => {
+            Conversion::new("Popular Visualisation Pseudo Mercator", Some(1024), vec![
+                (LATITUDE_OF_NATURAL_ORIGIN, 0.0),
+                (LONGITUDE_OF_NATURAL_ORIGIN, 0.0),
+                (FALSE_EASTING, 0.0),
+                (FALSE_NORTHING, 0.0),
+            ])
+        },
+        Description::TransverseMercator(system) => {
+            Conversion::new("Transverse Mercator", Some(9807), natural_origin(
+                system.lat_0, system.lon_0, system.scale_factor, system.false_easting, system.false_northing))
+        },
+        Description::LambertConic(system) => {
+            if system.lat_1 == system.lat_0 && system.lat_2 == system.lat_0 {
+                Conversion::new("Lambert Conic Conformal (1SP)", Some(9801), natural_origin(
+                    system.lat_0, system.lon_0, system.scale_factor, system.false_easting, system.false_northing))
+            } else {
+                let mut parameters = vec![
+                    (LATITUDE_OF_FALSE_ORIGIN, system.lat_0),
+                    (LONGITUDE_OF_FALSE_ORIGIN, system.lon_0),
+                    (LATITUDE_OF_1ST_STANDARD_PARALLEL, system.lat_1),
+                    (LATITUDE_OF_2ND_STANDARD_PARALLEL, system.lat_2),
+                    (EASTING_AT_FALSE_ORIGIN, system.false_easting),
+                    (NORTHING_AT_FALSE_ORIGIN, system.false_northing),
+                ];
+                if system.scale_factor == 1.0 {
+                    Conversion::new("Lambert Conic Conformal (2SP)", Some(9802), parameters)
+                } else {
+                    parameters.push((ELLIPSOID_SCALING_FACTOR, system.scale_factor));
+                    Conversion::new("Lambert Conic Conformal (2SP Michigan)", Some(1051), parameters)
+                }
+            }
+        },
+        Description::Geostationary(system) => {
+            let method = match system.sweep_axis {
+                SweepAxis::X => "Geostationary Satellite (Sweep X)",
+                SweepAxis::Y => "Geostationary Satellite (Sweep Y)",
+            };
+            Conversion::new(method, None, vec![
+                (LONGITUDE_OF_NATURAL_ORIGIN, system.sub_satellite_lon),
+                (SATELLITE_HEIGHT, system.satellite_height),
+                (FALSE_EASTING, 0.0),
+                (FALSE_NORTHING, 0.0),
+            ])
+        },
+        Description::Krovak(system) => {
+            let north = system.axis_order == KrovakAxisOrder::EastingNorthing;
+            let (method, code) = match (system.variant, north) {
+                (KrovakVariant::Standard, false) => ("Krovak", 9819),
+                (KrovakVariant::Standard, true) => ("Krovak (North Orientated)", 1041),
+                (KrovakVariant::Modified, false) => ("Krovak Modified", 1042),
+                (KrovakVariant::Modified, true) => ("Krovak Modified (North Orientated)", 1043),
+            };
+            let mut parameters = vec![
+                (LATITUDE_OF_PROJECTION_CENTRE, krovak::LATITUDE_OF_CENTRE),
+                (LONGITUDE_OF_ORIGIN, krovak::LONGITUDE_OF_ORIGIN),
+                (CO_LATITUDE_OF_CONE_AXIS, krovak::CONE_AXIS_COLATITUDE),
+                (LATITUDE_OF_PSEUDO_STANDARD_PARALLEL, krovak::PSEUDO_STANDARD_PARALLEL),
+                (SCALE_FACTOR_ON_PSEUDO_STANDARD_PARALLEL, krovak::SCALE_FACTOR),
+            ];
+            if system.variant == KrovakVariant::Modified {
+                parameters.extend_from_slice(&[
+                    (FALSE_EASTING, krovak::MODIFIED_FALSE_EASTING),
+                    (FALSE_NORTHING, krovak::MODIFIED_FALSE_NORTHING),
+                    (ORDINATE_1_OF_EVALUATION_POINT, krovak::MODIFIED_ORDINATE_1),
+                    (ORDINATE_2_OF_EVALUATION_POINT, krovak::MODIFIED_ORDINATE_2),
+                ]);
+                parameters.extend(KROVAK_COEFFICIENTS.iter().cloned().zip(krovak::MODIFIED_COEFFICIENTS.iter().cloned()));
+            } else {
+                parameters.extend_from_slice(&[(FALSE_EASTING, 0.0), (FALSE_NORTHING, 0.0)]);
+            }
+            Conversion::new(method, Some(code), parameters)
+        },
+        Description::GoodeHomolosine => {
+            Conversion::new("Interrupted Goode Homolosine", None, vec![
+                (LONGITUDE_OF_NATURAL_ORIGIN, 0.0),
+                (FALSE_EASTING, 0.0),
+                (FALSE_NORTHING, 0.0),
+            ])
+        },
+        Description::Healpix(system) => Conversion::new("HEALPix", None, vec![(LONGITUDE_OF_NATURAL_ORIGIN, system.lon_0)]),
+        Description::RHealpix(system) => {
+            Conversion::new("rHEALPix", None, vec![
+                (LONGITUDE_OF_NATURAL_ORIGIN, system.lon_0),
+                (NORTH_SQUARE, f64::from(system.north_square)),
+                (SOUTH_SQUARE, f64::from(system.south_square)),
+            ])
+        },
+    };
+
+    Ok(Some(conversion))
+}
+
+/// Writes an EPSG identifier
+fn id(code: u32) -> Json {
+    Json::object(vec![("authority", Json::string("EPSG")), ("code", Json::Number(f64::from(code)))])
+}
+
+/// Writes a length unit of the given size in meters
+fn length_unit(to_meter: f64) -> Json {
+    if to_meter == 1.0 {
+        return Json::string("metre");
+    }
+    let name = LENGTH_UNITS.iter().find(|&&(_, _, m)| m == to_meter).map(|&(wkt, _, _)| wkt).unwrap_or("unknown");
+    Json::object(vec![
+        ("type", Json::string("LinearUnit")),
+        ("name", Json::string(name)),
+        ("conversion_factor", Json::Number(to_meter)),
+    ])
+}
+
+fn ellipsoid_json(ellipsoid: &Ellipsoid) -> Json {
+    let name = ELLIPSOID_NAMES.iter().find(|&&(e, _, _)| e.a == ellipsoid.a && e.b == ellipsoid.b)
+        .map(|&(_, epsg, _)| epsg)
+        .unwrap_or(ellipsoid.nice_name);
+
+    let mut members = vec![("name", Json::string(name))];
+    if ellipsoid.a == ellipsoid.b {
+        members.push(("radius", Json::Number(ellipsoid.a)));
+    } else {
+        members.push(("semi_major_axis", Json::Number(ellipsoid.a)));
+        // the inverse flattening of unknown ellipsoids wouldn't give back the exact semi-minor axis
+        if find_ellipsoid(ellipsoid.a, ellipsoid.flattening) == *ellipsoid {
+            members.push(("inverse_flattening", Json::Number(ellipsoid.flattening)));
+        } else {
+            members.push(("semi_minor_axis", Json::Number(ellipsoid.b)));
+        }
+    }
+    Json::object(members)
+}
+
+/// Writes an ellipsoidal or Cartesian coordinate system
+fn coordinate_system(subtype: &str, axes: &[(&str, &str, &str)], unit: Json) -> Json {
+    let axes = axes.iter().map(|&(name, abbreviation, direction)| Json::object(vec![
+        ("name", Json::string(name)),
+        ("abbreviation", Json::string(abbreviation)),
+        ("direction", Json::string(direction)),
+        ("unit", unit.clone()),
+    ])).collect();
+    Json::object(vec![("subtype", Json::string(subtype)), ("axis", Json::Array(axes))])
+}
+
+/// Writes the `GeographicCRS`, or the base CRS of a projected one
+fn geographic_json(definition: &ProjJsonDefinition, prime_meridian: f64, base: bool) -> Json {
+    let crs_names = DATUM_NAMES.iter().find(|n| {
+        let key = name_key(&definition.geographic_name);
+        name_key(n.crs) == key || name_key(n.esri_crs) == key
+    });
+    let name = match crs_names {
+        _ if !base => definition.name.clone(),
+        Some(names) => names.crs.to_string(),
+        None => definition.geographic_name.clone(),
+    };
+    let datum_name = find_datum_names(&definition.datum_name)
+        .map(|n| n.wkt2.to_string())
+        .unwrap_or_else(|| definition.datum_name.clone());
+
+    let mut datum = vec![
+        ("type", Json::string("GeodeticReferenceFrame")),
+        ("name", Json::String(datum_name)),
+        ("ellipsoid", ellipsoid_json(&definition.ellipsoid)),
+    ];
+    if prime_meridian != 0.0 {
+        let meridian_name = PRIME_MERIDIANS.iter().find(|&&(_, lon)| lon == prime_meridian)
+            .map(|&(id, _)| id[..1].to_uppercase() + &id[1..])
+            .unwrap_or_else(|| "unknown".to_string());
+        datum.push(("prime_meridian", Json::object(vec![
+            ("name", Json::String(meridian_name)),
+            ("longitude", Json::Number(prime_meridian)),
+        ])));
+    }
+
+    Json::object(vec![
+        ("type", Json::string("GeographicCRS")),
+        ("name", Json::String(name)),
+        ("datum", Json::object(datum)),
+        ("coordinate_system", coordinate_system("ellipsoidal", &[("Geodetic longitude", "Lon", "east"),
+                                                                ("Geodetic latitude", "Lat", "north")], Json::string("degree"))),
+    ])
+}
+
+/// Writes the transformation to WGS 84 as a `BoundCRS` around the CRS
+fn bound_crs_json(source: Json, source_name: &str, helmert: &Helmert) -> Json {
+    let target = ProjJsonDefinition::new(Box::new(LonLatSystem), WGS_1984_ELLIPSOID, WGS84_DATUM.to_wgs84);
+
+    let method = if helmert.is_translation() {
+        ("Geocentric translations (geog2D domain)", 9603)
+    } else {
+        ("Position Vector transformation (geog2D domain)", 9606)
+    };
+
+    let parameter = |name: &str, value: f64, unit: Json, code: u32| Json::object(vec![
+        ("name", Json::string(name)),
+        ("value", Json::Number(value)),
+        ("unit", unit),
+        ("id", id(code)),
+    ]);
+    let arc_second = Json::object(vec![
+        ("type", Json::string("AngularUnit")),
+        ("name", Json::string("arc-second")),
+        ("conversion_factor", Json::Number(ARC_SECOND)),
+    ]);
+
+    let mut parameters = vec![
+        parameter("X-axis translation", helmert.dx, Json::string("metre"), 8605),
+        parameter("Y-axis translation", helmert.dy, Json::string("metre"), 8606),
+        parameter("Z-axis translation", helmert.dz, Json::string("metre"), 8607),
+    ];
+    if !helmert.is_translation() {
+        parameters.push(parameter("X-axis rotation", helmert.rx, arc_second.clone(), 8608));
+        parameters.push(parameter("Y-axis rotation", helmert.ry, arc_second.clone(), 8609));
+        parameters.push(parameter("Z-axis rotation", helmert.rz, arc_second, 8610));
+        parameters.push(parameter("Scale difference", helmert.ds, Json::object(vec![
+            ("type", Json::string("ScaleUnit")),
+            ("name", Json::string("parts per million")),
+            ("conversion_factor", Json::Number(1e-6)),
+        ]), 8611));
+    }
+
+    Json::object(vec![
+        ("type", Json::string("BoundCRS")),
+        ("source_crs", source),
+        ("target_crs", geographic_json(&target, 0.0, false)),
+        ("transformation", Json::object(vec![
+            ("name", Json::String(format!("{} to WGS 84", source_name))),
+            ("method", Json::object(vec![("name", Json::string(method.0)), ("id", id(method.1))])),
+            ("parameters", Json::Array(parameters)),
+        ])),
+    ])
+}
+
+fn write_crs(definition: &ProjJsonDefinition) -> Result<Json, ProjJsonError> {
+    let adjusted = definition.crs.as_any().downcast_ref::<AdjustedSystem>();
+    let inner = adjusted.map(|a| &*a.crs).unwrap_or(&*definition.crs);
+
+    let (to_meter, prime_meridian) = match adjusted {
+        Some(a) if a.axis != (AxisDirection::East, AxisDirection::North) => {
+            return Err(ProjJsonError::UnsupportedCrs(format!("{:?}", definition.crs)));
+        },
+        Some(a) => (a.to_meter, a.prime_meridian),
+        None => (1.0, 0.0),
+    };
+
+    let crs = match describe_conversion(inner)? {
+        None if to_meter != 1.0 => return Err(ProjJsonError::UnsupportedCrs(format!("{:?}", definition.crs))),
+        None => geographic_json(definition, prime_meridian, false),
+        Some(conversion) => {
+            // lengths stay in meters, converting them to the unit of the CRS could round them
+            let parameters = conversion.parameters.iter().map(|&((name, code, kind), value)| {
+                let unit = match kind {
+                    UnitKind::Angle => "degree",
+                    UnitKind::Length => "metre",
+                    UnitKind::Scale => "unity",
+                };
+                let mut members = vec![("name", Json::string(name)), ("value", Json::Number(value)), ("unit", Json::string(unit))];
+                members.extend(code.map(|code| ("id", id(code))));
+                Json::object(members)
+            }).collect();
+
+            let (method_name, method_code) = conversion.method;
+            let mut method = vec![("name", Json::string(method_name))];
+            method.extend(method_code.map(|code| ("id", id(code))));
+
+            let westing_southing = match Description::of(inner) {
+                Some(Description::Krovak(system)) => system.axis_order == KrovakAxisOrder::WestingSouthing,
+                _ => false,
+            };
+            let axes: &[(&str, &str, &str)] = if westing_southing {
+                &[("Westing", "Y", "west"), ("Southing", "X", "south")]
+            } else {
+                &[("Easting", "E", "east"), ("Northing", "N", "north")]
+            };
+
+            Json::object(vec![
+                ("type", Json::string("ProjectedCRS")),
+                ("name", Json::String(definition.name.clone())),
+                ("base_crs", geographic_json(definition, prime_meridian, true)),
+                ("conversion", Json::object(vec![
+                    ("name", Json::String(conversion.name)),
+                    ("method", Json::object(method)),
+                    ("parameters", Json::Array(parameters)),
+                ])),
+                ("coordinate_system", coordinate_system("Cartesian", axes, length_unit(to_meter))),
+            ])
+        },
+    };
+
+    Ok(match definition.to_wgs84 {
+        Some(ref helmert) => bound_crs_json(crs, &definition.name, helmert),
+        None => crs,
+    })
+}
+
+/// Writes a root object with the `$schema` member
+fn document(mut json: Json) -> String {
+    if let Json::Object(ref mut members) = json {
+        members.insert(0, ("$schema".to_string(), Json::string(SCHEMA)));
+    }
+    json.to_pretty_string()
+}
+
+/// Writes the CRS, ellipsoid and transformation to WGS84 as PROJJSON
+///
+/// The names are generated as in `ProjJsonDefinition::new`. Returns `UnsupportedCrs`
+/// for coordinate systems that have no PROJJSON equivalent in `parse_projjson`.
+pub fn to_projjson(crs: &dyn Crs, ellipsoid: &Ellipsoid, to_wgs84: Option<&Helmert>)
+                   -> Result<String, ProjJsonError>
+{
+    ProjJsonDefinition::new(Crs::clone(crs), *ellipsoid, to_wgs84.cloned()).to_projjson()
+}
+
+/// Writes the ellipsoid as a PROJJSON `Ellipsoid`
+pub fn ellipsoid_to_projjson(ellipsoid: &Ellipsoid)
+                             -> String
+{
+    let mut json = ellipsoid_json(ellipsoid);
+    if let Json::Object(ref mut members) = json {
+        members.insert(0, ("type".to_string(), Json::string("Ellipsoid")));
+    }
+    document(json)
+}
+
+/// Writes the datum as a PROJJSON `GeodeticReferenceFrame` with its EPSG name
+///
+/// PROJJSON datums have no transformation to WGS84, `parse_projjson_datum` finds
+/// the datum again by its name.
+pub fn datum_to_projjson(datum: &Datum)
+                         -> String
+{
+    let name = DATUM_NAMES.iter().find(|n| n.proj_id == datum.proj_id).map(|n| n.wkt2).unwrap_or(datum.nice_name);
+    document(Json::object(vec![
+        ("type", Json::string("GeodeticReferenceFrame")),
+        ("name", Json::string(name)),
+        ("ellipsoid", ellipsoid_json(&datum.ellipsoid)),
+    ]))
+}
@@ -0,0 +1,760 @@
+//! Hierarchical cell IDs on the sphere, compatible with the S2 geometry library
+//!
+//! Implementation based on:
+//!
+//! https://s2geometry.io/devguide/s2cell_hierarchy
+//!
+//! The sphere is projected onto the six faces of a cube. Each face is
+//! divided into a quadtree of cells, ordered along a Hilbert curve, down to
+//! level 30 (about 1 cm²). A `CellId` is a 64 bit number: 3 bits for the
+//! face, 2 bits for each level and a trailing 1 bit marking the level.
+//!
+//! Coordinates are treated as spherical, like in the S2 library: latitudes
+//! are used as-is, no matter on which ellipsoid they are defined.
+//!
+//! Bounding boxes are `(min_lon, min_lat, max_lon, max_lat)` in degrees.
+//! If `min_lon > max_lon`, the bounding box crosses the antimeridian.
+
+use prelude::*;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// Highest level (leaf cells)
+pub const MAX_LEVEL: u8 = 30;
+
+/// Number of cube faces
+const NUM_FACES: u8 = 6;
+
+/// Number of bits used for the position along the Hilbert curve
+const POS_BITS: u32 = 2 * MAX_LEVEL as u32 + 1;
+
+/// Number of leaf cells per row / column of a face
+const MAX_SIZE: u32 = 1 << MAX_LEVEL;
+
+/// Hilbert curve position of a sub-cell (i bit << 1 | j bit) for each orientation
+const IJ_TO_POS: [[u8; 4]; 4] = [
+    [0, 1, 3, 2],   // canonical order
+    [0, 3, 1, 2],   // axes swapped
+    [2, 3, 1, 0],   // bits inverted
+    [2, 1, 3, 0],   // swapped and inverted
+];
+
+/// Sub-cell (i bit << 1 | j bit) of a Hilbert curve position for each orientation
+const POS_TO_IJ: [[u8; 4]; 4] = [
+    [0, 1, 3, 2],
+    [0, 2, 3, 1],
+    [3, 2, 0, 1],
+    [3, 1, 0, 2],
+];
+
+/// Change of the orientation when descending into a sub-cell at a Hilbert curve position
+const POS_TO_ORIENTATION: [u8; 4] = [1, 0, 0, 3];
+
+/// Error that can occur when creating or parsing a cell ID
+#[derive(Debug, Clone, PartialEq)]
+pub enum S2Error {
+    /// Level must be between 0 and `MAX_LEVEL`
+    InvalidLevel(u8),
+    /// Longitude or latitude isn't a valid number
+    InvalidCoordinate(f64, f64),
+    /// The number is not a valid cell ID
+    InvalidCellId(u64),
+    /// The token or string is malformed
+    InvalidFormat(String),
+}
+
+impl fmt::Display for S2Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::S2Error::*;
+        match *self {
+            InvalidLevel(level) => write!(f, "invalid S2 level: {} (must be 0 to {})", level, MAX_LEVEL),
+            InvalidCoordinate(lon, lat) => write!(f, "invalid coordinate: ({}, {})", lon, lat),
+            InvalidCellId(id) => write!(f, "invalid S2 cell ID: {:#018x}", id),
+            InvalidFormat(ref s) => write!(f, "invalid S2 cell: \"{}\"", s),
+        }
+    }
+}
+
+impl Error for S2Error { }
+
+/// A cell of the S2 hierarchy
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CellId(u64);
+
+/// Converts a lon / lat coordinate (in degrees) into a point on the unit sphere
+#[inline]
+fn lonlat_to_xyz(lon: f64, lat: f64)
+                 -> [f64; 3]
+{
+    let (lon, lat) = (lon.to_radians(), lat.to_radians());
+    [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()]
+}
+
+/// Converts a point (not necessarily of unit length) into a lon / lat coordinate (in degrees)
+#[inline]
+fn xyz_to_lonlat(p: [f64; 3])
+                 -> (f64, f64)
+{
+    (p[1].atan2(p[0]).to_degrees(), p[2].atan2(p[0].hypot(p[1])).to_degrees())
+}
+
+#[inline]
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+#[inline]
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+/// Returns the face of a point and its (u, v) coordinates on that face
+#[inline]
+fn xyz_to_face_uv(p: [f64; 3])
+                  -> (u8, f64, f64)
+{
+    let (ax, ay, az) = (p[0].abs(), p[1].abs(), p[2].abs());
+    let axis = if ax > ay { if ax > az { 0 } else { 2 } } else if ay > az { 1 } else { 2 };
+    let face = if p[axis] < 0.0 { axis as u8 + 3 } else { axis as u8 };
+
+    let (u, v) = match face {
+        0 => (p[1] / p[0], p[2] / p[0]),
+        1 => (-p[0] / p[1], p[2] / p[1]),
+        2 => (-p[0] / p[2], -p[1] / p[2]),
+        3 => (p[2] / p[0], p[1] / p[0]),
+        4 => (p[2] / p[1], -p[0] / p[1]),
+        _ => (-p[1] / p[2], -p[0] / p[2]),
+    };
+
+    (face, u, v)
+}
+
+/// Converts (u, v) coordinates on a face into a point (not of unit length)
+#[inline]
+fn face_uv_to_xyz(face: u8, u: f64, v: f64)
+                  -> [f64; 3]
+{
+    match face {
+        0 => [1.0, u, v],
+        1 => [-u, 1.0, v],
+        2 => [-u, -v, 1.0],
+        3 => [-1.0, -v, -u],
+        4 => [v, -1.0, -u],
+        _ => [v, u, -1.0],
+    }
+}
+
+/// Quadratic transformation from the cube face (u, -1 to 1) to cell space (s, 0 to 1),
+/// which makes the cells roughly equal in area
+#[inline]
+fn uv_to_st(u: f64) -> f64 {
+    if u >= 0.0 { 0.5 * (1.0 + 3.0 * u).sqrt() } else { 1.0 - 0.5 * (1.0 - 3.0 * u).sqrt() }
+}
+
+/// Inverse of `uv_to_st`
+#[inline]
+fn st_to_uv(s: f64) -> f64 {
+    if s >= 0.5 {
+        (4.0 * s * s - 1.0) / 3.0
+    } else {
+        (1.0 - 4.0 * (1.0 - s) * (1.0 - s)) / 3.0
+    }
+}
+
+/// Converts a cell space coordinate into a leaf cell row / column
+#[inline]
+fn st_to_ij(s: f64) -> u32 {
+    (f64::from(MAX_SIZE) * s).floor().clamp(0.0, f64::from(MAX_SIZE - 1)) as u32
+}
+
+/// Converts the corner of a leaf cell row / column (may be `MAX_SIZE`) into (u, v) coordinates
+#[inline]
+fn ij_to_uv(i: u32) -> f64 {
+    st_to_uv(f64::from(i) / f64::from(MAX_SIZE))
+}
+
+/// Lowest set bit of a cell ID at a level
+#[inline]
+fn lsb_for_level(level: u8) -> u64 {
+    1 << (2 * u32::from(MAX_LEVEL - level))
+}
+
+/// Longitude difference from `a` to `b`, normalized to [-180, 180)
+#[inline]
+fn lon_difference(a: f64, b: f64) -> f64 {
+    (b - a + 180.0).rem_euclid(360.0) - 180.0
+}
+
+/// Returns true if two longitude ranges (in degrees, crossing the antimeridian
+/// if min > max) overlap, allowing for a small tolerance
+fn lon_ranges_intersect(a: (f64, f64), b: (f64, f64)) -> bool {
+    const EPSILON: f64 = 1e-9;
+
+    let split = |(min, max): (f64, f64)| {
+        if min > max { [(min, 180.0), (-180.0, max)] } else { [(min, max), (min, max)] }
+    };
+
+    split(a).iter().any(|&(a_min, a_max)| {
+        split(b).iter().any(|&(b_min, b_max)| a_min <= b_max + EPSILON && b_min <= a_max + EPSILON)
+    })
+}
+
+impl CellId {
+    /// Creates a cell ID from its 64 bit representation
+    pub fn new(id: u64) -> Result<Self, S2Error> {
+        let cell = CellId(id);
+        if cell.is_valid() { Ok(cell) } else { Err(S2Error::InvalidCellId(id)) }
+    }
+
+    /// Returns the cell of one of the six cube faces (level 0)
+    pub fn from_face(face: u8) -> Result<Self, S2Error> {
+        if face >= NUM_FACES {
+            return Err(S2Error::InvalidCellId(u64::from(face) << POS_BITS));
+        }
+
+        Ok(CellId((u64::from(face) << POS_BITS) | lsb_for_level(0)))
+    }
+
+    /// Returns the cell at the given level that contains the lon / lat coordinate (in degrees)
+    pub fn from_lon_lat(lon: f64, lat: f64, level: u8) -> Result<Self, S2Error> {
+        if level > MAX_LEVEL {
+            return Err(S2Error::InvalidLevel(level));
+        }
+
+        if !lon.is_finite() || !lat.is_finite() || !(-90.0..=90.0).contains(&lat) {
+            return Err(S2Error::InvalidCoordinate(lon, lat));
+        }
+
+        let (face, u, v) = xyz_to_face_uv(lonlat_to_xyz(lon, lat));
+        let leaf = CellId::from_face_ij(face, st_to_ij(uv_to_st(u)), st_to_ij(uv_to_st(v)));
+        Ok(leaf.parent(level).unwrap_or(leaf))
+    }
+
+    /// Returns the leaf cell at row / column (i, j) of a face
+    fn from_face_ij(face: u8, i: u32, j: u32) -> Self {
+        let mut orientation = face & 1;
+        let mut pos = 0_u64;
+
+        for k in (0..u32::from(MAX_LEVEL)).rev() {
+            let ij = (((i >> k) & 1) << 1 | ((j >> k) & 1)) as usize;
+            let p = IJ_TO_POS[orientation as usize][ij];
+            pos = (pos << 2) | u64::from(p);
+            orientation ^= POS_TO_ORIENTATION[p as usize];
+        }
+
+        CellId((u64::from(face) << POS_BITS) | (pos << 1) | 1)
+    }
+
+    /// Returns the face and the row / column (i, j) of the leaf cell
+    /// in the south-west corner of the cell (in (u, v) coordinates)
+    fn to_face_ij(self) -> (u8, u32, u32) {
+        let face = self.face();
+        let mut orientation = face & 1;
+        let (mut i, mut j) = (0_u32, 0_u32);
+
+        for k in 0..u32::from(MAX_LEVEL) {
+            let p = ((self.0 >> (2 * (u32::from(MAX_LEVEL) - 1 - k) + 1)) & 3) as usize;
+            let ij = POS_TO_IJ[orientation as usize][p];
+            i = (i << 1) | u32::from(ij >> 1);
+            j = (j << 1) | u32::from(ij & 1);
+            orientation ^= POS_TO_ORIENTATION[p];
+        }
+
+        let mask = !(self.size_ij() - 1);
+        (face, i & mask, j & mask)
+    }
+
+    /// Number of leaf cells per row / column of the cell
+    #[inline]
+    fn size_ij(self) -> u32 {
+        1 << (MAX_LEVEL - self.level())
+    }
+
+    /// Lowest set bit of the cell ID
+    #[inline]
+    fn lsb(self) -> u64 {
+        self.0 & self.0.wrapping_neg()
+    }
+
+    /// Returns true if the number is a valid cell ID
+    fn is_valid(self) -> bool {
+        self.face() < NUM_FACES && (self.lsb() & 0x1555_5555_5555_5555) != 0
+    }
+
+    /// The 64 bit representation of the cell ID
+    pub fn id(&self) -> u64 {
+        self.0
+    }
+
+    /// The cube face of the cell (0 to 5)
+    pub fn face(&self) -> u8 {
+        (self.0 >> POS_BITS) as u8
+    }
+
+    /// The level of the cell (0 for a face, `MAX_LEVEL` for a leaf cell)
+    pub fn level(&self) -> u8 {
+        MAX_LEVEL - (self.0.trailing_zeros() / 2) as u8
+    }
+
+    /// Returns true if the cell is a leaf cell
+    pub fn is_leaf(&self) -> bool {
+        self.0 & 1 == 1
+    }
+
+    /// Returns the cell at a lower level that contains this cell,
+    /// `None` if `level` is higher than the level of the cell
+    pub fn parent(&self, level: u8) -> Option<CellId> {
+        if level > self.level() {
+            return None;
+        }
+
+        let lsb = lsb_for_level(level);
+        Some(CellId((self.0 & lsb.wrapping_neg()) | lsb))
+    }
+
+    /// Returns the four cells at the next level, in Hilbert curve order,
+    /// `None` for leaf cells
+    pub fn children(&self) -> Option<[CellId; 4]> {
+        if self.is_leaf() {
+            return None;
+        }
+
+        let lsb = self.lsb();
+        let child_lsb = lsb >> 2;
+        let first = self.0 - lsb + child_lsb;
+
+        Some([
+            CellId(first),
+            CellId(first + 2 * child_lsb),
+            CellId(first + 4 * child_lsb),
+            CellId(first + 6 * child_lsb),
+        ])
+    }
+
+    /// The first leaf cell contained in this cell
+    pub fn range_min(&self) -> CellId {
+        CellId(self.0 - (self.lsb() - 1))
+    }
+
+    /// The last leaf cell contained in this cell
+    pub fn range_max(&self) -> CellId {
+        CellId(self.0 + (self.lsb() - 1))
+    }
+
+    /// Returns true if the other cell is equal to or lies within this cell
+    pub fn contains(&self, other: &CellId) -> bool {
+        *other >= self.range_min() && *other <= self.range_max()
+    }
+
+    /// Returns the center of the cell (lon / lat, in degrees)
+    pub fn center(&self) -> (f64, f64) {
+        let (face, i, j) = self.to_face_ij();
+        let size = f64::from(self.size_ij());
+        let u = st_to_uv((f64::from(i) + size / 2.0) / f64::from(MAX_SIZE));
+        let v = st_to_uv((f64::from(j) + size / 2.0) / f64::from(MAX_SIZE));
+        xyz_to_lonlat(face_uv_to_xyz(face, u, v))
+    }
+
+    /// Returns the corners of the cell (lon / lat, in degrees), counter-clockwise
+    pub fn vertices(&self) -> [(f64, f64); 4] {
+        let mut vertices = [(0.0, 0.0); 4];
+        for (vertex, p) in vertices.iter_mut().zip(self.vertices_xyz().iter()) {
+            *vertex = xyz_to_lonlat(*p);
+        }
+        vertices
+    }
+
+    /// Returns the outline of the cell as a closed, counter-clockwise ring of
+    /// lon / lat coordinates (in degrees)
+    pub fn polygon(&self) -> Vec<(f64, f64)> {
+        let vertices = self.vertices();
+        let mut polygon = vertices.to_vec();
+        polygon.push(vertices[0]);
+        polygon
+    }
+
+    /// Corners of the cell on the unit cube, counter-clockwise
+    fn vertices_xyz(self) -> [[f64; 3]; 4] {
+        let (face, i, j) = self.to_face_ij();
+        let size = self.size_ij();
+        let (u0, u1) = (ij_to_uv(i), ij_to_uv(i + size));
+        let (v0, v1) = (ij_to_uv(j), ij_to_uv(j + size));
+
+        [
+            face_uv_to_xyz(face, u0, v0),
+            face_uv_to_xyz(face, u1, v0),
+            face_uv_to_xyz(face, u1, v1),
+            face_uv_to_xyz(face, u0, v1),
+        ]
+    }
+
+    /// Returns the lon / lat bounding box of the cell (in degrees)
+    ///
+    /// Cells around the poles span all longitudes,
+    /// cells crossing the antimeridian have `min_lon > max_lon`.
+    pub fn bounds(&self) -> (f64, f64, f64, f64) {
+        let vertices = self.vertices_xyz();
+        let lonlats = self.vertices();
+
+        let mut min_lat = lonlats.iter().map(|v| v.1).fold(90.0, f64::min);
+        let mut max_lat = lonlats.iter().map(|v| v.1).fold(-90.0, f64::max);
+
+        // The edges are great circle arcs, which may bulge beyond the latitudes of the corners
+        for k in 0..4 {
+            let (a, b) = (vertices[k], vertices[(k + 1) % 4]);
+            let normal = cross(a, b);
+
+            // Point of the great circle that is nearest to the north pole
+            let n2 = dot(normal, normal);
+            let top = [-normal[0] * normal[2] / n2, -normal[1] * normal[2] / n2, 1.0 - normal[2] * normal[2] / n2];
+
+            if dot(top, top) < 1e-30 {
+                continue;
+            }
+
+            for &p in [top, [-top[0], -top[1], -top[2]]].iter() {
+                if dot(cross(a, p), normal) > 0.0 && dot(cross(p, b), normal) > 0.0 {
+                    let (_, lat) = xyz_to_lonlat(p);
+                    min_lat = min_lat.min(lat);
+                    max_lat = max_lat.max(lat);
+                }
+            }
+        }
+
+        // Walk along the edges, skipping corners at the poles (their edges are meridians)
+        let path: Vec<f64> = lonlats.iter()
+            .filter(|&&(_, lat)| lat.abs() < 90.0)
+            .map(|&(lon, _)| lon)
+            .collect();
+
+        let (mut lon, mut west, mut east) = (path[0], 0.0_f64, 0.0_f64);
+        for k in 1..path.len() {
+            lon += lon_difference(path[k - 1], path[k]);
+            west = west.min(lon - path[0]);
+            east = east.max(lon - path[0]);
+        }
+
+        // A corner at a pole or a ring winding once around a pole spans all longitudes
+        if path.len() < 4 || (lon + lon_difference(path[3], path[0]) - path[0]).abs() > 180.0 {
+            return if self.center().1 > 0.0 {
+                (-180.0, min_lat, 180.0, 90.0)
+            } else {
+                (-180.0, -90.0, 180.0, max_lat)
+            };
+        }
+
+        let min_lon = (path[0] + west + 180.0).rem_euclid(360.0) - 180.0;
+        let max_lon = (path[0] + east + 180.0).rem_euclid(360.0) - 180.0;
+
+        // Keep 180° instead of wrapping it to -180°
+        let max_lon = if max_lon < min_lon && max_lon == -180.0 { 180.0 } else { max_lon };
+
+        (min_lon, min_lat, max_lon, max_lat)
+    }
+
+    /// Returns the compact hexadecimal token of the cell (the ID without trailing zeros)
+    pub fn to_token(&self) -> String {
+        format!("{:016x}", self.0).trim_end_matches('0').to_string()
+    }
+
+    /// Parses a cell from its hexadecimal token
+    pub fn from_token(token: &str) -> Result<Self, S2Error> {
+        let invalid = || S2Error::InvalidFormat(token.to_string());
+        let trimmed = token.trim();
+
+        if trimmed.is_empty() || trimmed.len() > 16 || !trimmed.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+
+        let id = u64::from_str_radix(trimmed, 16).map_err(|_| invalid())? << (4 * (16 - trimmed.len()));
+        CellId::new(id)
+    }
+}
+
+impl fmt::Display for CellId {
+    /// Formats the cell as its face followed by the Hilbert curve position
+    /// at every level, e.g. "3/0213"
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/", self.face())?;
+        for level in 1..=self.level() {
+            let position = (self.0 >> (2 * u32::from(MAX_LEVEL - level) + 1)) & 3;
+            write!(f, "{}", position)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for CellId {
+    type Err = S2Error;
+
+    /// Parses a cell from "face/positions", e.g. "3/0213"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || S2Error::InvalidFormat(s.to_string());
+        let mut parts = s.trim().split('/');
+        let face = parts.next().and_then(|p| p.parse().ok()).ok_or_else(invalid)?;
+        let positions = parts.next().ok_or_else(invalid)?;
+
+        if parts.next().is_some() || positions.len() > MAX_LEVEL as usize {
+            return Err(invalid());
+        }
+
+        let mut cell = CellId::from_face(face).map_err(|_| invalid())?;
+        for c in positions.chars() {
+            let position = c.to_digit(4).ok_or_else(invalid)? as usize;
+            cell = cell.children().ok_or_else(invalid)?[position];
+        }
+
+        Ok(cell)
+    }
+}
+
+/// Returns all cells at the given level that intersect the given bounding box
+/// (in degrees), in Hilbert curve order
+///
+/// Cells are tested against the bounding box by their own lon / lat bounds,
+/// so the covering may contain a few cells that only touch the bounding box.
+pub fn covering(bbox: (f64, f64, f64, f64), level: u8)
+                -> Result<Vec<CellId>, S2Error>
+{
+    let (min_lon, min_lat, max_lon, max_lat) = bbox;
+
+    if level > MAX_LEVEL {
+        return Err(S2Error::InvalidLevel(level));
+    }
+
+    for &(lon, lat) in [(min_lon, min_lat), (max_lon, max_lat)].iter() {
+        if !(-180.0..=180.0).contains(&lon) || !(-90.0..=90.0).contains(&lat) {
+            return Err(S2Error::InvalidCoordinate(lon, lat));
+        }
+    }
+
+    let intersects = |cell: &CellId| {
+        let (cell_min_lon, cell_min_lat, cell_max_lon, cell_max_lat) = cell.bounds();
+        cell_min_lat <= max_lat + 1e-9 && min_lat <= cell_max_lat + 1e-9 &&
+        lon_ranges_intersect((cell_min_lon, cell_max_lon), (min_lon, max_lon))
+    };
+
+    let mut cells = Vec::new();
+    let mut stack: Vec<CellId> = (0..NUM_FACES).rev().filter_map(|face| CellId::from_face(face).ok()).collect();
+
+    while let Some(cell) = stack.pop() {
+        if !intersects(&cell) {
+            continue;
+        }
+
+        match cell.children() {
+            Some(children) if cell.level() < level => stack.extend(children.iter().rev()),
+            _ => cells.push(cell),
+        }
+    }
+
+    Ok(cells)
+}
+
+/// Returns the cell at the given level of every coordinate of a `LonLatBuf`
+///
+/// Invalid coordinates are returned as `None`.
+pub fn lonlat_buf_to_cell_ids(buf: &LonLatBuf, level: u8, strategy: &mut MultithreadingStrategy)
+                              -> Result<Vec<Option<CellId>>, S2Error>
+{
+    if level > MAX_LEVEL {
+        return Err(S2Error::InvalidLevel(level));
+    }
+
+    let mut cells = vec![None; buf.data.len()];
+
+    match *strategy {
+        SingleCore => {
+            for (&(lon, lat), cell) in buf.data.iter().zip(cells.iter_mut()) {
+                *cell = CellId::from_lon_lat(lon, lat, level).ok();
+            }
+        },
+        #[cfg(all(not(target_arch = "wasm32"), feature = "scoped_threadpool"))]
+        MultiCore(ref mut thread_pool) => {
+            thread_pool.scoped(|scoped| {
+                for (&(lon, lat), cell) in buf.data.iter().zip(cells.iter_mut()) {
+                    scoped.execute(move || {
+                        *cell = CellId::from_lon_lat(lon, lat, level).ok();
+                    });
+                }
+            });
+        },
+    }
+
+    Ok(cells)
+}
+
+/// Converts cells into a `LonLatBuf` with the center of each cell
+pub fn cell_ids_to_lonlat_buf(cells: &[CellId], ellipsoid: &Ellipsoid, strategy: &mut MultithreadingStrategy)
+                              -> LonLatBuf
+{
+    let mut data = vec![(f64::NAN, f64::NAN); cells.len()];
+
+    match *strategy {
+        SingleCore => {
+            for (cell, coordinate) in cells.iter().zip(data.iter_mut()) {
+                *coordinate = cell.center();
+            }
+        },
+        #[cfg(all(not(target_arch = "wasm32"), feature = "scoped_threadpool"))]
+        MultiCore(ref mut thread_pool) => {
+            thread_pool.scoped(|scoped| {
+                for (cell, coordinate) in cells.iter().zip(data.iter_mut()) {
+                    scoped.execute(move || {
+                        *coordinate = cell.center();
+                    });
+                }
+            });
+        },
+    }
+
+    LonLatBuf {
+        data,
+        ellipsoid: *ellipsoid,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lon / lat coordinates and their tokens at levels 0, 1, 5, 10, 13 and 30
+    const TOKENS: [(f64, f64, [&str; 6]); 5] = [
+        (-73.9857, 40.7484, ["9", "8c", "89c4", "89c259", "89c259ac", "89c259a9a9647d73"]),
+        (13.41, 52.52, ["5", "44", "47ac", "47a84f", "47a84e24", "47a84e20328d9d91"]),
+        (151.2093, -33.8688, ["7", "6c", "6b14", "6b12af", "6b12ae3c", "6b12ae3ff6290055"]),
+        (0.0, 90.0, ["5", "54", "5004", "500001", "50000004", "5000000000000001"]),
+        (0.0, -90.0, ["b", "b4", "b004", "b00001", "b0000004", "b000000000000001"]),
+    ];
+
+    #[test]
+    fn reference_tokens() {
+        assert_eq!(CellId::from_lon_lat(0.0, 0.0, MAX_LEVEL).map(|cell| cell.id()), Ok(0x1000_0000_0000_0001));
+
+        for &(lon, lat, ref tokens) in TOKENS.iter() {
+            for (&level, token) in [0, 1, 5, 10, 13, 30].iter().zip(tokens.iter()) {
+                let cell = CellId::from_lon_lat(lon, lat, level).unwrap();
+                assert_eq!(cell.to_token(), *token, "{} {} {}", lon, lat, level);
+                assert_eq!(cell.level(), level);
+                assert_eq!(CellId::from_token(token), Ok(cell));
+            }
+        }
+    }
+
+    #[test]
+    fn level_12_cell() {
+        let cell = CellId::from_token("89c25a3").unwrap();
+        assert_eq!(cell.id(), 0x89c2_5a30_0000_0000);
+        assert_eq!(cell.face(), 4);
+        assert_eq!(cell.level(), 12);
+        assert!(!cell.is_leaf());
+        assert_eq!(cell.to_string(), "4/103201023101");
+        assert_eq!("4/103201023101".parse::<CellId>(), Ok(cell));
+
+        let (lon, lat) = cell.center();
+        assert!((lon - -73.994_756_345_618_63).abs() < 1e-9 && (lat - 40.707_344_376_944_73).abs() < 1e-9);
+        assert_eq!(CellId::from_lon_lat(lon, lat, 12), Ok(cell));
+
+        let (min_lon, min_lat, max_lon, max_lat) = cell.bounds();
+        assert!(min_lon < lon && lon < max_lon && min_lat < lat && lat < max_lat);
+        for &(lon, lat) in cell.vertices().iter() {
+            assert!(min_lon <= lon && lon <= max_lon && min_lat <= lat && lat <= max_lat);
+        }
+
+        let polygon = cell.polygon();
+        assert_eq!(polygon.len(), 5);
+        assert_eq!(polygon.first(), polygon.last());
+    }
+
+    #[test]
+    fn hierarchy() {
+        let cell = CellId::from_token("89c25a3").unwrap();
+        assert_eq!(cell.parent(10).map(|parent| parent.to_token()), Some("89c25b".to_string()));
+        assert_eq!(cell.parent(13), None);
+        assert_eq!(cell.parent(12), Some(cell));
+
+        let children = cell.children().unwrap();
+        assert!(children.iter().all(|child| child.level() == 13 && child.parent(12) == Some(cell)));
+        assert!(children.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(children[0].range_min(), cell.range_min());
+        assert_eq!(children[3].range_max(), cell.range_max());
+
+        let leaf = CellId::from_lon_lat(-73.99, 40.71, MAX_LEVEL).unwrap();
+        assert!(leaf.is_leaf() && leaf.children().is_none());
+        assert!(cell.contains(&leaf) && cell.contains(&cell) && !leaf.contains(&cell));
+    }
+
+    #[test]
+    fn faces() {
+        let tokens: Vec<String> = (0..NUM_FACES).map(|face| CellId::from_face(face).unwrap().to_token()).collect();
+        assert_eq!(tokens, ["1", "3", "5", "7", "9", "b"]);
+        assert_eq!(CellId::from_face(6), Err(S2Error::InvalidCellId(6 << POS_BITS)));
+
+        // The north pole face spans all longitudes, down to the latitude of its corners
+        let (min_lon, min_lat, max_lon, max_lat) = CellId::from_face(2).unwrap().bounds();
+        assert_eq!((min_lon, max_lon, max_lat), (-180.0, 180.0, 90.0));
+        assert!((min_lat - 0.5_f64.sqrt().atan().to_degrees()).abs() < 1e-9);
+
+        // Face 0 is centered on (0, 0), its edges bulge beyond the latitude of its corners
+        let (min_lon, min_lat, max_lon, max_lat) = CellId::from_face(0).unwrap().bounds();
+        assert!((min_lon + 45.0).abs() < 1e-9 && (max_lon - 45.0).abs() < 1e-9);
+        assert!((min_lat + 45.0).abs() < 1e-9 && (max_lat - 45.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn coverings() {
+        assert_eq!(covering((-180.0, -90.0, 180.0, 90.0), 0).unwrap().len(), 6);
+        assert_eq!(covering((-180.0, -90.0, 180.0, 90.0), 1).unwrap().len(), 24);
+
+        let cell = CellId::from_token("89c25a3").unwrap();
+        let (lon, lat) = cell.center();
+        let cells = covering((lon - 0.001, lat - 0.001, lon + 0.001, lat + 0.001), 12).unwrap();
+        assert_eq!(cells, vec![cell]);
+
+        let cells = covering((lon - 0.1, lat - 0.1, lon + 0.1, lat + 0.1), 12).unwrap();
+        assert!(cells.len() > 1 && cells.contains(&cell));
+        assert!(cells.windows(2).all(|pair| pair[0] < pair[1]));
+
+        // across the antimeridian
+        let cells = covering((179.9, -0.1, -179.9, 0.1), 8).unwrap();
+        assert!(cells.iter().any(|cell| cell.center().0 > 179.0) && cells.iter().any(|cell| cell.center().0 < -179.0));
+
+        assert_eq!(covering((0.0, 0.0, 1.0, 1.0), 31), Err(S2Error::InvalidLevel(31)));
+        assert_eq!(covering((0.0, 0.0, 181.0, 1.0), 1), Err(S2Error::InvalidCoordinate(181.0, 1.0)));
+    }
+
+    #[test]
+    fn buffers() {
+        let buf = LonLatBuf {
+            data: vec![(-73.994_756_345_618_63, 40.707_344_376_944_73), (0.0, 91.0)],
+            ellipsoid: WGS_1984_ELLIPSOID,
+        };
+
+        let mut strategy = MultithreadingStrategy::SingleCore;
+        let cells = lonlat_buf_to_cell_ids(&buf, 12, &mut strategy).unwrap();
+        assert_eq!(cells, vec![CellId::from_token("89c25a3").ok(), None]);
+        assert_eq!(lonlat_buf_to_cell_ids(&buf, 31, &mut strategy), Err(S2Error::InvalidLevel(31)));
+
+        let centers = cell_ids_to_lonlat_buf(&[cells[0].unwrap()], &WGS_1984_ELLIPSOID, &mut strategy);
+        assert_eq!(centers.data, vec![cells[0].unwrap().center()]);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(CellId::from_lon_lat(0.0, 0.0, 31), Err(S2Error::InvalidLevel(31)));
+        assert_eq!(CellId::from_lon_lat(0.0, -90.5, 1), Err(S2Error::InvalidCoordinate(0.0, -90.5)));
+        assert_eq!(CellId::new(0), Err(S2Error::InvalidCellId(0)));
+        assert_eq!(CellId::new(0xd000_0000_0000_0000), Err(S2Error::InvalidCellId(0xd000_0000_0000_0000)));
+        // the trailing bit must be at an even position
+        assert_eq!(CellId::new(0x1800_0000_0000_0000), Err(S2Error::InvalidCellId(0x1800_0000_0000_0000)));
+
+        for token in &["", "x", "d", "89c25a30000000000", "18"] {
+            assert!(CellId::from_token(token).is_err(), "{}", token);
+        }
+
+        for s in &["4", "6/", "4/1032/0", "4/4", "0/1111111111111111111111111111111"] {
+            assert_eq!(s.parse::<CellId>(), Err(S2Error::InvalidFormat(s.to_string())), "{}", s);
+        }
+    }
+}
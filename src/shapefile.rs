@@ -0,0 +1,554 @@
+//! ESRI shapefile (`.shp`, `.shx`, `.prj`) reprojection
+//!
+//! `parse_shp` reads the main file of a shapefile with any of the shape types of
+//! the ESRI specification: points, multipoints, polylines, polygons and
+//! multipatches, with their Z and M variants. Like `wkb`, the shapes are kept as
+//! bytes: `Shapefile::reproject` projects all points in a single batched
+//! `CoordinateSource::project` call, overwrites their x and y in place and
+//! recalculates the bounding boxes of the records and of the file. Z and M values
+//! and their ranges stay as they were. Record sizes don't change, so
+//! `Shapefile::to_shx` writes the index file from the records alone.
+//!
+//! `reproject_shapefile` does the whole job on files: it reads the `.shp` and the
+//! CRS from the `.prj` (ESRI WKT, see `wkt`), reprojects the shapes and writes a
+//! new `.shp`, `.shx` and `.prj`. The attribute table (`.dbf`) and its code page
+//! (`.cpg`) are copied unchanged, spatial indexes (`.sbn`, `.qix`, ...) are not
+//! because they would no longer match. If the source and target datums differ,
+//! the points are shifted through geocentric WGS84 coordinates with the Helmert
+//! transformations of both datums (see `pipeline`), and the target datum is
+//! written to the `.prj`.
+//!
+//! ```rust
+//! extern crate proj5;
+//!
+//! use proj5::prelude::*;
+//! use proj5::shapefile::*;
+//! use proj5::wkt::WktVersion;
+//!
+//! fn main() {
+//!     // A point shapefile with one record, POINT(9 48)
+//!     let mut shp = vec![0; 100];
+//!     shp[0..4].copy_from_slice(&9994u32.to_be_bytes());
+//!     shp[24..28].copy_from_slice(&(128u32 / 2).to_be_bytes());
+//!     shp[28..32].copy_from_slice(&1000u32.to_le_bytes());
+//!     shp[32..36].copy_from_slice(&1u32.to_le_bytes());
+//!     shp.extend_from_slice(&1u32.to_be_bytes());
+//!     shp.extend_from_slice(&10u32.to_be_bytes());
+//!     shp.extend_from_slice(&1u32.to_le_bytes());
+//!     shp.extend_from_slice(&9.0f64.to_le_bytes());
+//!     shp.extend_from_slice(&48.0f64.to_le_bytes());
+//!
+//!     let directory = std::env::temp_dir().join("proj5_shapefile_doctest");
+//!     std::fs::create_dir_all(&directory).unwrap();
+//!     std::fs::write(directory.join("points.shp"), &shp).unwrap();
+//!     std::fs::write(directory.join("points.prj"), r#"GEOGCS["GCS_WGS_1984",DATUM["D_WGS_1984",SPHEROID["WGS_1984",6378137.0,298.257223563]],PRIMEM["Greenwich",0.0],UNIT["Degree",0.0174532925199433]]"#).unwrap();
+//!
+//!     let utm = UTMSystem { utm_zone: 32, hemisphere: Hemisphere::North };
+//!     let mut strategy = MultithreadingStrategy::SingleCore;
+//!     let report = reproject_shapefile(directory.join("points.shp"), directory.join("points_utm.shp"),
+//!                                      &utm, &WGS_1984_ELLIPSOID, WGS84_DATUM.to_wgs84.as_ref(),
+//!                                      &mut strategy).unwrap();
+//!     assert_eq!(report, ShapefileReport { records: 1, not_projectable: Vec::new() });
+//!
+//!     let (shapefile, prj) = read_shapefile(directory.join("points_utm.shp")).unwrap();
+//!     assert_eq!(shapefile.shape_type(), ShapeType::Point);
+//!     assert!((shapefile.coordinates()[0].0 - 500_000.0).abs() < 1e-6);
+//!     assert!(prj.unwrap().to_wkt(WktVersion::Wkt1Esri).unwrap().starts_with("PROJCS[\"WGS_84_UTM_zone_32N\""));
+//!     assert_eq!(std::fs::read(directory.join("points_utm.shx")).unwrap(), shapefile.to_shx());
+//! }
+//! ```
+
+use prelude::*;
+use coordinate_buf::project_batch;
+use pipeline;
+use wkt::{parse_wkt, WktDefinition, WktError, WktVersion};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// File code at the start of `.shp` and `.shx` files
+const FILE_CODE: u32 = 9994;
+/// Length of the file header of `.shp` and `.shx` files, in bytes
+const HEADER_LENGTH: usize = 100;
+/// Sidecar files that are copied by `reproject_shapefile`
+const COPIED_SIDECARS: [&str; 2] = ["dbf", "cpg"];
+
+/// Shape type of a shapefile
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ShapeType {
+    Null,
+    Point,
+    PolyLine,
+    Polygon,
+    MultiPoint,
+    PointZ,
+    PolyLineZ,
+    PolygonZ,
+    MultiPointZ,
+    PointM,
+    PolyLineM,
+    PolygonM,
+    MultiPointM,
+    MultiPatch,
+}
+
+impl ShapeType {
+    /// Returns the shape type with the code of the ESRI specification
+    pub fn from_code(code: u32) -> Option<Self> {
+        use self::ShapeType::*;
+        Some(match code {
+            0 => Null,
+            1 => Point,
+            3 => PolyLine,
+            5 => Polygon,
+            8 => MultiPoint,
+            11 => PointZ,
+            13 => PolyLineZ,
+            15 => PolygonZ,
+            18 => MultiPointZ,
+            21 => PointM,
+            23 => PolyLineM,
+            25 => PolygonM,
+            28 => MultiPointM,
+            31 => MultiPatch,
+            _ => return None,
+        })
+    }
+
+    /// Returns the code of the shape type in the ESRI specification
+    pub fn code(self) -> u32 {
+        use self::ShapeType::*;
+        match self {
+            Null => 0,
+            Point => 1,
+            PolyLine => 3,
+            Polygon => 5,
+            MultiPoint => 8,
+            PointZ => 11,
+            PolyLineZ => 13,
+            PolygonZ => 15,
+            MultiPointZ => 18,
+            PointM => 21,
+            PolyLineM => 23,
+            PolygonM => 25,
+            MultiPointM => 28,
+            MultiPatch => 31,
+        }
+    }
+}
+
+/// The shapes of a `.shp` file, with the byte offsets of their points
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shapefile {
+    bytes: Vec<u8>,
+    /// Offset of the x coordinate of every point, all little endian
+    positions: Vec<usize>,
+    records: Vec<Record>,
+}
+
+/// A record of the `.shp` file
+#[derive(Debug, Clone, PartialEq)]
+struct Record {
+    /// Offset of the record header
+    offset: usize,
+    /// Length of the record content, in bytes
+    length: usize,
+    /// Offset of the bounding box, for all shapes except points
+    bbox: Option<usize>,
+    /// The points of the record in `Shapefile::positions`
+    positions: Range<usize>,
+}
+
+/// Summary of `reproject_shapefile`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShapefileReport {
+    /// Number of records, including null shapes
+    pub records: usize,
+    /// Record numbers (starting at 1, like the rows of the `.dbf`) of the shapes
+    /// that have points whose projection isn't finite, e.g. outside the area of the CRS
+    pub not_projectable: Vec<usize>,
+}
+
+/// Error that can occur when reading or writing a shapefile
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShapefileError {
+    /// Reading or writing failed, with the message of the I/O error
+    Io(String),
+    /// The file doesn't start with a `.shp` header
+    InvalidHeader,
+    /// The file ends before the byte offset that is needed
+    UnexpectedEnd(usize),
+    /// The shape type code isn't part of the ESRI specification
+    UnsupportedType(u32),
+    /// The points of the record at the byte offset don't fit into its content length
+    InvalidRecord(usize),
+    /// There is no `.prj` file with the CRS of the shapes
+    MissingPrj,
+    /// The source and target datums differ, but the transformation to WGS84 of one of them is unknown
+    MissingDatumShift,
+    /// The `.prj` file can't be read, or the CRS can't be written as ESRI WKT
+    InvalidPrj(WktError),
+}
+
+impl fmt::Display for ShapefileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::ShapefileError::*;
+        match *self {
+            Io(ref message) => write!(f, "I/O error: {}", message),
+            InvalidHeader => write!(f, "not a shapefile: invalid file header"),
+            UnexpectedEnd(offset) => write!(f, "shapefile ends before byte {}", offset),
+            UnsupportedType(code) => write!(f, "unsupported shape type: {}", code),
+            InvalidRecord(offset) => write!(f, "invalid shapefile record at byte {}", offset),
+            MissingPrj => write!(f, "the shapefile has no .prj file"),
+            MissingDatumShift => write!(f, "the datum transformation to WGS84 of the source or target CRS is unknown"),
+            InvalidPrj(ref error) => write!(f, "invalid .prj: {}", error),
+        }
+    }
+}
+
+impl Error for ShapefileError { }
+
+impl From<::std::io::Error> for ShapefileError {
+    fn from(error: ::std::io::Error) -> Self {
+        ShapefileError::Io(error.to_string())
+    }
+}
+
+impl From<WktError> for ShapefileError {
+    fn from(error: WktError) -> Self {
+        ShapefileError::InvalidPrj(error)
+    }
+}
+
+impl Shapefile {
+    /// Returns the shape type in the file header
+    pub fn shape_type(&self) -> ShapeType {
+        // checked by parse_shp
+        ShapeType::from_code(read_u32_le(&self.bytes, 32)).unwrap()
+    }
+
+    /// Returns the number of records, including null shapes
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Returns the x and y coordinates of all points, in the order of the file
+    pub fn coordinates(&self) -> Vec<(f64, f64)> {
+        self.positions.iter().map(|&offset| {
+            (read_f64_le(&self.bytes, offset), read_f64_le(&self.bytes, offset + 8))
+        }).collect()
+    }
+
+    /// Overwrites the x and y coordinates of all points, in the order of the file,
+    /// and recalculates the bounding boxes
+    ///
+    /// # Panics
+    ///
+    /// If `data` doesn't have one coordinate per point, see `coordinates`
+    pub fn set_coordinates(&mut self, data: Vec<(f64, f64)>) {
+        assert_eq!(self.positions.len(), data.len(), "one coordinate per point is required");
+
+        for (&offset, (x, y)) in self.positions.iter().zip(data) {
+            write_f64_le(&mut self.bytes, offset, x);
+            write_f64_le(&mut self.bytes, offset + 8, y);
+        }
+        self.update_bboxes();
+    }
+
+    /// Projects all points from the source to the target CRS in one batch
+    pub fn reproject(&mut self,
+                     source_crs: &dyn Crs, source_ellipsoid: &Ellipsoid,
+                     target_crs: &dyn Crs, target_ellipsoid: &Ellipsoid,
+                     strategy: &mut MultithreadingStrategy)
+    {
+        let data = project_batch(self.coordinates(), source_crs, source_ellipsoid, target_crs, target_ellipsoid, strategy);
+        self.set_coordinates(data);
+    }
+
+    /// Returns the record numbers (starting at 1) of the shapes that have points
+    /// with coordinates that aren't finite
+    pub fn invalid_records(&self) -> Vec<usize> {
+        self.records.iter().enumerate().filter(|&(_, record)| {
+            self.positions[record.positions.clone()].iter().any(|&offset| {
+                !read_f64_le(&self.bytes, offset).is_finite() || !read_f64_le(&self.bytes, offset + 8).is_finite()
+            })
+        }).map(|(i, _)| i + 1).collect()
+    }
+
+    /// Writes the index (`.shx`) of the records
+    pub fn to_shx(&self) -> Vec<u8> {
+        let mut shx = Vec::with_capacity(HEADER_LENGTH + 8 * self.records.len());
+        shx.extend_from_slice(&self.bytes[..HEADER_LENGTH]);
+        let words = (HEADER_LENGTH + 8 * self.records.len()) / 2;
+        shx[24..28].copy_from_slice(&(words as u32).to_be_bytes());
+
+        for record in &self.records {
+            shx.extend_from_slice(&((record.offset / 2) as u32).to_be_bytes());
+            shx.extend_from_slice(&((record.length / 2) as u32).to_be_bytes());
+        }
+        shx
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    /// Recalculates the x and y ranges of the records and of the file header
+    fn update_bboxes(&mut self) {
+        let mut file_bbox = [f64::INFINITY, f64::INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY];
+
+        for record in &self.records {
+            let bbox = xy_bbox(&self.bytes, &self.positions[record.positions.clone()]);
+            file_bbox = [file_bbox[0].min(bbox[0]), file_bbox[1].min(bbox[1]),
+                         file_bbox[2].max(bbox[2]), file_bbox[3].max(bbox[3])];
+            if let Some(offset) = record.bbox {
+                write_bbox(&mut self.bytes, offset, bbox, f64::NAN);
+            }
+        }
+
+        // the specification leaves the bounding box of a file without shapes unspecified
+        write_bbox(&mut self.bytes, 36, file_bbox, 0.0);
+    }
+}
+
+/// Bounding box (xmin, ymin, xmax, ymax) of the finite coordinates of the points,
+/// infinite if there are none
+fn xy_bbox(bytes: &[u8], positions: &[usize]) -> [f64; 4] {
+    let mut bbox = [f64::INFINITY, f64::INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY];
+    for &offset in positions {
+        let (x, y) = (read_f64_le(bytes, offset), read_f64_le(bytes, offset + 8));
+        if x.is_finite() && y.is_finite() {
+            bbox = [bbox[0].min(x), bbox[1].min(y), bbox[2].max(x), bbox[3].max(y)];
+        }
+    }
+    bbox
+}
+
+/// Writes the bounding box at the offset, or `empty` for all four values if it is infinite
+fn write_bbox(bytes: &mut [u8], offset: usize, bbox: [f64; 4], empty: f64) {
+    let bbox = if bbox[0].is_finite() { bbox } else { [empty; 4] };
+    for (i, &value) in bbox.iter().enumerate() {
+        write_f64_le(bytes, offset + 8 * i, value);
+    }
+}
+
+/// Reads the main file (`.shp`) of a shapefile
+pub fn parse_shp(bytes: &[u8]) -> Result<Shapefile, ShapefileError> {
+    if bytes.len() < HEADER_LENGTH || read_u32_be(bytes, 0) != FILE_CODE {
+        return Err(ShapefileError::InvalidHeader);
+    }
+    let shape_type = read_u32_le(bytes, 32);
+    if ShapeType::from_code(shape_type).is_none() {
+        return Err(ShapefileError::UnsupportedType(shape_type));
+    }
+    let end = 2 * read_u32_be(bytes, 24) as usize;
+    if end > bytes.len() {
+        return Err(ShapefileError::UnexpectedEnd(end));
+    }
+
+    let mut positions = Vec::new();
+    let mut records = Vec::new();
+    let mut offset = HEADER_LENGTH;
+    while offset < end {
+        if end - offset < 12 {
+            return Err(ShapefileError::UnexpectedEnd(offset + 12));
+        }
+        let length = 2 * read_u32_be(bytes, offset + 4) as usize;
+        let content = offset + 8;
+        if end - content < length {
+            return Err(ShapefileError::UnexpectedEnd(content + length));
+        }
+
+        let first = positions.len();
+        let bbox = read_shape(&bytes[content..content + length], content, &mut positions)
+            .map_err(|error| match error {
+                ShapefileError::UnexpectedEnd(_) => ShapefileError::InvalidRecord(offset),
+                error => error,
+            })?;
+        records.push(Record { offset, length, bbox, positions: first..positions.len() });
+        offset = content + length;
+    }
+
+    Ok(Shapefile { bytes: bytes[..end].to_vec(), positions, records })
+}
+
+/// Finds the points of the record content, which starts at the byte offset
+/// `start` of the file, and returns the offset of its bounding box
+fn read_shape(content: &[u8], start: usize, positions: &mut Vec<usize>)
+              -> Result<Option<usize>, ShapefileError>
+{
+    use self::ShapeType::*;
+
+    // u64, as corrupt counts can overflow usize on 32-bit targets
+    let need = |length: u64| if (content.len() as u64) < length {
+        Err(ShapefileError::UnexpectedEnd(start + content.len()))
+    } else {
+        Ok(())
+    };
+
+    need(4)?;
+    let code = read_u32_le(content, 0);
+    let shape_type = ShapeType::from_code(code).ok_or(ShapefileError::UnsupportedType(code))?;
+
+    // offset of the first point and number of points
+    let (first, count) = match shape_type {
+        Null => return Ok(None),
+        Point | PointZ | PointM => (4, 1),
+        MultiPoint | MultiPointZ | MultiPointM => {
+            need(40)?;
+            (40, u64::from(read_u32_le(content, 36)))
+        },
+        PolyLine | PolyLineZ | PolyLineM | Polygon | PolygonZ | PolygonM | MultiPatch => {
+            need(44)?;
+            let parts = u64::from(read_u32_le(content, 36));
+            let part_arrays = if shape_type == MultiPatch { 2 } else { 1 };
+            (44 + 4 * parts * part_arrays, u64::from(read_u32_le(content, 40)))
+        },
+    };
+    need(first + 16 * count)?;
+
+    let (first, count) = (first as usize, count as usize);
+    positions.extend((0..count).map(|i| start + first + 16 * i));
+    Ok(if first == 4 { None } else { Some(start + 4) })
+}
+
+/// Reads a shapefile and the CRS of its `.prj` file, if there is one
+///
+/// `path` is the `.shp` file. The `.shx` index isn't needed, the records are
+/// read in the order of the `.shp` file.
+pub fn read_shapefile<P: AsRef<Path>>(path: P)
+                                      -> Result<(Shapefile, Option<WktDefinition>), ShapefileError>
+{
+    let path = path.as_ref();
+    let shapefile = parse_shp(&fs::read(path)?)?;
+
+    let prj = sidecar(path, "prj");
+    let definition = if prj.is_file() {
+        let text = fs::read_to_string(prj)?;
+        Some(parse_wkt(text.trim_start_matches('\u{feff}').trim())?)
+    } else {
+        None
+    };
+
+    Ok((shapefile, definition))
+}
+
+/// Writes the `.shp` and `.shx` files of a shapefile and the CRS as ESRI WKT
+/// into the `.prj` file
+///
+/// `path` is the `.shp` file. Without a CRS, an existing `.prj` is removed so that
+/// it doesn't describe the wrong CRS. The `.dbf` isn't written, see `reproject_shapefile`.
+pub fn write_shapefile<P: AsRef<Path>>(path: P, shapefile: &Shapefile, crs: Option<&WktDefinition>)
+                                       -> Result<(), ShapefileError>
+{
+    let path = path.as_ref();
+    // fails before any file is written if the CRS has no ESRI WKT
+    let prj = match crs {
+        Some(definition) => Some(definition.to_wkt(WktVersion::Wkt1Esri)?),
+        None => None,
+    };
+
+    fs::write(path, shapefile.as_bytes())?;
+    fs::write(sidecar(path, "shx"), shapefile.to_shx())?;
+
+    let prj_path = sidecar(path, "prj");
+    match prj {
+        Some(prj) => fs::write(prj_path, prj)?,
+        None if prj_path.is_file() => fs::remove_file(prj_path)?,
+        None => { },
+    }
+    Ok(())
+}
+
+/// Reprojects the shapefile `input` (the `.shp` file) from the CRS of its `.prj`
+/// file into the target CRS and writes it to `output`, see the module documentation
+///
+/// `target_to_wgs84` is the transformation of the target datum to WGS84, e.g.
+/// `WGS84_DATUM.to_wgs84`. If it differs from the one of the source datum (the
+/// `TOWGS84` of the `.prj` or the transformation of its named datum), the points
+/// are shifted through WGS84. Returns `MissingDatumShift` if the datums differ
+/// but one of the transformations is unknown.
+///
+/// Shapes with points that can't be projected are written with coordinates that
+/// aren't finite and listed in the report.
+pub fn reproject_shapefile<P: AsRef<Path>, Q: AsRef<Path>>(input: P, output: Q,
+                                                           target_crs: &dyn Crs, target_ellipsoid: &Ellipsoid,
+                                                           target_to_wgs84: Option<&Helmert>,
+                                                           strategy: &mut MultithreadingStrategy)
+                                                           -> Result<ShapefileReport, ShapefileError>
+{
+    let (input, output) = (input.as_ref(), output.as_ref());
+    let (mut shapefile, source) = read_shapefile(input)?;
+    let source = source.ok_or(ShapefileError::MissingPrj)?;
+    let source_to_wgs84 = source.to_wgs84.or_else(|| source.datum().and_then(|datum| datum.to_wgs84));
+
+    match (source_to_wgs84.as_ref(), target_to_wgs84) {
+        // the same datum, or two unknown datums on the same ellipsoid
+        (source_shift, target_shift) if source_shift == target_shift && source.ellipsoid == *target_ellipsoid => {
+            shapefile.reproject(&*source.crs, &source.ellipsoid, target_crs, target_ellipsoid, strategy);
+        },
+        (Some(source_shift), Some(target_shift)) => {
+            let pipeline = pipeline::through_wgs84(&*source.crs, &source.ellipsoid, source_shift,
+                                                   target_crs, target_ellipsoid, target_shift);
+            let data = pipeline.transform(shapefile.coordinates(), strategy);
+            shapefile.set_coordinates(data);
+        },
+        _ => return Err(ShapefileError::MissingDatumShift),
+    }
+
+    let target = WktDefinition::new(Crs::clone(target_crs), *target_ellipsoid, target_to_wgs84.cloned());
+    write_shapefile(output, &shapefile, Some(&target))?;
+
+    for extension in COPIED_SIDECARS.iter() {
+        let (from, to) = (sidecar(input, extension), sidecar(output, extension));
+        if from.is_file() && from != to {
+            fs::copy(from, to)?;
+        }
+    }
+
+    Ok(ShapefileReport { records: shapefile.len(), not_projectable: shapefile.invalid_records() })
+}
+
+/// Path of a sidecar file, with the extension in the case of the `.shp` extension
+fn sidecar(path: &Path, extension: &str) -> PathBuf {
+    let uppercase = path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| !e.is_empty() && e.chars().all(|c| c.is_ascii_uppercase()));
+    if uppercase {
+        path.with_extension(extension.to_ascii_uppercase())
+    } else {
+        path.with_extension(extension)
+    }
+}
+
+fn read_u32_be(bytes: &[u8], offset: usize) -> u32 {
+    let mut value = [0; 4];
+    value.copy_from_slice(&bytes[offset..offset + 4]);
+    u32::from_be_bytes(value)
+}
+
+fn read_u32_le(bytes: &[u8], offset: usize) -> u32 {
+    let mut value = [0; 4];
+    value.copy_from_slice(&bytes[offset..offset + 4]);
+    u32::from_le_bytes(value)
+}
+
+fn read_f64_le(bytes: &[u8], offset: usize) -> f64 {
+    let mut value = [0; 8];
+    value.copy_from_slice(&bytes[offset..offset + 8]);
+    f64::from_le_bytes(value)
+}
+
+fn write_f64_le(bytes: &mut [u8], offset: usize, value: f64) {
+    bytes[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+}
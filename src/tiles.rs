@@ -0,0 +1,516 @@
+//! Slippy map tiles (XYZ / TMS / quadkeys) on the Web Mercator grid
+//!
+//! Implementation based on:
+//!
+//! https://wiki.openstreetmap.org/wiki/Slippy_map_tilenames
+//! https://docs.microsoft.com/en-us/bingmaps/articles/bing-maps-tile-system
+//!
+//! At zoom level `z`, the Web Mercator square (±180° longitude, ±85.0511°
+//! latitude) is divided into `2^z * 2^z` tiles. XYZ tiles count rows from
+//! the north (y = 0 is the northernmost row), TMS tiles count from the south.
+//! Pixel coordinates are global pixel coordinates at the given zoom level,
+//! with (0, 0) in the north-west corner.
+//!
+//! Bounding boxes are `(min_lon, min_lat, max_lon, max_lat)` in degrees or
+//! `(min_x, min_y, max_x, max_y)` in Web Mercator meters (EPSG:3857, on the
+//! `WGS_1984_ELLIPSOID`).
+
+use prelude::*;
+use coordinate_systems::merc::utils::{WEB_MERCATOR_MAX_LATITUDE, lonlat_to_web_mercator_xy};
+use std::error::Error;
+use std::f64::consts::PI;
+use std::fmt;
+use std::str::FromStr;
+
+/// Highest supported zoom level
+pub const MAX_ZOOM: u8 = 30;
+
+/// Default size of a tile, in pixels
+pub const DEFAULT_TILE_SIZE: u32 = 256;
+
+/// Error that can occur when creating or parsing a tile
+#[derive(Debug, Clone, PartialEq)]
+pub enum TileError {
+    /// Zoom level must be between 0 and `MAX_ZOOM`
+    InvalidZoom(u8),
+    /// x or y is outside of `0..2^zoom`
+    InvalidTile(u32, u32, u8),
+    /// Longitude or latitude isn't a valid number
+    InvalidCoordinate(f64, f64),
+    /// A quadkey may only contain the digits 0 to 3
+    InvalidQuadkey(String),
+    /// A tile must be written as "z/x/y"
+    InvalidFormat(String),
+    /// The southern edge of a bounding box is north of its northern edge
+    InvalidBoundingBox(f64, f64, f64, f64),
+}
+
+impl fmt::Display for TileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::TileError::*;
+        match *self {
+            InvalidZoom(z) => write!(f, "invalid zoom level: {} (must be 0 to {})", z, MAX_ZOOM),
+            InvalidTile(x, y, z) => write!(f, "invalid tile: {}/{}/{}", z, x, y),
+            InvalidCoordinate(lon, lat) => write!(f, "invalid coordinate: ({}, {})", lon, lat),
+            InvalidQuadkey(ref s) => write!(f, "invalid quadkey: \"{}\"", s),
+            InvalidFormat(ref s) => write!(f, "invalid tile: \"{}\" (must be z/x/y)", s),
+            InvalidBoundingBox(min_lon, min_lat, max_lon, max_lat) => write!(f, "invalid bounding box: ({}, {}, {}, {}) (min_lat must not exceed max_lat)", min_lon, min_lat, max_lon, max_lat),
+        }
+    }
+}
+
+impl Error for TileError { }
+
+/// A tile in the XYZ scheme
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Tile {
+    pub z: u8,
+    pub x: u32,
+    pub y: u32,
+}
+
+/// Number of tiles per row / column at a zoom level
+#[inline]
+fn tile_count(z: u8) -> f64 {
+    f64::from(1_u32 << z)
+}
+
+/// Converts a lon / lat coordinate (in degrees) into fractional tile coordinates.
+/// Latitudes are clamped to the Web Mercator area.
+#[inline]
+fn lonlat_to_fractional_tile(lon: f64, lat: f64, z: u8)
+                             -> (f64, f64)
+{
+    // Web Mercator on the unit sphere, ranging from -PI to PI
+    let (x, y) = lonlat_to_web_mercator_xy(lon, lat, 1.0);
+    let n = tile_count(z);
+    ((x / PI + 1.0) / 2.0 * n, (1.0 - y / PI) / 2.0 * n)
+}
+
+/// Converts fractional tile coordinates into a lon / lat coordinate (in degrees)
+#[inline]
+fn fractional_tile_to_lonlat(x: f64, y: f64, z: u8)
+                             -> (f64, f64)
+{
+    let n = tile_count(z);
+    let lon = x / n * 360.0 - 180.0;
+    let lat = (PI * (1.0 - 2.0 * y / n)).sinh().atan().to_degrees();
+    (lon, lat)
+}
+
+impl Tile {
+    /// Creates a tile, validating that x and y exist at zoom level z
+    pub fn new(z: u8, x: u32, y: u32) -> Result<Self, TileError> {
+        if z > MAX_ZOOM {
+            return Err(TileError::InvalidZoom(z));
+        }
+
+        if u64::from(x) >= 1 << z || u64::from(y) >= 1 << z {
+            return Err(TileError::InvalidTile(x, y, z));
+        }
+
+        Ok(Tile { z, x, y })
+    }
+
+    /// Returns the tile that contains the given lon / lat coordinate (in degrees)
+    ///
+    /// Longitudes are wrapped around the antimeridian, latitudes outside of
+    /// ±85.0511° are clamped to the northernmost / southernmost row.
+    pub fn from_lon_lat(lon: f64, lat: f64, z: u8) -> Result<Self, TileError> {
+        if z > MAX_ZOOM {
+            return Err(TileError::InvalidZoom(z));
+        }
+
+        if !lon.is_finite() || !lat.is_finite() || !(-90.0..=90.0).contains(&lat) {
+            return Err(TileError::InvalidCoordinate(lon, lat));
+        }
+
+        let lon = (lon + 180.0).rem_euclid(360.0) - 180.0;
+        let (x, y) = lonlat_to_fractional_tile(lon, lat, z);
+        let max = tile_count(z) - 1.0;
+
+        Ok(Tile {
+            z,
+            x: x.floor().clamp(0.0, max) as u32,
+            y: y.floor().clamp(0.0, max) as u32,
+        })
+    }
+
+    /// Creates a tile from TMS coordinates (y counted from the south)
+    pub fn from_tms(z: u8, x: u32, tms_y: u32) -> Result<Self, TileError> {
+        if z > MAX_ZOOM {
+            return Err(TileError::InvalidZoom(z));
+        }
+
+        if u64::from(tms_y) >= 1 << z {
+            return Err(TileError::InvalidTile(x, tms_y, z));
+        }
+
+        Tile::new(z, x, (1 << z) - 1 - tms_y)
+    }
+
+    /// Row of the tile in the TMS scheme (y counted from the south)
+    pub fn tms_y(&self) -> u32 {
+        (1 << self.z) - 1 - self.y
+    }
+
+    /// Creates a tile from a Bing Maps quadkey, such as "120210233"
+    ///
+    /// The empty quadkey is the single tile at zoom level 0.
+    pub fn from_quadkey(quadkey: &str) -> Result<Self, TileError> {
+        if quadkey.len() > usize::from(MAX_ZOOM) {
+            return Err(TileError::InvalidQuadkey(quadkey.to_string()));
+        }
+
+        let (mut x, mut y) = (0, 0);
+        for c in quadkey.chars() {
+            let digit = match c {
+                '0'..='3' => c as u32 - '0' as u32,
+                _ => return Err(TileError::InvalidQuadkey(quadkey.to_string())),
+            };
+            x = (x << 1) | (digit & 1);
+            y = (y << 1) | (digit >> 1);
+        }
+
+        Ok(Tile { z: quadkey.len() as u8, x, y })
+    }
+
+    /// Bing Maps quadkey of the tile
+    pub fn quadkey(&self) -> String {
+        (1..=self.z).rev().map(|i| {
+            let digit = ((self.x >> (i - 1)) & 1) + 2 * ((self.y >> (i - 1)) & 1);
+            (b'0' + digit as u8) as char
+        }).collect()
+    }
+
+    /// Bounding box of the tile in degrees (min_lon, min_lat, max_lon, max_lat)
+    pub fn bounds(&self) -> (f64, f64, f64, f64) {
+        let (min_lon, max_lat) = fractional_tile_to_lonlat(f64::from(self.x), f64::from(self.y), self.z);
+        let (max_lon, min_lat) = fractional_tile_to_lonlat(f64::from(self.x + 1), f64::from(self.y + 1), self.z);
+        (min_lon, min_lat, max_lon, max_lat)
+    }
+
+    /// Bounding box of the tile in Web Mercator meters (min_x, min_y, max_x, max_y)
+    pub fn bounds_meters(&self) -> (f64, f64, f64, f64) {
+        let origin = PI * WGS_1984_ELLIPSOID.a;
+        let size = 2.0 * origin / tile_count(self.z);
+        let min_x = f64::from(self.x) * size - origin;
+        let max_y = origin - f64::from(self.y) * size;
+        (min_x, max_y - size, min_x + size, max_y)
+    }
+
+    /// Center of the tile, in degrees (lon, lat)
+    pub fn center(&self) -> (f64, f64) {
+        fractional_tile_to_lonlat(f64::from(self.x) + 0.5, f64::from(self.y) + 0.5, self.z)
+    }
+
+    /// The tile at the next lower zoom level that contains this tile
+    ///
+    /// Returns: `None` at zoom level 0
+    pub fn parent(&self) -> Option<Tile> {
+        if self.z == 0 {
+            return None;
+        }
+
+        Some(Tile { z: self.z - 1, x: self.x >> 1, y: self.y >> 1 })
+    }
+
+    /// The four tiles at the next higher zoom level that make up this tile
+    /// (north-west, north-east, south-west, south-east)
+    ///
+    /// Returns: `None` at `MAX_ZOOM`
+    pub fn children(&self) -> Option<[Tile; 4]> {
+        if self.z >= MAX_ZOOM {
+            return None;
+        }
+
+        let (z, x, y) = (self.z + 1, self.x << 1, self.y << 1);
+        Some([
+            Tile { z, x, y },
+            Tile { z, x: x + 1, y },
+            Tile { z, x, y: y + 1 },
+            Tile { z, x: x + 1, y: y + 1 },
+        ])
+    }
+}
+
+impl fmt::Display for Tile {
+    /// Formats the tile as "z/x/y"
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}/{}", self.z, self.x, self.y)
+    }
+}
+
+impl FromStr for Tile {
+    type Err = TileError;
+
+    /// Parses a tile from "z/x/y"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || TileError::InvalidFormat(s.to_string());
+        let mut parts = s.trim().split('/');
+        let z = parts.next().and_then(|p| p.parse().ok()).ok_or_else(invalid)?;
+        let x = parts.next().and_then(|p| p.parse().ok()).ok_or_else(invalid)?;
+        let y = parts.next().and_then(|p| p.parse().ok()).ok_or_else(invalid)?;
+
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+
+        Tile::new(z, x, y)
+    }
+}
+
+/// Converts a lon / lat coordinate (in degrees) into global pixel coordinates
+/// at the given zoom level
+///
+/// Latitudes are clamped to the Web Mercator area.
+#[inline]
+pub fn lonlat_to_pixel(lon: f64, lat: f64, z: u8, tile_size: u32)
+                       -> (f64, f64)
+{
+    let (x, y) = lonlat_to_fractional_tile(lon, lat, z);
+    (x * f64::from(tile_size), y * f64::from(tile_size))
+}
+
+/// Converts global pixel coordinates at the given zoom level
+/// into a lon / lat coordinate (in degrees)
+#[inline]
+pub fn pixel_to_lonlat(x: f64, y: f64, z: u8, tile_size: u32)
+                       -> (f64, f64)
+{
+    fractional_tile_to_lonlat(x / f64::from(tile_size), y / f64::from(tile_size), z)
+}
+
+/// Returns the tile that contains the given global pixel coordinates
+pub fn pixel_to_tile(x: f64, y: f64, z: u8, tile_size: u32)
+                     -> Result<Tile, TileError>
+{
+    if !x.is_finite() || !y.is_finite() || x < 0.0 || y < 0.0 {
+        return Err(TileError::InvalidCoordinate(x, y));
+    }
+
+    let tile_size = f64::from(tile_size);
+    Tile::new(z, (x / tile_size).floor() as u32, (y / tile_size).floor() as u32)
+}
+
+/// Returns all tiles at zoom level `z` that intersect the given bounding box
+/// (in degrees), row by row from north-west to south-east
+///
+/// If `min_lon > max_lon`, the bounding box crosses the antimeridian.
+/// `min_lat > max_lat` is an error.
+pub fn covering(bbox: (f64, f64, f64, f64), z: u8)
+                -> Result<Vec<Tile>, TileError>
+{
+    let (min_lon, min_lat, max_lon, max_lat) = bbox;
+
+    if min_lat > max_lat {
+        return Err(TileError::InvalidBoundingBox(min_lon, min_lat, max_lon, max_lat));
+    }
+
+    if min_lon > max_lon {
+        let mut tiles = covering((min_lon, min_lat, 180.0, max_lat), z)?;
+        tiles.extend(covering((-180.0, min_lat, max_lon, max_lat), z)?);
+        return Ok(tiles);
+    }
+
+    // Clamp the eastern edge, so that 180° doesn't wrap around to the first column
+    let north_west = Tile::from_lon_lat(min_lon, max_lat.min(WEB_MERCATOR_MAX_LATITUDE), z)?;
+    let mut south_east = Tile::from_lon_lat(max_lon, min_lat.max(-WEB_MERCATOR_MAX_LATITUDE), z)?;
+    if max_lon >= 180.0 {
+        south_east.x = (1 << z) - 1;
+    }
+
+    let mut tiles = Vec::with_capacity((u64::from(south_east.x - north_west.x + 1) * u64::from(south_east.y - north_west.y + 1)) as usize);
+    for y in north_west.y..=south_east.y {
+        for x in north_west.x..=south_east.x {
+            tiles.push(Tile { z, x, y });
+        }
+    }
+
+    Ok(tiles)
+}
+
+/// Returns the tile of every coordinate of a `LonLatBuf` at zoom level `z`
+///
+/// Invalid coordinates are returned as `None`.
+pub fn lonlat_buf_to_tiles(buf: &LonLatBuf, z: u8, strategy: &mut MultithreadingStrategy)
+                           -> Result<Vec<Option<Tile>>, TileError>
+{
+    if z > MAX_ZOOM {
+        return Err(TileError::InvalidZoom(z));
+    }
+
+    let mut tiles = vec![None; buf.data.len()];
+
+    match *strategy {
+        SingleCore => {
+            for (&(lon, lat), tile) in buf.data.iter().zip(tiles.iter_mut()) {
+                *tile = Tile::from_lon_lat(lon, lat, z).ok();
+            }
+        },
+        #[cfg(all(not(target_arch = "wasm32"), feature = "scoped_threadpool"))]
+        MultiCore(ref mut thread_pool) => {
+            thread_pool.scoped(|scoped| {
+                for (&(lon, lat), tile) in buf.data.iter().zip(tiles.iter_mut()) {
+                    scoped.execute(move || {
+                        *tile = Tile::from_lon_lat(lon, lat, z).ok();
+                    });
+                }
+            });
+        },
+    }
+
+    Ok(tiles)
+}
+
+/// Converts all coordinates of a `LonLatBuf` into global pixel coordinates at zoom level `z`
+pub fn lonlat_buf_to_pixels(buf: &LonLatBuf, z: u8, tile_size: u32, strategy: &mut MultithreadingStrategy)
+                            -> Vec<(f64, f64)>
+{
+    let mut data = buf.data.clone();
+
+    match *strategy {
+        SingleCore => {
+            for &mut (ref mut x, ref mut y) in data.iter_mut() {
+                let (px, py) = lonlat_to_pixel(*x, *y, z, tile_size);
+                *x = px; *y = py;
+            }
+        },
+        #[cfg(all(not(target_arch = "wasm32"), feature = "scoped_threadpool"))]
+        MultiCore(ref mut thread_pool) => {
+            thread_pool.scoped(|scoped| {
+                for &mut (ref mut x, ref mut y) in data.iter_mut() {
+                    scoped.execute(move || {
+                        let (px, py) = lonlat_to_pixel(*x, *y, z, tile_size);
+                        *x = px; *y = py;
+                    });
+                }
+            });
+        },
+    }
+
+    data
+}
+
+/// Converts global pixel coordinates at zoom level `z` into a `LonLatBuf`
+pub fn pixels_to_lonlat_buf(mut data: Vec<(f64, f64)>, z: u8, tile_size: u32, ellipsoid: &Ellipsoid, strategy: &mut MultithreadingStrategy)
+                            -> LonLatBuf
+{
+    match *strategy {
+        SingleCore => {
+            for &mut (ref mut x, ref mut y) in data.iter_mut() {
+                let (lon, lat) = pixel_to_lonlat(*x, *y, z, tile_size);
+                *x = lon; *y = lat;
+            }
+        },
+        #[cfg(all(not(target_arch = "wasm32"), feature = "scoped_threadpool"))]
+        MultiCore(ref mut thread_pool) => {
+            thread_pool.scoped(|scoped| {
+                for &mut (ref mut x, ref mut y) in data.iter_mut() {
+                    scoped.execute(move || {
+                        let (lon, lat) = pixel_to_lonlat(*x, *y, z, tile_size);
+                        *x = lon; *y = lat;
+                    });
+                }
+            });
+        },
+    }
+
+    LonLatBuf {
+        data,
+        ellipsoid: *ellipsoid,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slippy_map_tilenames() {
+        // Berlin, z10 (550, 335)
+        let tile = Tile::from_lon_lat(13.41, 52.52, 10).unwrap();
+        assert_eq!(tile, Tile { z: 10, x: 550, y: 335 });
+        assert_eq!(tile.to_string(), "10/550/335");
+        assert_eq!("10/550/335".parse::<Tile>(), Ok(tile));
+
+        let (min_lon, min_lat, max_lon, max_lat) = tile.bounds();
+        assert!((min_lon - 13.359_375).abs() < 1e-12 && (max_lon - 13.710_937_5).abs() < 1e-12);
+        assert!((min_lat - 52.482_780_222_078_21).abs() < 1e-10 && (max_lat - 52.696_361_078_274_48).abs() < 1e-10);
+
+        let (min_x, _, _, max_y) = tile.bounds_meters();
+        assert!((min_x - 1_487_158.822_316_389_5).abs() < 1e-6 && (max_y - 6_927_029.251_315_813_5).abs() < 1e-6);
+
+        assert_eq!(Tile::from_lon_lat(-122.4194, 37.7749, 12), Ok(Tile { z: 12, x: 655, y: 1583 }));
+        assert_eq!(Tile::from_lon_lat(151.2093, -33.8688, 15), Ok(Tile { z: 15, x: 30147, y: 19663 }));
+        assert_eq!(Tile::from_lon_lat(0.0, 0.0, 1), Ok(Tile { z: 1, x: 1, y: 1 }));
+        assert_eq!(Tile::from_lon_lat(180.0, 90.0, 3), Ok(Tile { z: 3, x: 0, y: 0 }));
+        assert_eq!(Tile::from_lon_lat(-180.0, -90.0, 3), Ok(Tile { z: 3, x: 0, y: 7 }));
+    }
+
+    #[test]
+    fn tms_and_quadkeys() {
+        // example of the Bing Maps tile system documentation
+        let tile = Tile::new(3, 3, 5).unwrap();
+        assert_eq!(tile.quadkey(), "213");
+        assert_eq!(Tile::from_quadkey("213"), Ok(tile));
+        assert_eq!(Tile::from_quadkey(""), Ok(Tile { z: 0, x: 0, y: 0 }));
+        assert_eq!(Tile::from_quadkey("124"), Err(TileError::InvalidQuadkey("124".to_string())));
+
+        assert_eq!(tile.tms_y(), 2);
+        assert_eq!(Tile::from_tms(3, 3, 2), Ok(tile));
+        assert_eq!(Tile::from_tms(3, 3, 8), Err(TileError::InvalidTile(3, 8, 3)));
+    }
+
+    #[test]
+    fn hierarchy() {
+        let tile = Tile::new(10, 550, 335).unwrap();
+        assert_eq!(tile.parent(), Some(Tile { z: 9, x: 275, y: 167 }));
+        assert_eq!(Tile::new(0, 0, 0).unwrap().parent(), None);
+
+        let children = tile.children().unwrap();
+        assert_eq!(children[0], Tile { z: 11, x: 1100, y: 670 });
+        assert_eq!(children[3], Tile { z: 11, x: 1101, y: 671 });
+        assert!(children.iter().all(|child| child.parent() == Some(tile)));
+        assert_eq!(Tile::new(MAX_ZOOM, 0, 0).unwrap().children(), None);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(Tile::new(31, 0, 0), Err(TileError::InvalidZoom(31)));
+        assert_eq!(Tile::new(2, 4, 0), Err(TileError::InvalidTile(4, 0, 2)));
+        assert_eq!(Tile::from_lon_lat(0.0, 91.0, 2), Err(TileError::InvalidCoordinate(0.0, 91.0)));
+        assert_eq!("10/550".parse::<Tile>(), Err(TileError::InvalidFormat("10/550".to_string())));
+        assert_eq!("10/550/335/1".parse::<Tile>(), Err(TileError::InvalidFormat("10/550/335/1".to_string())));
+        assert_eq!(pixel_to_tile(-1.0, 0.0, 1, DEFAULT_TILE_SIZE), Err(TileError::InvalidCoordinate(-1.0, 0.0)));
+    }
+
+    #[test]
+    fn pixels() {
+        let (x, y) = lonlat_to_pixel(13.41, 52.52, 10, DEFAULT_TILE_SIZE);
+        assert_eq!(pixel_to_tile(x, y, 10, DEFAULT_TILE_SIZE), Ok(Tile { z: 10, x: 550, y: 335 }));
+        let (lon, lat) = pixel_to_lonlat(x, y, 10, DEFAULT_TILE_SIZE);
+        assert!((lon - 13.41).abs() < 1e-10 && (lat - 52.52).abs() < 1e-10);
+        assert_eq!(lonlat_to_pixel(0.0, 0.0, 0, 512), (256.0, 256.0));
+    }
+
+    #[test]
+    fn coverings() {
+        let tile = Tile::new(10, 550, 335).unwrap();
+        let (lon, lat) = tile.center();
+        assert_eq!(covering((lon - 0.1, lat - 0.1, lon + 0.1, lat + 0.1), 10), Ok(vec![tile]));
+        assert_eq!(covering((lon - 0.1, lat - 0.1, lon + 0.2, lat + 0.2), 10).unwrap().len(), 4);
+
+        let tiles = covering((-180.0, -90.0, 180.0, 90.0), 2).unwrap();
+        assert_eq!(tiles.len(), 16);
+        assert_eq!(tiles[0], Tile { z: 2, x: 0, y: 0 });
+        assert_eq!(tiles[15], Tile { z: 2, x: 3, y: 3 });
+
+        // across the antimeridian: the eastern column first, then the western one
+        let tiles = covering((170.0, -10.0, -170.0, 10.0), 2).unwrap();
+        assert_eq!(tiles, vec![Tile { z: 2, x: 3, y: 1 }, Tile { z: 2, x: 3, y: 2 },
+                               Tile { z: 2, x: 0, y: 1 }, Tile { z: 2, x: 0, y: 2 }]);
+
+        assert_eq!(covering((0.0, 10.0, 1.0, -10.0), 2), Err(TileError::InvalidBoundingBox(0.0, 10.0, 1.0, -10.0)));
+    }
+}
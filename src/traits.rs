@@ -3,6 +3,7 @@
 use coordinate_buf::CoordinateBuf;
 use lonlat_buf::LonLatBuf;
 use prelude::*;
+use std::any::Any;
 use std::fmt::Debug;
 
 // The general idea is: If Coordinate-reference-system (CRS) A can project in CRS B
@@ -26,16 +27,24 @@ pub trait ToLonLat
 
 pub trait FromLonLat
 {
+    #[allow(clippy::wrong_self_convention)]
     fn from_lon_lat(&self, data: Vec<(f64, f64)>, ellipsoid: &Ellipsoid, strategy: &mut MultithreadingStrategy)
                     -> CoordinateBuf;
 }
 
 pub trait Crs: ToLonLat + FromLonLat + Debug {
     fn clone(&self) -> Box<dyn Crs>;
+    /// Returns the CRS as `Any`, so that a `Box<dyn Crs>` can be downcast
+    /// to its concrete type
+    fn as_any(&self) -> &dyn Any;
 }
 
 impl<T> Crs for T where T: ToLonLat + FromLonLat + Debug + Clone + 'static {
     fn clone(&self) -> Box<dyn Crs> {
         Box::new((*self).clone())
     }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
 }
@@ -0,0 +1,341 @@
+//! Well-Known Binary (WKB) and PostGIS Extended WKB (EWKB) reprojection
+//!
+//! `parse_wkb` reads `Point`, `LineString`, `Polygon`, their `Multi*` variants
+//! and `GeometryCollection`s in both byte orders, with the Z, M and ZM variants
+//! of ISO WKB (type codes 1001, 2001, 3001, ...) and of EWKB (flag bits, plus an
+//! optional SRID). The geometry is kept as bytes: `Wkb::reproject` projects all
+//! positions in a single batched `CoordinateSource::project` call and overwrites
+//! their x and y in place, so Z and M values, byte orders and type codes stay as
+//! they were. If the geometry has an SRID, it is replaced by the EPSG code of the
+//! target CRS (see `epsg::to_epsg`), or removed if the target CRS has none.
+//!
+//! PostGIS writes EWKB as hexadecimal text (`SELECT geom::text`), which
+//! `parse_wkb_hex` and `Wkb::to_hex` read and write.
+//!
+//! ```rust
+//! extern crate proj5;
+//!
+//! use proj5::prelude::*;
+//! use proj5::wkb::*;
+//!
+//! fn main() {
+//!     // SRID=4326;POINT(9 48)
+//!     let mut wkb = parse_wkb_hex("0101000020E610000000000000000022400000000000004840").unwrap();
+//!     assert_eq!(wkb.srid(), Some(4326));
+//!
+//!     let utm = UTMSystem { utm_zone: 32, hemisphere: Hemisphere::North };
+//!     let mut strategy = MultithreadingStrategy::SingleCore;
+//!     wkb.reproject(&LonLatSystem, &WGS_1984_ELLIPSOID, &utm, &WGS_1984_ELLIPSOID, &mut strategy);
+//!
+//!     assert_eq!(wkb.srid(), Some(32632));
+//!     assert!((wkb.coordinates()[0].0 - 500_000.0).abs() < 1e-6);
+//!     assert_eq!(wkb.as_bytes().len(), 25);
+//! }
+//! ```
+
+use prelude::*;
+use coordinate_buf::project_batch;
+use epsg;
+use std::error::Error;
+use std::fmt;
+
+/// EWKB flag of geometries with a Z coordinate
+const EWKB_Z: u32 = 0x8000_0000;
+/// EWKB flag of geometries with an M coordinate
+const EWKB_M: u32 = 0x4000_0000;
+/// EWKB flag of geometries that are followed by an SRID
+const EWKB_SRID: u32 = 0x2000_0000;
+
+/// Maximum nesting of multi geometries and collections, so that deep geometries can't overflow the stack
+const MAX_DEPTH: usize = 32;
+
+/// A WKB or EWKB geometry, with the byte offsets of its positions
+#[derive(Debug, Clone, PartialEq)]
+pub struct Wkb {
+    bytes: Vec<u8>,
+    /// Offset of the x coordinate of every position, and whether it is little endian
+    positions: Vec<(usize, bool)>,
+}
+
+/// Error that can occur when reading WKB
+#[derive(Debug, Clone, PartialEq)]
+pub enum WkbError {
+    /// The geometry ends before the byte offset that is needed
+    UnexpectedEnd(usize),
+    /// The byte order (second field) at the byte offset (first field) is neither 0 nor 1
+    InvalidByteOrder(usize, u8),
+    /// The geometry type code isn't supported, e.g. 8 (`CircularString`)
+    UnsupportedType(u32),
+    /// There are bytes after the end of the geometry, at the byte offset
+    TrailingBytes(usize),
+    /// The hexadecimal text has an invalid character at the offset, or an odd length
+    InvalidHex(usize),
+    /// The geometry at the byte offset is nested deeper than `MAX_DEPTH` levels
+    TooDeep(usize),
+}
+
+impl fmt::Display for WkbError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::WkbError::*;
+        match *self {
+            UnexpectedEnd(offset) => write!(f, "WKB ends before byte {}", offset),
+            InvalidByteOrder(offset, value) => write!(f, "invalid WKB byte order {} at byte {}", value, offset),
+            UnsupportedType(code) => write!(f, "unsupported WKB geometry type: {}", code),
+            TrailingBytes(offset) => write!(f, "unexpected bytes after the WKB geometry at byte {}", offset),
+            InvalidHex(offset) => write!(f, "invalid hexadecimal WKB at character {}", offset),
+            TooDeep(offset) => write!(f, "WKB geometry at byte {} is nested deeper than {} levels", offset, MAX_DEPTH),
+        }
+    }
+}
+
+impl Error for WkbError { }
+
+impl Wkb {
+    /// Returns the x and y coordinates of all positions, in the order of the geometry
+    pub fn coordinates(&self) -> Vec<(f64, f64)> {
+        self.positions.iter().map(|&(offset, little_endian)| {
+            (read_f64(&self.bytes, offset, little_endian), read_f64(&self.bytes, offset + 8, little_endian))
+        }).collect()
+    }
+
+    /// Overwrites the x and y coordinates of all positions, in the order of the geometry
+    ///
+    /// # Panics
+    ///
+    /// If `data` doesn't have one coordinate per position, see `coordinates`
+    pub fn set_coordinates(&mut self, data: Vec<(f64, f64)>) {
+        assert_eq!(self.positions.len(), data.len(), "one coordinate per position is required");
+
+        for (&(offset, little_endian), (x, y)) in self.positions.iter().zip(data) {
+            write_f64(&mut self.bytes, offset, little_endian, x);
+            write_f64(&mut self.bytes, offset + 8, little_endian, y);
+        }
+    }
+
+    /// Projects all positions from the source to the target CRS in one batch
+    ///
+    /// An existing SRID is replaced by the EPSG code of the target CRS, or
+    /// removed if the embedded EPSG registry doesn't know the target CRS.
+    pub fn reproject(&mut self,
+                     source_crs: &dyn Crs, source_ellipsoid: &Ellipsoid,
+                     target_crs: &dyn Crs, target_ellipsoid: &Ellipsoid,
+                     strategy: &mut MultithreadingStrategy)
+    {
+        let data = project_batch(self.coordinates(), source_crs, source_ellipsoid, target_crs, target_ellipsoid, strategy);
+        self.set_coordinates(data);
+
+        if self.srid().is_some() {
+            self.set_srid(epsg::to_epsg(target_crs, target_ellipsoid, None));
+        }
+    }
+
+    /// Returns the SRID of an EWKB geometry
+    pub fn srid(&self) -> Option<u32> {
+        let little_endian = self.bytes[0] == 1;
+        if read_u32(&self.bytes, 1, little_endian) & EWKB_SRID == 0 {
+            return None;
+        }
+        Some(read_u32(&self.bytes, 5, little_endian))
+    }
+
+    /// Sets the SRID, which turns the geometry into EWKB, or removes it for `None`
+    ///
+    /// ISO type codes of the outermost geometry, e.g. 1001 for `Point Z`, are
+    /// replaced by the EWKB flags when an SRID is added.
+    pub fn set_srid(&mut self, srid: Option<u32>) {
+        let little_endian = self.bytes[0] == 1;
+        let code = read_u32(&self.bytes, 1, little_endian);
+        let has_srid = code & EWKB_SRID != 0;
+
+        match srid {
+            Some(srid) if has_srid => write_u32(&mut self.bytes, 5, little_endian, srid),
+            Some(srid) => {
+                let (kind, z, m) = split_type(code);
+                let flags = if z { EWKB_Z } else { 0 } | if m { EWKB_M } else { 0 };
+                write_u32(&mut self.bytes, 1, little_endian, kind | flags | EWKB_SRID);
+                let srid_bytes = if little_endian { srid.to_le_bytes() } else { srid.to_be_bytes() };
+                self.bytes.splice(5..5, srid_bytes.iter().cloned());
+                self.positions.iter_mut().for_each(|p| p.0 += 4);
+            },
+            None if has_srid => {
+                write_u32(&mut self.bytes, 1, little_endian, code & !EWKB_SRID);
+                self.bytes.drain(5..9);
+                self.positions.iter_mut().for_each(|p| p.0 -= 4);
+            },
+            None => { },
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    /// Writes the geometry as uppercase hexadecimal text, like PostGIS
+    pub fn to_hex(&self) -> String {
+        self.bytes.iter().map(|b| format!("{:02X}", b)).collect()
+    }
+}
+
+/// Reads a WKB or EWKB geometry
+///
+/// Multi geometries and collections nested deeper than `MAX_DEPTH` (32) levels
+/// are rejected with `TooDeep`.
+pub fn parse_wkb(bytes: &[u8]) -> Result<Wkb, WkbError> {
+    let mut reader = Reader { bytes, offset: 0, positions: Vec::new(), depth: 0 };
+    reader.geometry()?;
+    if reader.offset != bytes.len() {
+        return Err(WkbError::TrailingBytes(reader.offset));
+    }
+    Ok(Wkb { bytes: bytes.to_vec(), positions: reader.positions })
+}
+
+/// Reads a WKB or EWKB geometry from hexadecimal text, in either case
+pub fn parse_wkb_hex(hex: &str) -> Result<Wkb, WkbError> {
+    let hex = hex.trim();
+    if hex.len() % 2 != 0 {
+        return Err(WkbError::InvalidHex(hex.len()));
+    }
+
+    let bytes = hex.as_bytes().chunks(2).enumerate().map(|(i, pair)| {
+        std::str::from_utf8(pair).ok()
+            .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+            .ok_or(WkbError::InvalidHex(i * 2))
+    }).collect::<Result<Vec<u8>, WkbError>>()?;
+
+    parse_wkb(&bytes)
+}
+
+/// Splits a type code into the geometry kind (1 - 7) and whether it has Z and M values,
+/// for both the ISO codes and the EWKB flags
+fn split_type(code: u32) -> (u32, bool, bool) {
+    let iso = code & 0x0FFF_FFFF;
+    let (kind, dimensions) = (iso % 1000, iso / 1000);
+    (kind, code & EWKB_Z != 0 || dimensions == 1 || dimensions == 3, code & EWKB_M != 0 || dimensions == 2 || dimensions == 3)
+}
+
+fn read_u32(bytes: &[u8], offset: usize, little_endian: bool) -> u32 {
+    let mut value = [0; 4];
+    value.copy_from_slice(&bytes[offset..offset + 4]);
+    if little_endian { u32::from_le_bytes(value) } else { u32::from_be_bytes(value) }
+}
+
+fn write_u32(bytes: &mut [u8], offset: usize, little_endian: bool, value: u32) {
+    let value = if little_endian { value.to_le_bytes() } else { value.to_be_bytes() };
+    bytes[offset..offset + 4].copy_from_slice(&value);
+}
+
+fn read_f64(bytes: &[u8], offset: usize, little_endian: bool) -> f64 {
+    let mut value = [0; 8];
+    value.copy_from_slice(&bytes[offset..offset + 8]);
+    if little_endian { f64::from_le_bytes(value) } else { f64::from_be_bytes(value) }
+}
+
+fn write_f64(bytes: &mut [u8], offset: usize, little_endian: bool, value: f64) {
+    let value = if little_endian { value.to_le_bytes() } else { value.to_be_bytes() };
+    bytes[offset..offset + 8].copy_from_slice(&value);
+}
+
+/// Walks through a geometry and records the offsets of its positions
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    positions: Vec<(usize, bool)>,
+    /// Number of multi geometries and collections that enclose the offset
+    depth: usize,
+}
+
+impl<'a> Reader<'a> {
+    /// Moves past `length` bytes and returns their offset
+    fn skip(&mut self, length: usize) -> Result<usize, WkbError> {
+        let offset = self.offset;
+        if self.bytes.len() - offset < length {
+            return Err(WkbError::UnexpectedEnd(offset + length));
+        }
+        self.offset += length;
+        Ok(offset)
+    }
+
+    fn u32(&mut self, little_endian: bool) -> Result<u32, WkbError> {
+        let offset = self.skip(4)?;
+        Ok(read_u32(self.bytes, offset, little_endian))
+    }
+
+    fn points(&mut self, count: u32, little_endian: bool, dimensions: usize) -> Result<(), WkbError> {
+        for _ in 0..count {
+            let offset = self.skip(8 * dimensions)?;
+            self.positions.push((offset, little_endian));
+        }
+        Ok(())
+    }
+
+    fn geometry(&mut self) -> Result<(), WkbError> {
+        let offset = self.skip(1)?;
+        let little_endian = match self.bytes[offset] {
+            0 => false,
+            1 => true,
+            value => return Err(WkbError::InvalidByteOrder(offset, value)),
+        };
+
+        let code = self.u32(little_endian)?;
+        if (code & 0x0FFF_FFFF) / 1000 > 3 {
+            return Err(WkbError::UnsupportedType(code & 0x0FFF_FFFF));
+        }
+        if code & EWKB_SRID != 0 {
+            self.skip(4)?;
+        }
+        let (kind, z, m) = split_type(code);
+        let dimensions = 2 + z as usize + m as usize;
+
+        match kind {
+            1 => self.points(1, little_endian, dimensions),
+            2 => {
+                let count = self.u32(little_endian)?;
+                self.points(count, little_endian, dimensions)
+            },
+            3 => {
+                for _ in 0..self.u32(little_endian)? {
+                    let count = self.u32(little_endian)?;
+                    self.points(count, little_endian, dimensions)?;
+                }
+                Ok(())
+            },
+            4..=7 if self.depth == MAX_DEPTH => Err(WkbError::TooDeep(offset)),
+            4..=7 => {
+                self.depth += 1;
+                for _ in 0..self.u32(little_endian)? {
+                    self.geometry()?;
+                }
+                self.depth -= 1;
+                Ok(())
+            },
+            _ => Err(WkbError::UnsupportedType(code & 0x0FFF_FFFF)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Little endian `GeometryCollection` headers with one member each, around an empty one
+    fn nested_collections(levels: usize) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for _ in 0..levels {
+            bytes.extend_from_slice(&[1, 7, 0, 0, 0, 1, 0, 0, 0]);
+        }
+        bytes.extend_from_slice(&[1, 7, 0, 0, 0, 0, 0, 0, 0]);
+        bytes
+    }
+
+    #[test]
+    fn nesting_depth() {
+        assert!(parse_wkb(&nested_collections(MAX_DEPTH - 1)).is_ok());
+        assert_eq!(parse_wkb(&nested_collections(MAX_DEPTH)), Err(WkbError::TooDeep(9 * MAX_DEPTH)));
+        // deep enough to overflow the stack without the limit
+        assert_eq!(parse_wkb(&nested_collections(100_000)), Err(WkbError::TooDeep(9 * MAX_DEPTH)));
+    }
+}
@@ -0,0 +1,1340 @@
+//! Well-known text (WKT) CRS definitions
+//!
+//! `parse_wkt` reads WKT1 (OGC 01-009, as written by GDAL), the ESRI dialect of
+//! WKT1 (shapefile `.prj` files) and WKT2:2019 (ISO 19162:2019) into a
+//! `WktDefinition`: the CRS, the ellipsoid, the transformation of the datum to
+//! WGS84 and the names of the CRS and its datum. `to_wkt` writes any of the three
+//! dialects. The dialect is detected when reading.
+//!
+//! Supported coordinate systems:
+//!
+//! - geographic CRS (`GEOGCS`, `GEOGCRS`, `GEODCRS`) in degrees: `LonLatSystem`
+//! - Transverse Mercator: `UTMSystem` for the UTM zones, `TransverseMercatorSystem` otherwise
+//! - Mercator (variant A / 1SP) with the default origin and scale: `MercatorSystem`
+//! - Popular Visualisation Pseudo Mercator (`Mercator_Auxiliary_Sphere` for ESRI,
+//!   `Mercator_1SP` with a spherical `PROJ4` extension for GDAL): `WebMercatorSystem`
+//! - Lambert Conic Conformal (1SP and 2SP): `LambertConformalConicSystem`
+//! - Polar Stereographic (variant A) with the UPS parameters: `UPSSystem`
+//! - Interrupted Goode Homolosine: `GoodeHomolosineSystem` with the standard lobes
+//!
+//! WKT1 `TOWGS84` and the WKT2 `BOUNDCRS` to WGS 84 are read as the `to_wgs84`
+//! transformation, compound CRS (`COMPD_CS`, `COMPOUNDCRS`) are read as their
+//! horizontal part. Like in `proj_string`, non-metric units and prime meridians
+//! other than Greenwich wrap the CRS in an `AdjustedSystem`.
+//!
+//! `AXIS` nodes are not applied: proj5 coordinates are always (easting, northing)
+//! or (longitude, latitude), which is also the order of GIS data files, even if
+//! the WKT of EPSG declares latitude first. The ESRI dialect has no `TOWGS84`,
+//! the transformation is lost when writing it.
+//!
+//! ```rust
+//! extern crate proj5;
+//!
+//! use proj5::prelude::*;
+//! use proj5::wkt::*;
+//! use proj5::proj_string::to_proj_string;
+//!
+//! fn main() {
+//!     let wkt = r#"PROJCS["OSGB 1936 / British National Grid",
+//!         GEOGCS["OSGB 1936",
+//!             DATUM["OSGB_1936",
+//!                 SPHEROID["Airy 1830",6377563.396,299.3249646],
+//!                 TOWGS84[446.448,-125.157,542.06,0.15,0.247,0.842,-20.489]],
+//!             PRIMEM["Greenwich",0],
+//!             UNIT["degree",0.0174532925199433]],
+//!         PROJECTION["Transverse_Mercator"],
+//!         PARAMETER["latitude_of_origin",49],
+//!         PARAMETER["central_meridian",-2],
+//!         PARAMETER["scale_factor",0.9996012717],
+//!         PARAMETER["false_easting",400000],
+//!         PARAMETER["false_northing",-100000],
+//!         UNIT["metre",1]]"#;
+//!
+//!     let definition: WktDefinition = wkt.parse().unwrap();
+//!     assert_eq!(definition.name, "OSGB 1936 / British National Grid");
+//!     assert_eq!(definition.ellipsoid, AIRY_1830_ELLIPSOID);
+//!     assert_eq!(to_proj_string(&*definition.crs, &definition.ellipsoid, None).unwrap(),
+//!                "+proj=tmerc +lat_0=49 +lon_0=-2 +k=0.9996012717 +x_0=400000 +y_0=-100000 +ellps=airy +units=m +no_defs");
+//!
+//!     // Every dialect reads back to the same definition
+//!     for &version in [WktVersion::Wkt1, WktVersion::Wkt1Esri, WktVersion::Wkt2].iter() {
+//!         let written = definition.to_wkt(version).unwrap();
+//!         let read = parse_wkt(&written).unwrap();
+//!         assert_eq!(read.to_wkt(version).unwrap(), written);
+//!         assert_eq!(read.ellipsoid, definition.ellipsoid);
+//!         assert_eq!(to_proj_string(&*read.crs, &read.ellipsoid, None),
+//!                    to_proj_string(&*definition.crs, &definition.ellipsoid, None));
+//!         if version != WktVersion::Wkt1Esri {
+//!             assert_eq!(read.to_wgs84, definition.to_wgs84);
+//!         }
+//!     }
+//!
+//!     // ESRI .prj files
+//!     let prj = r#"PROJCS["WGS_1984_Web_Mercator_Auxiliary_Sphere",GEOGCS["GCS_WGS_1984",DATUM["D_WGS_1984",SPHEROID["WGS_1984",6378137.0,298.257223563]],PRIMEM["Greenwich",0.0],UNIT["Degree",0.0174532925199433]],PROJECTION["Mercator_Auxiliary_Sphere"],PARAMETER["False_Easting",0.0],PARAMETER["False_Northing",0.0],PARAMETER["Central_Meridian",0.0],PARAMETER["Standard_Parallel_1",0.0],PARAMETER["Auxiliary_Sphere_Type",0.0],UNIT["Meter",1.0]]"#;
+//!     let definition = parse_wkt(prj).unwrap();
+//!     assert_eq!(definition.datum(), Some(&WGS84_DATUM));
+//!     assert_eq!(definition.to_wkt(WktVersion::Wkt1Esri).unwrap(), prj);
+//! }
+//! ```
+
+use prelude::*;
+use coordinate_systems::adjusted::{AdjustedSystem, AxisDirection};
+use coordinate_systems::description::{Degenerate, Description};
+use coordinate_systems::igh::utils::GOODE_HOMOLOSINE_LOBES;
+use proj_string::{ELLIPSOID_IDS, UNITS, PRIME_MERIDIANS};
+use std::error::Error;
+use std::f64::consts::PI;
+use std::fmt;
+use std::str::FromStr;
+
+/// The degree in radians, as written in WKT
+const DEGREE: &str = "0.0174532925199433";
+
+/// Maximum nesting of nodes, so that deep input can't overflow the stack
+const MAX_DEPTH: usize = 32;
+
+/// One arc second, in radians
+pub(crate) const ARC_SECOND: f64 = PI / 648_000.0;
+
+/// Dialect of well-known text
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum WktVersion {
+    /// WKT1 as written by GDAL (OGC 01-009), with `TOWGS84`
+    Wkt1,
+    /// The ESRI dialect of WKT1, as in shapefile `.prj` files
+    Wkt1Esri,
+    /// WKT2:2019 (ISO 19162:2019)
+    Wkt2,
+}
+
+/// Names of a datum and its geographic CRS in the WKT dialects
+pub(crate) struct DatumNames {
+    /// Identifier of the datum in `DATUMS`
+    pub(crate) proj_id: &'static str,
+    /// EPSG name of the geographic CRS
+    pub(crate) crs: &'static str,
+    /// Datum name in WKT1 (GDAL)
+    pub(crate) wkt1: &'static str,
+    /// EPSG name of the datum, used in WKT2
+    pub(crate) wkt2: &'static str,
+    /// ESRI name of the datum
+    pub(crate) esri: &'static str,
+    /// ESRI name of the geographic CRS
+    pub(crate) esri_crs: &'static str,
+}
+
+pub(crate) const DATUM_NAMES: [DatumNames; 9] = [
+    DatumNames { proj_id: "WGS84", crs: "WGS 84", wkt1: "WGS_1984", wkt2: "World Geodetic System 1984", esri: "D_WGS_1984", esri_crs: "GCS_WGS_1984" },
+    DatumNames { proj_id: "GGRS87", crs: "GGRS87", wkt1: "Greek_Geodetic_Reference_System_1987", wkt2: "Greek Geodetic Reference System 1987", esri: "D_GGRS_1987", esri_crs: "GCS_GGRS_1987" },
+    DatumNames { proj_id: "NAD83", crs: "NAD83", wkt1: "North_American_Datum_1983", wkt2: "North American Datum 1983", esri: "D_North_American_1983", esri_crs: "GCS_North_American_1983" },
+    DatumNames { proj_id: "NAD27", crs: "NAD27", wkt1: "North_American_Datum_1927", wkt2: "North American Datum 1927", esri: "D_North_American_1927", esri_crs: "GCS_North_American_1927" },
+    DatumNames { proj_id: "potsdam", crs: "DHDN", wkt1: "Deutsches_Hauptdreiecksnetz", wkt2: "Deutsches Hauptdreiecksnetz", esri: "D_Deutsches_Hauptdreiecksnetz", esri_crs: "GCS_Deutsches_Hauptdreiecksnetz" },
+    DatumNames { proj_id: "hermannskogel", crs: "MGI", wkt1: "Militar_Geographische_Institute", wkt2: "Militar-Geographische Institut", esri: "D_MGI", esri_crs: "GCS_MGI" },
+    DatumNames { proj_id: "ire65", crs: "TM65", wkt1: "TM65", wkt2: "TM65", esri: "D_TM65", esri_crs: "GCS_TM65" },
+    DatumNames { proj_id: "nzgd49", crs: "NZGD49", wkt1: "New_Zealand_Geodetic_Datum_1949", wkt2: "New Zealand Geodetic Datum 1949", esri: "D_New_Zealand_1949", esri_crs: "GCS_New_Zealand_1949" },
+    DatumNames { proj_id: "OSGB36", crs: "OSGB 1936", wkt1: "OSGB_1936", wkt2: "Ordnance Survey of Great Britain 1936", esri: "D_OSGB_1936", esri_crs: "GCS_OSGB_1936" },
+];
+
+/// EPSG and ESRI names of the ellipsoids
+pub(crate) const ELLIPSOID_NAMES: [(&Ellipsoid, &str, &str); 13] = [
+    (&WGS_1984_ELLIPSOID, "WGS 84", "WGS_1984"),
+    (&GRS_1980_ELLIPSOID, "GRS 1980", "GRS_1980"),
+    (&WGS_1972_ELLIPSOID, "WGS 72", "WGS_1972"),
+    (&GRS_1967_ELLIPSOID, "GRS 1967", "GRS_1967"),
+    (&AIRY_1830_ELLIPSOID, "Airy 1830", "Airy_1830"),
+    (&MODIFIED_AIRY_ELLIPSOID, "Airy Modified 1849", "Airy_Modified"),
+    (&BESSEL_1841_ELLIPSOID, "Bessel 1841", "Bessel_1841"),
+    (&CLARKE_1866_ELLIPSOID, "Clarke 1866", "Clarke_1866"),
+    (&CLARKE_1880_ELLIPSOID, "Clarke 1880 (RGS)", "Clarke_1880_RGS"),
+    (&INTERNATIONAL_1924_ELLIPSOID, "International 1924", "International_1924"),
+    (&KRASSOVSKY_1940_ELLIPSOID, "Krassowsky 1940", "Krasovsky_1940"),
+    (&HELMERT_1906_ELLIPSOID, "Helmert 1906", "Helmert_1906"),
+    (&AUSTRALIAN_1966_ELLIPSOID, "Australian National Spheroid", "Australian"),
+];
+
+/// WKT and ESRI names of length units, in meters
+pub(crate) const LENGTH_UNITS: [(&str, &str, f64); 4] = [
+    ("metre", "Meter", 1.0),
+    ("kilometre", "Kilometer", 1000.0),
+    ("foot", "Foot", 0.3048),
+    ("US survey foot", "Foot_US", 1200.0 / 3937.0),
+];
+
+/// A CRS definition read from or written to WKT
+#[derive(Debug)]
+pub struct WktDefinition {
+    /// Name of the CRS, e.g. "WGS 84 / UTM zone 32N"
+    pub name: String,
+    /// Name of the geographic CRS (the base CRS of projected ones), e.g. "WGS 84"
+    pub geographic_name: String,
+    /// Name of the datum, e.g. "WGS_1984" or "World Geodetic System 1984"
+    pub datum_name: String,
+    /// The coordinate system
+    pub crs: Box<dyn Crs>,
+    /// The ellipsoid the coordinates refer to
+    pub ellipsoid: Ellipsoid,
+    /// Transformation of the datum to WGS84 (`TOWGS84` or `BOUNDCRS`)
+    pub to_wgs84: Option<Helmert>,
+}
+
+impl Clone for WktDefinition {
+    fn clone(&self) -> Self {
+        WktDefinition {
+            name: self.name.clone(),
+            geographic_name: self.geographic_name.clone(),
+            datum_name: self.datum_name.clone(),
+            crs: Crs::clone(&*self.crs),
+            ellipsoid: self.ellipsoid,
+            to_wgs84: self.to_wgs84,
+        }
+    }
+}
+
+impl WktDefinition {
+    /// Creates a definition, naming the CRS and the datum after the datum
+    /// identified by the ellipsoid and the transformation (see `Datum::identify`),
+    /// "unknown" otherwise
+    pub fn new(crs: Box<dyn Crs>, ellipsoid: Ellipsoid, to_wgs84: Option<Helmert>) -> Self {
+        let names = Datum::identify(&ellipsoid, to_wgs84.as_ref())
+            .and_then(|datum| DATUM_NAMES.iter().find(|n| n.proj_id == datum.proj_id));
+        let geographic_name = names.map(|n| n.crs).unwrap_or("unknown").to_string();
+        let datum_name = names.map(|n| n.wkt2).unwrap_or("unknown").to_string();
+
+        let adjusted = crs.as_any().downcast_ref::<AdjustedSystem>();
+        let inner = adjusted.map(|a| &*a.crs).unwrap_or(&*crs);
+        let name = match describe_projection(inner) {
+            Ok(Some(projection)) => format!("{} / {}", geographic_name, projection.name),
+            _ => geographic_name.clone(),
+        };
+
+        WktDefinition { name, geographic_name, datum_name, crs, ellipsoid, to_wgs84 }
+    }
+
+    /// Returns the datum, known by its name or identified by the ellipsoid and
+    /// the transformation to WGS84
+    pub fn datum(&self) -> Option<&'static Datum> {
+        find_datum_names(&self.datum_name)
+            .and_then(|names| DATUMS.iter().cloned().find(|d| d.proj_id == names.proj_id))
+            .or_else(|| Datum::identify(&self.ellipsoid, self.to_wgs84.as_ref()))
+    }
+
+    /// Writes the definition as WKT of the given dialect
+    ///
+    /// Returns `UnsupportedCrs` for coordinate systems that have no WKT equivalent
+    /// in `parse_wkt`, including flipped or swapped axes.
+    pub fn to_wkt(&self, version: WktVersion) -> Result<String, WktError> {
+        write_wkt(self, version)
+    }
+}
+
+impl FromStr for WktDefinition {
+    type Err = WktError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_wkt(s)
+    }
+}
+
+/// Error that can occur when reading or writing WKT
+#[derive(Debug, Clone, PartialEq)]
+pub enum WktError {
+    /// The text isn't well-formed WKT, the field is the byte offset of the error
+    InvalidSyntax(usize),
+    /// The type of CRS (keyword of the root node) isn't supported, e.g. `VERT_CS`
+    UnsupportedCrsType(String),
+    /// A required node is missing
+    MissingNode(String),
+    /// The node lacks a value or has a malformed one
+    InvalidNode(String),
+    /// proj5 doesn't implement the projection method
+    UnsupportedProjection(String),
+    /// The parameter (second field) isn't used by the projection method (first field)
+    UnsupportedParameter(String, String),
+    /// The parameter (first field) is only supported with a different value than the given one (second field)
+    UnsupportedValue(String, String),
+    /// The projection is undefined for the value (second field) of the parameter (first field)
+    InvalidValue(String, String),
+    /// The CRS can't be written as WKT
+    UnsupportedCrs(String),
+}
+
+impl fmt::Display for WktError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::WktError::*;
+        match *self {
+            InvalidSyntax(position) => write!(f, "invalid WKT syntax at byte {}", position),
+            UnsupportedCrsType(ref keyword) => write!(f, "unsupported type of CRS: {}", keyword),
+            MissingNode(ref keyword) => write!(f, "missing WKT node: {}", keyword),
+            InvalidNode(ref keyword) => write!(f, "invalid WKT node: {}", keyword),
+            UnsupportedProjection(ref method) => write!(f, "unsupported projection: {}", method),
+            UnsupportedParameter(ref method, ref parameter) => write!(f, "parameter {} is not supported by {}", parameter, method),
+            UnsupportedValue(ref parameter, ref value) => write!(f, "unsupported value for {}: {}", parameter, value),
+            InvalidValue(ref parameter, ref value) => write!(f, "invalid value for {}: {}", parameter, value),
+            UnsupportedCrs(ref crs) => write!(f, "CRS can't be written as WKT: {}", crs),
+        }
+    }
+}
+
+impl Error for WktError { }
+
+/// A node of the WKT tree, e.g. `UNIT["metre",1]`
+#[derive(Debug, Clone)]
+struct Node {
+    /// Upper-case keyword
+    keyword: String,
+    values: Vec<Value>,
+}
+
+#[derive(Debug, Clone)]
+enum Value {
+    Node(Node),
+    Text(String),
+    Number(f64),
+    /// Unquoted identifier such as `EAST` or `ellipsoidal`
+    Enum(String),
+}
+
+impl Node {
+    fn children<'a>(&'a self, keywords: &'static [&'static str]) -> impl Iterator<Item = &'a Node> + 'a {
+        self.nodes().filter(move |n| keywords.contains(&n.keyword.as_str()))
+    }
+
+    fn child(&self, keywords: &'static [&'static str]) -> Option<&Node> {
+        self.children(keywords).next()
+    }
+
+    fn nodes(&self) -> impl Iterator<Item = &Node> {
+        self.values.iter().filter_map(|v| match *v {
+            Value::Node(ref node) => Some(node),
+            _ => None,
+        })
+    }
+
+    fn text(&self, index: usize) -> Option<&str> {
+        match self.values.get(index) {
+            Some(&Value::Text(ref s)) | Some(&Value::Enum(ref s)) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn number(&self, index: usize) -> Result<f64, WktError> {
+        match self.values.get(index) {
+            Some(&Value::Number(value)) => Ok(value),
+            _ => Err(WktError::InvalidNode(self.keyword.clone())),
+        }
+    }
+
+    /// The conversion factor of a unit node, which has to be positive
+    fn unit_factor(&self) -> Result<f64, WktError> {
+        match self.number(1)? {
+            factor if factor > 0.0 => Ok(factor),
+            _ => Err(WktError::InvalidNode(self.keyword.clone())),
+        }
+    }
+
+    /// The first value, which is the name for most nodes
+    fn name(&self) -> &str {
+        self.text(0).unwrap_or("")
+    }
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    /// Number of nodes that enclose the position
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.position).cloned()
+    }
+
+    fn error(&self) -> WktError {
+        WktError::InvalidSyntax(self.position)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|c| c.is_ascii_whitespace()) {
+            self.position += 1;
+        }
+    }
+
+    fn identifier(&mut self) -> Result<&'a str, WktError> {
+        let start = self.position;
+        while self.peek().is_some_and(|c| c.is_ascii_alphanumeric() || c == b'_') {
+            self.position += 1;
+        }
+        if start == self.position {
+            return Err(self.error());
+        }
+        // only ASCII characters were consumed
+        Ok(::std::str::from_utf8(&self.bytes[start..self.position]).unwrap())
+    }
+
+    fn node(&mut self) -> Result<Node, WktError> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error());
+        }
+        self.depth += 1;
+        let node = self.node_contents();
+        self.depth -= 1;
+        node
+    }
+
+    fn node_contents(&mut self) -> Result<Node, WktError> {
+        let keyword = self.identifier()?.to_ascii_uppercase();
+        self.skip_whitespace();
+
+        let close = match self.peek() {
+            Some(b'[') => b']',
+            Some(b'(') => b')',
+            _ => return Err(self.error()),
+        };
+        self.position += 1;
+
+        let mut values = Vec::new();
+        loop {
+            self.skip_whitespace();
+            values.push(self.value()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.position += 1,
+                Some(c) if c == close => {
+                    self.position += 1;
+                    break;
+                },
+                _ => return Err(self.error()),
+            }
+        }
+
+        Ok(Node { keyword, values })
+    }
+
+    fn value(&mut self) -> Result<Value, WktError> {
+        match self.peek() {
+            Some(b'"') => {
+                self.position += 1;
+                let mut text = Vec::new();
+                loop {
+                    match self.peek() {
+                        None => return Err(self.error()),
+                        // a doubled quote is an escaped quote
+                        Some(b'"') if self.bytes.get(self.position + 1) == Some(&b'"') => {
+                            text.push(b'"');
+                            self.position += 2;
+                        },
+                        Some(b'"') => {
+                            self.position += 1;
+                            break;
+                        },
+                        Some(c) => {
+                            text.push(c);
+                            self.position += 1;
+                        },
+                    }
+                }
+                String::from_utf8(text).map(Value::Text).map_err(|_| self.error())
+            },
+            Some(c) if c.is_ascii_digit() || c == b'-' || c == b'+' || c == b'.' => {
+                let start = self.position;
+                while self.peek().is_some_and(|c| c.is_ascii_digit() || b"+-.eE".contains(&c)) {
+                    self.position += 1;
+                }
+                ::std::str::from_utf8(&self.bytes[start..self.position]).ok()
+                    .and_then(|s| s.parse::<f64>().ok())
+                    .filter(|v| v.is_finite())
+                    .map(Value::Number)
+                    .ok_or(WktError::InvalidSyntax(start))
+            },
+            Some(c) if c.is_ascii_alphabetic() => {
+                let start = self.position;
+                let identifier = self.identifier()?;
+                self.skip_whitespace();
+                if self.peek() == Some(b'[') || self.peek() == Some(b'(') {
+                    self.position = start;
+                    self.node().map(Value::Node)
+                } else {
+                    Ok(Value::Enum(identifier.to_string()))
+                }
+            },
+            _ => Err(self.error()),
+        }
+    }
+}
+
+fn parse_tree(s: &str) -> Result<Node, WktError> {
+    let mut parser = Parser { bytes: s.as_bytes(), position: 0, depth: 0 };
+    parser.skip_whitespace();
+    let node = parser.node()?;
+    parser.skip_whitespace();
+    if parser.position != s.len() {
+        return Err(parser.error());
+    }
+    Ok(node)
+}
+
+/// Lower-case name with spaces and dashes replaced by underscores, e.g. "false_easting"
+pub(crate) fn normalize(name: &str) -> String {
+    name.trim().to_lowercase().chars().map(|c| if c == ' ' || c == '-' { '_' } else { c }).collect()
+}
+
+/// Lower-case alphanumeric characters of a name, to compare names across dialects
+pub(crate) fn name_key(name: &str) -> String {
+    name.chars().filter(|c| c.is_alphanumeric()).flat_map(|c| c.to_lowercase()).collect()
+}
+
+pub(crate) fn find_datum_names(datum_name: &str) -> Option<&'static DatumNames> {
+    // PROJ writes WGS 84 as a datum ensemble
+    let key = name_key(datum_name.trim_end_matches(" ensemble"));
+    DATUM_NAMES.iter().find(|n| [n.wkt1, n.wkt2, n.esri].iter().any(|name| name_key(name) == key))
+}
+
+/// Returns `exact` if the factor only differs from it by rounding, e.g. for
+/// the degree written as "0.0174532925199433"
+pub(crate) fn snap(factor: f64, exact: f64) -> f64 {
+    if ((factor - exact) / exact).abs() < 1e-12 { exact } else { factor }
+}
+
+/// Converts an angular unit in radians to degrees, snapping to degrees and grads
+pub(crate) fn unit_to_degrees(radians: f64) -> f64 {
+    match snap(snap(radians, PI / 180.0), PI / 200.0) {
+        r if r == PI / 180.0 => 1.0,
+        r if r == PI / 200.0 => 0.9,
+        r => r.to_degrees(),
+    }
+}
+
+/// Snaps a length unit in meters to the known units
+pub(crate) fn unit_to_meters(meters: f64) -> f64 {
+    UNITS.iter().map(|&(_, m)| m).find(|&m| snap(meters, m) == m).unwrap_or(meters)
+}
+
+/// Projection parameters, with their unit
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Parameter {
+    LatitudeOfOrigin,
+    LongitudeOfOrigin,
+    ScaleFactor,
+    FalseEasting,
+    FalseNorthing,
+    StandardParallel1,
+    StandardParallel2,
+    AuxiliarySphereType,
+}
+
+/// Normalized WKT1, ESRI and EPSG names of the parameters
+const PARAMETER_NAMES: [(Parameter, &[&str]); 8] = [
+    (Parameter::LatitudeOfOrigin, &["latitude_of_origin", "latitude_of_natural_origin", "latitude_of_false_origin", "latitude_of_center"]),
+    (Parameter::LongitudeOfOrigin, &["central_meridian", "longitude_of_natural_origin", "longitude_of_false_origin", "longitude_of_origin", "longitude_of_center"]),
+    (Parameter::ScaleFactor, &["scale_factor", "scale_factor_at_natural_origin"]),
+    (Parameter::FalseEasting, &["false_easting", "easting_at_false_origin"]),
+    (Parameter::FalseNorthing, &["false_northing", "northing_at_false_origin"]),
+    (Parameter::StandardParallel1, &["standard_parallel_1", "latitude_of_1st_standard_parallel"]),
+    (Parameter::StandardParallel2, &["standard_parallel_2", "latitude_of_2nd_standard_parallel"]),
+    (Parameter::AuxiliarySphereType, &["auxiliary_sphere_type"]),
+];
+
+impl Parameter {
+    fn from_name(name: &str) -> Option<Self> {
+        let name = normalize(name);
+        PARAMETER_NAMES.iter().find(|&&(_, names)| names.contains(&name.as_str())).map(|&(p, _)| p)
+    }
+
+    fn is_angle(self) -> bool {
+        use self::Parameter::*;
+        matches!(self, LatitudeOfOrigin | LongitudeOfOrigin | StandardParallel1 | StandardParallel2)
+    }
+
+    fn is_length(self) -> bool {
+        self == Parameter::FalseEasting || self == Parameter::FalseNorthing
+    }
+
+    fn wkt1_name(self) -> &'static str {
+        PARAMETER_NAMES.iter().find(|&&(p, _)| p == self).map(|&(_, names)| names[0]).unwrap_or("")
+    }
+
+    fn esri_name(self) -> &'static str {
+        use self::Parameter::*;
+        match self {
+            LatitudeOfOrigin => "Latitude_Of_Origin",
+            LongitudeOfOrigin => "Central_Meridian",
+            ScaleFactor => "Scale_Factor",
+            FalseEasting => "False_Easting",
+            FalseNorthing => "False_Northing",
+            StandardParallel1 => "Standard_Parallel_1",
+            StandardParallel2 => "Standard_Parallel_2",
+            AuxiliarySphereType => "Auxiliary_Sphere_Type",
+        }
+    }
+
+    /// EPSG name and code, the Lambert Conic Conformal (2SP) uses a false origin
+    fn epsg_name(self, method: Method) -> (&'static str, u32) {
+        use self::Parameter::*;
+        match (self, method == Method::LambertConic2SP) {
+            (LatitudeOfOrigin, false) => ("Latitude of natural origin", 8801),
+            (LongitudeOfOrigin, false) => ("Longitude of natural origin", 8802),
+            (ScaleFactor, _) => ("Scale factor at natural origin", 8805),
+            (FalseEasting, false) => ("False easting", 8806),
+            (FalseNorthing, false) => ("False northing", 8807),
+            (LatitudeOfOrigin, true) => ("Latitude of false origin", 8821),
+            (LongitudeOfOrigin, true) => ("Longitude of false origin", 8822),
+            (StandardParallel1, _) => ("Latitude of 1st standard parallel", 8823),
+            (StandardParallel2, _) => ("Latitude of 2nd standard parallel", 8824),
+            (FalseEasting, true) => ("Easting at false origin", 8826),
+            (FalseNorthing, true) => ("Northing at false origin", 8827),
+            (AuxiliarySphereType, _) => ("Auxiliary sphere type", 0),
+        }
+    }
+}
+
+/// The geographic part of a CRS definition
+struct Geographic {
+    name: String,
+    datum_name: String,
+    ellipsoid: Ellipsoid,
+    to_wgs84: Option<Helmert>,
+    /// Longitude of the prime meridian, in degrees
+    prime_meridian: f64,
+    /// Size of the angular unit, in degrees
+    angle_unit: f64,
+}
+
+/// Returns a known ellipsoid with the same semi-major axis and inverse flattening,
+/// or a user-defined one
+pub(crate) fn find_ellipsoid(a: f64, inverse_flattening: f64) -> Ellipsoid {
+    // GRS80 and WGS84 only differ by 0.1 mm in the semi-minor axis
+    let same = |e: &Ellipsoid| (e.a - a).abs() < 1e-6 && if inverse_flattening == 0.0 {
+        e.a == e.b
+    } else {
+        ((e.flattening - inverse_flattening) / inverse_flattening).abs() < 1e-10
+    };
+
+    ELLIPSOID_IDS.iter().map(|&(_, e)| e)
+        .chain(ELLIPSOIDS.iter().cloned())
+        .find(|e| same(e))
+        .cloned()
+        .unwrap_or_else(|| {
+            let b = if inverse_flattening == 0.0 { a } else { a * (1.0 - 1.0 / inverse_flattening) };
+            Ellipsoid::from_axes(a, b)
+        })
+}
+
+fn read_geographic(node: &Node) -> Result<Geographic, WktError> {
+    let datum = node.child(&["DATUM", "GEODETICDATUM", "TRF", "ENSEMBLE", "DATUMENSEMBLE"])
+        .ok_or_else(|| WktError::MissingNode("DATUM".to_string()))?;
+    let spheroid = datum.child(&["SPHEROID", "ELLIPSOID"])
+        .ok_or_else(|| WktError::MissingNode("SPHEROID".to_string()))?;
+
+    let length_unit = match spheroid.child(&["LENGTHUNIT", "UNIT"]) {
+        Some(unit) => unit.unit_factor()?,
+        None => 1.0,
+    };
+    let a = spheroid.number(1)? * length_unit;
+    let rf = spheroid.number(2)?;
+    if !(a > 0.0 && (rf == 0.0 || rf > 1.0)) {
+        return Err(WktError::InvalidNode(spheroid.keyword.clone()));
+    }
+
+    let to_wgs84 = match datum.child(&["TOWGS84"]) {
+        Some(towgs84) => {
+            let values = (0..towgs84.values.len()).map(|i| towgs84.number(i)).collect::<Result<Vec<f64>, _>>()?;
+            Some(Helmert::from_towgs84(&values).ok_or_else(|| WktError::InvalidNode("TOWGS84".to_string()))?)
+        },
+        None => None,
+    };
+
+    // WKT2 puts the unit on the axes
+    let angle_unit = match node.child(&["UNIT", "ANGLEUNIT"])
+        .or_else(|| node.children(&["AXIS"]).filter_map(|axis| axis.child(&["ANGLEUNIT", "UNIT"])).next())
+    {
+        Some(unit) => unit_to_degrees(unit.unit_factor()?),
+        None => 1.0,
+    };
+
+    // The WKT1 prime meridian is in degrees (as written by GDAL and ESRI)
+    let prime_meridian = match node.child(&["PRIMEM", "PRIMEMERIDIAN"]) {
+        Some(primem) => {
+            let unit = match primem.child(&["ANGLEUNIT", "UNIT"]) {
+                Some(unit) => unit_to_degrees(unit.unit_factor()?),
+                None => 1.0,
+            };
+            primem.number(1)? * unit
+        },
+        None => 0.0,
+    };
+
+    Ok(Geographic {
+        name: node.name().to_string(),
+        datum_name: datum.name().to_string(),
+        ellipsoid: find_ellipsoid(a, rf),
+        to_wgs84,
+        prime_meridian,
+        angle_unit,
+    })
+}
+
+/// Wraps the CRS in an `AdjustedSystem` for non-metric units and prime meridians
+pub(crate) fn adjust(crs: Box<dyn Crs>, to_meter: f64, prime_meridian: f64) -> Box<dyn Crs> {
+    if to_meter != 1.0 || prime_meridian != 0.0 {
+        Box::new(AdjustedSystem { crs, to_meter, prime_meridian, axis: (AxisDirection::East, AxisDirection::North) })
+    } else {
+        crs
+    }
+}
+
+/// Builds the CRS of a projection method from its parameters (in degrees and meters)
+fn build_projection(method: &str, params: &[(Parameter, f64)], proj4: Option<&str>)
+                    -> Result<Box<dyn Crs>, WktError>
+{
+    use self::Parameter::*;
+
+    let get = |p: Parameter, default: f64| params.iter().find(|&&(q, _)| q == p).map(|&(_, v)| v).unwrap_or(default);
+    let check = |allowed: &[Parameter], defaults: &[(Parameter, f64)]| -> Result<(), WktError> {
+        for &(p, value) in params {
+            if !allowed.contains(&p) && !defaults.iter().any(|&(q, _)| q == p) {
+                return Err(WktError::UnsupportedParameter(method.to_string(), p.wkt1_name().to_string()));
+            }
+            if defaults.iter().any(|&(q, default)| q == p && value != default) {
+                return Err(WktError::UnsupportedValue(p.wkt1_name().to_string(), value.to_string()));
+            }
+        }
+        Ok(())
+    };
+
+    // GDAL writes the web mercator as Mercator_1SP on a sphere
+    let spherical_mercator = proj4.is_some_and(|p| {
+        let tokens: Vec<&str> = p.split_whitespace().collect();
+        ["+proj=merc", "+a=6378137", "+b=6378137"].iter().all(|t| tokens.contains(t))
+    });
+
+    let mercator_defaults = [(LatitudeOfOrigin, 0.0), (LongitudeOfOrigin, 0.0), (ScaleFactor, 1.0), (FalseEasting, 0.0),
+                             (FalseNorthing, 0.0), (StandardParallel1, 0.0), (AuxiliarySphereType, 0.0)];
+
+    let crs: Box<dyn Crs> = match &*normalize(method) {
+        "transverse_mercator" | "gauss_kruger" => {
+            check(&[LatitudeOfOrigin, LongitudeOfOrigin, ScaleFactor, FalseEasting, FalseNorthing], &[])?;
+            let system = TransverseMercatorSystem {
+                lat_0: get(LatitudeOfOrigin, 0.0),
+                lon_0: get(LongitudeOfOrigin, 0.0),
+                scale_factor: get(ScaleFactor, 1.0),
+                false_easting: get(FalseEasting, 0.0),
+                false_northing: get(FalseNorthing, 0.0),
+            };
+            let zone = (system.lon_0 + 183.0) / 6.0;
+            let is_utm = system.lat_0 == 0.0 && system.scale_factor == 0.9996 && system.false_easting == 500_000.0 &&
+                (system.false_northing == 0.0 || system.false_northing == 10_000_000.0) &&
+                zone.fract() == 0.0 && (1.0..=60.0).contains(&zone);
+            if is_utm {
+                let hemisphere = if system.false_northing == 0.0 { Hemisphere::North } else { Hemisphere::South };
+                Box::new(UTMSystem { utm_zone: zone as u8, hemisphere })
+            } else {
+                Box::new(system)
+            }
+        },
+        "mercator_1sp" | "mercator_(variant_a)" | "mercator_variant_a" | "mercator" => {
+            check(&[], &mercator_defaults)?;
+            if spherical_mercator { Box::new(WebMercatorSystem) } else { Box::new(MercatorSystem) }
+        },
+        "popular_visualisation_pseudo_mercator" | "mercator_auxiliary_sphere" | "pseudo_mercator" => {
+            check(&[], &mercator_defaults)?;
+            Box::new(WebMercatorSystem)
+        },
+        "lambert_conformal_conic_1sp" | "lambert_conic_conformal_(1sp)" |
+        "lambert_conformal_conic_2sp" | "lambert_conic_conformal_(2sp)" | "lambert_conformal_conic" => {
+            check(&[LatitudeOfOrigin, LongitudeOfOrigin, StandardParallel1, StandardParallel2,
+                    ScaleFactor, FalseEasting, FalseNorthing], &[])?;
+            let lat_0 = get(LatitudeOfOrigin, 0.0);
+            let lat_1 = get(StandardParallel1, lat_0);
+            Box::new(LambertConformalConicSystem {
+                lat_0,
+                lon_0: get(LongitudeOfOrigin, 0.0),
+                lat_1,
+                lat_2: get(StandardParallel2, lat_1),
+                scale_factor: get(ScaleFactor, 1.0),
+                false_easting: get(FalseEasting, 0.0),
+                false_northing: get(FalseNorthing, 0.0),
+            })
+        },
+        "polar_stereographic" | "polar_stereographic_(variant_a)" | "polar_stereographic_variant_a" => {
+            let lat_0 = get(LatitudeOfOrigin, 90.0);
+            if lat_0.abs() != 90.0 {
+                return Err(WktError::UnsupportedValue(LatitudeOfOrigin.wkt1_name().to_string(), lat_0.to_string()));
+            }
+            check(&[LatitudeOfOrigin], &[(LongitudeOfOrigin, 0.0), (ScaleFactor, 0.994),
+                                         (FalseEasting, 2_000_000.0), (FalseNorthing, 2_000_000.0)])?;
+            let hemisphere = if lat_0 > 0.0 { Hemisphere::North } else { Hemisphere::South };
+            Box::new(UPSSystem { hemisphere })
+        },
+        "interrupted_goode_homolosine" => {
+            check(&[], &[(LongitudeOfOrigin, 0.0), (FalseEasting, 0.0), (FalseNorthing, 0.0)])?;
+            Box::new(GoodeHomolosineSystem { lobes: &GOODE_HOMOLOSINE_LOBES })
+        },
+        _ => return Err(WktError::UnsupportedProjection(method.to_string())),
+    };
+
+    if let Some(description) = Description::of(&*crs) {
+        let invalid = |name: &str, value: f64| WktError::InvalidValue(name.to_string(), value.to_string());
+        description.validate().map_err(|degenerate| match degenerate {
+            Degenerate::ScaleFactor(k) => invalid(ScaleFactor.wkt1_name(), k),
+            Degenerate::SatelliteHeight(h) => invalid("satellite_height", h),
+            Degenerate::StandardParallels(_, lat_2) => {
+                // the parameter that lat_2 was taken from
+                let parallel = [StandardParallel2, StandardParallel1, LatitudeOfOrigin].iter()
+                    .find(|&&p| params.iter().any(|&(q, _)| q == p))
+                    .map_or("standard_parallel_2", |p| p.wkt1_name());
+                invalid(parallel, lat_2)
+            },
+        })?;
+    }
+
+    Ok(crs)
+}
+
+fn read_geographic_crs(node: &Node) -> Result<WktDefinition, WktError> {
+    let is_cartesian = node.child(&["CS"]).and_then(|cs| cs.text(0)).is_some_and(|cs| cs.eq_ignore_ascii_case("cartesian"));
+    if is_cartesian {
+        return Err(WktError::UnsupportedCrsType(format!("{} (geocentric)", node.keyword)));
+    }
+
+    let geographic = read_geographic(node)?;
+    if geographic.angle_unit != 1.0 {
+        return Err(WktError::UnsupportedValue("UNIT".to_string(), geographic.angle_unit.to_string()));
+    }
+
+    Ok(WktDefinition {
+        name: geographic.name.clone(),
+        geographic_name: geographic.name,
+        datum_name: geographic.datum_name,
+        crs: adjust(Box::new(LonLatSystem), 1.0, geographic.prime_meridian),
+        ellipsoid: geographic.ellipsoid,
+        to_wgs84: geographic.to_wgs84,
+    })
+}
+
+fn read_projected_crs(node: &Node) -> Result<WktDefinition, WktError> {
+    let base = node.child(&["GEOGCS", "BASEGEOGCRS", "BASEGEODCRS", "GEOGCRS", "GEODCRS"])
+        .ok_or_else(|| WktError::MissingNode("GEOGCS".to_string()))?;
+    let geographic = read_geographic(base)?;
+
+    // WKT2 puts the method and the parameters in a CONVERSION, WKT1 directly in the CRS
+    let conversion = node.child(&["CONVERSION", "DERIVINGCONVERSION"]).unwrap_or(node);
+    let method = conversion.child(&["METHOD", "PROJECTION"])
+        .ok_or_else(|| WktError::MissingNode("PROJECTION".to_string()))?
+        .name();
+
+    let to_meter = match node.child(&["UNIT", "LENGTHUNIT"])
+        .or_else(|| node.children(&["AXIS"]).filter_map(|axis| axis.child(&["LENGTHUNIT", "UNIT"])).next())
+    {
+        Some(unit) => unit_to_meters(unit.unit_factor()?),
+        None => 1.0,
+    };
+
+    let mut params = Vec::new();
+    for param in conversion.children(&["PARAMETER"]) {
+        let parameter = Parameter::from_name(param.name())
+            .ok_or_else(|| WktError::UnsupportedParameter(method.to_string(), param.name().to_string()))?;
+        let value = param.number(1)?;
+
+        // WKT2 parameters have their own unit, WKT1 ones use the units of the CRS
+        let value = match param.child(&["ANGLEUNIT", "LENGTHUNIT", "SCALEUNIT", "UNIT"]) {
+            Some(unit) if parameter.is_angle() => value * unit_to_degrees(unit.unit_factor()?),
+            Some(unit) if parameter.is_length() => value * unit_to_meters(unit.unit_factor()?),
+            Some(unit) => value * unit.unit_factor()?,
+            None if parameter.is_angle() => value * geographic.angle_unit,
+            None if parameter.is_length() => value * to_meter,
+            None => value,
+        };
+        params.push((parameter, value));
+    }
+
+    let proj4 = node.children(&["EXTENSION"]).find(|e| e.name() == "PROJ4").and_then(|e| e.text(1));
+    let crs = build_projection(method, &params, proj4)?;
+
+    Ok(WktDefinition {
+        name: node.name().to_string(),
+        geographic_name: geographic.name,
+        datum_name: geographic.datum_name,
+        crs: adjust(crs, to_meter, geographic.prime_meridian),
+        ellipsoid: geographic.ellipsoid,
+        to_wgs84: geographic.to_wgs84,
+    })
+}
+
+/// Reads a WKT2 `BOUNDCRS` with a Helmert transformation to WGS 84
+fn read_bound_crs(node: &Node) -> Result<WktDefinition, WktError> {
+    let source = node.child(&["SOURCECRS"]).and_then(|s| s.nodes().next())
+        .ok_or_else(|| WktError::MissingNode("SOURCECRS".to_string()))?;
+    let mut definition = read_crs(source)?;
+
+    let target = node.child(&["TARGETCRS"]).and_then(|t| t.nodes().next())
+        .ok_or_else(|| WktError::MissingNode("TARGETCRS".to_string()))?;
+    let target_ellipsoid = read_geographic(target)?.ellipsoid;
+    if target_ellipsoid != WGS_1984_ELLIPSOID {
+        return Err(WktError::UnsupportedValue("TARGETCRS".to_string(), target.name().to_string()));
+    }
+
+    let transformation = node.child(&["ABRIDGEDTRANSFORMATION"])
+        .ok_or_else(|| WktError::MissingNode("ABRIDGEDTRANSFORMATION".to_string()))?;
+    let method = transformation.child(&["METHOD"])
+        .ok_or_else(|| WktError::MissingNode("METHOD".to_string()))?
+        .name();
+
+    // The coordinate frame convention has the opposite sign of the rotations
+    let normalized = normalize(method);
+    let rotation_sign = if normalized.starts_with("coordinate_frame") {
+        -1.0
+    } else if normalized.starts_with("position_vector") || normalized.starts_with("geocentric_translations") {
+        1.0
+    } else {
+        return Err(WktError::UnsupportedValue("METHOD".to_string(), method.to_string()));
+    };
+
+    let mut helmert = Helmert::translation(0.0, 0.0, 0.0);
+    for param in transformation.children(&["PARAMETER"]) {
+        let factor = match param.child(&["LENGTHUNIT", "ANGLEUNIT", "SCALEUNIT", "UNIT"]) {
+            Some(unit) => Some(unit.unit_factor()?),
+            None => None,
+        };
+        let value = param.number(1)?;
+        let meters = || value * factor.unwrap_or(1.0);
+        let arc_seconds = || value * factor.map(|f| snap(f / ARC_SECOND, 1.0)).unwrap_or(1.0);
+        let ppm = || value * factor.map(|f| snap(f / 1e-6, 1.0)).unwrap_or(1.0);
+
+        match &*normalize(param.name()) {
+            "x_axis_translation" => helmert.dx = meters(),
+            "y_axis_translation" => helmert.dy = meters(),
+            "z_axis_translation" => helmert.dz = meters(),
+            "x_axis_rotation" => helmert.rx = rotation_sign * arc_seconds(),
+            "y_axis_rotation" => helmert.ry = rotation_sign * arc_seconds(),
+            "z_axis_rotation" => helmert.rz = rotation_sign * arc_seconds(),
+            "scale_difference" => helmert.ds = ppm(),
+            _ => return Err(WktError::UnsupportedParameter(method.to_string(), param.name().to_string())),
+        }
+    }
+
+    definition.to_wgs84 = Some(helmert);
+    Ok(definition)
+}
+
+fn read_crs(node: &Node) -> Result<WktDefinition, WktError> {
+    match node.keyword.as_str() {
+        "GEOGCS" | "GEOGCRS" | "GEODCRS" | "GEOGRAPHICCRS" | "GEODETICCRS" => read_geographic_crs(node),
+        "PROJCS" | "PROJCRS" | "PROJECTEDCRS" => read_projected_crs(node),
+        "BOUNDCRS" => read_bound_crs(node),
+        "COMPD_CS" | "COMPOUNDCRS" => {
+            let horizontal = node.children(&["GEOGCS", "GEOGCRS", "GEODCRS", "GEOGRAPHICCRS", "GEODETICCRS",
+                                             "PROJCS", "PROJCRS", "PROJECTEDCRS", "BOUNDCRS"]).next()
+                .ok_or_else(|| WktError::MissingNode("PROJCS".to_string()))?;
+            let mut definition = read_crs(horizontal)?;
+            definition.name = node.name().to_string();
+            Ok(definition)
+        },
+        keyword => Err(WktError::UnsupportedCrsType(keyword.to_string())),
+    }
+}
+
+/// Parses a WKT1, ESRI or WKT2 CRS definition
+///
+/// Keywords are case-insensitive and both `[]` and `()` delimiters are accepted.
+/// Nodes nested deeper than 32 levels are rejected as `InvalidSyntax`.
+/// Authority codes, axes, usages and remarks are ignored. See the module
+/// documentation for the supported coordinate systems.
+pub fn parse_wkt(s: &str)
+                 -> Result<WktDefinition, WktError>
+{
+    read_crs(&parse_tree(s)?)
+}
+
+/// The projection methods that can be written as WKT
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Method {
+    TransverseMercator,
+    Mercator,
+    PseudoMercator,
+    LambertConic1SP,
+    LambertConic2SP,
+    PolarStereographic,
+    GoodeHomolosine,
+}
+
+impl Method {
+    /// WKT1, ESRI and EPSG names, EPSG code
+    fn names(self) -> (&'static str, &'static str, &'static str, Option<u32>) {
+        use self::Method::*;
+        match self {
+            TransverseMercator => ("Transverse_Mercator", "Transverse_Mercator", "Transverse Mercator", Some(9807)),
+            Mercator => ("Mercator_1SP", "Mercator", "Mercator (variant A)", Some(9804)),
+            PseudoMercator => ("Mercator_1SP", "Mercator_Auxiliary_Sphere", "Popular Visualisation Pseudo Mercator", Some(1024)),
+            LambertConic1SP => ("Lambert_Conformal_Conic_1SP", "Lambert_Conformal_Conic", "Lambert Conic Conformal (1SP)", Some(9801)),
+            LambertConic2SP => ("Lambert_Conformal_Conic_2SP", "Lambert_Conformal_Conic", "Lambert Conic Conformal (2SP)", Some(9802)),
+            PolarStereographic => ("Polar_Stereographic", "Polar_Stereographic_Variant_A", "Polar Stereographic (variant A)", Some(9810)),
+            GoodeHomolosine => ("Interrupted_Goode_Homolosine", "Interrupted_Goode_Homolosine", "Interrupted Goode Homolosine", None),
+        }
+    }
+
+    /// The parameters written in each dialect, in the usual order
+    fn parameters(self, version: WktVersion) -> &'static [Parameter] {
+        use self::Method::*;
+        use self::Parameter::*;
+        use self::WktVersion::*;
+        match (self, version) {
+            (TransverseMercator, Wkt1Esri) | (PolarStereographic, Wkt1Esri) =>
+                &[FalseEasting, FalseNorthing, LongitudeOfOrigin, ScaleFactor, LatitudeOfOrigin],
+            (Mercator, Wkt1Esri) => &[FalseEasting, FalseNorthing, LongitudeOfOrigin, StandardParallel1],
+            (PseudoMercator, Wkt1Esri) => &[FalseEasting, FalseNorthing, LongitudeOfOrigin, StandardParallel1, AuxiliarySphereType],
+            (Mercator, Wkt1) | (PseudoMercator, Wkt1) => &[LongitudeOfOrigin, ScaleFactor, FalseEasting, FalseNorthing],
+            (PseudoMercator, Wkt2) => &[LatitudeOfOrigin, LongitudeOfOrigin, FalseEasting, FalseNorthing],
+            (LambertConic1SP, Wkt1Esri) => &[FalseEasting, FalseNorthing, LongitudeOfOrigin, StandardParallel1, ScaleFactor, LatitudeOfOrigin],
+            (LambertConic2SP, Wkt1Esri) =>
+                &[FalseEasting, FalseNorthing, LongitudeOfOrigin, StandardParallel1, StandardParallel2, ScaleFactor, LatitudeOfOrigin],
+            (LambertConic2SP, Wkt1) => &[StandardParallel1, StandardParallel2, LatitudeOfOrigin, LongitudeOfOrigin, FalseEasting, FalseNorthing],
+            (LambertConic2SP, Wkt2) => &[LatitudeOfOrigin, LongitudeOfOrigin, StandardParallel1, StandardParallel2, FalseEasting, FalseNorthing],
+            (GoodeHomolosine, _) => &[LongitudeOfOrigin, FalseEasting, FalseNorthing],
+            _ => &[LatitudeOfOrigin, LongitudeOfOrigin, ScaleFactor, FalseEasting, FalseNorthing],
+        }
+    }
+}
+
+/// A projection method with its parameters, in degrees and meters
+struct Projection {
+    method: Method,
+    /// Name of the conversion, e.g. "UTM zone 32N"
+    name: String,
+    lat_0: f64,
+    lon_0: f64,
+    lat_1: f64,
+    lat_2: f64,
+    scale_factor: f64,
+    false_easting: f64,
+    false_northing: f64,
+}
+
+impl Projection {
+    fn new(method: Method) -> Self {
+        Projection {
+            method,
+            name: method.names().2.to_string(),
+            lat_0: 0.0,
+            lon_0: 0.0,
+            lat_1: 0.0,
+            lat_2: 0.0,
+            scale_factor: 1.0,
+            false_easting: 0.0,
+            false_northing: 0.0,
+        }
+    }
+
+    fn value(&self, parameter: Parameter) -> f64 {
+        use self::Parameter::*;
+        match parameter {
+            LatitudeOfOrigin => self.lat_0,
+            LongitudeOfOrigin => self.lon_0,
+            ScaleFactor => self.scale_factor,
+            FalseEasting => self.false_easting,
+            FalseNorthing => self.false_northing,
+            StandardParallel1 => self.lat_1,
+            StandardParallel2 => self.lat_2,
+            AuxiliarySphereType => 0.0,
+        }
+    }
+}
+
+/// Returns the projection of the CRS, `None` for geographic coordinates
+fn describe_projection(crs: &dyn Crs) -> Result<Option<Projection>, WktError> {
+    let unsupported = || WktError::UnsupportedCrs(format!("{:?}", crs));
+
+    let projection = match Description::of(crs).ok_or_else(unsupported)? {
+        Description::LonLat => return Ok(None),
+        Description::Utm(system) => {
+            let north = system.hemisphere == Hemisphere::North;
+            Projection {
+                name: format!("UTM zone {}{}", system.utm_zone, if north { "N" } else { "S" }),
+                lon_0: f64::from(system.utm_zone) * 6.0 - 183.0,
+                scale_factor: 0.9996,
+                false_easting: 500_000.0,
+                false_northing: if north { 0.0 } else { 10_000_000.0 },
+                ..Projection::new(Method::TransverseMercator)
+            }
+        },
+        Description::Ups(system) => {
+            let north = system.hemisphere == Hemisphere::North;
+            Projection {
+                name: format!("Universal Polar Stereographic {}", if north { "North" } else { "South" }),
+                lat_0: if north { 90.0 } else { -90.0 },
+                scale_factor: 0.994,
+                false_easting: 2_000_000.0,
+                false_northing: 2_000_000.0,
+                ..Projection::new(Method::PolarStereographic)
+            }
+        },
+        Description::Mercator => Projection::new(Method::Mercator),
+        Description::WebMercator => Projection::new(Method::PseudoMercator),
+        Description::TransverseMercator(system) => Projection {
+            lat_0: system.lat_0,
+            lon_0: system.lon_0,
+            scale_factor: system.scale_factor,
+            false_easting: system.false_easting,
+            false_northing: system.false_northing,
+            ..Projection::new(Method::TransverseMercator)
+        },
+        Description::LambertConic(system) => {
+            let one_parallel = system.lat_1 == system.lat_0 && system.lat_2 == system.lat_0;
+            Projection {
+                lat_0: system.lat_0,
+                lon_0: system.lon_0,
+                lat_1: system.lat_1,
+                lat_2: system.lat_2,
+                scale_factor: system.scale_factor,
+                false_easting: system.false_easting,
+                false_northing: system.false_northing,
+                ..Projection::new(if one_parallel { Method::LambertConic1SP } else { Method::LambertConic2SP })
+            }
+        },
+        Description::GoodeHomolosine => Projection::new(Method::GoodeHomolosine),
+        // parse_wkt doesn't read these methods
+        Description::Geostationary(_) | Description::Krovak(_) |
+        Description::Healpix(_) | Description::RHealpix(_) => return Err(unsupported()),
+    };
+
+    Ok(Some(projection))
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('"', "\"\""))
+}
+
+/// Formats a number, ESRI writes integers with a trailing ".0"
+fn number(value: f64, version: WktVersion) -> String {
+    // avoid "-0"
+    let value = if value == 0.0 { 0.0 } else { value };
+    if version == WktVersion::Wkt1Esri && value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{:.1}", value)
+    } else {
+        value.to_string()
+    }
+}
+
+/// ESRI names use underscores, e.g. "WGS_84_UTM_zone_32N"
+fn esri_name(name: &str) -> String {
+    let name = name.replace(" / ", "_");
+    name.split(|c: char| !(c.is_alphanumeric() || c == '.'))
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// Writes the SPHEROID / ELLIPSOID node
+fn write_ellipsoid(ellipsoid: &Ellipsoid, version: WktVersion) -> String {
+    let names = ELLIPSOID_NAMES.iter().find(|&&(e, _, _)| e.a == ellipsoid.a && e.b == ellipsoid.b);
+    let name = match (names, version) {
+        (Some(&(_, _, esri)), WktVersion::Wkt1Esri) => esri.to_string(),
+        (Some(&(_, epsg, _)), _) => epsg.to_string(),
+        (None, WktVersion::Wkt1Esri) => esri_name(ellipsoid.nice_name),
+        (None, _) => ellipsoid.nice_name.to_string(),
+    };
+
+    let inverse_flattening = if ellipsoid.a == ellipsoid.b { 0.0 } else { ellipsoid.flattening };
+
+    match version {
+        WktVersion::Wkt2 => format!("ELLIPSOID[{},{},{},LENGTHUNIT[\"metre\",1]]",
+                                    quote(&name), number(ellipsoid.a, version), number(inverse_flattening, version)),
+        _ => format!("SPHEROID[{},{},{}]", quote(&name), number(ellipsoid.a, version), number(inverse_flattening, version)),
+    }
+}
+
+/// Writes the GEOGCS / GEOGCRS / BASEGEOGCRS node
+fn write_geographic(definition: &WktDefinition, prime_meridian: f64, base: bool, version: WktVersion) -> String {
+    let datum_names = find_datum_names(&definition.datum_name);
+    let crs_names = DATUM_NAMES.iter().find(|n| {
+        let key = name_key(&definition.geographic_name);
+        name_key(n.crs) == key || name_key(n.esri_crs) == key
+    });
+
+    let (name, datum_name) = match version {
+        WktVersion::Wkt1 => (
+            crs_names.map(|n| n.crs.to_string()).unwrap_or_else(|| definition.geographic_name.clone()),
+            datum_names.map(|n| n.wkt1.to_string()).unwrap_or_else(|| definition.datum_name.clone()),
+        ),
+        WktVersion::Wkt1Esri => (
+            crs_names.map(|n| n.esri_crs.to_string())
+                .unwrap_or_else(|| format!("GCS_{}", esri_name(definition.geographic_name.trim_start_matches("GCS_")))),
+            datum_names.map(|n| n.esri.to_string())
+                .unwrap_or_else(|| format!("D_{}", esri_name(definition.datum_name.trim_start_matches("D_")))),
+        ),
+        WktVersion::Wkt2 => (
+            crs_names.map(|n| n.crs.to_string()).unwrap_or_else(|| definition.geographic_name.clone()),
+            datum_names.map(|n| n.wkt2.to_string()).unwrap_or_else(|| definition.datum_name.clone()),
+        ),
+    };
+
+    let meridian_name = PRIME_MERIDIANS.iter().find(|&&(_, lon)| lon == prime_meridian)
+        .map(|&(id, _)| id[..1].to_uppercase() + &id[1..])
+        .unwrap_or_else(|| "unknown".to_string());
+
+    match version {
+        WktVersion::Wkt1 | WktVersion::Wkt1Esri => {
+            let mut datum = format!("DATUM[{},{}", quote(&datum_name), write_ellipsoid(&definition.ellipsoid, version));
+            if let (Some(helmert), WktVersion::Wkt1) = (definition.to_wgs84, version) {
+                let values: Vec<String> = [helmert.dx, helmert.dy, helmert.dz, helmert.rx, helmert.ry, helmert.rz, helmert.ds]
+                    .iter().map(|&v| number(v, version)).collect();
+                datum.push_str(&format!(",TOWGS84[{}]", values.join(",")));
+            }
+            let unit = if version == WktVersion::Wkt1Esri { "Degree" } else { "degree" };
+            format!("GEOGCS[{},{}],PRIMEM[{},{}],UNIT[\"{}\",{}]]",
+                    quote(&name), datum, quote(&meridian_name), number(prime_meridian, version), unit, DEGREE)
+        },
+        WktVersion::Wkt2 => {
+            let mut s = format!("{}[{},DATUM[{},{}],PRIMEM[{},{},ANGLEUNIT[\"degree\",{}]]",
+                                if base { "BASEGEOGCRS" } else { "GEOGCRS" }, quote(&name), quote(&datum_name),
+                                write_ellipsoid(&definition.ellipsoid, version), quote(&meridian_name),
+                                number(prime_meridian, version), DEGREE);
+            if !base {
+                s.push_str(&format!(",CS[ellipsoidal,2],\
+                                     AXIS[\"geodetic longitude (Lon)\",east,ORDER[1],ANGLEUNIT[\"degree\",{0}]],\
+                                     AXIS[\"geodetic latitude (Lat)\",north,ORDER[2],ANGLEUNIT[\"degree\",{0}]]", DEGREE));
+            }
+            s.push(']');
+            s
+        },
+    }
+}
+
+/// Writes the transformation to WGS 84 as a WKT2 `BOUNDCRS` around the CRS
+fn write_bound_crs(source: &str, source_name: &str, helmert: &Helmert) -> String {
+    let target = "GEOGCRS[\"WGS 84\",DATUM[\"World Geodetic System 1984\",\
+                  ELLIPSOID[\"WGS 84\",6378137,298.257223563,LENGTHUNIT[\"metre\",1]]],\
+                  PRIMEM[\"Greenwich\",0,ANGLEUNIT[\"degree\",0.0174532925199433]],CS[ellipsoidal,2],\
+                  AXIS[\"geodetic longitude (Lon)\",east,ORDER[1],ANGLEUNIT[\"degree\",0.0174532925199433]],\
+                  AXIS[\"geodetic latitude (Lat)\",north,ORDER[2],ANGLEUNIT[\"degree\",0.0174532925199433]]]";
+
+    let method = if helmert.is_translation() {
+        "METHOD[\"Geocentric translations (geog2D domain)\",ID[\"EPSG\",9603]]"
+    } else {
+        "METHOD[\"Position Vector transformation (geog2D domain)\",ID[\"EPSG\",9606]]"
+    };
+
+    let translation = |name: &str, value: f64, id: u32| {
+        format!(",PARAMETER[\"{}\",{},LENGTHUNIT[\"metre\",1],ID[\"EPSG\",{}]]", name, number(value, WktVersion::Wkt2), id)
+    };
+    let mut parameters = translation("X-axis translation", helmert.dx, 8605) +
+        &translation("Y-axis translation", helmert.dy, 8606) +
+        &translation("Z-axis translation", helmert.dz, 8607);
+
+    if !helmert.is_translation() {
+        for &(name, value, id) in [("X-axis rotation", helmert.rx, 8608), ("Y-axis rotation", helmert.ry, 8609),
+                                   ("Z-axis rotation", helmert.rz, 8610)].iter() {
+            parameters.push_str(&format!(",PARAMETER[\"{}\",{},ANGLEUNIT[\"arc-second\",4.84813681109536E-06],ID[\"EPSG\",{}]]",
+                                         name, number(value, WktVersion::Wkt2), id));
+        }
+        parameters.push_str(&format!(",PARAMETER[\"Scale difference\",{},SCALEUNIT[\"parts per million\",1E-06],ID[\"EPSG\",8611]]",
+                                     number(helmert.ds, WktVersion::Wkt2)));
+    }
+
+    format!("BOUNDCRS[SOURCECRS[{}],TARGETCRS[{}],ABRIDGEDTRANSFORMATION[{},{}{}]]",
+            source, target, quote(&format!("{} to WGS 84", source_name)), method, parameters)
+}
+
+fn write_wkt(definition: &WktDefinition, version: WktVersion) -> Result<String, WktError> {
+    let adjusted = definition.crs.as_any().downcast_ref::<AdjustedSystem>();
+    let inner = adjusted.map(|a| &*a.crs).unwrap_or(&*definition.crs);
+
+    let (to_meter, prime_meridian) = match adjusted {
+        Some(a) if a.axis != (AxisDirection::East, AxisDirection::North) => {
+            return Err(WktError::UnsupportedCrs(format!("{:?}", definition.crs)));
+        },
+        Some(a) => (a.to_meter, a.prime_meridian),
+        None => (1.0, 0.0),
+    };
+
+    let projection = match describe_projection(inner)? {
+        Some(projection) => projection,
+        None if to_meter != 1.0 => return Err(WktError::UnsupportedCrs(format!("{:?}", definition.crs))),
+        None => {
+            let geographic = write_geographic(definition, prime_meridian, false, version);
+            return Ok(match (version, definition.to_wgs84) {
+                (WktVersion::Wkt2, Some(ref helmert)) => write_bound_crs(&geographic, &definition.name, helmert),
+                _ => geographic,
+            });
+        },
+    };
+
+    // WKT1 and WKT2 only have a scale factor for the LCC with one standard parallel
+    if projection.method == Method::LambertConic2SP && projection.scale_factor != 1.0 && version != WktVersion::Wkt1Esri {
+        return Err(WktError::UnsupportedCrs(format!("{:?}", definition.crs)));
+    }
+
+    let unit = LENGTH_UNITS.iter().find(|&&(_, _, m)| m == to_meter);
+    let unit_name = match (unit, version) {
+        (Some(&(_, esri, _)), WktVersion::Wkt1Esri) => esri,
+        (Some(&(wkt, _, _)), _) => wkt,
+        (None, _) => "unknown",
+    };
+
+    let geographic = write_geographic(definition, prime_meridian, true, version);
+    let (wkt1_method, esri_method, epsg_method, method_code) = projection.method.names();
+    let parameters = projection.method.parameters(version);
+
+    // parameters are written in degrees and in the unit of the CRS
+    let value = |parameter: Parameter| {
+        let value = projection.value(parameter);
+        if parameter.is_length() { value / to_meter } else { value }
+    };
+
+    let wkt = match version {
+        WktVersion::Wkt1 | WktVersion::Wkt1Esri => {
+            let esri = version == WktVersion::Wkt1Esri;
+            let name = if esri { esri_name(&definition.name) } else { definition.name.clone() };
+            let mut s = format!("PROJCS[{},{},PROJECTION[\"{}\"]", quote(&name), geographic, if esri { esri_method } else { wkt1_method });
+            for &parameter in parameters {
+                let parameter_name = if esri { parameter.esri_name() } else { parameter.wkt1_name() };
+                s.push_str(&format!(",PARAMETER[\"{}\",{}]", parameter_name, number(value(parameter), version)));
+            }
+            s.push_str(&format!(",UNIT[{},{}]", quote(unit_name), number(to_meter, version)));
+            if !esri {
+                if projection.method == Method::PseudoMercator {
+                    s.push_str(",EXTENSION[\"PROJ4\",\"+proj=merc +a=6378137 +b=6378137 +lat_ts=0 +lon_0=0 \
+                                +x_0=0 +y_0=0 +k=1 +units=m +nadgrids=@null +wktext +no_defs\"]");
+                }
+                s.push_str(",AXIS[\"Easting\",EAST],AXIS[\"Northing\",NORTH]");
+            }
+            s.push(']');
+            s
+        },
+        WktVersion::Wkt2 => {
+            let method_id = method_code.map(|code| format!(",ID[\"EPSG\",{}]", code)).unwrap_or_default();
+            let length_unit = format!("LENGTHUNIT[{},{}]", quote(unit_name), number(to_meter, version));
+            let mut s = format!("PROJCRS[{},{},CONVERSION[{},METHOD[\"{}\"{}]",
+                                quote(&definition.name), geographic, quote(&projection.name), epsg_method, method_id);
+            for &parameter in parameters {
+                let (parameter_name, code) = parameter.epsg_name(projection.method);
+                let unit = if parameter.is_angle() {
+                    format!("ANGLEUNIT[\"degree\",{}]", DEGREE)
+                } else if parameter.is_length() {
+                    length_unit.clone()
+                } else {
+                    "SCALEUNIT[\"unity\",1]".to_string()
+                };
+                s.push_str(&format!(",PARAMETER[\"{}\",{},{},ID[\"EPSG\",{}]]", parameter_name, number(value(parameter), version), unit, code));
+            }
+            s.push_str(&format!("],CS[Cartesian,2],AXIS[\"easting (E)\",east,ORDER[1],{0}],AXIS[\"northing (N)\",north,ORDER[2],{0}]]",
+                                length_unit));
+            match definition.to_wgs84 {
+                Some(ref helmert) => write_bound_crs(&s, &definition.name, helmert),
+                None => s,
+            }
+        },
+    };
+
+    Ok(wkt)
+}
+
+/// Writes the CRS, ellipsoid and transformation to WGS84 as WKT of the given dialect
+///
+/// The names are generated as in `WktDefinition::new`. Returns `UnsupportedCrs`
+/// for coordinate systems that have no WKT equivalent in `parse_wkt`.
+pub fn to_wkt(crs: &dyn Crs, ellipsoid: &Ellipsoid, to_wgs84: Option<&Helmert>, version: WktVersion)
+              -> Result<String, WktError>
+{
+    WktDefinition::new(Crs::clone(crs), *ellipsoid, to_wgs84.cloned()).to_wkt(version)
+}